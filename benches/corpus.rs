@@ -0,0 +1,76 @@
+//! Parser and renderer benchmarks over `benches/corpus/`, a bundled set of
+//! representative pages. Run with `cargo bench --features bench`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use wiki2md::parse::parse_wiki;
+use wiki2md::render::{RenderOptions, render_doc_with_options};
+
+/// `(name, wikitext)` for each page of the corpus.
+const CORPUS: &[(&str, &str)] = &[
+    // a short biography stub.
+    ("small", include_str!("corpus/small.wiki")),
+    // a typical article: headings, lists, a code block, refs, and links.
+    ("medium", include_str!("corpus/medium.wiki")),
+    // a tournament page made of large tables.
+    ("table_heavy", include_str!("corpus/table_heavy.wiki")),
+    // a citation-dense page with named and list-defined refs.
+    ("ref_heavy", include_str!("corpus/ref_heavy.wiki")),
+];
+
+/// A page dense in inline tags (`<ref>`, `<span>`, `<br>`) and stray `<`s, where
+/// the inline parser tries a tag at nearly every line position.
+fn tag_heavy_page() -> String {
+    let mut src = String::new();
+    for i in 0..2_000 {
+        src.push_str(&format!(
+            "Line {i} with a <span id=\"a{i}\">span</span>, a ref<ref name=\"r{i}\">See [[Page {i}]].</ref>,\
+             <br/>a break, and 1 < 2 < 3 comparisons.<BR>\n"
+        ));
+    }
+    src
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let tag_heavy = tag_heavy_page();
+    let pages = CORPUS
+        .iter()
+        .copied()
+        .chain([("tag_heavy", tag_heavy.as_str())]);
+    let mut group = c.benchmark_group("parse");
+    for (name, src) in pages {
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), src, |b, src| {
+            b.iter(|| parse_wiki(black_box(src)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_render(c: &mut Criterion) {
+    let opts = RenderOptions::default();
+    let mut group = c.benchmark_group("render");
+    for (name, src) in CORPUS {
+        let doc = parse_wiki(src).document;
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &doc, |b, doc| {
+            b.iter(|| render_doc_with_options(black_box(doc), &opts))
+        });
+    }
+    group.finish();
+}
+
+fn bench_convert(c: &mut Criterion) {
+    let opts = RenderOptions::default();
+    let mut group = c.benchmark_group("convert");
+    for (name, src) in CORPUS {
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), src, |b, src| {
+            b.iter(|| render_doc_with_options(&parse_wiki(black_box(src)).document, &opts))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_render, bench_convert);
+criterion_main!(benches);
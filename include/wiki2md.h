@@ -0,0 +1,40 @@
+/* C interface to wiki2md. Build with `cargo build --release --features wiki2md-ffi`
+ * and link against target/release/libwiki2md.{so,dylib} or wiki2md.dll.
+ *
+ * Inputs are UTF-8 (pointer + length, not NUL-terminated). Outputs go into a
+ * caller-owned buffer, snprintf-style: each function returns the full output
+ * length in bytes and only writes the output if it fits in `out_cap`. Call with
+ * out = NULL, out_cap = 0 first to size the buffer. Output is not NUL-terminated.
+ * Negative return values are errors.
+ */
+#ifndef WIKI2MD_H
+#define WIKI2MD_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define WIKI2MD_ERR_NULL     (-1) /* NULL pointer with a non-zero length */
+#define WIKI2MD_ERR_UTF8     (-2) /* input is not valid UTF-8 */
+#define WIKI2MD_ERR_OPTIONS  (-3) /* invalid options JSON */
+#define WIKI2MD_ERR_INTERNAL (-4) /* unexpected failure */
+
+/* Parse wikitext into the JSON AST envelope (as written by `wiki2md --json`). */
+intptr_t wiki2md_parse_to_json(const uint8_t *src, size_t src_len,
+                               uint8_t *out, size_t out_cap);
+
+/* Render wikitext to Markdown. `options_json` takes the keys of the
+ * `wiki2md.render:` frontmatter overrides, e.g. {"center_tables": true};
+ * pass NULL, 0 for the defaults. */
+intptr_t wiki2md_render_markdown(const uint8_t *src, size_t src_len,
+                                 const uint8_t *options_json, size_t options_len,
+                                 uint8_t *out, size_t out_cap);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WIKI2MD_H */
@@ -0,0 +1,228 @@
+//! Section anchor map (`--anchor-map`): for every article, the `#Section` anchors
+//! other articles link to (`[[Perft#Divide]]`) and the fragment that section got
+//! in the Markdown, so deep links from other systems can be rewritten:
+//!
+//! ```json
+//! { "Perft": { "Divide": "divide", "Perft_Results": "perft-results", "Gone": null } }
+//! ```
+//!
+//! Headings get the GitHub-style slug of their text (`perft-results`, `-1`, `-2`
+//! for repeats); `<span id>` anchors, emitted as `<a name>`, keep their id, and so
+//! do tables with `--table-anchors` (see [`table_ids`]). Anchors no section, span,
+//! or table of the article matches map to `null`.
+
+use crate::ast::{self, BlockKind, Document};
+use crate::title::Title;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// What the anchor map needs from one article: its section fragments and the
+/// anchored links it makes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArticleAnchors {
+    pub id: String,
+    /// Normalized wiki anchor (`Perft Results`) -> fragment in the Markdown.
+    sections: HashMap<String, String>,
+    /// `(target article id, anchor as written)` of each `[[Page#Anchor]]` link.
+    links: Vec<(String, String)>,
+}
+
+impl ArticleAnchors {
+    pub fn new(article_id: &str, doc: &Document) -> Self {
+        let mut sections = HashMap::new();
+        let mut used: HashMap<String, usize> = HashMap::new();
+        ast::walk_blocks(&doc.blocks, &mut |block| {
+            if let BlockKind::Heading { content, .. } = &block.kind {
+                let mut text = String::new();
+                ast::plain_text(content, &mut text);
+                let slug = heading_slug(&text);
+                let n = used.entry(slug.clone()).or_default();
+                let fragment = match *n {
+                    0 => slug,
+                    n => format!("{}-{}", slug, n),
+                };
+                *n += 1;
+                sections.entry(normalize_anchor(&text)).or_insert(fragment);
+            }
+        });
+        for id in ast::span_anchors(doc) {
+            sections.insert(normalize_anchor(id), id.to_string());
+        }
+
+        let links = ast::internal_links(doc)
+            .into_iter()
+            .filter_map(|link| {
+                let anchor = link
+                    .anchor
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())?;
+                let target = match link.target.trim() {
+                    "" => article_id.to_string(),
+                    target => Title::parse(target).db_key(),
+                };
+                Some((target, anchor.to_string()))
+            })
+            .collect();
+
+        ArticleAnchors {
+            id: article_id.to_string(),
+            sections,
+            links,
+        }
+    }
+
+    /// Also resolve anchors to the article's tables (see [`table_ids`]).
+    pub fn add_tables(&mut self, doc: &Document) {
+        for id in table_ids(doc) {
+            self.sections.entry(normalize_anchor(&id)).or_insert(id);
+        }
+    }
+
+    /// The Markdown fragment for the wiki anchor `anchor`, if the article has it.
+    pub fn fragment(&self, anchor: &str) -> Option<&str> {
+        self.sections
+            .get(&normalize_anchor(anchor))
+            .map(String::as_str)
+    }
+}
+
+/// Article id -> anchor as linked -> fragment (`None` if it doesn't resolve).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct AnchorMap(pub BTreeMap<String, BTreeMap<String, Option<String>>>);
+
+impl AnchorMap {
+    /// Resolve every anchored link of `articles` against the article it points at.
+    /// Links to articles not in `articles` are left out.
+    pub fn build(articles: &[ArticleAnchors]) -> Self {
+        let by_id: HashMap<&str, &ArticleAnchors> =
+            articles.iter().map(|a| (a.id.as_str(), a)).collect();
+        let mut map: BTreeMap<String, BTreeMap<String, Option<String>>> = BTreeMap::new();
+        for (target, anchor) in articles.iter().flat_map(|a| &a.links) {
+            let Some(article) = by_id.get(target.as_str()) else {
+                continue;
+            };
+            map.entry(target.clone())
+                .or_default()
+                .insert(anchor.clone(), article.fragment(anchor).map(str::to_string));
+        }
+        AnchorMap(map)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// The id GitHub-style Markdown renderers give a heading: lowercase, spaces as
+/// `-`, punctuation other than `-` and `_` removed.
+pub fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The anchor id of every table of `doc`, in document order (nested tables after
+/// the table they're in): the table's `id` attribute, else `table-` and the slug
+/// of its caption (`table-perft-results`), else `table-N` for the Nth table.
+/// Repeated ids get `-1`, `-2`.
+pub fn table_ids(doc: &Document) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+    ast::walk_blocks(&doc.blocks, &mut |block| {
+        let BlockKind::Table { table } = &block.kind else {
+            return;
+        };
+        let attr_id = table
+            .attrs
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case("id"))
+            .and_then(|a| a.value.as_deref())
+            .map(str::trim)
+            .filter(|id| !id.is_empty());
+        let caption = table.caption.as_ref().map(|c| {
+            let mut text = String::new();
+            ast::plain_text(&c.content, &mut text);
+            heading_slug(&text)
+        });
+        let id = match (attr_id, caption) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(slug)) if !slug.is_empty() => format!("table-{}", slug),
+            _ => format!("table-{}", ids.len() + 1),
+        };
+        let n = used.entry(id.clone()).or_default();
+        ids.push(match *n {
+            0 => id,
+            n => format!("{}-{}", id, n),
+        });
+        *n += 1;
+    });
+    ids
+}
+
+/// Anchors match like MediaWiki's: `_` and spaces are the same, and runs of
+/// whitespace count once.
+fn normalize_anchor(anchor: &str) -> String {
+    anchor
+        .replace('_', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn maps_linked_anchors_to_markdown_fragments() {
+        let perft = parse_wiki("Intro.\n=Perft Results=\nA.\n==Divide==\n<span id=\"Bulk\"></span>\nB.\n=Perft Results=\nC.\n").document;
+        let divide = parse_wiki("See [[Perft#Perft_Results]], [[perft#Divide|divide]], [[Perft#Bulk]], [[Perft#Gone]] and [[#Top]].\n=Top=\n").document;
+        let articles = [
+            ArticleAnchors::new("Perft", &perft),
+            ArticleAnchors::new("Divide", &divide),
+        ];
+        let map = AnchorMap::build(&articles);
+
+        let perft = &map.0["Perft"];
+        assert_eq!(perft["Perft_Results"].as_deref(), Some("perft-results"));
+        assert_eq!(perft["Divide"].as_deref(), Some("divide"));
+        assert_eq!(perft["Bulk"].as_deref(), Some("Bulk"));
+        assert_eq!(perft["Gone"], None);
+        assert_eq!(map.0["Divide"]["Top"].as_deref(), Some("top"));
+        assert_eq!(articles[0].fragment("Perft Results"), Some("perft-results"));
+        assert_eq!(heading_slug("C++ & Java (1990s)"), "c--java-1990s");
+    }
+
+    #[test]
+    fn tables_are_named_by_id_caption_or_position() {
+        let doc = parse_wiki("{|\n|+ Perft Results\n| 20\n|}\n{|\n| a\n|}\n{| id=\"divide\"\n| b\n|}\n{|\n|+ Perft Results\n| 400\n|}\n").document;
+        assert_eq!(
+            table_ids(&doc),
+            [
+                "table-perft-results",
+                "table-2",
+                "divide",
+                "table-perft-results-1"
+            ]
+        );
+
+        let other = parse_wiki("See [[Perft#table-2]] and [[Perft#Divide]].\n").document;
+        let mut perft = ArticleAnchors::new("Perft", &doc);
+        assert_eq!(perft.fragment("table-2"), None);
+        perft.add_tables(&doc);
+        let map = AnchorMap::build(&[perft, ArticleAnchors::new("Other", &other)]);
+        assert_eq!(map.0["Perft"]["table-2"].as_deref(), Some("table-2"));
+        assert_eq!(map.0["Perft"]["Divide"], None);
+    }
+}
@@ -1,5 +1,10 @@
 use crate::ast::Span;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Code of the diagnostic [`cap_diagnostics`] appends when it drops diagnostics.
+pub const DIAGNOSTICS_TRUNCATED_CODE: &str = "wiki2md.diagnostics.truncated";
 
 /// Severity level of a diagnostic emitted by the parser or validator.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -46,4 +51,125 @@ pub struct Diagnostic {
     /// Optional notes that can help explain recovery decisions.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub notes: Vec<String>,
+
+    /// How many times this diagnostic occurred. After [`dedup_diagnostics`], `span` and `notes`
+    /// are those of the first occurrence.
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub occurrences: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn is_one(n: &u32) -> bool {
+    *n == 1
+}
+
+/// Merge diagnostics with the same code and message into the first one, adding up
+/// their occurrences. Order is kept.
+pub fn dedup_diagnostics(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let mut first: HashMap<(Option<String>, String), usize> = HashMap::new();
+    let mut out: Vec<Diagnostic> = Vec::with_capacity(diagnostics.len());
+    for d in diagnostics {
+        match first.get(&(d.code.clone(), d.message.clone())) {
+            Some(&i) => out[i].occurrences = out[i].occurrences.saturating_add(d.occurrences),
+            None => {
+                first.insert((d.code.clone(), d.message.clone()), out.len());
+                out.push(d);
+            }
+        }
+    }
+    out
+}
+
+/// Keep the first `max` diagnostics; if any are dropped, append one
+/// [`DIAGNOSTICS_TRUNCATED_CODE`] diagnostic saying how many.
+pub fn cap_diagnostics(diagnostics: &mut Vec<Diagnostic>, max: usize) {
+    if diagnostics.len() <= max {
+        return;
+    }
+    let dropped: u64 = diagnostics
+        .drain(max..)
+        .map(|d| u64::from(d.occurrences))
+        .sum();
+    diagnostics.push(Diagnostic {
+        severity: Severity::Info,
+        phase: None,
+        code: Some(DIAGNOSTICS_TRUNCATED_CODE.to_string()),
+        message: format!("{} more diagnostics omitted (limit {})", dropped, max),
+        span: None,
+        notes: vec![],
+        occurrences: 1,
+    });
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        })
+    }
+}
+
+/// `warning[wikitext.unclosed_tag]: message`
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.severity, code, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
+        }
+        if self.occurrences > 1 {
+            write!(f, " ({} times)", self.occurrences)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warning(code: &str, message: &str, start: u64) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            phase: Some(DiagnosticPhase::Parse),
+            code: Some(code.to_string()),
+            message: message.to_string(),
+            span: Some(Span::new(start, start + 1)),
+            notes: vec![],
+            occurrences: 1,
+        }
+    }
+
+    #[test]
+    fn dedup_counts_repeats_and_cap_marks_overflow() {
+        let mut diagnostics = dedup_diagnostics(vec![
+            warning("table.unexpected_line", "Unexpected line", 1),
+            warning("list.missing_parent", "Missing parent", 2),
+            warning("table.unexpected_line", "Unexpected line", 3),
+            warning("table.unexpected_line", "Unexpected line", 4),
+            warning("table.unexpected_line", "Other message", 5),
+        ]);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].occurrences, 3);
+        assert_eq!(diagnostics[0].span, Some(Span::new(1, 2)));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning[table.unexpected_line]: Unexpected line (3 times)"
+        );
+
+        cap_diagnostics(&mut diagnostics, 1);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[1].code.as_deref(),
+            Some(DIAGNOSTICS_TRUNCATED_CODE)
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "2 more diagnostics omitted (limit 1)"
+        );
+    }
 }
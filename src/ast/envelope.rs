@@ -24,7 +24,7 @@ pub struct AstFile {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub diagnostics: Vec<Diagnostic>,
 
-    pub document: Document,
+    pub document: Document<'static>,
 }
 
 /// Identifies the program that produced the AST.
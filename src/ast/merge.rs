@@ -0,0 +1,294 @@
+use crate::ast::{
+    BlockKind, BlockNode, Document, HtmlAttr, InlineKind, InlineNode, Span, TableCell,
+};
+use serde::{Deserialize, Serialize};
+
+/// Where one of the documents combined by [`merge_documents`] sits in the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentPart {
+    /// The part's span in the merged document. Spans inside it, less
+    /// `span.start`, are offsets into that part's own source.
+    pub span: Span,
+
+    /// Index of the part's first block in `Document::blocks`.
+    pub first_block: usize,
+}
+
+impl Document<'_> {
+    /// For a merged document, the index of the part `span` belongs to and `span`
+    /// relative to that part's source. `None` for documents that weren't merged.
+    pub fn part_of(&self, span: Span) -> Option<(usize, Span)> {
+        let index = self
+            .parts
+            .iter()
+            .rposition(|p| p.span.start <= span.start)?;
+        let start = self.parts[index].span.start;
+        Some((index, Span::new(span.start - start, span.end - start)))
+    }
+}
+
+/// Concatenate `docs` into one document, e.g. to render "Perft" and "Perft Results"
+/// as a single note.
+///
+/// The first document keeps its headings. Every later one has its headings moved
+/// one level down and, when it has a breadcrumb, is introduced by a level 1
+/// heading with the breadcrumb's page title in place of the breadcrumb line.
+/// Spans are shifted so each document covers its own range of the merged
+/// document; [`Document::parts`] records the ranges ([`Document::part_of`] maps a
+/// span back). Categories are combined; the redirect and breadcrumb are the
+/// first document's.
+pub fn merge_documents<'a>(docs: Vec<Document<'a>>) -> Document<'a> {
+    let mut merged = Document {
+        span: Span::default(),
+        blocks: Vec::new(),
+        categories: Vec::new(),
+        redirect: None,
+        breadcrumb: None,
+        trivia: Vec::new(),
+        parts: Vec::new(),
+    };
+
+    for (i, mut doc) in docs.into_iter().enumerate() {
+        let shift = Shift {
+            offset: merged.span.end,
+            levels: if i == 0 { 0 } else { 1 },
+        };
+        merged.parts.push(DocumentPart {
+            span: shift.span(doc.span),
+            first_block: merged.blocks.len(),
+        });
+
+        if i == 0 {
+            merged.redirect = doc.redirect.take().map(|mut r| {
+                r.span = shift.span(r.span);
+                r
+            });
+            merged.breadcrumb = doc.breadcrumb.take().map(|mut b| {
+                b.span = shift.span(b.span);
+                for link in &mut b.links {
+                    if let Some(text) = &mut link.text {
+                        shift.inlines(text);
+                    }
+                }
+                b
+            });
+        } else if let Some(breadcrumb) = doc.breadcrumb.take() {
+            doc.blocks.retain(|b| !breadcrumb.is_block(b));
+            if let Some(title) = breadcrumb.current {
+                merged.blocks.push(BlockNode {
+                    span: shift.span(breadcrumb.span),
+                    kind: BlockKind::Heading {
+                        level: 1,
+                        content: vec![InlineNode {
+                            span: shift.span(breadcrumb.span),
+                            kind: InlineKind::Text {
+                                value: title.into(),
+                            },
+                        }],
+                    },
+                });
+            }
+        }
+
+        shift.blocks(&mut doc.blocks);
+        merged.blocks.append(&mut doc.blocks);
+        for mut category in doc.categories {
+            if merged.categories.iter().all(|c| c.name != category.name) {
+                category.span = shift.span(category.span);
+                merged.categories.push(category);
+            }
+        }
+        merged.trivia.extend(doc.trivia.into_iter().map(|mut t| {
+            t.span = shift.span(t.span);
+            t
+        }));
+        merged.span.end = shift.offset + doc.span.end;
+    }
+    merged
+}
+
+/// Moves spans by `offset` and headings down by `levels`.
+struct Shift {
+    offset: u64,
+    levels: u8,
+}
+
+impl Shift {
+    fn span(&self, span: Span) -> Span {
+        Span::new(span.start + self.offset, span.end + self.offset)
+    }
+
+    fn blocks(&self, blocks: &mut [BlockNode]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&self, block: &mut BlockNode) {
+        block.span = self.span(block.span);
+        match &mut block.kind {
+            BlockKind::Heading { level, content } => {
+                *level = (*level + self.levels).min(6);
+                self.inlines(content);
+            }
+            BlockKind::Paragraph { content } => self.inlines(content),
+            BlockKind::List { items } => {
+                for item in items {
+                    item.span = self.span(item.span);
+                    self.blocks(&mut item.blocks);
+                }
+            }
+            BlockKind::Table { table } => {
+                self.attrs(&mut table.attrs);
+                if let Some(caption) = &mut table.caption {
+                    caption.span = self.span(caption.span);
+                    self.attrs(&mut caption.attrs);
+                    self.inlines(&mut caption.content);
+                }
+                for row in &mut table.rows {
+                    row.span = self.span(row.span);
+                    self.attrs(&mut row.attrs);
+                    row.cells.iter_mut().for_each(|cell| self.cell(cell));
+                }
+            }
+            BlockKind::Poem { lines } => {
+                for line in lines {
+                    self.inlines(line);
+                }
+            }
+            BlockKind::References { node } => {
+                self.attrs(&mut node.attrs);
+                self.inlines(&mut node.refs);
+            }
+            BlockKind::HtmlBlock { node } => {
+                self.attrs(&mut node.attrs);
+                self.blocks(&mut node.children);
+            }
+            BlockKind::BlockQuote {
+                blocks,
+                attribution,
+                ..
+            } => {
+                self.blocks(blocks);
+                if let Some(attribution) = attribution {
+                    attribution.span = self.span(attribution.span);
+                    self.inlines(&mut attribution.content);
+                }
+            }
+            BlockKind::Inclusion { blocks, .. } => self.blocks(blocks),
+            BlockKind::CodeBlock { .. }
+            | BlockKind::Timeline { .. }
+            | BlockKind::MagicWord { .. }
+            | BlockKind::HorizontalRule
+            | BlockKind::Raw { .. } => {}
+        }
+    }
+
+    fn cell(&self, cell: &mut TableCell) {
+        cell.span = self.span(cell.span);
+        self.attrs(&mut cell.attrs);
+        self.blocks(&mut cell.blocks);
+    }
+
+    fn attrs(&self, attrs: &mut [HtmlAttr]) {
+        for attr in attrs {
+            attr.span = attr.span.map(|s| self.span(s));
+        }
+    }
+
+    fn inlines(&self, nodes: &mut [InlineNode]) {
+        for node in nodes {
+            self.inline(node);
+        }
+    }
+
+    fn inline(&self, node: &mut InlineNode) {
+        node.span = self.span(node.span);
+        match &mut node.kind {
+            InlineKind::Bold { content }
+            | InlineKind::Italic { content }
+            | InlineKind::BoldItalic { content }
+            | InlineKind::Inclusion { content, .. } => self.inlines(content),
+            InlineKind::InternalLink { link } => {
+                if let Some(text) = &mut link.text {
+                    self.inlines(text);
+                }
+            }
+            InlineKind::ExternalLink { link } => {
+                if let Some(text) = &mut link.text {
+                    self.inlines(text);
+                }
+            }
+            InlineKind::FileLink { link } => {
+                for param in &mut link.params {
+                    param.span = self.span(param.span);
+                    self.inlines(&mut param.content);
+                }
+            }
+            InlineKind::Ref { node } => {
+                self.attrs(&mut node.attrs);
+                if let Some(content) = &mut node.content {
+                    self.inlines(content);
+                }
+            }
+            InlineKind::HtmlTag { node } => {
+                self.attrs(&mut node.attrs);
+                self.inlines(&mut node.children);
+            }
+            InlineKind::Template { node } => {
+                for param in &mut node.params {
+                    param.span = self.span(param.span);
+                    self.inlines(&mut param.value);
+                }
+            }
+            InlineKind::TemplateArg { default, .. } => {
+                if let Some(default) = default {
+                    self.inlines(default);
+                }
+            }
+            InlineKind::Text { .. }
+            | InlineKind::MagicLink { .. }
+            | InlineKind::LineBreak
+            | InlineKind::Raw { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+    use crate::render::render_doc;
+
+    #[test]
+    fn merges_pages_under_their_titles() {
+        let perft = "'''[[Main Page|Home]] * [[Search]] * Perft'''\n\n'''Perft''' counts nodes.<ref>A</ref>\n\n=See also=\n* [[Divide]]\n\n[[Category:Debugging]]\n";
+        let results = "'''[[Main Page|Home]] * [[Search]] * [[Perft]] * Perft Results'''\n\nThe numbers.\n\n=Initial Position=\n{|\n| 20\n|}\n\n[[Category:Debugging]]\n[[Category:Tables]]\n";
+        let merged = merge_documents(vec![
+            parse_wiki(perft).document,
+            parse_wiki(results).document,
+        ]);
+
+        let md = render_doc(&merged);
+        assert!(md.contains("## See also"), "{md}");
+        assert!(
+            md.contains("## Perft Results\n\nThe numbers.\n\n### Initial Position"),
+            "{md}"
+        );
+        assert!(!md.contains("Home * Search * [[Perft]]"), "{md}");
+        let names: Vec<&str> = merged.categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Debugging", "Tables"]);
+
+        // spans map back to the page they came from.
+        assert_eq!(merged.parts.len(), 2);
+        assert_eq!(merged.parts[1].span.start, perft.len() as u64);
+        let table = merged
+            .blocks
+            .iter()
+            .find(|b| matches!(b.kind, BlockKind::Table { .. }))
+            .unwrap();
+        let (part, span) = merged.part_of(table.span).unwrap();
+        assert_eq!(part, 1);
+        assert!(results[span.start as usize..span.end as usize].starts_with("{|"));
+    }
+}
@@ -12,13 +12,19 @@
 
 mod diagnostic;
 mod envelope;
+mod merge;
 mod nodes;
 mod span;
+mod trivia;
+mod walk;
 
 pub use diagnostic::*;
 pub use envelope::*;
+pub use merge::*;
 pub use nodes::*;
 pub use span::*;
+pub use trivia::*;
+pub use walk::*;
 
 /// JSON schema version for the AST envelope.
 ///
@@ -46,7 +52,7 @@ mod tests {
                     content: vec![InlineNode {
                         span: Span::new(1, 11),
                         kind: InlineKind::Text {
-                            value: "Title".to_string(),
+                            value: "Title".into(),
                         },
                     }],
                 },
@@ -57,6 +63,9 @@ mod tests {
                 sort_key: Some("Thompson".to_string()),
             }],
             redirect: None,
+            breadcrumb: None,
+            trivia: vec![],
+            parts: vec![],
         };
 
         let ast = AstFile {
@@ -78,6 +87,7 @@ mod tests {
                 message: "example diagnostic".to_string(),
                 span: Some(Span::new(5, 10)),
                 notes: vec!["note".to_string()],
+                occurrences: 3,
             }],
             document: doc,
         };
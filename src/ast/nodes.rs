@@ -1,14 +1,18 @@
-use crate::ast::Span;
+use crate::ast::{DocumentPart, Span, Trivia};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Root AST node for a parsed wikitext document.
+///
+/// Text nodes borrow from the parsed source (`'a`) instead of copying it;
+/// [`Document::into_owned`] detaches a tree, e.g. for the JSON envelope.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Document {
+pub struct Document<'a> {
     /// Span covering the entire document.
     pub span: Span,
 
     #[serde(default)]
-    pub blocks: Vec<BlockNode>,
+    pub blocks: Vec<BlockNode<'a>>,
 
     /// Categories (e.g., `[[Category:Name|sort]]`) captured as metadata.
     ///
@@ -20,6 +24,25 @@ pub struct Document {
     /// Redirect target if the page is a redirect.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub redirect: Option<Redirect>,
+
+    /// The `Home * People * Name` navigation line at the top of the page.
+    ///
+    /// Its paragraph stays in `blocks` (so spans and lossless round trips are
+    /// unaffected); renderers restyle or skip the block at the breadcrumb's span.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breadcrumb: Option<Breadcrumb<'a>>,
+
+    /// Source formatting recorded by the lossless parse mode.
+    ///
+    /// Empty unless the document was produced by `parse::parse_wiki_lossless`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trivia: Vec<Trivia>,
+
+    /// The documents this one was combined from, in order.
+    ///
+    /// Empty unless the document was produced by [`merge_documents`](crate::ast::merge_documents).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<DocumentPart>,
 }
 
 /// A category membership tag, e.g. `[[Category:Chess Programmer|Thompson]]`.
@@ -32,6 +55,27 @@ pub struct CategoryTag {
     pub sort_key: Option<String>,
 }
 
+/// A breadcrumb, e.g. `'''[[Main Page|Home]] * [[People]] * Barend Swets'''`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Breadcrumb<'a> {
+    /// Span of the breadcrumb paragraph.
+    pub span: Span,
+
+    /// The linked steps of the path, in order (`Main Page`, `People`).
+    pub links: Vec<InternalLink<'a>>,
+
+    /// Unlinked text after the last link, usually the page's own title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+impl Breadcrumb<'_> {
+    /// True for a block that is this breadcrumb's paragraph.
+    pub fn is_block(&self, block: &BlockNode) -> bool {
+        block.span == self.span && matches!(block.kind, BlockKind::Paragraph { .. })
+    }
+}
+
 /// Redirect marker, e.g. `#REDIRECT [[Target]]`.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Redirect {
@@ -44,35 +88,35 @@ pub struct Redirect {
 
 /// A block node with a source span and a tagged kind.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct BlockNode {
+pub struct BlockNode<'a> {
     pub span: Span,
 
     #[serde(flatten)]
-    pub kind: BlockKind,
+    pub kind: BlockKind<'a>,
 }
 
 /// Block-level node kinds.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
-pub enum BlockKind {
+pub enum BlockKind<'a> {
     Heading {
         /// Heading level (1..=6). Level 1 is allowed.
         level: u8,
-        content: Vec<InlineNode>,
+        content: Vec<InlineNode<'a>>,
     },
 
     Paragraph {
-        content: Vec<InlineNode>,
+        content: Vec<InlineNode<'a>>,
     },
 
     /// A hierarchical list block.
     List {
-        items: Vec<ListItem>,
+        items: Vec<ListItem<'a>>,
     },
 
     /// A MediaWiki table.
     Table {
-        table: Table,
+        table: Table<'a>,
     },
 
     /// A fenced or otherwise verbatim `code`/`pre` block.
@@ -80,14 +124,26 @@ pub enum BlockKind {
         block: CodeBlock,
     },
 
+    /// A `<poem>` block: verse whose line breaks are kept.
+    Poem {
+        /// One entry per source line; an empty line separates stanzas.
+        #[serde(default)]
+        lines: Vec<Vec<InlineNode<'a>>>,
+    },
+
+    /// An EasyTimeline chart (`<timeline>...</timeline>`).
+    Timeline {
+        timeline: Timeline,
+    },
+
     /// A placeholder for `<references />`.
     References {
-        node: ReferencesNode,
+        node: ReferencesNode<'a>,
     },
 
     /// A generic HTML-ish block tag (e.g. `<div>...</div>`).
     HtmlBlock {
-        node: HtmlBlock,
+        node: HtmlBlock<'a>,
     },
 
     /// A magic word like `__TOC__`.
@@ -100,7 +156,25 @@ pub enum BlockKind {
 
     /// A blockquote, typically from wikitext indentation or explicit HTML.
     BlockQuote {
-        blocks: Vec<BlockNode>,
+        blocks: Vec<BlockNode<'a>>,
+
+        /// The intro line naming the quote's source (`By [[Robert Hyatt]], 1997:`),
+        /// when the parser found one directly above the quote, or the `<cite>` closing
+        /// an HTML quote.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attribution: Option<QuoteAttribution<'a>>,
+
+        /// True for an HTML `<blockquote>`, false for leading-space lines.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        html: bool,
+    },
+
+    /// A `<noinclude>`, `<includeonly>`, or `<onlyinclude>` section.
+    Inclusion {
+        mode: InclusionMode,
+
+        #[serde(default)]
+        blocks: Vec<BlockNode<'a>>,
     },
 
     /// Unparsed or unsupported block text preserved for round-tripping/debug.
@@ -109,15 +183,93 @@ pub enum BlockKind {
     },
 }
 
+impl BlockKind<'_> {
+    /// The `type` tag this kind is serialized with (`"code_block"`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockKind::Heading { .. } => "heading",
+            BlockKind::Paragraph { .. } => "paragraph",
+            BlockKind::List { .. } => "list",
+            BlockKind::Table { .. } => "table",
+            BlockKind::CodeBlock { .. } => "code_block",
+            BlockKind::Poem { .. } => "poem",
+            BlockKind::Timeline { .. } => "timeline",
+            BlockKind::References { .. } => "references",
+            BlockKind::HtmlBlock { .. } => "html_block",
+            BlockKind::MagicWord { .. } => "magic_word",
+            BlockKind::HorizontalRule => "horizontal_rule",
+            BlockKind::BlockQuote { .. } => "block_quote",
+            BlockKind::Inclusion { .. } => "inclusion",
+            BlockKind::Raw { .. } => "raw",
+        }
+    }
+}
+
+/// The line introducing a quote, e.g. `By [[Robert Hyatt]], 1997:`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteAttribution<'a> {
+    pub span: Span,
+
+    /// The intro line as written, including the leading `By` and trailing `:`.
+    #[serde(default)]
+    pub content: Vec<InlineNode<'a>>,
+
+    /// True when the source follows the quote (`<cite>Ken Thompson</cite>`,
+    /// `— Ken Thompson`) instead of introducing it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trailing: bool,
+}
+
+/// Which inclusion-control tag a section is wrapped in. These decide what is
+/// shown on the page itself and what is included where the page is transcluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InclusionMode {
+    /// `<noinclude>`: shown on the page, left out when transcluded.
+    NoInclude,
+    /// `<includeonly>`: hidden on the page, included when transcluded.
+    IncludeOnly,
+    /// `<onlyinclude>`: shown on the page; if a page has any, only these parts
+    /// are included when it is transcluded.
+    OnlyInclude,
+}
+
+impl InclusionMode {
+    /// The tag name (`"noinclude"`).
+    pub fn tag(self) -> &'static str {
+        match self {
+            InclusionMode::NoInclude => "noinclude",
+            InclusionMode::IncludeOnly => "includeonly",
+            InclusionMode::OnlyInclude => "onlyinclude",
+        }
+    }
+
+    /// The mode of a tag name, ignoring case.
+    pub fn from_tag(name: &str) -> Option<Self> {
+        [
+            InclusionMode::NoInclude,
+            InclusionMode::IncludeOnly,
+            InclusionMode::OnlyInclude,
+        ]
+        .into_iter()
+        .find(|mode| name.eq_ignore_ascii_case(mode.tag()))
+    }
+
+    /// Whether the content is shown when the page itself is rendered.
+    pub fn shown_on_page(self) -> bool {
+        self != InclusionMode::IncludeOnly
+    }
+}
+
 /// A list item.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct ListItem {
+pub struct ListItem<'a> {
     pub span: Span,
     pub marker: ListMarker,
 
     /// Blocks that make up this list item's content.
     #[serde(default)]
-    pub blocks: Vec<BlockNode>,
+    pub blocks: Vec<BlockNode<'a>>,
 }
 
 /// List marker types in wikitext.
@@ -157,23 +309,90 @@ pub enum CodeBlockKind {
     LeadingSpace,
 }
 
+/// An EasyTimeline script. The settings and bars the renderer understands are
+/// parsed out; everything else is only kept in `source`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// The script between the tags, as written.
+    pub source: String,
+
+    /// `DateFormat`, e.g. `yyyy` or `dd/mm/yyyy`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+
+    /// `Period = from:1990 till:2010`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<TimelinePeriod>,
+
+    /// The `BarData` bars, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bars: Vec<TimelineBar>,
+
+    /// The `PlotData` bars and events, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<TimelineEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelinePeriod {
+    pub from: String,
+    pub till: String,
+}
+
+/// A `BarData` line: `bar:SF text:"Stockfish"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineBar {
+    pub id: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// A `PlotData` line: a span (`from:1995 till:2005`) or a point (`at:2000`).
+/// Dates are as written; `from:start` and `till:end` refer to the period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// The bar the entry is drawn on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub till: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+
+    /// The label, with wiki links reduced to their text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
 /// Represents the `<references />` tag.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct ReferencesNode {
+pub struct ReferencesNode<'a> {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
+
+    /// List-defined references: the `<ref name=...>...</ref>` children of
+    /// `<references>...</references>`, used in the text as `<ref name=... />`.
+    /// Each node is an [`InlineKind::Ref`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refs: Vec<InlineNode<'a>>,
 }
 
 /// A generic HTML-ish block tag.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct HtmlBlock {
+pub struct HtmlBlock<'a> {
     pub name: String,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub children: Vec<BlockNode>,
+    pub children: Vec<BlockNode<'a>>,
 
     pub self_closing: bool,
 }
@@ -193,47 +412,116 @@ pub struct HtmlAttr {
 
 /// An inline node with a span and tagged kind.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct InlineNode {
+pub struct InlineNode<'a> {
     pub span: Span,
 
     #[serde(flatten)]
-    pub kind: InlineKind,
+    pub kind: InlineKind<'a>,
 }
 
 /// Inline-level node kinds.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
-pub enum InlineKind {
-    Text { value: String },
+pub enum InlineKind<'a> {
+    /// Plain text, usually a slice of the source.
+    Text {
+        value: Cow<'a, str>,
+    },
 
-    Bold { content: Vec<InlineNode> },
-    Italic { content: Vec<InlineNode> },
-    BoldItalic { content: Vec<InlineNode> },
+    Bold {
+        content: Vec<InlineNode<'a>>,
+    },
+    Italic {
+        content: Vec<InlineNode<'a>>,
+    },
+    BoldItalic {
+        content: Vec<InlineNode<'a>>,
+    },
 
-    InternalLink { link: InternalLink },
-    ExternalLink { link: ExternalLink },
+    InternalLink {
+        link: InternalLink<'a>,
+    },
+    ExternalLink {
+        link: ExternalLink<'a>,
+    },
 
     /// `[[File:...|...]]` / `[[Image:...|...]]` / `[[Media:...|...]]`.
-    FileLink { link: FileLink },
+    FileLink {
+        link: FileLink<'a>,
+    },
+
+    /// A bare `ISBN ...`, `RFC ...`, or `PMID ...` token, which MediaWiki links
+    /// automatically.
+    MagicLink {
+        link: MagicLink,
+    },
 
     /// `<br>` / `<br/>`.
     LineBreak,
 
     /// `<ref ...>...</ref>` or `<ref ... />`.
-    Ref { node: RefNode },
+    Ref {
+        node: RefNode<'a>,
+    },
 
     /// Generic HTML-ish inline tag, e.g. `<span id=...></span>`.
-    HtmlTag { node: HtmlTag },
+    HtmlTag {
+        node: HtmlTag<'a>,
+    },
 
     /// `{{...}}` templates and parser functions.
-    Template { node: TemplateInvocation },
+    Template {
+        node: TemplateInvocation<'a>,
+    },
+
+    /// A `{{{name}}}` or `{{{1|default}}}` parameter placeholder in a template's
+    /// (or transcluded page's) body.
+    TemplateArg {
+        /// The parameter name or position (`1`), trimmed.
+        name: String,
+
+        /// The content used when the parameter isn't given.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<Vec<InlineNode<'a>>>,
+    },
+
+    /// A `<noinclude>`, `<includeonly>`, or `<onlyinclude>` section inside a line.
+    Inclusion {
+        mode: InclusionMode,
+        content: Vec<InlineNode<'a>>,
+    },
 
     /// Unparsed or unsupported inline content preserved for debug.
-    Raw { text: String },
+    Raw {
+        text: String,
+    },
+}
+
+impl InlineKind<'_> {
+    /// The `type` tag this kind is serialized with (`"internal_link"`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            InlineKind::Text { .. } => "text",
+            InlineKind::Bold { .. } => "bold",
+            InlineKind::Italic { .. } => "italic",
+            InlineKind::BoldItalic { .. } => "bold_italic",
+            InlineKind::InternalLink { .. } => "internal_link",
+            InlineKind::ExternalLink { .. } => "external_link",
+            InlineKind::FileLink { .. } => "file_link",
+            InlineKind::MagicLink { .. } => "magic_link",
+            InlineKind::LineBreak => "line_break",
+            InlineKind::Ref { .. } => "ref",
+            InlineKind::HtmlTag { .. } => "html_tag",
+            InlineKind::Template { .. } => "template",
+            InlineKind::TemplateArg { .. } => "template_arg",
+            InlineKind::Inclusion { .. } => "inclusion",
+            InlineKind::Raw { .. } => "raw",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct InternalLink {
+pub struct InternalLink<'a> {
     /// Raw target text inside `[[...]]`, excluding the optional label.
     pub target: String,
 
@@ -243,19 +531,38 @@ pub struct InternalLink {
 
     /// Optional label (after `|`).
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub text: Option<Vec<InlineNode>>,
+    pub text: Option<Vec<InlineNode<'a>>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct ExternalLink {
+pub struct ExternalLink<'a> {
     pub url: String,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub text: Option<Vec<InlineNode>>,
+    pub text: Option<Vec<InlineNode<'a>>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct FileLink {
+pub struct MagicLink {
+    pub kind: MagicLinkKind,
+
+    /// The identifier without separators: `0201896834` for `ISBN 0-201-89683-4`.
+    pub id: String,
+
+    /// The token as written, e.g. `ISBN 0-201-89683-4`.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MagicLinkKind {
+    Isbn,
+    Rfc,
+    Pmid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLink<'a> {
     pub namespace: FileNamespace,
     pub target: String,
 
@@ -264,7 +571,7 @@ pub struct FileLink {
     /// We intentionally keep these as a list of parsed inline fragments rather
     /// than prematurely classifying them into "options" vs. "caption".
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub params: Vec<FileParam>,
+    pub params: Vec<FileParam<'a>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -276,41 +583,55 @@ pub enum FileNamespace {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct FileParam {
+pub struct FileParam<'a> {
     pub span: Span,
-    pub content: Vec<InlineNode>,
+    pub content: Vec<InlineNode<'a>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RefNode {
+pub struct RefNode<'a> {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub content: Option<Vec<InlineNode>>,
+    pub content: Option<Vec<InlineNode<'a>>>,
 
     pub self_closing: bool,
 }
 
+impl RefNode<'_> {
+    /// The `name` attribute, which ties uses of a reference to its definition.
+    pub fn name(&self) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case("name"))
+            .and_then(|a| a.value.as_deref())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct HtmlTag {
+pub struct HtmlTag<'a> {
     pub name: String,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub children: Vec<InlineNode>,
+    pub children: Vec<InlineNode<'a>>,
 
     pub self_closing: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TemplateInvocation {
+pub struct TemplateInvocation<'a> {
     pub name: TemplateName,
 
+    /// Parameters in source order. Empty positional parameters are kept (with an
+    /// empty `value`), so `{{a||c}}` has three and `c` stays parameter 3.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub params: Vec<TemplateParam>,
+    pub params: Vec<TemplateParam<'a>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -329,7 +650,7 @@ pub enum TemplateNameKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TemplateParam {
+pub struct TemplateParam<'a> {
     pub span: Span,
 
     /// Named parameter key (left of `=`), if present.
@@ -337,7 +658,7 @@ pub struct TemplateParam {
     pub name: Option<String>,
 
     /// Parameter value as parsed inline content.
-    pub value: Vec<InlineNode>,
+    pub value: Vec<InlineNode<'a>>,
 }
 
 /* -----------------------------
@@ -345,40 +666,40 @@ pub struct TemplateParam {
  * ----------------------------- */
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Table {
+pub struct Table<'a> {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub caption: Option<TableCaption>,
+    pub caption: Option<TableCaption<'a>>,
 
     #[serde(default)]
-    pub rows: Vec<TableRow>,
+    pub rows: Vec<TableRow<'a>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TableCaption {
+pub struct TableCaption<'a> {
     pub span: Span,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
-    pub content: Vec<InlineNode>,
+    pub content: Vec<InlineNode<'a>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TableRow {
+pub struct TableRow<'a> {
     pub span: Span,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<HtmlAttr>,
 
     #[serde(default)]
-    pub cells: Vec<TableCell>,
+    pub cells: Vec<TableCell<'a>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TableCell {
+pub struct TableCell<'a> {
     pub span: Span,
     pub kind: TableCellKind,
 
@@ -393,7 +714,7 @@ pub struct TableCell {
 
     /// Cell content as nested blocks (tables and lists can nest).
     #[serde(default)]
-    pub blocks: Vec<BlockNode>,
+    pub blocks: Vec<BlockNode<'a>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -402,3 +723,265 @@ pub enum TableCellKind {
     Header,
     Data,
 }
+
+/* -----------------------------
+ * Owned conversion
+ * ----------------------------- */
+
+// The parser borrows text from the source (`Cow::Borrowed`); these detach a tree
+// from it, e.g. to keep it past the source or to write it out as JSON.
+
+fn owned_blocks(blocks: Vec<BlockNode<'_>>) -> Vec<BlockNode<'static>> {
+    blocks.into_iter().map(BlockNode::into_owned).collect()
+}
+
+fn owned_inlines(nodes: Vec<InlineNode<'_>>) -> Vec<InlineNode<'static>> {
+    nodes.into_iter().map(InlineNode::into_owned).collect()
+}
+
+impl Document<'_> {
+    /// This document with every borrowed string copied, so it outlives the source.
+    pub fn into_owned(self) -> Document<'static> {
+        Document {
+            span: self.span,
+            blocks: owned_blocks(self.blocks),
+            categories: self.categories,
+            redirect: self.redirect,
+            breadcrumb: self.breadcrumb.map(Breadcrumb::into_owned),
+            trivia: self.trivia,
+            parts: self.parts,
+        }
+    }
+}
+
+impl Breadcrumb<'_> {
+    pub fn into_owned(self) -> Breadcrumb<'static> {
+        Breadcrumb {
+            span: self.span,
+            links: self
+                .links
+                .into_iter()
+                .map(InternalLink::into_owned)
+                .collect(),
+            current: self.current,
+        }
+    }
+}
+
+impl BlockNode<'_> {
+    pub fn into_owned(self) -> BlockNode<'static> {
+        BlockNode {
+            span: self.span,
+            kind: self.kind.into_owned(),
+        }
+    }
+}
+
+impl BlockKind<'_> {
+    pub fn into_owned(self) -> BlockKind<'static> {
+        match self {
+            BlockKind::Heading { level, content } => BlockKind::Heading {
+                level,
+                content: owned_inlines(content),
+            },
+            BlockKind::Paragraph { content } => BlockKind::Paragraph {
+                content: owned_inlines(content),
+            },
+            BlockKind::List { items } => BlockKind::List {
+                items: items.into_iter().map(ListItem::into_owned).collect(),
+            },
+            BlockKind::Table { table } => BlockKind::Table {
+                table: table.into_owned(),
+            },
+            BlockKind::CodeBlock { block } => BlockKind::CodeBlock { block },
+            BlockKind::Poem { lines } => BlockKind::Poem {
+                lines: lines.into_iter().map(owned_inlines).collect(),
+            },
+            BlockKind::Timeline { timeline } => BlockKind::Timeline { timeline },
+            BlockKind::References { node } => BlockKind::References {
+                node: ReferencesNode {
+                    attrs: node.attrs,
+                    refs: owned_inlines(node.refs),
+                },
+            },
+            BlockKind::HtmlBlock { node } => BlockKind::HtmlBlock {
+                node: HtmlBlock {
+                    name: node.name,
+                    attrs: node.attrs,
+                    children: owned_blocks(node.children),
+                    self_closing: node.self_closing,
+                },
+            },
+            BlockKind::MagicWord { name } => BlockKind::MagicWord { name },
+            BlockKind::HorizontalRule => BlockKind::HorizontalRule,
+            BlockKind::BlockQuote {
+                blocks,
+                attribution,
+                html,
+            } => BlockKind::BlockQuote {
+                blocks: owned_blocks(blocks),
+                attribution: attribution.map(|a| QuoteAttribution {
+                    span: a.span,
+                    content: owned_inlines(a.content),
+                    trailing: a.trailing,
+                }),
+                html,
+            },
+            BlockKind::Inclusion { mode, blocks } => BlockKind::Inclusion {
+                mode,
+                blocks: owned_blocks(blocks),
+            },
+            BlockKind::Raw { text } => BlockKind::Raw { text },
+        }
+    }
+}
+
+impl ListItem<'_> {
+    pub fn into_owned(self) -> ListItem<'static> {
+        ListItem {
+            span: self.span,
+            marker: self.marker,
+            blocks: owned_blocks(self.blocks),
+        }
+    }
+}
+
+impl InlineNode<'_> {
+    pub fn into_owned(self) -> InlineNode<'static> {
+        InlineNode {
+            span: self.span,
+            kind: self.kind.into_owned(),
+        }
+    }
+}
+
+impl InlineKind<'_> {
+    pub fn into_owned(self) -> InlineKind<'static> {
+        match self {
+            InlineKind::Text { value } => InlineKind::Text {
+                value: Cow::Owned(value.into_owned()),
+            },
+            InlineKind::Bold { content } => InlineKind::Bold {
+                content: owned_inlines(content),
+            },
+            InlineKind::Italic { content } => InlineKind::Italic {
+                content: owned_inlines(content),
+            },
+            InlineKind::BoldItalic { content } => InlineKind::BoldItalic {
+                content: owned_inlines(content),
+            },
+            InlineKind::InternalLink { link } => InlineKind::InternalLink {
+                link: link.into_owned(),
+            },
+            InlineKind::ExternalLink { link } => InlineKind::ExternalLink {
+                link: ExternalLink {
+                    url: link.url,
+                    text: link.text.map(owned_inlines),
+                },
+            },
+            InlineKind::FileLink { link } => InlineKind::FileLink {
+                link: FileLink {
+                    namespace: link.namespace,
+                    target: link.target,
+                    params: link
+                        .params
+                        .into_iter()
+                        .map(|p| FileParam {
+                            span: p.span,
+                            content: owned_inlines(p.content),
+                        })
+                        .collect(),
+                },
+            },
+            InlineKind::MagicLink { link } => InlineKind::MagicLink { link },
+            InlineKind::LineBreak => InlineKind::LineBreak,
+            InlineKind::Ref { node } => InlineKind::Ref {
+                node: RefNode {
+                    attrs: node.attrs,
+                    content: node.content.map(owned_inlines),
+                    self_closing: node.self_closing,
+                },
+            },
+            InlineKind::HtmlTag { node } => InlineKind::HtmlTag {
+                node: HtmlTag {
+                    name: node.name,
+                    attrs: node.attrs,
+                    children: owned_inlines(node.children),
+                    self_closing: node.self_closing,
+                },
+            },
+            InlineKind::Template { node } => InlineKind::Template {
+                node: node.into_owned(),
+            },
+            InlineKind::TemplateArg { name, default } => InlineKind::TemplateArg {
+                name,
+                default: default.map(owned_inlines),
+            },
+            InlineKind::Inclusion { mode, content } => InlineKind::Inclusion {
+                mode,
+                content: owned_inlines(content),
+            },
+            InlineKind::Raw { text } => InlineKind::Raw { text },
+        }
+    }
+}
+
+impl InternalLink<'_> {
+    pub fn into_owned(self) -> InternalLink<'static> {
+        InternalLink {
+            target: self.target,
+            anchor: self.anchor,
+            text: self.text.map(owned_inlines),
+        }
+    }
+}
+
+impl TemplateInvocation<'_> {
+    pub fn into_owned(self) -> TemplateInvocation<'static> {
+        TemplateInvocation {
+            name: self.name,
+            params: self
+                .params
+                .into_iter()
+                .map(|p| TemplateParam {
+                    span: p.span,
+                    name: p.name,
+                    value: owned_inlines(p.value),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Table<'_> {
+    pub fn into_owned(self) -> Table<'static> {
+        Table {
+            attrs: self.attrs,
+            caption: self.caption.map(|c| TableCaption {
+                span: c.span,
+                attrs: c.attrs,
+                content: owned_inlines(c.content),
+            }),
+            rows: self
+                .rows
+                .into_iter()
+                .map(|r| TableRow {
+                    span: r.span,
+                    attrs: r.attrs,
+                    cells: r
+                        .cells
+                        .into_iter()
+                        .map(|c| TableCell {
+                            span: c.span,
+                            kind: c.kind,
+                            attrs: c.attrs,
+                            colspan: c.colspan,
+                            rowspan: c.rowspan,
+                            blocks: owned_blocks(c.blocks),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
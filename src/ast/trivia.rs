@@ -0,0 +1,54 @@
+use crate::ast::Span;
+use serde::{Deserialize, Serialize};
+
+/// Which kind of top-level item a [`Trivia`] record belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriviaKind {
+    /// An entry in `Document::blocks`.
+    Block,
+    /// An entry in `Document::categories`.
+    Category,
+    /// The `Document::redirect` marker.
+    Redirect,
+    /// Source text after the last top-level item.
+    Eof,
+}
+
+/// Source formatting details recorded by the lossless parse mode.
+///
+/// Trivia is keyed by the `span` of the top-level node it belongs to, so the
+/// AST can be edited (nodes inserted, removed, or reordered) without the
+/// remaining trivia drifting onto the wrong node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+
+    /// Span of the node this trivia belongs to (as produced by the parser).
+    pub span: Span,
+
+    /// Raw source text between the previous top-level item and this one
+    /// (blank lines, indentation, line endings).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub leading: String,
+
+    /// Number of blank lines contained in `leading`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub blank_lines: u32,
+
+    /// Original source text of the node, recorded only when it differs from the
+    /// canonical wikitext serialization (e.g. `== Title ==` vs `==Title==`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+
+    /// MD5 of the canonical serialization at parse time.
+    ///
+    /// The serializer only trusts `original` while the node still serializes to
+    /// the same canonical text; edited nodes fall back to canonical output.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub fingerprint: String,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
@@ -0,0 +1,477 @@
+use crate::ast::{
+    BlockKind, BlockNode, Breadcrumb, Document, ExternalLink, InlineKind, InlineNode, InternalLink,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Call `f` for every block in `blocks`, depth-first, including blocks nested in
+/// lists, tables, quotes, and HTML blocks.
+pub fn walk_blocks<'a, 's>(blocks: &'a [BlockNode<'s>], f: &mut impl FnMut(&'a BlockNode<'s>)) {
+    for block in blocks {
+        f(block);
+        match &block.kind {
+            BlockKind::List { items } => {
+                for item in items {
+                    walk_blocks(&item.blocks, f);
+                }
+            }
+            BlockKind::Table { table } => {
+                for row in &table.rows {
+                    for cell in &row.cells {
+                        walk_blocks(&cell.blocks, f);
+                    }
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. } => walk_blocks(blocks, f),
+            BlockKind::HtmlBlock { node } => walk_blocks(&node.children, f),
+            BlockKind::Inclusion { blocks, .. } => walk_blocks(blocks, f),
+            BlockKind::Heading { .. }
+            | BlockKind::Paragraph { .. }
+            | BlockKind::CodeBlock { .. }
+            | BlockKind::Poem { .. }
+            | BlockKind::Timeline { .. }
+            | BlockKind::References { .. }
+            | BlockKind::MagicWord { .. }
+            | BlockKind::HorizontalRule
+            | BlockKind::Raw { .. } => {}
+        }
+    }
+}
+
+/// Call `f` for every inline node in `blocks`, depth-first, including link
+/// labels, ref contents, template parameters, and table captions.
+pub fn walk_inlines<'a, 's>(blocks: &'a [BlockNode<'s>], f: &mut impl FnMut(&'a InlineNode<'s>)) {
+    walk_blocks(blocks, &mut |block| match &block.kind {
+        BlockKind::Heading { content, .. } | BlockKind::Paragraph { content } => {
+            walk_inline_nodes(content, f)
+        }
+        BlockKind::Table { table } => {
+            if let Some(caption) = &table.caption {
+                walk_inline_nodes(&caption.content, f);
+            }
+        }
+        BlockKind::BlockQuote {
+            attribution: Some(attribution),
+            ..
+        } => walk_inline_nodes(&attribution.content, f),
+        BlockKind::References { node } => walk_inline_nodes(&node.refs, f),
+        BlockKind::Poem { lines } => {
+            for line in lines {
+                walk_inline_nodes(line, f);
+            }
+        }
+        _ => {}
+    });
+}
+
+/// Call `f` on every list of inline nodes in `blocks` (paragraphs, headings,
+/// captions, link labels, ref bodies, template parameters, ...). Nested lists come
+/// before the list containing them, so whatever `f` inserts isn't visited.
+pub fn walk_inline_lists_mut<'s>(
+    blocks: &mut [BlockNode<'s>],
+    f: &mut impl FnMut(&mut Vec<InlineNode<'s>>),
+) {
+    for block in blocks {
+        match &mut block.kind {
+            BlockKind::Heading { content, .. } | BlockKind::Paragraph { content } => {
+                inline_list_mut(content, f)
+            }
+            BlockKind::List { items } => {
+                for item in items {
+                    walk_inline_lists_mut(&mut item.blocks, f);
+                }
+            }
+            BlockKind::Table { table } => {
+                if let Some(caption) = &mut table.caption {
+                    inline_list_mut(&mut caption.content, f);
+                }
+                for row in &mut table.rows {
+                    for cell in &mut row.cells {
+                        walk_inline_lists_mut(&mut cell.blocks, f);
+                    }
+                }
+            }
+            BlockKind::BlockQuote {
+                blocks,
+                attribution,
+                ..
+            } => {
+                walk_inline_lists_mut(blocks, f);
+                if let Some(attribution) = attribution {
+                    inline_list_mut(&mut attribution.content, f);
+                }
+            }
+            BlockKind::HtmlBlock { node } => walk_inline_lists_mut(&mut node.children, f),
+            BlockKind::Inclusion { blocks, .. } => walk_inline_lists_mut(blocks, f),
+            BlockKind::References { node } => inline_list_mut(&mut node.refs, f),
+            BlockKind::Poem { lines } => {
+                for line in lines {
+                    inline_list_mut(line, f);
+                }
+            }
+            BlockKind::CodeBlock { .. }
+            | BlockKind::Timeline { .. }
+            | BlockKind::MagicWord { .. }
+            | BlockKind::HorizontalRule
+            | BlockKind::Raw { .. } => {}
+        }
+    }
+}
+
+fn inline_list_mut<'s>(
+    nodes: &mut Vec<InlineNode<'s>>,
+    f: &mut impl FnMut(&mut Vec<InlineNode<'s>>),
+) {
+    for node in nodes.iter_mut() {
+        match &mut node.kind {
+            InlineKind::Bold { content }
+            | InlineKind::Italic { content }
+            | InlineKind::BoldItalic { content }
+            | InlineKind::Inclusion { content, .. } => inline_list_mut(content, f),
+            InlineKind::InternalLink { link } => {
+                if let Some(text) = &mut link.text {
+                    inline_list_mut(text, f);
+                }
+            }
+            InlineKind::ExternalLink { link } => {
+                if let Some(text) = &mut link.text {
+                    inline_list_mut(text, f);
+                }
+            }
+            InlineKind::FileLink { link } => {
+                for p in &mut link.params {
+                    inline_list_mut(&mut p.content, f);
+                }
+            }
+            InlineKind::Ref { node } => {
+                if let Some(content) = &mut node.content {
+                    inline_list_mut(content, f);
+                }
+            }
+            InlineKind::HtmlTag { node } => inline_list_mut(&mut node.children, f),
+            InlineKind::Template { node } => {
+                for p in &mut node.params {
+                    inline_list_mut(&mut p.value, f);
+                }
+            }
+            InlineKind::TemplateArg { default, .. } => {
+                if let Some(default) = default {
+                    inline_list_mut(default, f);
+                }
+            }
+            InlineKind::Text { .. }
+            | InlineKind::MagicLink { .. }
+            | InlineKind::LineBreak
+            | InlineKind::Raw { .. } => {}
+        }
+    }
+    f(nodes);
+}
+
+/// The content of every list-defined reference (a named `<ref>` inside
+/// `<references>...</references>`) in `blocks`, by name.
+pub fn list_defined_refs<'a>(blocks: &[BlockNode<'a>]) -> HashMap<String, Vec<InlineNode<'a>>> {
+    let mut defs = HashMap::new();
+    walk_blocks(blocks, &mut |block| {
+        let BlockKind::References { node } = &block.kind else {
+            return;
+        };
+        for r in &node.refs {
+            if let InlineKind::Ref { node } = &r.kind
+                && let (Some(name), Some(content)) = (node.name(), &node.content)
+            {
+                defs.entry(name.to_string())
+                    .or_insert_with(|| content.clone());
+            }
+        }
+    });
+    defs
+}
+
+/// Call `f` for each node in `nodes` and everything nested inside it.
+pub fn walk_inline_nodes<'a, 's>(
+    nodes: &'a [InlineNode<'s>],
+    f: &mut impl FnMut(&'a InlineNode<'s>),
+) {
+    for node in nodes {
+        f(node);
+        match &node.kind {
+            InlineKind::Bold { content }
+            | InlineKind::Italic { content }
+            | InlineKind::BoldItalic { content } => walk_inline_nodes(content, f),
+            InlineKind::InternalLink { link } => {
+                if let Some(text) = &link.text {
+                    walk_inline_nodes(text, f);
+                }
+            }
+            InlineKind::ExternalLink { link } => {
+                if let Some(text) = &link.text {
+                    walk_inline_nodes(text, f);
+                }
+            }
+            InlineKind::FileLink { link } => {
+                for p in &link.params {
+                    walk_inline_nodes(&p.content, f);
+                }
+            }
+            InlineKind::Ref { node } => {
+                if let Some(content) = &node.content {
+                    walk_inline_nodes(content, f);
+                }
+            }
+            InlineKind::HtmlTag { node } => walk_inline_nodes(&node.children, f),
+            InlineKind::Inclusion { content, .. } => walk_inline_nodes(content, f),
+            InlineKind::Template { node } => {
+                for p in &node.params {
+                    walk_inline_nodes(&p.value, f);
+                }
+            }
+            InlineKind::TemplateArg { default, .. } => {
+                if let Some(default) = default {
+                    walk_inline_nodes(default, f);
+                }
+            }
+            InlineKind::Text { .. }
+            | InlineKind::LineBreak
+            | InlineKind::MagicLink { .. }
+            | InlineKind::Raw { .. } => {}
+        }
+    }
+}
+
+/// The breadcrumb of a page: the first top-level paragraph that links to
+/// `Main Page`, as its links and the text after the last one.
+pub fn find_breadcrumb<'a>(blocks: &[BlockNode<'a>]) -> Option<Breadcrumb<'a>> {
+    blocks.iter().find_map(|block| {
+        let BlockKind::Paragraph { content } = &block.kind else {
+            return None;
+        };
+        let mut links: Vec<InternalLink<'a>> = Vec::new();
+        walk_inline_nodes(content, &mut |node| {
+            if let InlineKind::InternalLink { link } = &node.kind {
+                links.push(link.clone());
+            }
+        });
+        if !links
+            .iter()
+            .any(|l| l.target.eq_ignore_ascii_case("Main Page"))
+        {
+            return None;
+        }
+        let mut current = String::new();
+        trailing_text(content, &mut current);
+        let current = current
+            .trim_matches(|c: char| c.is_whitespace() || c == '*')
+            .trim()
+            .to_string();
+        Some(Breadcrumb {
+            span: block.span,
+            links,
+            current: (!current.is_empty()).then_some(current),
+        })
+    })
+}
+
+/// The plain text of `nodes` after the last internal link, looking inside
+/// formatting (the breadcrumb is usually bold).
+fn trailing_text(nodes: &[InlineNode], out: &mut String) {
+    let start = nodes
+        .iter()
+        .rposition(|n| matches!(n.kind, InlineKind::InternalLink { .. }))
+        .map_or(0, |i| i + 1);
+    for node in &nodes[start..] {
+        match &node.kind {
+            InlineKind::Bold { content }
+            | InlineKind::Italic { content }
+            | InlineKind::BoldItalic { content } => trailing_text(content, out),
+            _ => plain_text(std::slice::from_ref(node), out),
+        }
+    }
+}
+
+/// The number of raw blocks (source the parser couldn't classify), nested ones
+/// included. A rough measure of how well a page converts.
+pub fn raw_block_count(doc: &Document) -> usize {
+    let mut count = 0;
+    walk_blocks(&doc.blocks, &mut |block| {
+        if matches!(block.kind, BlockKind::Raw { .. }) {
+            count += 1;
+        }
+    });
+    count
+}
+
+/// Every `[[internal link]]` in the document, in source order.
+pub fn internal_links<'a>(doc: &'a Document) -> Vec<&'a InternalLink<'a>> {
+    let mut links = Vec::new();
+    walk_inlines(&doc.blocks, &mut |node| {
+        if let InlineKind::InternalLink { link } = &node.kind {
+            links.push(link);
+        }
+    });
+    links
+}
+
+/// Every `[https://... label]` external link in the document, in source order.
+pub fn external_links<'a>(doc: &'a Document) -> Vec<&'a ExternalLink<'a>> {
+    let mut links = Vec::new();
+    walk_inlines(&doc.blocks, &mut |node| {
+        if let InlineKind::ExternalLink { link } = &node.kind {
+            links.push(link);
+        }
+    });
+    links
+}
+
+/// The ids of the document's `<span id="...">` anchors, in source order: the
+/// targets of `[[#id]]` links that aren't headings.
+pub fn span_anchors<'a>(doc: &'a Document) -> Vec<&'a str> {
+    let mut anchors = Vec::new();
+    walk_inlines(&doc.blocks, &mut |node| {
+        if let InlineKind::HtmlTag { node } = &node.kind
+            && node.name.eq_ignore_ascii_case("span")
+            && let Some(id) = node
+                .attrs
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case("id"))
+                .and_then(|a| a.value.as_deref())
+        {
+            anchors.push(id);
+        }
+    });
+    anchors
+}
+
+/// The blocks under the heading reached by `heading_path`, up to the next heading
+/// of the same or a higher level. Each step matches a heading's plain text
+/// (case-insensitively) inside the previous step's section, at any depth, so
+/// `["Publications", "1990s"]` finds a "1990s" subsection of "Publications".
+/// The heading itself is not included. `None` if a step matches no heading.
+pub fn section_blocks<'a>(doc: &'a Document, heading_path: &[&str]) -> Option<&'a [BlockNode<'a>]> {
+    let mut section: &[BlockNode<'a>] = &doc.blocks;
+    for name in heading_path {
+        let name = name.trim().to_lowercase();
+        let (start, level) =
+            section
+                .iter()
+                .enumerate()
+                .find_map(|(i, block)| match &block.kind {
+                    BlockKind::Heading { level, content } => {
+                        let mut text = String::new();
+                        plain_text(content, &mut text);
+                        (text.trim().to_lowercase() == name).then_some((i, *level))
+                    }
+                    _ => None,
+                })?;
+        let rest = &section[start + 1..];
+        let end = rest
+            .iter()
+            .position(
+                |block| matches!(&block.kind, BlockKind::Heading { level: l, .. } if *l <= level),
+            )
+            .unwrap_or(rest.len());
+        section = &rest[..end];
+    }
+    Some(section)
+}
+
+/// The indices of the top-level "See also" section: its heading and the blocks up
+/// to the next heading of the same or a higher level.
+pub fn see_also_section(blocks: &[BlockNode]) -> Option<Range<usize>> {
+    let (start, level) = blocks
+        .iter()
+        .enumerate()
+        .find_map(|(i, block)| match &block.kind {
+            BlockKind::Heading { level, content } => {
+                let mut text = String::new();
+                plain_text(content, &mut text);
+                text.trim()
+                    .eq_ignore_ascii_case("see also")
+                    .then_some((i, *level))
+            }
+            _ => None,
+        })?;
+    let end = blocks[start + 1..]
+        .iter()
+        .position(|block| matches!(&block.kind, BlockKind::Heading { level: l, .. } if *l <= level))
+        .map_or(blocks.len(), |n| start + 1 + n);
+    Some(start..end)
+}
+
+/// Append the readable text of `nodes` to `out`: link labels (or targets), no
+/// markup, refs, templates, or images.
+pub fn plain_text(nodes: &[InlineNode], out: &mut String) {
+    for n in nodes {
+        match &n.kind {
+            InlineKind::Text { value } => out.push_str(value),
+            InlineKind::Raw { text } => out.push_str(text),
+            InlineKind::MagicLink { link } => out.push_str(&link.text),
+            InlineKind::Bold { content }
+            | InlineKind::Italic { content }
+            | InlineKind::BoldItalic { content } => plain_text(content, out),
+            InlineKind::InternalLink { link } => match &link.text {
+                Some(t) => plain_text(t, out),
+                None => out.push_str(&link.target.replace('_', " ")),
+            },
+            InlineKind::ExternalLink { link } => {
+                if let Some(t) = &link.text {
+                    plain_text(t, out);
+                }
+            }
+            InlineKind::HtmlTag { node } => plain_text(&node.children, out),
+            InlineKind::Inclusion { mode, content } => {
+                if mode.shown_on_page() {
+                    plain_text(content, out)
+                }
+            }
+            InlineKind::LineBreak => out.push(' '),
+            InlineKind::FileLink { .. }
+            | InlineKind::Ref { .. }
+            | InlineKind::Template { .. }
+            | InlineKind::TemplateArg { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn finds_links_in_nested_content() {
+        let src = "See [[Alpha]].\n\
+                   * item with '''[[Beta|b]]'''\n\
+                   {|\n|-\n| cell [[Gamma]]<ref>[[Delta]]</ref>\n|}\n";
+        let doc = parse_wiki(src).document;
+        let targets: Vec<&str> = internal_links(&doc)
+            .iter()
+            .map(|l| l.target.as_str())
+            .collect();
+        assert_eq!(targets, ["Alpha", "Beta", "Gamma", "Delta"]);
+    }
+
+    #[test]
+    fn finds_nested_sections() {
+        let src = "Intro.\n== Publications ==\nAll.\n=== 1990s ===\nOld.\n=== 2000s ===\nNew.\n== See also ==\nMore.\n";
+        let doc = parse_wiki(src).document;
+        assert_eq!(
+            section_blocks(&doc, &["publications"]).map(|b| b.len()),
+            Some(5)
+        );
+        let old = section_blocks(&doc, &["Publications", "1990s"]).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(
+            section_blocks(&doc, &["See also"]).map(|b| b.len()),
+            Some(1)
+        );
+        assert!(section_blocks(&doc, &["See also", "1990s"]).is_none());
+    }
+
+    #[test]
+    fn finds_span_anchors_anywhere() {
+        let src = "== <span id=\"H\"></span>Heading ==\n<span id=\"A\"></span>\nText.\n{|\n| <span id=\"C\">cell</span>\n|}\n";
+        let doc = parse_wiki(src).document;
+        assert_eq!(span_anchors(&doc), ["H", "A", "C"]);
+    }
+}
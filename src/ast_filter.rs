@@ -0,0 +1,85 @@
+//! External AST filters, in the spirit of pandoc's `--filter`.
+//!
+//! A filter is a command that reads an [`AstFile`] as JSON on stdin and writes
+//! the (possibly modified) file back as JSON on stdout. Filters run in order
+//! between parsing and rendering, so one-off corpus transformations can be
+//! written in any language without linking against this crate. A filter that
+//! exits unsuccessfully, or prints anything but an AST, fails the article; its
+//! stderr goes to ours.
+
+use crate::ast::AstFile;
+use crate::error::{BoxError, Error, Result};
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// A filter command: a program and its arguments. No shell is involved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AstFilter {
+    program: String,
+    args: Vec<String>,
+}
+
+impl AstFilter {
+    pub fn new(program: impl Into<String>) -> Self {
+        AstFilter {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Add an argument.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Parse a command line: the program and its arguments, separated by
+    /// whitespace (`python3 filters/strip_navboxes.py`).
+    pub fn parse(command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("empty filter command")?;
+        Ok(words.fold(AstFilter::new(program), AstFilter::arg))
+    }
+
+    /// Pipe `ast` through the command and read back the AST it prints.
+    pub fn run(&self, ast: &AstFile) -> Result<AstFile> {
+        let fail = |source: BoxError| Error::Filter {
+            article_id: ast.article_id.clone(),
+            command: self.to_string(),
+            source,
+        };
+        let input = serde_json::to_vec(ast).map_err(|e| fail(e.into()))?;
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| fail(e.into()))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // feed stdin from another thread: a filter may write output before it has
+        // read all of its input, and both pipes have limited buffers. A filter that
+        // exits without reading everything breaks the pipe, which is only a
+        // problem if its output is, so write errors are ignored.
+        let output = std::thread::scope(|scope| {
+            scope.spawn(move || stdin.write_all(&input));
+            child.wait_with_output()
+        })
+        .map_err(|e| fail(e.into()))?;
+        if !output.status.success() {
+            return Err(fail(format!("exited with {}", output.status).into()));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| fail(format!("invalid AST on stdout: {}", e).into()))
+    }
+}
+
+impl fmt::Display for AstFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+//! EPUB book builder.
+//!
+//! Collects a list of articles from the local wiki corpus, renders each one as an
+//! XHTML chapter (via the HTML backend), rewrites internal links between chapters
+//! to intra-book hrefs, optionally embeds the referenced images, and packages the
+//! result as a single EPUB 3 file.
+
+mod zip;
+
+use crate::driver::wiki_path_for;
+use crate::render::html::{self, HtmlOptions};
+use crate::title::Title;
+use crate::{Config, parse_file, sanitize_article_id};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use zip::ZipWriter;
+
+/// Options controlling EPUB output.
+#[derive(Debug, Clone)]
+pub struct BookOptions {
+    /// Book title (`dc:title`).
+    pub title: String,
+
+    /// Book author (`dc:creator`).
+    pub author: String,
+
+    /// Book language (`dc:language`).
+    pub language: String,
+
+    /// If true, download images referenced by the chapters and store them inside
+    /// the EPUB. Otherwise images keep pointing at the wiki.
+    pub embed_images: bool,
+
+    /// If true, fetch articles missing from the wiki root instead of failing.
+    pub fetch_missing: bool,
+
+    /// Base URL used for MediaWiki file resolution and links to articles that are
+    /// not part of the book.
+    pub mediawiki_base_url: String,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            title: "Chess Programming Wiki".to_string(),
+            author: "chessprogramming.org contributors".to_string(),
+            language: "en".to_string(),
+            embed_images: true,
+            fetch_missing: true,
+            mediawiki_base_url: "https://www.chessprogramming.org".to_string(),
+        }
+    }
+}
+
+const BOOK_CSS: &str = "body { font-family: serif; line-height: 1.4; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #aaa; padding: 0.2em 0.4em; }
+figure { margin: 1em 0; text-align: center; }
+img { max-width: 100%; }
+pre { white-space: pre-wrap; font-size: 0.85em; }";
+
+struct Chapter {
+    article_id: String,
+    title: String,
+    file_name: String,
+    body: String,
+}
+
+/// Parse a book index: one article title per line, in chapter order.
+///
+/// Blank lines and lines starting with `#` are ignored. Duplicate articles are
+/// only included the first time they appear.
+pub fn parse_book_index(text: &str) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(sanitize_article_id)
+        .filter(|id| seen.insert(id.clone()))
+        .collect()
+}
+
+/// What went into a book.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookReport {
+    pub chapters: usize,
+    /// Images stored inside the EPUB.
+    pub images: usize,
+}
+
+/// Build an EPUB from the given article titles, reading `.wiki` sources from the
+/// config's wiki directory (fetching missing ones with its fetcher, parsing with its
+/// parse options) and writing `out_path`.
+pub fn build_epub(
+    titles: &[String],
+    config: &Config,
+    out_path: &Path,
+    opts: &BookOptions,
+) -> Result<BookReport, Box<dyn Error>> {
+    if titles.is_empty() {
+        return Err("No articles given for the book.".into());
+    }
+
+    let ids: Vec<String> = titles.iter().map(|t| sanitize_article_id(t)).collect();
+
+    // every article in the book gets a chapter file; links between them stay inside the book.
+    let mut link_targets: HashMap<String, String> = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        link_targets
+            .entry(id.clone())
+            .or_insert_with(|| chapter_file_name(i));
+    }
+
+    let html_opts = HtmlOptions {
+        standalone: false,
+        css: None,
+        mediawiki_base_url: opts.mediawiki_base_url.clone(),
+        internal_link_prefix: format!("{}/", opts.mediawiki_base_url.trim_end_matches('/')),
+        internal_link_targets: link_targets,
+        ..Default::default()
+    };
+
+    let mut chapters: Vec<Chapter> = Vec::with_capacity(ids.len());
+    for (i, (raw_title, id)) in titles.iter().zip(&ids).enumerate() {
+        let wiki_path = wiki_path_for(&config.wiki_dir, id);
+        if !wiki_path.exists() {
+            if !opts.fetch_missing {
+                return Err(format!("Article source not found: {}", wiki_path.display()).into());
+            }
+            if let Some(parent) = wiki_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            config
+                .fetcher
+                .fetch_and_save(&Title::parse(raw_title).to_string(), &wiki_path)?;
+        }
+
+        let ast = parse_file(&wiki_path, &config.parse)?;
+        let title = id.replace('_', " ");
+        let chapter_opts = HtmlOptions {
+            title: Some(title.clone()),
+            ..html_opts.clone()
+        };
+        let body = html::render_doc(&ast.document, &chapter_opts);
+        chapters.push(Chapter {
+            article_id: id.clone(),
+            title,
+            file_name: chapter_file_name(i),
+            body: xhtml_entities(&body),
+        });
+    }
+
+    let mut images: Vec<(String, &'static str, Vec<u8>)> = Vec::new();
+    if opts.embed_images {
+        embed_images(&mut chapters, &mut images);
+    }
+
+    let file = fs::File::create(out_path)?;
+    let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+
+    // the mimetype entry must come first and be stored uncompressed.
+    zip.add_file("mimetype", b"application/epub+zip")?;
+    zip.add_file("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+    zip.add_file(
+        "OEBPS/content.opf",
+        content_opf(&chapters, &images, opts).as_bytes(),
+    )?;
+    zip.add_file("OEBPS/nav.xhtml", nav_xhtml(&chapters, opts).as_bytes())?;
+    zip.add_file("OEBPS/toc.ncx", toc_ncx(&chapters, opts).as_bytes())?;
+    zip.add_file("OEBPS/style.css", BOOK_CSS.as_bytes())?;
+    for ch in &chapters {
+        zip.add_file(
+            &format!("OEBPS/{}", ch.file_name),
+            chapter_xhtml(ch, opts).as_bytes(),
+        )?;
+    }
+    for (name, _, data) in &images {
+        zip.add_file(&format!("OEBPS/{}", name), data)?;
+    }
+    zip.finish()?;
+
+    tracing::info!(path = %out_path.display(), chapters = chapters.len(), images = images.len(), "wrote book");
+    Ok(BookReport {
+        chapters: chapters.len(),
+        images: images.len(),
+    })
+}
+
+fn chapter_file_name(index: usize) -> String {
+    format!("ch{:03}.xhtml", index + 1)
+}
+
+/// Download every remote `<img src>` and point it at a file inside the book.
+/// Images that fail to download keep their remote URL.
+fn embed_images(chapters: &mut [Chapter], images: &mut Vec<(String, &'static str, Vec<u8>)>) {
+    let mut by_url: HashMap<String, Option<String>> = HashMap::new();
+    for ch in chapters.iter_mut() {
+        let mut out = String::with_capacity(ch.body.len());
+        let mut rest = ch.body.as_str();
+        while let Some(pos) = rest.find("<img src=\"") {
+            let value_start = pos + "<img src=\"".len();
+            let Some(len) = rest[value_start..].find('"') else {
+                break;
+            };
+            out.push_str(&rest[..value_start]);
+            let url = html_escape::decode_html_entities(&rest[value_start..value_start + len])
+                .to_string();
+            let local = by_url
+                .entry(url.clone())
+                .or_insert_with(|| match download_image(&url) {
+                    Ok((media_type, ext, data)) => {
+                        let name = format!("images/img{:03}.{}", images.len() + 1, ext);
+                        images.push((name.clone(), media_type, data));
+                        Some(name)
+                    }
+                    Err(e) => {
+                        tracing::warn!(%url, error = %e, "could not embed image");
+                        None
+                    }
+                });
+            match local {
+                Some(name) => out.push_str(name),
+                None => out.push_str(&rest[value_start..value_start + len]),
+            }
+            rest = &rest[value_start + len..];
+        }
+        out.push_str(rest);
+        ch.body = out;
+    }
+}
+
+fn download_image(
+    url: &str,
+) -> Result<(&'static str, &'static str, Vec<u8>), crate::error::BoxError> {
+    let resp = crate::http_cache::get(url)?;
+    if !resp.is_success() {
+        return Err(format!("Request failed: {}", resp.status).into());
+    }
+    let bytes = resp.body;
+    let (media_type, ext) = image_media_type(&bytes).ok_or("unsupported image format")?;
+    Ok((media_type, ext, bytes))
+}
+
+/// Sniff the EPUB core media type of an image from its magic bytes.
+fn image_media_type(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if bytes.starts_with(b"<?xml") || bytes.starts_with(b"<svg") {
+        Some(("image/svg+xml", "svg"))
+    } else {
+        None
+    }
+}
+
+/// EPUB content documents are XML, so HTML named entities (`&nbsp;`, `&rarr;`)
+/// passed through from wikitext must become characters, and stray `&` must be escaped.
+fn xhtml_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('&') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let entity_len = after.find(';').filter(|&n| n > 0 && n <= 32).filter(|&n| {
+            let name = &after[..n];
+            name.starts_with('#') || name.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+        match entity_len {
+            Some(n) => {
+                let name = &after[..n];
+                if matches!(name, "amp" | "lt" | "gt" | "quot" | "apos") || name.starts_with('#') {
+                    out.push('&');
+                    out.push_str(name);
+                    out.push(';');
+                } else {
+                    let entity = &rest[pos..pos + n + 2];
+                    let decoded = html_escape::decode_html_entities(entity);
+                    if decoded == entity {
+                        out.push_str("&amp;");
+                        out.push_str(&entity[1..]);
+                    } else {
+                        out.push_str(&html_escape::encode_text(&decoded));
+                    }
+                }
+                rest = &after[n + 1..];
+            }
+            None => {
+                out.push_str("&amp;");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn xml_text(s: &str) -> String {
+    html_escape::encode_text(s).to_string()
+}
+
+fn book_identifier(chapters: &[Chapter], opts: &BookOptions) -> String {
+    let mut key = opts.title.clone();
+    for ch in chapters {
+        key.push('\n');
+        key.push_str(&ch.article_id);
+    }
+    format!("urn:wiki2md:{:x}", md5::compute(key.as_bytes()))
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn content_opf(
+    chapters: &[Chapter],
+    images: &[(String, &'static str, Vec<u8>)],
+    opts: &BookOptions,
+) -> String {
+    let modified = time::OffsetDateTime::now_utc()
+        .replace_nanosecond(0)
+        .ok()
+        .and_then(|t| {
+            t.format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        })
+        .unwrap_or_else(|| "1970-01-01T00:00:00Z".to_string());
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n");
+    out.push_str("  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+    out.push_str(&format!(
+        "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n",
+        book_identifier(chapters, opts)
+    ));
+    out.push_str(&format!(
+        "    <dc:title>{}</dc:title>\n",
+        xml_text(&opts.title)
+    ));
+    out.push_str(&format!(
+        "    <dc:creator>{}</dc:creator>\n",
+        xml_text(&opts.author)
+    ));
+    out.push_str(&format!(
+        "    <dc:language>{}</dc:language>\n",
+        xml_text(&opts.language)
+    ));
+    out.push_str(&format!(
+        "    <meta property=\"dcterms:modified\">{}</meta>\n",
+        modified
+    ));
+    out.push_str("  </metadata>\n  <manifest>\n");
+    out.push_str("    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n");
+    out.push_str(
+        "    <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n",
+    );
+    out.push_str("    <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n");
+    for (i, ch) in chapters.iter().enumerate() {
+        out.push_str(&format!(
+            "    <item id=\"ch{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            i + 1,
+            ch.file_name
+        ));
+    }
+    for (i, (name, media_type, _)) in images.iter().enumerate() {
+        out.push_str(&format!(
+            "    <item id=\"img{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            i + 1,
+            name,
+            media_type
+        ));
+    }
+    out.push_str("  </manifest>\n  <spine toc=\"ncx\">\n");
+    for i in 0..chapters.len() {
+        out.push_str(&format!("    <itemref idref=\"ch{}\"/>\n", i + 1));
+    }
+    out.push_str("  </spine>\n</package>\n");
+    out
+}
+
+fn nav_xhtml(chapters: &[Chapter], opts: &BookOptions) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n");
+    out.push_str("<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n");
+    out.push_str(&format!(
+        "<head>\n<title>{}</title>\n</head>\n<body>\n",
+        xml_text(&opts.title)
+    ));
+    out.push_str("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n");
+    for ch in chapters {
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            ch.file_name,
+            xml_text(&ch.title)
+        ));
+    }
+    out.push_str("</ol>\n</nav>\n</body>\n</html>\n");
+    out
+}
+
+fn toc_ncx(chapters: &[Chapter], opts: &BookOptions) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n");
+    out.push_str(&format!(
+        "<head>\n<meta name=\"dtb:uid\" content=\"{}\"/>\n</head>\n",
+        book_identifier(chapters, opts)
+    ));
+    out.push_str(&format!(
+        "<docTitle><text>{}</text></docTitle>\n<navMap>\n",
+        xml_text(&opts.title)
+    ));
+    for (i, ch) in chapters.iter().enumerate() {
+        out.push_str(&format!(
+            "<navPoint id=\"nav{n}\" playOrder=\"{n}\"><navLabel><text>{}</text></navLabel><content src=\"{}\"/></navPoint>\n",
+            xml_text(&ch.title),
+            ch.file_name,
+            n = i + 1
+        ));
+    }
+    out.push_str("</navMap>\n</ncx>\n");
+    out
+}
+
+fn chapter_xhtml(ch: &Chapter, opts: &BookOptions) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"{lang}\" lang=\"{lang}\">\n<head>\n<title>{title}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        lang = xml_text(&opts.language),
+        title = xml_text(&ch.title),
+        body = ch.body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_skips_comments_blanks_and_duplicates() {
+        let ids = parse_book_index("# Basics\nPerft\n\nMove Generation\nPerft\n");
+        assert_eq!(
+            ids,
+            vec!["Perft".to_string(), "Move_Generation".to_string()]
+        );
+    }
+
+    #[test]
+    fn named_entities_are_converted_for_xml() {
+        assert_eq!(
+            xhtml_entities("a&nbsp;b &amp; c &#8968; AT&T &bogus;"),
+            "a\u{a0}b &amp; c &#8968; AT&amp;T &amp;bogus;"
+        );
+    }
+}
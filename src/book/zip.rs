@@ -0,0 +1,137 @@
+//! Minimal ZIP writer (STORE only) sufficient for EPUB containers.
+//!
+//! EPUB requires the `mimetype` entry to be the first entry and uncompressed,
+//! so a stored-only writer is both the simplest and a fully valid option.
+
+use std::io::{self, Write};
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub struct ZipWriter<W: Write> {
+    out: W,
+    entries: Vec<Entry>,
+    offset: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            entries: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Append an uncompressed file entry.
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "zip entry too large"))?;
+        let crc = crc32(data);
+        let name_bytes = name.as_bytes();
+
+        let mut header: Vec<u8> = Vec::with_capacity(30 + name_bytes.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes()); // local file header signature
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed to extract
+        header.extend_from_slice(&0u16.to_le_bytes()); // flags
+        header.extend_from_slice(&0u16.to_le_bytes()); // method: store
+        header.extend_from_slice(&0u16.to_le_bytes()); // mod time
+        header.extend_from_slice(&0x21u16.to_le_bytes()); // mod date (1980-01-01)
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes()); // compressed size
+        header.extend_from_slice(&size.to_le_bytes()); // uncompressed size
+        header.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name_bytes);
+
+        self.out.write_all(&header)?;
+        self.out.write_all(data)?;
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset: self.offset,
+        });
+        self.offset += header.len() as u32 + size;
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let cd_start = self.offset;
+        let mut cd: Vec<u8> = Vec::new();
+        for e in &self.entries {
+            let name_bytes = e.name.as_bytes();
+            cd.extend_from_slice(&0x0201_4b50u32.to_le_bytes()); // central directory signature
+            cd.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            cd.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            cd.extend_from_slice(&0u16.to_le_bytes()); // flags
+            cd.extend_from_slice(&0u16.to_le_bytes()); // method
+            cd.extend_from_slice(&0u16.to_le_bytes()); // mod time
+            cd.extend_from_slice(&0x21u16.to_le_bytes()); // mod date
+            cd.extend_from_slice(&e.crc.to_le_bytes());
+            cd.extend_from_slice(&e.size.to_le_bytes());
+            cd.extend_from_slice(&e.size.to_le_bytes());
+            cd.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+            cd.extend_from_slice(&0u16.to_le_bytes()); // extra length
+            cd.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            cd.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            cd.extend_from_slice(&0u16.to_le_bytes()); // internal attrs
+            cd.extend_from_slice(&0u32.to_le_bytes()); // external attrs
+            cd.extend_from_slice(&e.offset.to_le_bytes());
+            cd.extend_from_slice(name_bytes);
+        }
+        self.out.write_all(&cd)?;
+
+        let count = self.entries.len() as u16;
+        let mut end: Vec<u8> = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes()); // end of central directory
+        end.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        end.extend_from_slice(&0u16.to_le_bytes()); // disk with central directory
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&(cd.len() as u32).to_le_bytes());
+        end.extend_from_slice(&cd_start.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn first_entry_is_readable_at_fixed_offset() {
+        let mut z = ZipWriter::new(Vec::new());
+        z.add_file("mimetype", b"application/epub+zip").unwrap();
+        let bytes = z.finish().unwrap();
+        assert_eq!(&bytes[0..4], b"PK\x03\x04");
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..58], b"application/epub+zip");
+    }
+}
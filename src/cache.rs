@@ -0,0 +1,329 @@
+//! Cache maintenance (`wiki2md cache`): sizes of the `wiki`/`json`/`md`
+//! directories per bucket, and outputs whose `.wiki` source is gone (or sources
+//! that were never converted).
+//!
+//! Outputs are matched with their source by article id: JSON files are named by
+//! id, and Markdown files carry it in their frontmatter (`wiki2md.article_id`),
+//! falling back to the file name for files without one.
+
+use crate::driver::{article_id_from_path, is_json_ast_path, wiki_source_files};
+use crate::error::{Error, Result};
+use crate::frontmatter;
+use crate::layout;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// The three cache directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheDirs {
+    pub wiki: PathBuf,
+    pub json: PathBuf,
+    pub md: PathBuf,
+}
+
+impl Default for CacheDirs {
+    fn default() -> Self {
+        let docs = PathBuf::from("docs");
+        CacheDirs {
+            wiki: docs.join("wiki"),
+            json: docs.join("json"),
+            md: docs.join("md"),
+        }
+    }
+}
+
+/// Number and total size of files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Usage of one bucket (`k`) in each directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BucketUsage {
+    pub wiki: Usage,
+    pub json: Usage,
+    pub md: Usage,
+}
+
+/// `wiki2md cache status`.
+#[derive(Debug, Clone, Default)]
+pub struct CacheStatus {
+    /// By bucket directory name; files directly in a cache directory are under `.`.
+    pub buckets: BTreeMap<String, BucketUsage>,
+}
+
+impl CacheStatus {
+    pub fn total(&self) -> BucketUsage {
+        let mut total = BucketUsage::default();
+        for usage in self.buckets.values() {
+            total.wiki.add(usage.wiki);
+            total.json.add(usage.json);
+            total.md.add(usage.md);
+        }
+        total
+    }
+
+    /// A table with one row per bucket and a total row.
+    pub fn to_text(&self) -> String {
+        let row = |name: &str, u: &BucketUsage| {
+            format!(
+                "{:<8} {:>7} {:>10} {:>7} {:>10} {:>7} {:>10}\n",
+                name,
+                u.wiki.files,
+                u.wiki.bytes,
+                u.json.files,
+                u.json.bytes,
+                u.md.files,
+                u.md.bytes
+            )
+        };
+        let mut out = format!(
+            "{:<8} {:>7} {:>10} {:>7} {:>10} {:>7} {:>10}\n",
+            "bucket", "wiki", "bytes", "json", "bytes", "md", "bytes"
+        );
+        for (name, usage) in &self.buckets {
+            out.push_str(&row(name, usage));
+        }
+        out.push_str(&row("total", &self.total()));
+        out
+    }
+}
+
+/// `wiki2md cache verify`: outputs without a source and sources without output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheReport {
+    /// `.md` files whose `.wiki` source is missing.
+    pub orphaned_md: Vec<PathBuf>,
+    /// `.json` files whose `.wiki` source is missing.
+    pub orphaned_json: Vec<PathBuf>,
+    /// `.wiki` files without a `.md` file.
+    pub unconverted: Vec<PathBuf>,
+}
+
+impl CacheReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_md.is_empty() && self.orphaned_json.is_empty() && self.unconverted.is_empty()
+    }
+
+    /// One `kind<TAB>path` line per problem.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (kind, paths) in [
+            ("orphaned-md", &self.orphaned_md),
+            ("orphaned-json", &self.orphaned_json),
+            ("unconverted", &self.unconverted),
+        ] {
+            for path in paths {
+                out.push_str(&format!("{}\t{}\n", kind, path.display()));
+            }
+        }
+        out
+    }
+}
+
+/// Count the files of each directory per bucket. Missing directories count as empty.
+pub fn status(dirs: &CacheDirs) -> Result<CacheStatus> {
+    let mut status = CacheStatus::default();
+    for (dir, ext) in [(&dirs.wiki, "wiki"), (&dirs.json, "json"), (&dirs.md, "md")] {
+        let paths = match ext {
+            "json" => json_ast_files(dir),
+            _ => files_with_extension(dir, ext),
+        };
+        for path in paths {
+            let bytes = fs::metadata(&path).map_err(Error::io(&path))?.len();
+            let bucket = match path.strip_prefix(dir).ok().and_then(|rel| rel.parent()) {
+                Some(parent) if parent != Path::new("") => parent
+                    .components()
+                    .next()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                _ => ".".to_string(),
+            };
+            let usage = status.buckets.entry(bucket).or_default();
+            let slot = match ext {
+                "wiki" => &mut usage.wiki,
+                "json" => &mut usage.json,
+                _ => &mut usage.md,
+            };
+            slot.add(Usage { files: 1, bytes });
+        }
+    }
+    Ok(status)
+}
+
+/// Match every output with its source.
+pub fn verify(dirs: &CacheDirs) -> Result<CacheReport> {
+    let sources: Vec<(String, PathBuf)> = wiki_source_files(&dirs.wiki)
+        .into_iter()
+        .map(|e| (article_id_from_path(e.path()), e.into_path()))
+        .collect();
+    let ids: HashSet<&str> = sources.iter().map(|(id, _)| id.as_str()).collect();
+
+    let mut report = CacheReport::default();
+    for path in json_ast_files(&dirs.json) {
+        if !ids.contains(article_id_from_path(&path).as_str()) {
+            report.orphaned_json.push(path);
+        }
+    }
+
+    let mut converted: HashSet<String> = HashSet::new();
+    for path in files_with_extension(&dirs.md, "md") {
+        let id = md_article_id(&path)?;
+        if ids.contains(id.as_str()) {
+            converted.insert(id);
+        } else {
+            report.orphaned_md.push(path);
+        }
+    }
+    report.unconverted = sources
+        .into_iter()
+        .filter(|(id, _)| !converted.contains(id))
+        .map(|(_, path)| path)
+        .collect();
+    Ok(report)
+}
+
+/// Remove the orphaned `.md` and `.json` files found by [`verify`] (and bucket
+/// directories left empty). With `dry_run`, only report them.
+pub fn clean(dirs: &CacheDirs, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let report = verify(dirs)?;
+    let removed: Vec<PathBuf> = report
+        .orphaned_md
+        .into_iter()
+        .chain(report.orphaned_json)
+        .collect();
+    if dry_run {
+        return Ok(removed);
+    }
+    for path in &removed {
+        fs::remove_file(path).map_err(Error::io(path))?;
+        if let Some(parent) = path.parent()
+            && parent != dirs.md
+            && parent != dirs.json
+            && fs::read_dir(parent)
+                .map_err(Error::io(parent))?
+                .next()
+                .is_none()
+        {
+            fs::remove_dir(parent).map_err(Error::io(parent))?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Every file under `dir` with extension `ext`, in path order.
+/// `.json` and `.json.gz` AST files under `dir`.
+fn json_ast_files(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_json_ast_path(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn files_with_extension(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|x| x == ext))
+        .map(|e| e.into_path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The `wiki2md.article_id` of a Markdown file's frontmatter, or the id its file
+/// name was derived from (`Ken Thompson.md` -> `Ken_Thompson`).
+fn md_article_id(path: &Path) -> Result<String> {
+    let text = fs::read_to_string(path).map_err(Error::io(path))?;
+    let from_frontmatter = frontmatter::split_yaml_frontmatter(&text).and_then(|(fm, _)| {
+        fm.lines()
+            .find_map(|line| line.strip_prefix("  article_id:"))
+            .map(|id| id.trim().to_string())
+    });
+    Ok(from_frontmatter.unwrap_or_else(|| {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default();
+        layout::decode_file_name(&stem).replace(' ', "_")
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_verify_and_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let dirs = CacheDirs {
+            wiki: dir.path().join("wiki"),
+            json: dir.path().join("json"),
+            md: dir.path().join("md"),
+        };
+        for (path, text) in [
+            ("wiki/k/Ken_Thompson.wiki", "Ken."),
+            ("wiki/p/Perft.wiki", "Perft."),
+            ("json/k/Ken_Thompson.json", "{}"),
+            ("json/o/Old_Page.json", "{}"),
+            (
+                "md/k/Ken Thompson.md",
+                "---\nwiki2md:\n  article_id: Ken_Thompson\n---\n\n# Ken\n",
+            ),
+            ("md/o/Old Page.md", "# Old\n"),
+            ("md/o/Old Page.md.bak", "# Old\n"),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+
+        let status = status(&dirs).unwrap();
+        assert_eq!(status.buckets.keys().collect::<Vec<_>>(), ["k", "o", "p"]);
+        assert_eq!(status.buckets["o"].md, Usage { files: 1, bytes: 6 });
+        let total = status.total();
+        assert_eq!(
+            (total.wiki.files, total.json.files, total.md.files),
+            (2, 2, 2)
+        );
+        assert!(
+            status
+                .to_text()
+                .lines()
+                .last()
+                .unwrap()
+                .starts_with("total")
+        );
+
+        let report = verify(&dirs).unwrap();
+        assert_eq!(report.orphaned_md, [dirs.md.join("o").join("Old Page.md")]);
+        assert_eq!(
+            report.orphaned_json,
+            [dirs.json.join("o").join("Old_Page.json")]
+        );
+        assert_eq!(report.unconverted, [dirs.wiki.join("p").join("Perft.wiki")]);
+
+        assert_eq!(clean(&dirs, true).unwrap().len(), 2);
+        assert!(dirs.json.join("o").exists());
+        clean(&dirs, false).unwrap();
+        assert!(!dirs.json.join("o").exists());
+        assert!(dirs.md.join("o").join("Old Page.md.bak").exists());
+        let report = verify(&dirs).unwrap();
+        assert!(report.orphaned_md.is_empty() && report.orphaned_json.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Everything a conversion run needs, in one place.
+
+use crate::WriteOptions;
+use crate::parse::ParseOptions;
+use crate::render::RenderOptions;
+use crate::vault::Vault;
+use crate::wiki::{HttpFetcher, WikiFetcher};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Settings for [`run_with_config`](crate::run_with_config) and
+/// [`regenerate_all_with_config`](crate::regenerate_all_with_config).
+///
+/// ```no_run
+/// use wiki2md::{Config, render::RenderOptions};
+///
+/// let config = Config::new()
+///     .render_options(RenderOptions {
+///         center_tables_and_captions: true,
+///         ..Default::default()
+///     })
+///     .wiki_dir("mirror/wiki")
+///     .path_template("site/content/{slug}.{ext}")
+///     .offline(true);
+/// wiki2md::regenerate_all_with_config(&config)?;
+/// # Ok::<(), wiki2md::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub(crate) render: RenderOptions,
+    pub(crate) parse: ParseOptions,
+    pub(crate) write: WriteOptions,
+    pub(crate) write_json: bool,
+    pub(crate) offline: bool,
+    pub(crate) wiki_dir: PathBuf,
+    pub(crate) md_dir: PathBuf,
+    pub(crate) json_dir: PathBuf,
+    pub(crate) fetcher: Arc<dyn WikiFetcher>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            render: RenderOptions::default(),
+            parse: ParseOptions::default(),
+            write: WriteOptions::default(),
+            write_json: false,
+            offline: false,
+            wiki_dir: PathBuf::from("docs").join("wiki"),
+            md_dir: PathBuf::from("docs").join("md"),
+            json_dir: PathBuf::from("docs").join("json"),
+            fetcher: Arc::new(HttpFetcher),
+        }
+    }
+}
+
+impl Config {
+    /// The defaults: fetch into `docs/wiki`, write to `docs/md`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How Markdown is rendered.
+    pub fn render_options(mut self, render: RenderOptions) -> Self {
+        self.render = render;
+        self
+    }
+
+    /// Nesting and diagnostic limits of the parser. Bulk runs with
+    /// [`WriteOptions::max_warnings`] keep every diagnostic, so all are counted.
+    pub fn parse_options(mut self, parse: ParseOptions) -> Self {
+        self.parse = parse;
+        self
+    }
+
+    /// How files are written (frontmatter, backups, bulk selection, ...).
+    pub fn write_options(mut self, write: WriteOptions) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Also write the parsed AST as JSON in single file mode.
+    pub fn write_json(mut self, write_json: bool) -> Self {
+        self.write_json = write_json;
+        self
+    }
+
+    /// Fail instead of fetching articles missing from the wiki directory.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Where `.wiki` sources are cached (`docs/wiki`). Articles are stored in
+    /// bucket sub-directories (`k/Ken_Thompson.wiki`).
+    pub fn wiki_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.wiki_dir = dir.into();
+        self
+    }
+
+    /// Where `.md` files are written (`docs/md`), in the wiki directory's bucket
+    /// layout. Ignored when a path template is set.
+    pub fn md_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.md_dir = dir.into();
+        self
+    }
+
+    /// Where `.json` ASTs are written (`docs/json`) when JSON output is enabled, in
+    /// the wiki directory's bucket layout. Ignored when a path template is set.
+    pub fn json_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.json_dir = dir.into();
+        self
+    }
+
+    /// Where articles missing from the wiki directory are fetched from
+    /// ([`HttpFetcher`] by default).
+    pub fn fetcher(mut self, fetcher: impl WikiFetcher + 'static) -> Self {
+        self.fetcher = Arc::new(fetcher);
+        self
+    }
+
+    /// Place generated files by this template (see [`layout`](crate::layout)).
+    pub fn path_template(mut self, template: impl Into<String>) -> Self {
+        self.write.path_template = Some(template.into());
+        self
+    }
+
+    /// Write Markdown into an Obsidian vault: `.md` files go into the vault's
+    /// notes folder and the files they show into its attachments folder. Call it
+    /// after [`write_options`](Self::write_options), which replaces it.
+    pub fn vault(mut self, vault: Vault) -> Self {
+        self.md_dir = vault.notes_dir();
+        self.write.vault = Some(vault);
+        self
+    }
+}
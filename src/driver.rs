@@ -0,0 +1,1889 @@
+//! Single file and bulk modes: fetching articles into the `.wiki` cache and
+//! writing `.md`/`.json` files.
+
+use crate::anchors::{AnchorMap, ArticleAnchors};
+use crate::ast_filter::AstFilter;
+use crate::error::{Error, Result};
+use crate::generation::GenerationInfo;
+use crate::index::{self, IndexPages};
+use crate::manifest::{self, Manifest, ManifestEntry, ManifestOutput};
+use crate::progress::{ProgressMode, Verbosity};
+use crate::search::{SearchDocument, SearchIndex};
+use crate::title::Title;
+use crate::vault::Vault;
+use crate::{
+    Config, ast, filter, frontmatter, graph, infobox, layout, linkcheck, lower_first_letter_bucket,
+    parse, parse_source, progress, render, render_body, render_body_pooled, sanitize_article_id,
+    stats, tables, transclude,
+};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use walkdir::WalkDir;
+
+/// Options controlling how Markdown files are written on disk.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// If true, regenerate YAML frontmatter even when the destination `.md`
+    /// already contains a frontmatter block.
+    pub regenerate_frontmatter: bool,
+
+    /// If true, keep the previous version of a changed `.md` file as `<name>.md.bak`.
+    pub backup: bool,
+
+    /// If set, previous versions are copied into this directory (keeping the
+    /// bucket sub-directory) instead of next to the file. Implies `backup`.
+    pub backup_dir: Option<PathBuf>,
+
+    /// If true, render everything but write nothing; print a unified diff
+    /// between each existing `.md` file and its would-be contents instead.
+    pub dry_run: bool,
+
+    /// Where generated `.md`/`.json` files go (see [`layout`]); `None` uses
+    /// [`layout::DEFAULT_PATH_TEMPLATE`]. When set, bulk regeneration places files
+    /// by this template instead of mirroring the wiki tree under `md_root`.
+    pub path_template: Option<String>,
+
+    /// If true, generated file names use the lowercase ASCII slug of the article id
+    /// (`ken-thompson.md`). The title is still listed in the frontmatter `aliases`.
+    pub slugify_filenames: bool,
+
+    /// Which articles bulk regeneration converts (include/exclude patterns, `--since`).
+    pub filter: filter::ArticleFilter,
+
+    /// If true, bulk regeneration leaves `.md` files that already exist untouched.
+    pub skip_existing: bool,
+
+    /// If set, bulk regeneration starts at this article (in path order), skipping
+    /// everything before it, so an interrupted run can continue.
+    pub resume_from: Option<String>,
+
+    /// How much bulk regeneration reports on stderr.
+    pub verbosity: Verbosity,
+
+    /// Per-file progress lines or a progress bar.
+    pub progress: ProgressMode,
+
+    /// If true, bulk regeneration records per-file errors (and panics) and carries
+    /// on instead of stopping at the first one. The run still fails at the end,
+    /// after printing a summary table.
+    pub keep_going: bool,
+
+    /// With `keep_going`, where the list of failed files (file, stage, error) is
+    /// written as JSON. Written on every run, so an empty list means a clean run.
+    pub failures_path: Option<PathBuf>,
+
+    /// If set, bulk regeneration fails once it is done (after writing everything)
+    /// when the articles' diagnostics add up to more than this many warnings, or
+    /// include any error, and prints the articles and codes with the most.
+    pub max_warnings: Option<usize>,
+
+    /// If set, bulk regeneration writes a lunr/elasticlunr-compatible search index
+    /// of every converted article (including skipped ones) to this JSON file.
+    pub search_index: Option<PathBuf>,
+
+    /// If set, bulk regeneration writes an [`AnchorMap`](crate::anchors::AnchorMap)
+    /// to this JSON file: for each article, the `#Section` anchors the converted
+    /// articles link to and the Markdown fragments they became.
+    pub anchor_map: Option<PathBuf>,
+
+    /// If set, bulk regeneration writes a [`manifest`](crate::manifest) of every
+    /// converted article (source, outputs, hashes) to this JSON file. A run that
+    /// only converts some articles updates their entries and keeps the rest.
+    pub manifest: Option<PathBuf>,
+
+    /// If true, bulk regeneration also writes each article's parsed AST as JSON
+    /// (like single file mode does), mirroring the wiki tree under the JSON root
+    /// or placed by the path template, so the `json` and `md` trees stay in sync.
+    pub write_json: bool,
+
+    /// If true, `.json` ASTs are written without indentation: smaller and faster to
+    /// write, but harder to read and diff.
+    pub json_compact: bool,
+
+    /// If true, `.json` ASTs are gzip-compressed and written as `<name>.json.gz`.
+    /// [`read_ast_file`] reads either form.
+    pub json_gzip: bool,
+
+    /// If true, each `.md` file ends with a [`generation`](crate::generation) comment
+    /// recording the parser version, schema version, and render options it was
+    /// generated with. With `skip_existing`, files whose comment is missing or
+    /// differs from the current build are regenerated instead of skipped.
+    pub generation_metadata: bool,
+
+    /// Which alphabetical index pages (`Index.md`) bulk regeneration writes into
+    /// the Markdown root. Only a full run (no filter, no `resume_from`) rewrites them.
+    pub index_pages: IndexPages,
+
+    /// If true, bulk regeneration also writes one page per category listing its
+    /// articles (`Categories/<name>.md`) and a `Categories.md` overview. Like the
+    /// index, only a full run rewrites them.
+    pub category_pages: bool,
+
+    /// If true, bulk regeneration ends with a report of the time spent reading,
+    /// parsing, rendering, building frontmatter, and writing, and the slowest files.
+    pub timing_report: bool,
+
+    /// If true, `{{:Other Page}}` transclusions are replaced by the content of that
+    /// page, read from the wiki directory (never fetched).
+    pub transclude: bool,
+
+    /// [`AstFilter`]s each article's AST is piped through, in order, after parsing
+    /// (and transclusion) and before anything is written or rendered.
+    pub ast_filters: Vec<AstFilter>,
+
+    /// If set, wiki-hosted files the Markdown links to are downloaded into this
+    /// [`Vault`]'s attachments folder and linked locally (see [`Config::vault`]).
+    pub vault: Option<Vault>,
+}
+
+impl WriteOptions {
+    fn path_template(&self) -> &str {
+        self.path_template
+            .as_deref()
+            .unwrap_or(layout::DEFAULT_PATH_TEMPLATE)
+    }
+
+    /// `path` with `.gz` appended when JSON is written compressed.
+    fn json_output_path(&self, path: PathBuf) -> PathBuf {
+        match self.json_gzip {
+            true => {
+                let mut path = path.into_os_string();
+                path.push(".gz");
+                PathBuf::from(path)
+            }
+            false => path,
+        }
+    }
+}
+
+/// Single file mode: Fetch if needed, then convert.
+pub fn run(raw_title: &str, write_json: bool) -> Result<()> {
+    run_with_config(raw_title, &Config::new().write_json(write_json))
+}
+
+/// Single file mode: like [`run`], but allows callers to customize Markdown rendering.
+#[deprecated(note = "use `run_with_config`")]
+pub fn run_with_render_options(
+    raw_title: &str,
+    write_json: bool,
+    render_opts: &render::RenderOptions,
+) -> Result<()> {
+    run_with_config(
+        raw_title,
+        &Config::new()
+            .write_json(write_json)
+            .render_options(render_opts.clone()),
+    )
+}
+
+/// Single file mode: like [`run_with_render_options`], but also controls how
+/// Markdown files are written (frontmatter preservation, etc.).
+#[deprecated(note = "use `run_with_config`")]
+pub fn run_with_options(
+    raw_title: &str,
+    write_json: bool,
+    render_opts: &render::RenderOptions,
+    write_opts: &WriteOptions,
+) -> Result<()> {
+    run_with_config(
+        raw_title,
+        &Config::new()
+            .write_json(write_json)
+            .render_options(render_opts.clone())
+            .write_options(write_opts.clone()),
+    )
+}
+
+/// Single file mode: fetch the article into the config's wiki directory if needed
+/// (unless offline), then convert it.
+#[tracing::instrument(name = "article", level = "info", skip_all, fields(title = raw_title))]
+pub fn run_with_config(raw_title: &str, config: &Config) -> Result<()> {
+    let render_opts = &config.render;
+    let write_opts = &config.write;
+    let write_json = config.write_json;
+    let article_id = sanitize_article_id(raw_title);
+    let bucket = lower_first_letter_bucket(&article_id);
+    let template = write_opts.path_template();
+    layout::validate_path_template(template).map_err(Error::config)?;
+    // without a template, files go into the configured directories, like in bulk mode.
+    let output_path = |kind: &str, doc: Option<&ast::Document>| match &write_opts.path_template {
+        Some(_) => layout::expand_path_template(
+            template,
+            kind,
+            &article_id,
+            doc,
+            write_opts.slugify_filenames,
+        ),
+        None => {
+            let root = if kind == "md" {
+                &config.md_dir
+            } else {
+                &config.json_dir
+            };
+            layout::expand_path_template(
+                "{bucket}/{name}.{ext}",
+                kind,
+                &article_id,
+                doc,
+                write_opts.slugify_filenames,
+            )
+            .map(|rel| root.join(rel))
+        }
+    };
+
+    let wiki_path = wiki_path_for(&config.wiki_dir, &article_id);
+    let wiki_dir = config.wiki_dir.join(&bucket);
+    // `{category}` needs the parsed article, so those paths are resolved after parsing.
+    let early_md_path = match layout::uses_document(template) {
+        true => None,
+        false => Some(output_path("md", None).map_err(Error::config)?),
+    };
+
+    if write_opts.dry_run {
+        let src = if wiki_path.exists() {
+            read_wiki_source(&wiki_path)?
+        } else if config.offline {
+            return Err(missing_offline(&wiki_path));
+        } else {
+            config.fetcher.fetch(&Title::parse(raw_title).to_string())?
+        };
+        let mut ast = parse_source(&src, &config.parse);
+        if write_opts.transclude {
+            transclude_from_cache(&mut ast, &article_id, &config.wiki_dir);
+        }
+        apply_ast_filters(&mut ast, &article_id, &wiki_path, &write_opts.ast_filters)?;
+        let md_path = match early_md_path {
+            Some(p) => p,
+            None => output_path("md", Some(&ast.document)).map_err(Error::config)?,
+        };
+        let article_opts = article_render_options(&md_path, render_opts);
+        let md_body = render_body(&ast.document, &article_opts);
+        write_markdown_file(
+            &md_path,
+            &wiki_path,
+            &article_id,
+            &ast.document,
+            &md_body,
+            write_opts,
+            &article_opts,
+            &[],
+            &mut Default::default(),
+        )?;
+        return Ok(());
+    }
+
+    // does ./docs/md/{bucket}/{article id}.md exist?
+    if let Some(md_path) = &early_md_path
+        && md_path.exists()
+    {
+        let content = fs::read_to_string(md_path).map_err(Error::io(md_path))?;
+        println!("{}", content);
+        return Ok(());
+    }
+
+    // does ./docs/wiki/{bucket}/{article_id}.wiki exist? fetch if not.
+    if !wiki_path.exists() && config.offline {
+        return Err(missing_offline(&wiki_path));
+    }
+    fs::create_dir_all(&wiki_dir).map_err(Error::io(&wiki_dir))?;
+    if !wiki_path.exists() {
+        config
+            .fetcher
+            .fetch_and_save(&Title::parse(raw_title).to_string(), &wiki_path)?;
+    }
+
+    // parse wikitext into ast
+    let wiki_content = read_wiki_source(&wiki_path)?;
+    let mut ast = parse_source(&wiki_content, &config.parse);
+    if write_opts.transclude {
+        transclude_from_cache(&mut ast, &article_id, &config.wiki_dir);
+    }
+    apply_ast_filters(&mut ast, &article_id, &wiki_path, &write_opts.ast_filters)?;
+
+    let md_path = match early_md_path {
+        Some(p) => p,
+        None => {
+            let p = output_path("md", Some(&ast.document)).map_err(Error::config)?;
+            if p.exists() {
+                println!("{}", fs::read_to_string(&p).map_err(Error::io(&p))?);
+                return Ok(());
+            }
+            p
+        }
+    };
+    let json_path = write_opts
+        .json_output_path(output_path("json", Some(&ast.document)).map_err(Error::config)?);
+
+    // ensure directories exist
+    for path in [Some(&md_path), write_json.then_some(&json_path)]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::io(parent))?;
+        }
+    }
+
+    match write_json {
+        true => {
+            // write .json
+            write_json_ast_for_wiki(&article_id, &wiki_path, &ast, &json_path, write_opts)?;
+
+            // write .md
+            let md_content = render_markdown_from_json(
+                &article_id,
+                &wiki_path,
+                &json_path,
+                &md_path,
+                render_opts,
+                write_opts,
+            )?;
+            println!("{}", md_content);
+        }
+        false => {
+            let md_body = render_body(&ast.document, render_opts);
+            let md_content = write_markdown_file(
+                &md_path,
+                &wiki_path,
+                &article_id,
+                &ast.document,
+                &md_body,
+                write_opts,
+                render_opts,
+                &[],
+                &mut Default::default(),
+            )?;
+            println!("{}", md_content);
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert one `.wiki` file anywhere on disk to `output`, outside the `docs/`
+/// layout. The article id comes from the file name (`Ken_Thompson.wiki` ->
+/// `Ken_Thompson`); nothing is fetched. With JSON output enabled, the AST is
+/// written next to `output` (`out.json`).
+#[tracing::instrument(name = "convert", level = "info", skip_all, fields(input = %input.display()))]
+pub fn convert_file(input: &Path, output: &Path, config: &Config) -> Result<()> {
+    let write_opts = &config.write;
+    let article_id = article_id_from_path(input);
+    let src = read_wiki_source(input)?;
+    let mut ast = parse_source(&src, &config.parse);
+    if write_opts.transclude {
+        transclude_from_cache(&mut ast, &article_id, &config.wiki_dir);
+    }
+    apply_ast_filters(&mut ast, &article_id, input, &write_opts.ast_filters)?;
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(Error::io(parent))?;
+    }
+    if config.write_json {
+        let json_path = write_opts.json_output_path(output.with_extension("json"));
+        write_json_ast_for_wiki(&article_id, input, &ast, &json_path, write_opts)?;
+    }
+    let article_opts = article_render_options(output, &config.render);
+    let md_body = render_body(&ast.document, &article_opts);
+    write_markdown_file(
+        output,
+        input,
+        &article_id,
+        &ast.document,
+        &md_body,
+        write_opts,
+        &article_opts,
+        &[],
+        &mut Default::default(),
+    )?;
+    Ok(())
+}
+
+/// Stdout mode: render an article (frontmatter, title heading, and body) without
+/// creating directories or writing any files. The config's `.wiki` cache is used if
+/// present; otherwise the article is fetched with its fetcher.
+pub fn render_article(raw_title: &str, config: &Config) -> Result<String> {
+    let render_opts = &config.render;
+    let (article_id, wiki_path, src) = read_or_fetch_article(raw_title, config)?;
+    let ast = parse_source(&src, &config.parse);
+    let md_body = render_body(&ast.document, render_opts);
+    compose_markdown(
+        None,
+        &wiki_path,
+        &article_id,
+        &ast.document,
+        &md_body,
+        &WriteOptions::default(),
+        render_opts,
+        &[],
+    )
+}
+
+/// Stdout mode for one section: render only the blocks under the heading reached
+/// by `heading_path` (e.g. `["Publications"]`), without frontmatter or title. Like
+/// [`render_article`], nothing is written.
+pub fn render_article_section(
+    raw_title: &str,
+    heading_path: &[&str],
+    config: &Config,
+) -> Result<String> {
+    let (_, _, src) = read_or_fetch_article(raw_title, config)?;
+    let ast = parse_source(&src, &config.parse);
+    render::render_section(&ast.document, heading_path, &config.render)
+        .ok_or_else(|| Error::Config(format!("section not found: {}", heading_path.join(" > "))))
+}
+
+/// JSON input mode: render an [`ast::AstFile`] (as written with `write_json`,
+/// possibly edited since) to Markdown with frontmatter, like [`render_article`].
+/// No `.wiki` file is needed; nothing is written.
+pub fn render_ast_file(json_path: &Path, render_opts: &render::RenderOptions) -> Result<String> {
+    let ast_file = read_ast_file(json_path)?;
+    let wiki_path = match &ast_file.source.path {
+        Some(path) => PathBuf::from(path),
+        None => wiki_path_for(Path::new("docs/wiki"), &ast_file.article_id),
+    };
+    let md_body = render_body(&ast_file.document, render_opts);
+    compose_markdown(
+        None,
+        &wiki_path,
+        &ast_file.article_id,
+        &ast_file.document,
+        &md_body,
+        &WriteOptions::default(),
+        render_opts,
+        &[],
+    )
+}
+
+/// Read an [`ast::AstFile`] JSON file, gzip-compressed if its name ends in `.gz`.
+pub fn read_ast_file(json_path: &Path) -> Result<ast::AstFile> {
+    let file = fs::File::open(json_path).map_err(Error::io(json_path))?;
+    let reader = BufReader::new(file);
+    let parsed = match is_gzip_path(json_path) {
+        true => serde_json::from_reader(BufReader::new(GzDecoder::new(reader))),
+        false => serde_json::from_reader(reader),
+    };
+    parsed.map_err(|source| match source.is_io() {
+        true => Error::io(json_path)(source.into()),
+        false => Error::Json {
+            article_id: article_id_from_path(json_path),
+            path: json_path.to_path_buf(),
+            source,
+        },
+    })
+}
+
+fn is_gzip_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Whether `path` names a `.json` AST file, compressed or not.
+pub(crate) fn is_json_ast_path(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    name.ends_with(".json") || name.ends_with(".json.gz")
+}
+
+/// Every table of an article, flattened to plain-text cells (for `wiki2md tables`).
+/// Fetches the article if it isn't cached, like [`render_article`].
+pub fn article_tables(
+    raw_title: &str,
+    config: &Config,
+) -> Result<(String, Vec<tables::DataTable>)> {
+    let (article_id, _, src) = read_or_fetch_article(raw_title, config)?;
+    let ast = parse_source(&src, &config.parse);
+    Ok((article_id, tables::extract_tables(&ast.document)))
+}
+
+/// The article id, `.wiki` cache path, and wikitext of an article, fetched with the
+/// config's fetcher if it isn't cached (and the config isn't offline).
+fn read_or_fetch_article(raw_title: &str, config: &Config) -> Result<(String, PathBuf, String)> {
+    let article_id = sanitize_article_id(raw_title);
+    let wiki_path = wiki_path_for(&config.wiki_dir, &article_id);
+    let src = if wiki_path.exists() {
+        read_wiki_source(&wiki_path)?
+    } else if config.offline {
+        return Err(missing_offline(&wiki_path));
+    } else {
+        config.fetcher.fetch(&Title::parse(raw_title).to_string())?
+    };
+    Ok((article_id, wiki_path, src))
+}
+
+/// Bulk mode: Walk ./docs/wiki and regenerate all corresponding .md files.
+pub fn regenerate_all() -> Result<()> {
+    regenerate_all_with_config(&Config::new())
+}
+
+/// Bulk mode: like [`regenerate_all`], but allows callers to customize Markdown rendering.
+#[deprecated(note = "use `regenerate_all_with_config`")]
+pub fn regenerate_all_with_render_options(render_opts: &render::RenderOptions) -> Result<()> {
+    regenerate_all_with_config(&Config::new().render_options(render_opts.clone()))
+}
+
+/// Bulk mode: like [`regenerate_all_with_render_options`], but also controls how
+/// Markdown files are written (frontmatter preservation, etc.).
+#[deprecated(note = "use `regenerate_all_with_config`")]
+pub fn regenerate_all_with_options(
+    render_opts: &render::RenderOptions,
+    write_opts: &WriteOptions,
+) -> Result<()> {
+    regenerate_all_with_config(
+        &Config::new()
+            .render_options(render_opts.clone())
+            .write_options(write_opts.clone()),
+    )
+}
+
+/// Bulk mode: Walk the provided wiki root directory and regenerate all corresponding Markdown files
+/// under the provided md root directory.
+#[deprecated(note = "use `regenerate_all_with_config` with `Config::wiki_dir`/`Config::md_dir`")]
+pub fn regenerate_all_in_dirs(
+    wiki_root: &Path,
+    md_root: &Path,
+    render_opts: &render::RenderOptions,
+    write_opts: &WriteOptions,
+) -> Result<()> {
+    regenerate_all_with_config(
+        &Config::new()
+            .wiki_dir(wiki_root)
+            .md_dir(md_root)
+            .render_options(render_opts.clone())
+            .write_options(write_opts.clone()),
+    )
+}
+
+/// How many of the slowest files the timing report lists.
+const TIMING_REPORT_TOP: usize = 10;
+
+/// How many articles and codes a failed `max_warnings` check lists.
+const DIAGNOSTICS_REPORT_TOP: usize = 10;
+
+/// Bulk mode: Walk the config's wiki directory and regenerate all corresponding
+/// Markdown files under its md directory (or where the path template puts them).
+#[tracing::instrument(name = "regenerate_all", level = "info", skip_all, fields(wiki_root = %config.wiki_dir.display()))]
+pub fn regenerate_all_with_config(config: &Config) -> Result<()> {
+    let wiki_root: &Path = &config.wiki_dir;
+    let md_root: &Path = &config.md_dir;
+    let json_root: &Path = &config.json_dir;
+    let render_opts = &config.render;
+    let write_opts = &config.write;
+    // `max_warnings` counts every warning, so none may be capped away.
+    let parse_opts = parse::ParseOptions {
+        max_diagnostics: config
+            .parse
+            .max_diagnostics
+            .filter(|_| write_opts.max_warnings.is_none()),
+        ..config.parse.clone()
+    };
+    if let Some(template) = &write_opts.path_template {
+        layout::validate_path_template(template).map_err(Error::config)?;
+    }
+    require_wiki_root(wiki_root)?;
+
+    let mut entries = wiki_source_files(wiki_root);
+
+    if let Some(resume_from) = &write_opts.resume_from {
+        let resume_id = sanitize_article_id(resume_from);
+        let start = entries
+            .iter()
+            .position(|e| {
+                e.path()
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| layout::decode_file_name(stem) == resume_id)
+            })
+            .ok_or_else(|| {
+                Error::Config(format!("--resume-from article not found: {}", resume_from))
+            })?;
+        entries.drain(..start);
+    }
+
+    if !write_opts.filter.is_empty() {
+        entries.retain(|e| {
+            let stem = e.path().file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let bucket = e
+                .path()
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|b| b.to_str())
+                .unwrap_or("");
+            let modified = e.metadata().ok().and_then(|m| m.modified().ok());
+            write_opts
+                .filter
+                .matches(&layout::decode_file_name(stem), bucket, modified)
+        });
+    }
+
+    let total = entries.len();
+    let progress = progress::Progress::new(total, write_opts.verbosity, write_opts.progress);
+    let mut count = 0;
+    let mut changed = 0;
+    let mut skipped = 0;
+
+    let mut failures: Vec<Failure> = Vec::new();
+    let mut articles: Vec<Article> = Vec::new();
+    let mut timings = progress::TimingReport::default();
+    let mut pool = render::RenderPool::new();
+    // the whole corpus, so filtered runs still list every redirect.
+    let redirects = collect_redirects(wiki_root);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let path = entry.path();
+        let stage = Cell::new(Stage::Read);
+        let outcome = if write_opts.keep_going {
+            // a panic in the parser or renderer is just another failed file.
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                regenerate_entry(
+                    path,
+                    wiki_root,
+                    md_root,
+                    json_root,
+                    render_opts,
+                    &parse_opts,
+                    write_opts,
+                    &stage,
+                    &mut pool,
+                    &redirects,
+                )
+            }))
+            .unwrap_or_else(|payload| Err(panic_error(path, stage.get(), &*payload)))
+        } else {
+            regenerate_entry(
+                path,
+                wiki_root,
+                md_root,
+                json_root,
+                render_opts,
+                &parse_opts,
+                write_opts,
+                &stage,
+                &mut pool,
+                &redirects,
+            )
+        };
+
+        match outcome {
+            Ok(EntryOutcome::Skipped { article }) => {
+                articles.push(article);
+                skipped += 1;
+                progress.file_skipped(i + 1);
+            }
+            Ok(EntryOutcome::Regenerated {
+                md_path,
+                timing,
+                changed: file_changed,
+                article,
+            }) => {
+                articles.push(article);
+                count += 1;
+                timings.add(&md_path, timing);
+                if write_opts.dry_run {
+                    if file_changed {
+                        changed += 1;
+                    }
+                    progress.file_skipped(i + 1);
+                } else {
+                    progress.file_done(i + 1, "Regenerated", &md_path, timing);
+                }
+            }
+            Err(e) if write_opts.keep_going => {
+                let failure = Failure {
+                    file: path.to_path_buf(),
+                    stage: stage.get(),
+                    error: e.to_string(),
+                };
+                tracing::warn!(file = %path.display(), stage = failure.stage.as_str(), error = %e, "failed");
+                progress.file_failed(i + 1, path, failure.stage.as_str(), &failure.error);
+                failures.push(failure);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    progress.finish();
+    let tally = write_opts
+        .max_warnings
+        .map(|_| DiagnosticTally::new(&articles));
+    if write_opts.timing_report {
+        progress.summary(timings.to_text(TIMING_REPORT_TOP).trim_end());
+    }
+
+    let total_secs = progress.elapsed().as_secs_f64();
+    if write_opts.dry_run {
+        progress.summary(&format!(
+            "Dry run: {} of {} files would change ({:.3}s).",
+            changed, count, total_secs
+        ));
+        let gate = check_diagnostics(tally.as_ref(), write_opts, &progress);
+        return report_failures(&failures, total, write_opts, &progress).and(gate);
+    }
+
+    let full_run = write_opts.filter.is_empty() && write_opts.resume_from.is_none();
+    if full_run {
+        let ids: Vec<&str> = articles.iter().map(|a| a.id.as_str()).collect();
+        for page in index::alphabetical_index(&ids, write_opts.index_pages) {
+            let page_path = md_root.join(&page.path);
+            write_generated_page(&page_path, &page.content)?;
+        }
+        if write_opts.category_pages {
+            let members = articles
+                .iter()
+                .map(|a| (a.id.as_str(), a.categories.as_slice()));
+            for page in index::category_pages(members) {
+                write_generated_page(&md_root.join(&page.path), &page.content)?;
+            }
+        }
+    }
+    if let Some(manifest_path) = &write_opts.manifest {
+        let entries: Vec<ManifestEntry> = articles
+            .iter_mut()
+            .filter_map(|a| a.manifest.take())
+            .collect();
+        let manifest = if full_run {
+            Manifest::new(entries)
+        } else {
+            Manifest::load(manifest_path)?
+                .unwrap_or_default()
+                .merge(entries)
+        };
+        write_generated_page(manifest_path, &manifest.to_json())?;
+        progress.summary(&format!("Wrote manifest to {}.", manifest_path.display()));
+    }
+    if let Some(map_path) = &write_opts.anchor_map {
+        let anchors: Vec<ArticleAnchors> = articles
+            .iter_mut()
+            .filter_map(|a| a.anchors.take())
+            .collect();
+        write_generated_page(map_path, &AnchorMap::build(&anchors).to_json())?;
+        progress.summary(&format!("Wrote anchor map to {}.", map_path.display()));
+    }
+    if let Some(index_path) = &write_opts.search_index {
+        let docs = articles.into_iter().filter_map(|a| a.search).collect();
+        write_search_index(index_path, docs)?;
+        progress.summary(&format!("Wrote search index to {}.", index_path.display()));
+    }
+
+    let avg_str = if count > 0 {
+        format!("{:.3}s", total_secs / count as f64)
+    } else {
+        "-".to_string()
+    };
+
+    tracing::info!(count, skipped, seconds = total_secs, "regenerated");
+    progress.summary(&format!(
+        "Done. Regenerated {} files in {:.3}s (avg {}/doc).",
+        count, total_secs, avg_str
+    ));
+    if skipped > 0 {
+        progress.summary(&format!("Skipped {} existing files.", skipped));
+    }
+    if let Some(vault) = &write_opts.vault {
+        let failed = vault.failed_downloads();
+        if !failed.is_empty() {
+            progress.summary(&format!(
+                "Could not download {} attachments; their links keep the wiki URL.",
+                failed.len()
+            ));
+        }
+    }
+    let gate = check_diagnostics(tally.as_ref(), write_opts, &progress);
+    report_failures(&failures, total, write_opts, &progress).and(gate)
+}
+
+/// Every `.wiki` file under `wiki_root`, in path order.
+pub(crate) fn wiki_source_files(wiki_root: &Path) -> Vec<walkdir::DirEntry> {
+    let mut entries: Vec<_> = WalkDir::new(wiki_root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "wiki")
+        })
+        .collect();
+
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    entries
+}
+
+/// The redirect pages under `wiki_root`, by target. Only files starting with
+/// `#REDIRECT` are parsed.
+fn collect_redirects(wiki_root: &Path) -> frontmatter::RedirectAliases {
+    let mut redirects = frontmatter::RedirectAliases::new();
+    for entry in wiki_source_files(wiki_root) {
+        let Ok(src) = read_wiki_source(entry.path()) else {
+            continue;
+        };
+        if !parse::util::starts_with_ignore_ascii_case(src.trim_start(), "#redirect") {
+            continue;
+        }
+        if let Some(redirect) = parse_source(&src, &parse::ParseOptions::default())
+            .document
+            .redirect
+        {
+            redirects.add(&article_id_from_path(entry.path()), &redirect);
+        }
+    }
+    redirects
+}
+
+/// Where the `.wiki` source of `article_id` is cached under `wiki_root`.
+pub(crate) fn wiki_path_for(wiki_root: &Path, article_id: &str) -> PathBuf {
+    wiki_root
+        .join(lower_first_letter_bucket(article_id))
+        .join(format!("{}.wiki", layout::safe_file_name(article_id)))
+}
+
+/// The article id a `.wiki` file was cached under (`k/Ken_Thompson.wiki` -> `Ken_Thompson`).
+pub(crate) fn article_id_from_path(wiki_path: &Path) -> String {
+    // `X.json.gz` is still article `X`.
+    let path = match is_gzip_path(wiki_path) {
+        true => Path::new(wiki_path.file_stem().unwrap_or_default()),
+        false => wiki_path,
+    };
+    path.file_stem()
+        .map(|s| layout::decode_file_name(&s.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+/// Graph mode: parse every article in the config's wiki directory and collect the
+/// page-to-page links between them.
+pub fn build_link_graph(config: &Config, opts: &graph::GraphOptions) -> Result<graph::LinkGraph> {
+    let wiki_root: &Path = &config.wiki_dir;
+    require_wiki_root(wiki_root)?;
+    let mut pages = Vec::new();
+    for entry in wiki_source_files(wiki_root) {
+        let article_id = article_id_from_path(entry.path());
+        let ast = parse_file(entry.path(), &config.parse)?;
+        pages.push((article_id, ast.document));
+    }
+    Ok(graph::LinkGraph::build(
+        pages.iter().map(|(id, doc)| (id.as_str(), doc)),
+        opts,
+    ))
+}
+
+/// Watch mode: regenerate the `.md` file for one changed `.wiki` file under the
+/// config's wiki directory, the way bulk mode would. Returns the `.md` path and
+/// the parser's diagnostics.
+pub(crate) fn regenerate_file(
+    config: &Config,
+    path: &Path,
+) -> Result<(PathBuf, Vec<ast::Diagnostic>)> {
+    let stage = Cell::new(Stage::Read);
+    let write_opts = WriteOptions {
+        skip_existing: false,
+        ..config.write.clone()
+    };
+    match regenerate_entry(
+        path,
+        &config.wiki_dir,
+        &config.md_dir,
+        &config.json_dir,
+        &config.render,
+        &config.parse,
+        &write_opts,
+        &stage,
+        &mut render::RenderPool::default(),
+        &frontmatter::RedirectAliases::default(),
+    )? {
+        EntryOutcome::Regenerated {
+            md_path, article, ..
+        } => Ok((md_path, article.diagnostics)),
+        EntryOutcome::Skipped { .. } => unreachable!("skip_existing is off"),
+    }
+}
+
+/// Stats mode: parse every article in the config's wiki directory, or with
+/// `json_dir`, read the cached `.json` ASTs under it instead, and count what's in them.
+pub fn corpus_stats(config: &Config, json_dir: Option<&Path>) -> Result<stats::CorpusStats> {
+    let mut corpus = stats::CorpusStats::new();
+    if let Some(json_dir) = json_dir {
+        if !json_dir.exists() {
+            return Err(Error::Io {
+                path: json_dir.to_path_buf(),
+                source: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "JSON directory not found",
+                ),
+            });
+        }
+        let mut paths: Vec<PathBuf> = WalkDir::new(json_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && is_json_ast_path(e.path()))
+            .map(|e| e.into_path())
+            .collect();
+        paths.sort();
+        for path in paths {
+            let ast = read_ast_file(&path)?;
+            corpus.add(
+                &ast.article_id,
+                &ast.document,
+                &ast.diagnostics,
+                ast.source.byte_len,
+                None,
+            );
+        }
+        return Ok(corpus);
+    }
+
+    let wiki_root: &Path = &config.wiki_dir;
+    require_wiki_root(wiki_root)?;
+    for entry in wiki_source_files(wiki_root) {
+        let wiki_content = read_wiki_source(entry.path())?;
+        let start = Instant::now();
+        let parsed = parse_source(&wiki_content, &config.parse);
+        let parse_time = start.elapsed();
+        corpus.add(
+            &article_id_from_path(entry.path()),
+            &parsed.document,
+            &parsed.diagnostics,
+            wiki_content.len() as u64,
+            Some(parse_time),
+        );
+    }
+    Ok(corpus)
+}
+
+/// Check the external links of every article in the `.wiki` cache.
+pub fn check_external_links(
+    config: &Config,
+    opts: &linkcheck::LinkCheckOptions,
+) -> Result<linkcheck::LinkReport> {
+    let wiki_root: &Path = &config.wiki_dir;
+    require_wiki_root(wiki_root)?;
+    let mut pages = Vec::new();
+    for entry in wiki_source_files(wiki_root) {
+        let article_id = article_id_from_path(entry.path());
+        let ast = parse_file(entry.path(), &config.parse)?;
+        pages.push((article_id, ast.document));
+    }
+    linkcheck::check_links(pages.iter().map(|(id, doc)| (id.as_str(), doc)), opts)
+}
+
+/// The step of regenerating one file that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Stage {
+    Read,
+    Parse,
+    Render,
+    Write,
+}
+
+impl Stage {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stage::Read => "read",
+            Stage::Parse => "parse",
+            Stage::Render => "render",
+            Stage::Write => "write",
+        }
+    }
+}
+
+/// One entry of `failures.json`.
+#[derive(Debug, Serialize)]
+struct Failure {
+    file: PathBuf,
+    stage: Stage,
+    error: String,
+}
+
+/// What bulk mode keeps about each converted (or skipped) article for the pages
+/// written at the end of the run.
+struct Article {
+    id: String,
+    categories: Vec<ast::CategoryTag>,
+    diagnostics: Vec<ast::Diagnostic>,
+    search: Option<SearchDocument>,
+    anchors: Option<ArticleAnchors>,
+    manifest: Option<ManifestEntry>,
+}
+
+/// Warning and error occurrences across a bulk run, for `max_warnings`.
+#[derive(Debug, Default)]
+struct DiagnosticTally {
+    warnings: usize,
+    errors: usize,
+    /// Warnings plus errors per article, for those that have any.
+    articles: Vec<(String, usize)>,
+    codes: BTreeMap<String, usize>,
+}
+
+impl DiagnosticTally {
+    fn new(articles: &[Article]) -> Self {
+        let mut tally = DiagnosticTally::default();
+        for article in articles {
+            let mut total = 0;
+            for d in &article.diagnostics {
+                let n = d.occurrences as usize;
+                match d.severity {
+                    ast::Severity::Warning => tally.warnings += n,
+                    ast::Severity::Error => tally.errors += n,
+                    ast::Severity::Info => continue,
+                }
+                total += n;
+                *tally
+                    .codes
+                    .entry(d.code.clone().unwrap_or_else(|| "(none)".to_string()))
+                    .or_default() += n;
+            }
+            if total > 0 {
+                tally.articles.push((article.id.clone(), total));
+            }
+        }
+        tally
+    }
+}
+
+/// With `max_warnings`, print the articles and codes with the most warnings and
+/// errors and fail if there are too many.
+fn check_diagnostics(
+    tally: Option<&DiagnosticTally>,
+    write_opts: &WriteOptions,
+    progress: &progress::Progress,
+) -> Result<()> {
+    let (Some(tally), Some(max_warnings)) = (tally, write_opts.max_warnings) else {
+        return Ok(());
+    };
+    if tally.warnings <= max_warnings && tally.errors == 0 {
+        return Ok(());
+    }
+
+    let mut articles: Vec<(&str, usize)> = tally
+        .articles
+        .iter()
+        .map(|(id, n)| (id.as_str(), *n))
+        .collect();
+    articles.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let mut codes: Vec<(&str, usize)> = tally
+        .codes
+        .iter()
+        .map(|(code, n)| (code.as_str(), *n))
+        .collect();
+    codes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut report = format!(
+        "{} warnings (at most {} allowed) and {} errors.",
+        tally.warnings, max_warnings, tally.errors
+    );
+    for (heading, rows) in [("ARTICLE", &articles), ("CODE", &codes)] {
+        let rows = &rows[..rows.len().min(DIAGNOSTICS_REPORT_TOP)];
+        let width = rows
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(heading.len());
+        report.push_str(&format!("\n\n{:<width$}  COUNT", heading));
+        for (name, n) in rows {
+            report.push_str(&format!("\n{:<width$}  {}", name, n));
+        }
+    }
+    progress.error(&report);
+
+    Err(Error::Diagnostics {
+        warnings: tally.warnings,
+        errors: tally.errors,
+        max_warnings,
+    })
+}
+
+enum EntryOutcome {
+    Skipped {
+        article: Article,
+    },
+    Regenerated {
+        md_path: PathBuf,
+        timing: progress::FileTiming,
+        changed: bool,
+        article: Article,
+    },
+}
+
+/// Regenerate the `.md` file for one `.wiki` file in bulk mode. `stage` is kept
+/// up to date so a failure (or panic) can be attributed to a step; `pool` and
+/// `redirects` are shared by every entry of the run.
+#[allow(clippy::too_many_arguments)]
+fn regenerate_entry(
+    path: &Path,
+    wiki_root: &Path,
+    md_root: &Path,
+    json_root: &Path,
+    render_opts: &render::RenderOptions,
+    parse_opts: &parse::ParseOptions,
+    write_opts: &WriteOptions,
+    stage: &Cell<Stage>,
+    pool: &mut render::RenderPool,
+    redirects: &frontmatter::RedirectAliases,
+) -> Result<EntryOutcome> {
+    // determine relative path structure to maintain the same structure in the md/ directory.
+    let relative = path.strip_prefix(wiki_root).map_err(Error::config)?;
+
+    // convert the filename from underscores to spaces for the destination `.md`
+    let parent_rel = relative.parent().unwrap_or(Path::new(""));
+    let stem = relative
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Untitled");
+    let article_id = layout::decode_file_name(stem);
+    let _span = tracing::info_span!("article", id = %article_id).entered();
+    let md_name = if write_opts.slugify_filenames {
+        format!("{}.md", layout::slugify(&article_id))
+    } else {
+        format!(
+            "{}.md",
+            layout::safe_file_name(&article_id.replace('_', " "))
+        )
+    };
+
+    stage.set(Stage::Read);
+    let read_start = Instant::now();
+    let wiki_content = read_wiki_source(path)?;
+    let mut timing = progress::FileTiming {
+        read: read_start.elapsed(),
+        ..Default::default()
+    };
+    stage.set(Stage::Parse);
+    let parse_start = Instant::now();
+    let ast = {
+        let _span = tracing::info_span!("parse", path = %path.display()).entered();
+        let mut ast = parse_source(&wiki_content, parse_opts);
+        if write_opts.transclude {
+            transclude_from_cache(&mut ast, &article_id, wiki_root);
+        }
+        apply_ast_filters(&mut ast, &article_id, path, &write_opts.ast_filters)?;
+        ast
+    };
+    timing.parse = parse_start.elapsed();
+
+    stage.set(Stage::Write);
+    let md_path = match &write_opts.path_template {
+        Some(template) => layout::expand_path_template(
+            template,
+            "md",
+            &article_id,
+            Some(&ast.document),
+            write_opts.slugify_filenames,
+        )
+        .map_err(Error::config)?,
+        None => md_root.join(parent_rel).join(md_name),
+    };
+    let json_path = match &write_opts.path_template {
+        Some(template) => layout::expand_path_template(
+            template,
+            "json",
+            &article_id,
+            Some(&ast.document),
+            write_opts.slugify_filenames,
+        )
+        .map_err(Error::config)?,
+        None => json_root.join(parent_rel).join(format!("{}.json", stem)),
+    };
+    let json_path = write_opts.json_output_path(json_path);
+
+    // skipped files are still indexed, so the index always covers the whole site.
+    let search = write_opts.search_index.as_ref().map(|_| {
+        let rel = md_path.strip_prefix(md_root).unwrap_or(&md_path);
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        SearchDocument::new(
+            &article_id,
+            rel,
+            &ast.document,
+            frontmatter::extract_tags(&ast.document, &article_id),
+        )
+    });
+
+    let anchors = write_opts.anchor_map.as_ref().map(|_| {
+        let mut anchors = ArticleAnchors::new(&article_id, &ast.document);
+        if render_opts.table_anchors {
+            anchors.add_tables(&ast.document);
+        }
+        anchors
+    });
+
+    // the entry for the manifest, once the output is known.
+    let manifest_entry =
+        |md: &[u8], json_hash: Option<String>, opts: &render::RenderOptions| ManifestEntry {
+            article_id: article_id.clone(),
+            source: manifest::manifest_path(path),
+            source_hash: manifest::content_hash(wiki_content.as_bytes()),
+            outputs: std::iter::once(ManifestOutput::new(&md_path, md))
+                .chain(json_hash.map(|hash| ManifestOutput::hashed(&json_path, hash)))
+                .collect(),
+            diagnostics: ast.diagnostics.len(),
+            raw_blocks: ast::raw_block_count(&ast.document),
+            render_options: opts.fingerprint(),
+        };
+
+    if write_opts.skip_existing && md_path.exists() && !is_stale(&md_path, render_opts, write_opts)?
+    {
+        let manifest = match &write_opts.manifest {
+            Some(_) => {
+                let md = fs::read(&md_path).map_err(Error::io(&md_path))?;
+                Some(manifest_entry(
+                    &md,
+                    None,
+                    &article_render_options(&md_path, render_opts),
+                ))
+            }
+            None => None,
+        };
+        return Ok(EntryOutcome::Skipped {
+            article: Article {
+                id: article_id,
+                categories: ast.document.categories,
+                diagnostics: ast.diagnostics,
+                search,
+                anchors,
+                manifest,
+            },
+        });
+    }
+
+    // ensure the parent and bucket directories exist for the target files
+    let json_enabled = write_opts.write_json && !write_opts.dry_run;
+    for out_path in [Some(&md_path), json_enabled.then_some(&json_path)]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(parent) = out_path.parent()
+            && !write_opts.dry_run
+        {
+            fs::create_dir_all(parent).map_err(Error::io(parent))?;
+        }
+    }
+    let json_hash = match json_enabled {
+        true => Some(write_json_ast_for_wiki(
+            &article_id,
+            path,
+            &ast,
+            &json_path,
+            write_opts,
+        )?),
+        false => None,
+    };
+
+    stage.set(Stage::Render);
+    let article_opts = article_render_options(&md_path, render_opts);
+    let render_start = Instant::now();
+    let md_body = render_body_pooled(&ast.document, &article_opts, pool);
+    timing.render = render_start.elapsed();
+
+    stage.set(Stage::Write);
+    let existing = if write_opts.dry_run {
+        fs::read_to_string(&md_path).ok()
+    } else {
+        None
+    };
+    let full_md = write_markdown_file(
+        &md_path,
+        path,
+        &article_id,
+        &ast.document,
+        &md_body,
+        write_opts,
+        &article_opts,
+        redirects.for_article(&article_id),
+        &mut timing,
+    )?;
+    let changed = !write_opts.dry_run || existing.as_deref() != Some(full_md.as_str());
+    let manifest = write_opts
+        .manifest
+        .as_ref()
+        .map(|_| manifest_entry(full_md.as_bytes(), json_hash, &article_opts));
+
+    Ok(EntryOutcome::Regenerated {
+        md_path,
+        timing,
+        changed,
+        article: Article {
+            id: article_id,
+            categories: ast.document.categories,
+            diagnostics: ast.diagnostics,
+            search,
+            anchors,
+            manifest,
+        },
+    })
+}
+
+/// With `generation_metadata`, whether an existing `.md` file was generated by
+/// another parser or schema version or with other render options.
+fn is_stale(
+    md_path: &Path,
+    render_opts: &render::RenderOptions,
+    write_opts: &WriteOptions,
+) -> Result<bool> {
+    if !write_opts.generation_metadata {
+        return Ok(false);
+    }
+    let md = fs::read_to_string(md_path).map_err(Error::io(md_path))?;
+    let current = GenerationInfo::current(&article_render_options(md_path, render_opts));
+    Ok(GenerationInfo::from_markdown(&md) != Some(current))
+}
+
+/// Write the search index for the articles of a bulk run.
+fn write_search_index(index_path: &Path, documents: Vec<SearchDocument>) -> Result<()> {
+    write_generated_page(index_path, &SearchIndex::new(documents).to_json())
+}
+
+/// Write a file generated at the end of a bulk run, creating its directory.
+fn write_generated_page(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(Error::io(parent))?;
+    }
+    write_atomic(path, contents.as_bytes()).map_err(Error::io(path))
+}
+
+/// Print the keep-going summary table and write the failures file. Errors if any
+/// file failed, so the run still exits non-zero.
+fn report_failures(
+    failures: &[Failure],
+    total: usize,
+    write_opts: &WriteOptions,
+    progress: &progress::Progress,
+) -> Result<()> {
+    if !write_opts.keep_going {
+        return Ok(());
+    }
+    if let Some(failures_path) = &write_opts.failures_path
+        && !write_opts.dry_run
+    {
+        if let Some(parent) = failures_path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(Error::io(parent))?;
+        }
+        let mut json =
+            serde_json::to_string_pretty(failures).map_err(|e| Error::Config(e.to_string()))?;
+        json.push('\n');
+        write_atomic(failures_path, json.as_bytes()).map_err(Error::io(failures_path))?;
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    let files: Vec<String> = failures
+        .iter()
+        .map(|f| f.file.display().to_string())
+        .collect();
+    let file_width = files
+        .iter()
+        .map(|f| f.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let mut table = format!("{:<6}  {:<file_width$}  ERROR", "STAGE", "FILE");
+    for (f, file) in failures.iter().zip(&files) {
+        table.push_str(&format!(
+            "\n{:<6}  {:<file_width$}  {}",
+            f.stage.as_str(),
+            file,
+            f.error.lines().next().unwrap_or("")
+        ));
+    }
+    progress.error(&table);
+
+    Err(Error::Failures {
+        failed: failures.len(),
+        total,
+        failures_path: write_opts
+            .failures_path
+            .clone()
+            .filter(|_| !write_opts.dry_run),
+    })
+}
+
+fn require_wiki_root(wiki_root: &Path) -> Result<()> {
+    if wiki_root.exists() {
+        return Ok(());
+    }
+    Err(Error::Io {
+        path: wiki_root.to_path_buf(),
+        source: std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "wiki source directory not found",
+        ),
+    })
+}
+
+fn missing_offline(wiki_path: &Path) -> Error {
+    Error::Io {
+        path: wiki_path.to_path_buf(),
+        source: std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "article source not found (offline, not fetching)",
+        ),
+    }
+}
+
+/// The error for a panic caught while `stage` of regenerating `wiki_path` ran.
+fn panic_error(wiki_path: &Path, stage: Stage, payload: &(dyn std::any::Any + Send)) -> Error {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", s)
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", s)
+    } else {
+        "panicked".to_string()
+    };
+    let article_id = article_id_from_path(wiki_path);
+    match stage {
+        Stage::Parse => Error::Parse {
+            article_id,
+            path: wiki_path.to_path_buf(),
+            message,
+        },
+        Stage::Render => Error::Render {
+            article_id,
+            message,
+        },
+        Stage::Read | Stage::Write => Error::Io {
+            path: wiki_path.to_path_buf(),
+            source: std::io::Error::other(message),
+        },
+    }
+}
+
+/// `render_opts` with the `wiki2md.render:` overrides from the existing `.md` file's
+/// frontmatter applied, if it has any.
+fn article_render_options<'a>(
+    md_path: &Path,
+    render_opts: &'a render::RenderOptions,
+) -> Cow<'a, render::RenderOptions> {
+    let Ok(existing) = fs::read_to_string(md_path) else {
+        return Cow::Borrowed(render_opts);
+    };
+    let Some((fm, _)) = frontmatter::split_yaml_frontmatter(&existing) else {
+        return Cow::Borrowed(render_opts);
+    };
+    match frontmatter::parse_render_overrides(&fm) {
+        Ok(Some(overrides)) => Cow::Owned(overrides.apply(render_opts)),
+        Ok(None) => Cow::Borrowed(render_opts),
+        Err(e) => {
+            tracing::warn!(path = %md_path.display(), error = %e, "ignoring invalid wiki2md.render frontmatter");
+            Cow::Borrowed(render_opts)
+        }
+    }
+}
+
+/// Parse a `.wiki` file into a document that owns its text, for callers that
+/// keep documents around after the source is gone.
+#[tracing::instrument(name = "parse", level = "info", skip_all, fields(path = %wiki_path.display()))]
+pub(crate) fn parse_file(
+    wiki_path: &Path,
+    opts: &parse::ParseOptions,
+) -> Result<parse::ParseOutput<'static>> {
+    let wiki_content = read_wiki_source(wiki_path)?;
+    let out = parse_source(&wiki_content, opts);
+    Ok(parse::ParseOutput {
+        document: out.document.into_owned(),
+        diagnostics: out.diagnostics,
+        byte_len: out.byte_len,
+    })
+}
+
+/// Expand the `{{:Page}}` transclusions of `ast` with pages from the wiki directory,
+/// adding diagnostics for the ones that stay.
+fn transclude_from_cache(ast: &mut parse::ParseOutput, article_id: &str, wiki_root: &Path) {
+    let mut load = |title: &str| {
+        let path = wiki_path_for(wiki_root, &sanitize_article_id(title));
+        path.exists()
+            .then(|| read_wiki_source(&path).ok())
+            .flatten()
+    };
+    let diagnostics = transclude::transclude(
+        &mut ast.document,
+        article_id,
+        &mut load,
+        &transclude::TranscludeOptions::default(),
+    );
+    ast.diagnostics.extend(diagnostics);
+}
+
+/// Pipe `ast` through `filters` in order, keeping the document and diagnostics
+/// the last one returns.
+fn apply_ast_filters(
+    ast: &mut parse::ParseOutput,
+    article_id: &str,
+    wiki_path: &Path,
+    filters: &[AstFilter],
+) -> Result<()> {
+    if filters.is_empty() {
+        return Ok(());
+    }
+    let mut file = ast_file(article_id, wiki_path, ast);
+    for filter in filters {
+        file = filter.run(&file)?;
+    }
+    ast.document = file.document;
+    ast.diagnostics = file.diagnostics;
+    Ok(())
+}
+
+fn read_wiki_source(wiki_path: &Path) -> Result<String> {
+    let bytes = fs::read(wiki_path).map_err(Error::io(wiki_path))?;
+
+    // if we ever encounter invalid UTF-8, fallback to lossy conversion
+    Ok(String::from_utf8(bytes)
+        .unwrap_or_else(|e| String::from_utf8_lossy(&e.into_bytes()).to_string()))
+}
+
+/// Stream the article's AST to `json_path` and return the [`manifest::content_hash`]
+/// of the bytes written, so large ASTs are never held in memory as one string.
+fn write_json_ast_for_wiki(
+    article_id: &str,
+    wiki_path: &Path,
+    parse_out: &parse::ParseOutput,
+    json_path: &Path,
+    write_opts: &WriteOptions,
+) -> Result<String> {
+    let ast_file = ast_file(article_id, wiki_path, parse_out);
+
+    let mut hash = None;
+    write_atomic_with(json_path, |file| {
+        let mut out = HashingWriter::new(BufWriter::new(file));
+        match write_opts.json_gzip {
+            true => {
+                let mut gz = GzEncoder::new(&mut out, Compression::default());
+                write_json(&mut gz, &ast_file, write_opts.json_compact)?;
+                gz.finish()?;
+            }
+            false => write_json(&mut out, &ast_file, write_opts.json_compact)?,
+        }
+        hash = Some(out.finish()?);
+        Ok(())
+    })
+    .map_err(|e| match e.downcast::<serde_json::Error>() {
+        Ok(source) => Error::Json {
+            article_id: article_id.to_string(),
+            path: json_path.to_path_buf(),
+            source,
+        },
+        Err(e) => Error::io(json_path)(e),
+    })?;
+    Ok(hash.unwrap_or_default())
+}
+
+fn write_json(out: impl Write, ast_file: &ast::AstFile, compact: bool) -> std::io::Result<()> {
+    // pretty by default, so it's easy to inspect / diff.
+    match compact {
+        true => serde_json::to_writer(out, ast_file),
+        false => serde_json::to_writer_pretty(out, ast_file),
+    }
+    .map_err(|e| match e.is_io() {
+        true => e.into(),
+        false => std::io::Error::other(e),
+    })
+}
+
+/// A writer that hashes everything passed through it, like [`manifest::content_hash`].
+struct HashingWriter<W: Write> {
+    inner: W,
+    hash: md5::Context,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hash: md5::Context::new(),
+        }
+    }
+
+    /// Flush the inner writer and return the hash.
+    fn finish(mut self) -> std::io::Result<String> {
+        self.inner.flush()?;
+        Ok(format!("{:x}", self.hash.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hash.consume(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The JSON envelope for a parsed article.
+fn ast_file(article_id: &str, wiki_path: &Path, parse_out: &parse::ParseOutput) -> ast::AstFile {
+    ast::AstFile {
+        schema_version: ast::SCHEMA_VERSION,
+        parser: ast::ParserInfo {
+            name: ast::PARSER_NAME.to_string(),
+            version: ast::PARSER_VERSION.to_string(),
+        },
+        span_encoding: ast::SpanEncoding::default(),
+        article_id: article_id.to_string(),
+        source: ast::SourceInfo {
+            path: Some(wiki_path.to_string_lossy().to_string()),
+            byte_len: parse_out.byte_len as u64,
+        },
+        diagnostics: parse_out.diagnostics.clone(),
+        document: parse_out.document.clone().into_owned(),
+    }
+}
+
+fn render_markdown_from_json(
+    article_id: &str,
+    wiki_path: &Path,
+    json_path: &Path,
+    md_path: &Path,
+    render_opts: &render::RenderOptions,
+    write_opts: &WriteOptions,
+) -> Result<String> {
+    let ast_file = read_ast_file(json_path)?;
+    let md_body = render_body(&ast_file.document, render_opts);
+    let full = write_markdown_file(
+        md_path,
+        wiki_path,
+        article_id,
+        &ast_file.document,
+        &md_body,
+        write_opts,
+        render_opts,
+        &[],
+        &mut Default::default(),
+    )?;
+    Ok(full)
+}
+
+#[tracing::instrument(name = "write", level = "info", skip_all, fields(path = %md_path.display()))]
+#[allow(clippy::too_many_arguments)]
+fn write_markdown_file(
+    md_path: &Path,
+    wiki_path: &Path,
+    article_id: &str,
+    doc: &ast::Document,
+    md_body: &str,
+    write_opts: &WriteOptions,
+    render_opts: &render::RenderOptions,
+    aliases: &[String],
+    timing: &mut progress::FileTiming,
+) -> Result<String> {
+    let write_start = Instant::now();
+    let localized;
+    let md_body = match &write_opts.vault {
+        Some(vault) => {
+            localized = vault.localize_files(
+                md_body,
+                md_path,
+                &render_opts.image_base(),
+                write_opts.dry_run,
+            );
+            localized.as_str()
+        }
+        None => md_body,
+    };
+    let existing = if md_path.exists() {
+        Some(fs::read_to_string(md_path).map_err(Error::io(md_path))?)
+    } else {
+        None
+    };
+
+    let frontmatter_start = Instant::now();
+    let out = compose_markdown(
+        existing.as_deref(),
+        wiki_path,
+        article_id,
+        doc,
+        md_body,
+        write_opts,
+        render_opts,
+        aliases,
+    )?;
+    timing.frontmatter = frontmatter_start.elapsed();
+
+    let changed = existing.as_deref() != Some(out.as_str());
+    if write_opts.dry_run {
+        tracing::info!(changed, dry_run = true, "compared");
+        print!("{}", unified_diff(md_path, existing.as_deref(), &out));
+        timing.write = write_start.elapsed() - timing.frontmatter;
+        return Ok(out);
+    }
+
+    if let Some(previous) = existing.as_deref()
+        && previous != out
+        && (write_opts.backup || write_opts.backup_dir.is_some())
+    {
+        backup_markdown_file(md_path, previous, write_opts.backup_dir.as_deref())
+            .map_err(Error::io(md_path))?;
+    }
+    write_atomic(md_path, out.as_bytes()).map_err(Error::io(md_path))?;
+    tracing::info!(bytes = out.len(), changed, "wrote");
+    timing.write = write_start.elapsed() - timing.frontmatter;
+    Ok(out)
+}
+
+/// Frontmatter (kept from `existing` unless regenerating), title heading, and body.
+/// `aliases` (the pages redirecting to the article) are added to generated frontmatter.
+#[allow(clippy::too_many_arguments)]
+fn compose_markdown(
+    existing: Option<&str>,
+    wiki_path: &Path,
+    article_id: &str,
+    doc: &ast::Document,
+    md_body: &str,
+    write_opts: &WriteOptions,
+    render_opts: &render::RenderOptions,
+    aliases: &[String],
+) -> Result<String> {
+    let mut frontmatter_text: Option<String> = None;
+
+    if let Some(existing_text) = existing
+        && let Some((fm, _)) = frontmatter::split_yaml_frontmatter(existing_text)
+        && !write_opts.regenerate_frontmatter
+    {
+        frontmatter_text = Some(fm);
+    }
+
+    if frontmatter_text.is_none() {
+        let mut fm = frontmatter::build_frontmatter(
+            article_id,
+            wiki_path,
+            doc,
+            &render_opts.mediawiki_base_url,
+        )
+        .map_err(|e| Error::Frontmatter {
+            article_id: article_id.to_string(),
+            path: wiki_path.to_path_buf(),
+            source: e.into(),
+        })?;
+        fm.add_aliases(aliases.iter().map(String::as_str));
+
+        fm.infobox = render_opts
+            .infobox
+            .as_ref()
+            .and_then(|opts| infobox::extract_infobox(doc, opts));
+
+        // when explicitly regenerating frontmatter, preserve user-authored summary and any
+        // unknown top-level YAML keys.
+        if write_opts.regenerate_frontmatter
+            && let Some(existing_text) = existing
+        {
+            frontmatter::merge_existing_frontmatter_for_regeneration(&mut fm, existing_text);
+        }
+
+        frontmatter_text = Some(fm.to_yaml_string());
+    }
+
+    let mut out = String::new();
+    if let Some(fm) = frontmatter_text {
+        out.push_str(&fm);
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        // blank line after frontmatter for readability.
+        out.push('\n');
+    }
+
+    // article title as the top-level heading.
+    let title = frontmatter::display_title(doc, article_id);
+    out.push_str("# ");
+    out.push_str(title.trim());
+    out.push_str("\n\n");
+
+    // avoid leading blank lines in the body to keep output stable.
+    let body = md_body.trim_start_matches(['\n', '\r']);
+    out.push_str(body);
+
+    if write_opts.generation_metadata {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(&GenerationInfo::current(render_opts).to_comment());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// A unified diff from `old` (`None` = the file doesn't exist yet) to `new`.
+/// Empty when nothing changed.
+fn unified_diff(path: &Path, old: Option<&str>, new: &str) -> String {
+    if old == Some(new) {
+        return String::new();
+    }
+    let name = path.to_string_lossy();
+    let original = match old {
+        Some(_) => format!("a/{}", name),
+        None => "/dev/null".to_string(),
+    };
+    diffy::DiffOptions::new()
+        .set_original_filename(original)
+        .set_modified_filename(format!("b/{}", name))
+        .create_patch(old.unwrap_or(""), new)
+        .to_string()
+}
+
+/// Write `contents` to a temp file next to `path`, then rename it into place, so
+/// a crash mid-write never leaves a truncated file behind.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    write_atomic_with(path, |f| f.write_all(contents))
+}
+
+/// Like [`write_atomic`], but `write` streams the contents into the temporary file.
+fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut f = fs::File::create(&tmp_path)?;
+        write(&mut f)?;
+        f.sync_all()?;
+        drop(f);
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn backup_markdown_file(
+    md_path: &Path,
+    previous: &str,
+    backup_dir: Option<&Path>,
+) -> std::io::Result<()> {
+    let file_name = md_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let backup_path = match backup_dir {
+        Some(dir) => {
+            let bucket = md_path.parent().and_then(|p| p.file_name());
+            let dir = match bucket {
+                Some(b) => dir.join(b),
+                None => dir.to_path_buf(),
+            };
+            fs::create_dir_all(&dir)?;
+            dir.join(file_name)
+        }
+        None => md_path.with_file_name(format!("{}.bak", file_name)),
+    };
+    write_atomic(&backup_path, previous.as_bytes())
+}
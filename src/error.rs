@@ -0,0 +1,144 @@
+//! The library's error type.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `Result` with [`Error`] as the default error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An underlying error of any kind.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Everything that can go wrong converting an article.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Downloading the article's wikitext failed.
+    #[error("failed to fetch '{title}': {source}")]
+    Fetch {
+        title: String,
+        #[source]
+        source: BoxError,
+    },
+
+    /// The wikitext couldn't be parsed. The parser accepts any input, so this only
+    /// happens when it panics during a keep-going bulk run.
+    #[error("failed to parse '{article_id}' ({}): {message}", path.display())]
+    Parse {
+        article_id: String,
+        path: PathBuf,
+        message: String,
+    },
+
+    /// Rendering failed. Like [`Error::Parse`], only reported for panics caught by
+    /// a keep-going bulk run.
+    #[error("failed to render '{article_id}': {message}")]
+    Render { article_id: String, message: String },
+
+    /// Reading or writing a file failed.
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Building the article's YAML frontmatter failed.
+    #[error("failed to build frontmatter for '{article_id}' ({}): {source}", path.display())]
+    Frontmatter {
+        article_id: String,
+        path: PathBuf,
+        #[source]
+        source: BoxError,
+    },
+
+    /// Reading or writing the JSON AST failed.
+    #[error("invalid JSON AST for '{article_id}' ({}): {source}", path.display())]
+    Json {
+        article_id: String,
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// An AST filter command failed or printed something that isn't an AST.
+    #[error("AST filter '{command}' failed for '{article_id}': {source}")]
+    Filter {
+        article_id: String,
+        command: String,
+        #[source]
+        source: BoxError,
+    },
+
+    /// An option is invalid (unknown path template variable, `resume_from` article
+    /// not found, ...).
+    #[error("{0}")]
+    Config(String),
+
+    /// A keep-going bulk run finished, but some files failed.
+    #[error("{failed} of {total} files failed{}", failures_path.as_ref().map(|p| format!(" (see {})", p.display())).unwrap_or_default())]
+    Failures {
+        failed: usize,
+        total: usize,
+        failures_path: Option<PathBuf>,
+    },
+
+    /// A bulk run finished with more warnings than
+    /// [`WriteOptions::max_warnings`](crate::WriteOptions::max_warnings) allows, or
+    /// with errors.
+    #[error("{warnings} warnings (at most {max_warnings} allowed) and {errors} errors")]
+    Diagnostics {
+        warnings: usize,
+        errors: usize,
+        max_warnings: usize,
+    },
+}
+
+impl Error {
+    /// `map_err` adapter attaching `path` to an I/O error.
+    #[cfg(feature = "native")]
+    pub(crate) fn io(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// `map_err` adapter for [`Error::Config`].
+    #[cfg(feature = "native")]
+    pub(crate) fn config(e: impl std::fmt::Display) -> Error {
+        Error::Config(e.to_string())
+    }
+
+    /// The path this error is about, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Parse { path, .. }
+            | Error::Io { path, .. }
+            | Error::Frontmatter { path, .. }
+            | Error::Json { path, .. } => Some(path),
+            Error::Failures { failures_path, .. } => failures_path.as_deref(),
+            Error::Fetch { .. }
+            | Error::Render { .. }
+            | Error::Filter { .. }
+            | Error::Config(_)
+            | Error::Diagnostics { .. } => None,
+        }
+    }
+
+    /// The article this error is about, if any.
+    pub fn article_id(&self) -> Option<&str> {
+        match self {
+            Error::Parse { article_id, .. }
+            | Error::Render { article_id, .. }
+            | Error::Frontmatter { article_id, .. }
+            | Error::Json { article_id, .. }
+            | Error::Filter { article_id, .. } => Some(article_id),
+            Error::Fetch { .. }
+            | Error::Io { .. }
+            | Error::Config(_)
+            | Error::Failures { .. }
+            | Error::Diagnostics { .. } => None,
+        }
+    }
+}
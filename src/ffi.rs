@@ -0,0 +1,192 @@
+//! C ABI (the `wiki2md-ffi` feature). See `include/wiki2md.h`.
+//!
+//! Every function takes UTF-8 input as pointer + length and writes its output into
+//! a caller-owned buffer, `snprintf`-style: the return value is the full output
+//! length in bytes, and the output is only written if it fits in `out_cap`. Call
+//! once with `out = NULL, out_cap = 0` to size the buffer. Output is not
+//! NUL-terminated. Negative return values are errors (`WIKI2MD_ERR_*`).
+
+use crate::frontmatter::RenderOverrides;
+use crate::render::RenderOptions;
+use crate::{parse, parse_source, render_body};
+use std::panic::{self, AssertUnwindSafe};
+
+/// An argument pointer was NULL (with a non-zero length).
+pub const WIKI2MD_ERR_NULL: isize = -1;
+/// The input was not valid UTF-8.
+pub const WIKI2MD_ERR_UTF8: isize = -2;
+/// The options JSON was invalid.
+pub const WIKI2MD_ERR_OPTIONS: isize = -3;
+/// The conversion failed unexpectedly (a panic).
+pub const WIKI2MD_ERR_INTERNAL: isize = -4;
+
+/// Parse wikitext into the JSON AST envelope (as written by `--json`).
+///
+/// # Safety
+///
+/// `src` must point to `src_len` readable bytes, and `out` to `out_cap` writable
+/// bytes. Either pointer may be NULL when its length is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wiki2md_parse_to_json(
+    src: *const u8,
+    src_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+) -> isize {
+    // SAFETY: forwarded from this function's contract.
+    let src = match unsafe { input_str(src, src_len) } {
+        Ok(s) => s,
+        Err(code) => return code,
+    };
+    let json = panic::catch_unwind(|| {
+        serde_json::to_string(&parse::parse_wiki_to_envelope(src)).map_err(|_| WIKI2MD_ERR_INTERNAL)
+    });
+    match json {
+        // SAFETY: forwarded from this function's contract.
+        Ok(Ok(json)) => unsafe { write_output(json.as_bytes(), out, out_cap) },
+        Ok(Err(code)) => code,
+        Err(_) => WIKI2MD_ERR_INTERNAL,
+    }
+}
+
+/// Render wikitext to Markdown. `options_json` uses the keys of the
+/// `wiki2md.render:` frontmatter overrides (`{"center_tables": true}`); pass
+/// NULL/0 for the defaults.
+///
+/// # Safety
+///
+/// `src` must point to `src_len` readable bytes, `options_json` to `options_len`
+/// readable bytes, and `out` to `out_cap` writable bytes. Any pointer may be NULL
+/// when its length is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn wiki2md_render_markdown(
+    src: *const u8,
+    src_len: usize,
+    options_json: *const u8,
+    options_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+) -> isize {
+    // SAFETY: forwarded from this function's contract.
+    let (src, options_json) = match unsafe {
+        (
+            input_str(src, src_len),
+            input_str(options_json, options_len),
+        )
+    } {
+        (Ok(src), Ok(options)) => (src, options),
+        (Err(code), _) | (_, Err(code)) => return code,
+    };
+    let render_opts = match RenderOverrides::from_json(options_json) {
+        Ok(overrides) => overrides.apply(&RenderOptions::default()),
+        Err(_) => return WIKI2MD_ERR_OPTIONS,
+    };
+    let md = panic::catch_unwind(AssertUnwindSafe(|| {
+        render_body(
+            &parse_source(src, &parse::ParseOptions::default()).document,
+            &render_opts,
+        )
+    }));
+    match md {
+        // SAFETY: forwarded from this function's contract.
+        Ok(md) => unsafe { write_output(md.as_bytes(), out, out_cap) },
+        Err(_) => WIKI2MD_ERR_INTERNAL,
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes (or be NULL with `len == 0`).
+unsafe fn input_str<'a>(ptr: *const u8, len: usize) -> Result<&'a str, isize> {
+    if len == 0 {
+        return Ok("");
+    }
+    if ptr.is_null() {
+        return Err(WIKI2MD_ERR_NULL);
+    }
+    // SAFETY: the caller guarantees `len` readable bytes at `ptr`.
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+    std::str::from_utf8(bytes).map_err(|_| WIKI2MD_ERR_UTF8)
+}
+
+/// # Safety
+///
+/// `out` must point to `out_cap` writable bytes (or be NULL with `out_cap == 0`).
+unsafe fn write_output(data: &[u8], out: *mut u8, out_cap: usize) -> isize {
+    if data.len() <= out_cap && !data.is_empty() {
+        if out.is_null() {
+            return WIKI2MD_ERR_NULL;
+        }
+        // SAFETY: `data.len() <= out_cap` writable bytes at `out`; the caller's
+        // buffer can't overlap our freshly allocated output.
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), out, data.len()) };
+    }
+    data.len() as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    fn render(src: &str, options: &str) -> Result<String, isize> {
+        unsafe {
+            let needed = wiki2md_render_markdown(
+                src.as_ptr(),
+                src.len(),
+                options.as_ptr(),
+                options.len(),
+                ptr::null_mut(),
+                0,
+            );
+            if needed < 0 {
+                return Err(needed);
+            }
+            let mut buf = vec![0u8; needed as usize];
+            let written = wiki2md_render_markdown(
+                src.as_ptr(),
+                src.len(),
+                options.as_ptr(),
+                options.len(),
+                buf.as_mut_ptr(),
+                buf.len(),
+            );
+            assert_eq!(written, needed);
+            Ok(String::from_utf8(buf).unwrap())
+        }
+    }
+
+    #[test]
+    fn render_sizes_then_fills_the_buffer() {
+        assert_eq!(
+            render("'''Bold''' text.\n", "").unwrap().trim(),
+            "**Bold** text."
+        );
+        assert_eq!(render("x", r#"{"nope": true}"#), Err(WIKI2MD_ERR_OPTIONS));
+
+        let bad = [0xffu8, 0xfe];
+        let code = unsafe {
+            wiki2md_render_markdown(bad.as_ptr(), bad.len(), ptr::null(), 0, ptr::null_mut(), 0)
+        };
+        assert_eq!(code, WIKI2MD_ERR_UTF8);
+    }
+
+    #[test]
+    fn parse_to_json_writes_the_envelope() {
+        let src = "== Heading ==\n";
+        let mut buf = vec![0u8; 64 * 1024];
+        let n =
+            unsafe { wiki2md_parse_to_json(src.as_ptr(), src.len(), buf.as_mut_ptr(), buf.len()) };
+        assert!(n > 0);
+        let json: serde_json::Value = serde_json::from_slice(&buf[..n as usize]).unwrap();
+        assert!(json["document"].is_object());
+
+        // too small: nothing written, full length returned.
+        let mut small = [0u8; 4];
+        let m = unsafe {
+            wiki2md_parse_to_json(src.as_ptr(), src.len(), small.as_mut_ptr(), small.len())
+        };
+        assert_eq!(m, n);
+        assert_eq!(small, [0u8; 4]);
+    }
+}
@@ -0,0 +1,166 @@
+//! Article selection for bulk regeneration.
+//!
+//! Patterns are globs (`*` and `?`) unless prefixed with `re:`, in which case the
+//! rest is a regular expression. Each pattern is tried against the article id
+//! (`Ken_Thompson`), its title (`Ken Thompson`), and `bucket/id` (`k/Ken_Thompson`),
+//! so `k/*` selects a whole bucket.
+
+use regex::Regex;
+use std::error::Error;
+use std::time::SystemTime;
+use time::{Date, Month, OffsetDateTime};
+
+/// Which articles bulk regeneration converts. The default selects everything.
+#[derive(Debug, Clone, Default)]
+pub struct ArticleFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    since: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn parse(s: &str) -> Result<Pattern, Box<dyn Error>> {
+        match s.strip_prefix("re:") {
+            Some(re) => Ok(Pattern::Regex(Regex::new(re)?)),
+            None => Ok(Pattern::Glob(s.to_string())),
+        }
+    }
+
+    fn matches(&self, candidate: &str) -> bool {
+        match self {
+            Pattern::Glob(g) => glob_match(g, candidate),
+            Pattern::Regex(re) => re.is_match(candidate),
+        }
+    }
+}
+
+impl ArticleFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only convert articles matching at least one include pattern.
+    pub fn include(mut self, pattern: &str) -> Result<Self, Box<dyn Error>> {
+        self.include.push(Pattern::parse(pattern)?);
+        Ok(self)
+    }
+
+    /// Skip articles matching this pattern (exclusions win over inclusions).
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, Box<dyn Error>> {
+        self.exclude.push(Pattern::parse(pattern)?);
+        Ok(self)
+    }
+
+    /// Only convert articles whose `.wiki` source was modified at or after `since`.
+    pub fn since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// True if the filter selects everything.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.since.is_none()
+    }
+
+    /// Whether the article `article_id` in `bucket`, whose source was last
+    /// modified at `modified`, is selected.
+    pub fn matches(&self, article_id: &str, bucket: &str, modified: Option<SystemTime>) -> bool {
+        let title = article_id.replace('_', " ");
+        let qualified = format!("{}/{}", bucket, article_id);
+        let hit = |p: &Pattern| p.matches(article_id) || p.matches(&title) || p.matches(&qualified);
+
+        if !self.include.is_empty() && !self.include.iter().any(hit) {
+            return false;
+        }
+        if self.exclude.iter().any(hit) {
+            return false;
+        }
+        match (self.since, modified) {
+            (Some(since), Some(modified)) => modified >= since,
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date as midnight UTC.
+pub fn parse_date(s: &str) -> Result<SystemTime, Box<dyn Error>> {
+    let bad = || format!("invalid date '{}' (expected YYYY-MM-DD)", s);
+    let parts: Vec<&str> = s.trim().split('-').collect();
+    let [y, m, d] = parts.as_slice() else {
+        return Err(bad().into());
+    };
+    let year: i32 = y.parse().map_err(|_| bad())?;
+    let month: u8 = m.parse().map_err(|_| bad())?;
+    let day: u8 = d.parse().map_err(|_| bad())?;
+    let month = Month::try_from(month).map_err(|_| bad())?;
+    let date = Date::from_calendar_date(year, month, day).map_err(|_| bad())?;
+    let dt: OffsetDateTime = date.midnight().assume_utc();
+    Ok(dt.into())
+}
+
+/// `*` matches any run of characters, `?` matches exactly one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0usize, 0usize);
+    // position of the last `*` and the text index it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("A*", "Alpha-Beta"));
+        assert!(glob_match("*_Tournament*", "Dutch_Open_Tournament_1980"));
+        assert!(glob_match("Perf?", "Perft"));
+        assert!(!glob_match("A*", "Beta"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn include_exclude_and_since() {
+        let f = ArticleFilter::new()
+            .include("A*")
+            .unwrap()
+            .exclude("*_Tournament*")
+            .unwrap();
+        assert!(f.matches("Alpha-Beta", "a", None));
+        assert!(!f.matches("Aegon_Tournament_1990", "a", None));
+        assert!(!f.matches("Perft", "p", None));
+
+        let f = ArticleFilter::new().include("re:^k/Ken").unwrap();
+        assert!(f.matches("Ken_Thompson", "k", None));
+
+        let since = parse_date("2024-01-01").unwrap();
+        let f = ArticleFilter::new().since(since);
+        assert!(f.matches("X", "x", Some(parse_date("2024-06-01").unwrap())));
+        assert!(!f.matches("X", "x", Some(parse_date("2023-12-31").unwrap())));
+        assert!(parse_date("2024-13-01").is_err());
+    }
+}
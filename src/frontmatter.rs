@@ -6,8 +6,14 @@
 //! - Optionally regenerate frontmatter, best-effort merge of preserved fields.
 
 use crate::ast::*;
+use crate::graph::{is_category, link_title};
+use crate::infobox::Infobox;
+use crate::render::{CodeNormalization, RenderOptions, TableCaptionPosition};
+use crate::title::Title;
 use deunicode::deunicode;
-use serde_yaml::Value;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
 use std::path::Path;
 use std::{fs, io};
 use time::{OffsetDateTime, macros::format_description};
@@ -19,38 +25,153 @@ pub struct Frontmatter {
     pub aliases: Vec<String>,
     pub tags: Vec<String>,
 
+    /// Pages linked from the "See also" section, as titles ([`extract_related`]).
+    pub related: Vec<String>,
+
     /// Reserved for future use. If empty/None, it is omitted from generated YAML.
     pub summary: Option<String>,
 
-    /// Extra unrecognized YAML keys preserved during regeneration.
-    pub extras_yaml: Option<String>,
+    /// Infobox fields, written as a mapping under the infobox's namespace key.
+    pub infobox: Option<Infobox>,
+
+    /// Extra unrecognized top-level keys preserved during regeneration, in order.
+    pub extras: Mapping,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Wiki2mdMeta {
     pub article_id: String,
     pub source_url: String,
     pub generated_by: String,
     pub last_fetched_date: String,
     pub schema_version: u32,
+
+    /// Per-article render overrides (`wiki2md.render:`), kept across regeneration.
+    #[serde(skip_serializing_if = "no_overrides")]
+    pub render: Option<RenderOverrides>,
+}
+
+fn no_overrides(render: &Option<RenderOverrides>) -> bool {
+    render
+        .as_ref()
+        .is_none_or(|r| *r == RenderOverrides::default())
+}
+
+/// Overrides for [`RenderOptions`] read from an article's `wiki2md.render:` section.
+///
+/// ```yaml
+/// wiki2md:
+///   render:
+///     center_tables: true
+///     respect_image_width: true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center_tables: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub respect_image_width: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_links_as_images: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hr_after_top_image: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub references_heading: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leading_space_as_blockquote: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infer_code_lang: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize_code: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap_width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table_caption_below: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_ids: Option<bool>,
+}
+
+impl RenderOverrides {
+    /// Parse overrides from a JSON object with the same keys (`{"center_tables": true}`).
+    /// An empty string means no overrides.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(json)
+    }
+
+    /// `base` with every override that is set applied on top.
+    pub fn apply(&self, base: &RenderOptions) -> RenderOptions {
+        let mut opts = base.clone();
+        if let Some(v) = self.center_tables {
+            opts.center_tables_and_captions = v;
+        }
+        if let Some(v) = self.respect_image_width {
+            opts.respect_wikitext_image_width = v;
+        }
+        if let Some(v) = self.image_width {
+            opts.default_image_width_px = v;
+        }
+        if let Some(v) = self.file_links_as_images {
+            opts.render_file_links_as_images = v;
+        }
+        if let Some(v) = self.hr_after_top_image {
+            opts.insert_hr_after_top_image = v;
+        }
+        if let Some(v) = self.references_heading {
+            opts.emit_references_heading = v;
+        }
+        if let Some(v) = self.leading_space_as_blockquote {
+            opts.leading_space_as_blockquote = Some(v);
+        }
+        if let Some(v) = self.infer_code_lang {
+            opts.infer_code_lang = v;
+        }
+        if let Some(v) = self.normalize_code {
+            opts.code_normalization = if v {
+                CodeNormalization::all()
+            } else {
+                CodeNormalization::default()
+            };
+        }
+        if let Some(v) = self.wrap_width {
+            opts.wrap_width = Some(v).filter(|w| *w > 0);
+        }
+        if let Some(v) = self.table_caption_below {
+            opts.table_caption_position = if v {
+                TableCaptionPosition::Below
+            } else {
+                TableCaptionPosition::Above
+            };
+        }
+        if let Some(v) = self.block_ids {
+            opts.block_ids = v;
+        }
+        opts
+    }
 }
 
 impl Frontmatter {
+    /// Append `aliases` that aren't listed yet.
+    pub fn add_aliases<'a>(&mut self, aliases: impl IntoIterator<Item = &'a str>) {
+        for alias in aliases {
+            if !self.aliases.iter().any(|a| a == alias) {
+                self.aliases.push(alias.to_string());
+            }
+        }
+    }
+
+    /// The frontmatter block, `---` lines included. Keys keep their order; aliases,
+    /// the summary, related links, and infobox values are always double-quoted.
     pub fn to_yaml_string(&self) -> String {
         let mut out = String::new();
         out.push_str("---\n");
         out.push_str("wiki2md:\n");
-        out.push_str(&format!("  article_id: {}\n", self.wiki2md.article_id));
-        out.push_str(&format!("  source_url: {}\n", self.wiki2md.source_url));
-        out.push_str(&format!("  generated_by: {}\n", self.wiki2md.generated_by));
-        out.push_str(&format!(
-            "  last_fetched_date: {}\n",
-            self.wiki2md.last_fetched_date
-        ));
-        out.push_str(&format!(
-            "  schema_version: {}\n",
-            self.wiki2md.schema_version
-        ));
+        push_indented(&mut out, &to_yaml(&self.wiki2md));
 
         out.push_str("aliases:\n");
         for a in &self.aliases {
@@ -65,40 +186,59 @@ impl Frontmatter {
             out.push_str("tags: []\n");
         } else {
             out.push_str("tags:\n");
-            for t in &self.tags {
-                out.push_str(&format!("  - {}\n", t));
-            }
+            push_indented(&mut out, &to_yaml(&self.tags));
         }
 
-        if let Some(extra) = self.extras_yaml.as_ref().filter(|s| !s.trim().is_empty()) {
-            // ensure we end with a newline before appending.
-            if !out.ends_with('\n') {
-                out.push('\n');
+        if !self.related.is_empty() {
+            out.push_str("related:\n");
+            for title in &self.related {
+                out.push_str(&format!("  - {}\n", yaml_quote(&format!("[[{}]]", title))));
             }
-            out.push_str(extra);
-            if !out.ends_with('\n') {
-                out.push('\n');
+        }
+
+        if let Some(infobox) = self.infobox.as_ref().filter(|i| !i.fields.is_empty()) {
+            out.push_str(&format!("{}:\n", yaml_key(&infobox.namespace)));
+            for (key, value) in &infobox.fields {
+                out.push_str(&format!("  {}: {}\n", yaml_key(key), yaml_quote(value)));
             }
         }
 
+        if !self.extras.is_empty() {
+            out.push_str(&to_yaml(&self.extras));
+        }
+
         out.push_str("---\n");
         out
     }
 }
 
-fn yaml_quote(s: &str) -> String {
-    // escape backslashes and double quotes.
-    let mut out = String::with_capacity(s.len() + 2);
-    out.push('"');
-    for ch in s.chars() {
-        match ch {
-            '\\' => out.push_str("\\\\"),
-            '"' => out.push_str("\\\""),
-            _ => out.push(ch),
-        }
+/// `value` as a YAML document body, without document markers.
+fn to_yaml(value: &impl Serialize) -> String {
+    let yaml = serde_yaml::to_string(value).unwrap_or_default();
+    strip_yaml_document_markers(&yaml)
+}
+
+/// Append `yaml` indented by two spaces, nesting it under the previous key.
+fn push_indented(out: &mut String, yaml: &str) {
+    for line in yaml.lines() {
+        out.push_str("  ");
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// A mapping key, quoted if it couldn't be written plain.
+fn yaml_key(s: &str) -> String {
+    match to_yaml(&s).trim_end() {
+        plain if !plain.contains('\n') => plain.to_string(),
+        _ => yaml_quote(s),
     }
-    out.push('"');
-    out
+}
+
+/// A double-quoted scalar. JSON string escapes are valid YAML, so newlines and
+/// control characters survive.
+fn yaml_quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string())
 }
 
 /// If `text` starts with YAML frontmatter (`---` ... `---`), return the frontmatter
@@ -148,23 +288,115 @@ pub fn build_frontmatter(
 
     let last_fetched_date = wiki_file_mod_date(wiki_path)?;
 
-    let aliases = vec![article_id.replace('_', " ")];
+    let title = display_title(doc, article_id);
+    let mut aliases = vec![article_id.replace('_', " ")];
+    if title != aliases[0] {
+        aliases.insert(0, title);
+    }
 
     let tags = extract_tags(doc, article_id);
 
-    Ok(Frontmatter {
+    let mut fm = Frontmatter {
         wiki2md: Wiki2mdMeta {
             article_id: article_id.to_string(),
             source_url,
             generated_by: "wiki2md".to_string(),
             last_fetched_date,
             schema_version: 1,
+            render: None,
         },
         aliases,
         tags,
+        related: extract_related(doc),
         summary: None,
-        extras_yaml: None,
-    })
+        infobox: None,
+        extras: Mapping::new(),
+    };
+    fm.add_aliases(lead_bold_phrases(doc).iter().map(String::as_str));
+    Ok(fm)
+}
+
+/// The bold phrases of the lead's first sentence, which name the subject
+/// (`'''Kenneth Lane Thompson''', known as '''Ken'''`).
+pub fn lead_bold_phrases(doc: &Document) -> Vec<String> {
+    let lead = doc
+        .blocks
+        .iter()
+        .take_while(|b| !matches!(b.kind, BlockKind::Heading { .. }))
+        .filter(|b| !doc.breadcrumb.as_ref().is_some_and(|bc| bc.is_block(b)))
+        .find_map(|b| match &b.kind {
+            BlockKind::Paragraph { content } => Some(content),
+            _ => None,
+        });
+
+    let mut phrases: Vec<String> = Vec::new();
+    for node in lead.into_iter().flatten() {
+        match &node.kind {
+            InlineKind::Bold { content } | InlineKind::BoldItalic { content } => {
+                let mut bold = String::new();
+                plain_text(content, &mut bold);
+                let bold = bold.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !bold.is_empty() && !phrases.contains(&bold) {
+                    phrases.push(bold);
+                }
+            }
+            // the first sentence ends at the first full stop followed by a space.
+            InlineKind::Text { value } if value.contains(". ") || value.ends_with('.') => break,
+            _ => {}
+        }
+    }
+    phrases
+}
+
+/// The pages the "See also" section links to, as titles (`Perft Results`), in order.
+/// Categories and files are left out.
+pub fn extract_related(doc: &Document) -> Vec<String> {
+    let Some(section) = see_also_section(&doc.blocks) else {
+        return Vec::new();
+    };
+    let mut related: Vec<String> = Vec::new();
+    walk_inlines(&doc.blocks[section], &mut |node| {
+        if let InlineKind::InternalLink { link } = &node.kind {
+            let title = link_title(&link.target);
+            if !title.is_empty() && !is_category(&title) && !related.contains(&title) {
+                related.push(title);
+            }
+        }
+    });
+    related
+}
+
+/// Pages that redirect to each article, collected in a pass over the corpus, so
+/// their titles can be listed in the target's `aliases`.
+#[derive(Debug, Clone, Default)]
+pub struct RedirectAliases {
+    by_target: HashMap<String, Vec<String>>,
+}
+
+impl RedirectAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the page `article_id` is a redirect to `redirect`'s target.
+    pub fn add(&mut self, article_id: &str, redirect: &Redirect) {
+        let title = Title::parse(article_id).to_string();
+        let titles = self
+            .by_target
+            .entry(Title::parse(&redirect.target).db_key())
+            .or_default();
+        if !titles.contains(&title) {
+            titles.push(title);
+        }
+    }
+
+    /// Titles of the pages redirecting to `article_id`, in the order they were added.
+    pub fn for_article(&self, article_id: &str) -> &[String] {
+        self.by_target
+            .get(&Title::parse(article_id).db_key())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
 }
 
 /// When frontmatter regeneration is requested, we still want to preserve user-authored
@@ -172,7 +404,7 @@ pub fn build_frontmatter(
 ///
 /// This function:
 /// - Extracts a top-level `summary` string (if present).
-/// - Preserves any other unknown top-level keys by serializing them back to YAML.
+/// - Keeps any other unknown top-level keys in `extras`.
 pub fn merge_existing_frontmatter_for_regeneration(
     generated: &mut Frontmatter,
     existing_yaml: &str,
@@ -192,6 +424,8 @@ pub fn merge_existing_frontmatter_for_regeneration(
         return;
     };
 
+    generated.wiki2md.render = render_overrides_from_yaml(&map).ok().flatten();
+
     // preserve `summary` if present and non-empty.
     if let Some(Value::String(s)) = map.get(Value::String("summary".to_string()))
         && !s.trim().is_empty()
@@ -200,20 +434,39 @@ pub fn merge_existing_frontmatter_for_regeneration(
     }
 
     // remove keys we manage.
-    for k in ["wiki2md", "aliases", "tags", "summary"] {
+    for k in ["wiki2md", "aliases", "tags", "related", "summary"] {
         map.remove(Value::String(k.to_string()));
     }
+    if let Some(infobox) = &generated.infobox {
+        map.remove(Value::String(infobox.namespace.clone()));
+    }
 
-    if map.is_empty() {
-        generated.extras_yaml = None;
-        return;
+    generated.extras = map;
+}
+
+/// Read the `wiki2md.render:` section of a frontmatter block (as returned by
+/// [`split_yaml_frontmatter`]). Returns `Ok(None)` when there is no such section.
+pub fn parse_render_overrides(
+    frontmatter_block: &str,
+) -> Result<Option<RenderOverrides>, serde_yaml::Error> {
+    let Some(inner) = extract_yaml_inner(frontmatter_block) else {
+        return Ok(None);
+    };
+    match serde_yaml::from_str::<Value>(&inner)? {
+        Value::Mapping(map) => render_overrides_from_yaml(&map),
+        _ => Ok(None),
     }
+}
 
-    // serialize the remaining keys.
-    let serialized = serde_yaml::to_string(&Value::Mapping(map)).unwrap_or_default();
-    let extras = strip_yaml_document_markers(&serialized);
-    if !extras.trim().is_empty() {
-        generated.extras_yaml = Some(extras);
+fn render_overrides_from_yaml(
+    map: &serde_yaml::Mapping,
+) -> Result<Option<RenderOverrides>, serde_yaml::Error> {
+    let Some(Value::Mapping(meta)) = map.get(Value::String("wiki2md".to_string())) else {
+        return Ok(None);
+    };
+    match meta.get(Value::String("render".to_string())) {
+        Some(render @ Value::Mapping(_)) => serde_yaml::from_value(render.clone()).map(Some),
+        _ => Ok(None),
     }
 }
 
@@ -249,15 +502,78 @@ fn strip_yaml_document_markers(s: &str) -> String {
 }
 
 fn wiki_file_mod_date(wiki_path: &Path) -> io::Result<String> {
-    let meta = fs::metadata(wiki_path)?;
-    let mtime = meta.modified()?;
-    let dt = OffsetDateTime::from(mtime);
+    let dt = match fs::metadata(wiki_path) {
+        Ok(meta) => OffsetDateTime::from(meta.modified()?),
+        // fetched but not saved (dry run): it was fetched just now.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => OffsetDateTime::now_utc(),
+        Err(e) => return Err(e),
+    };
     let fmt = format_description!("[year]-[month]-[day]");
     Ok(dt.format(&fmt).unwrap_or_else(|_| "1970-01-01".to_string()))
 }
 
+/// The title a page is shown under: its `{{DISPLAYTITLE:...}}`, else the bold
+/// title at the start of its first paragraph (`'''iCE''' is a chess engine`), else
+/// the article id with spaces. Like MediaWiki, a display title is only used if it
+/// names the same page, differing from the id in case alone.
+pub fn display_title(doc: &Document, article_id: &str) -> String {
+    let id_title = Title::parse(article_id).to_string();
+    let same_page =
+        |title: &str| Title::parse(title).to_string().to_lowercase() == id_title.to_lowercase();
+
+    let mut display = None;
+    walk_inlines(&doc.blocks, &mut |node| {
+        if display.is_none()
+            && let InlineKind::Template { node } = &node.kind
+            && let Some((name, value)) = node.name.raw.split_once(':')
+            && name.trim().eq_ignore_ascii_case("DISPLAYTITLE")
+        {
+            display = Some(strip_title_markup(value));
+        }
+    });
+    if let Some(title) = display.filter(|t| same_page(t)) {
+        return title;
+    }
+
+    let lead = doc
+        .blocks
+        .iter()
+        .take_while(|b| !matches!(b.kind, BlockKind::Heading { .. }))
+        .filter(|b| !doc.breadcrumb.as_ref().is_some_and(|bc| bc.is_block(b)))
+        .find_map(|b| match &b.kind {
+            BlockKind::Paragraph { content } => content.iter().find_map(|n| match &n.kind {
+                InlineKind::Bold { content } | InlineKind::BoldItalic { content } => Some(content),
+                _ => None,
+            }),
+            _ => None,
+        });
+    if let Some(content) = lead {
+        let mut bold = String::new();
+        plain_text(content, &mut bold);
+        if same_page(&bold) {
+            return bold.trim().to_string();
+        }
+    }
+    id_title.trim().to_string()
+}
+
+/// `''iCE''` / `<i>iCE</i>` -> `iCE`.
+fn strip_title_markup(s: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for ch in s.replace("''", "").chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
 /// Extract tags from:
-/// - The first "breadcrumb" paragraph (detected by a link to `Main Page`).
+/// - The page's breadcrumb ([`Document::breadcrumb`]).
 /// - bottom-of-article `[[Category:...]]` metadata stored in `doc.categories`.
 pub fn extract_tags(doc: &Document, article_id: &str) -> Vec<String> {
     let mut out: Vec<String> = Vec::new();
@@ -265,8 +581,8 @@ pub fn extract_tags(doc: &Document, article_id: &str) -> Vec<String> {
     let article_title = article_id.replace('_', " ").to_ascii_lowercase();
 
     // top-of-page nav tags.
-    if let Some(nav) = find_top_nav_links(doc) {
-        for target in nav {
+    if let Some(breadcrumb) = &doc.breadcrumb {
+        for target in breadcrumb.links.iter().map(|l| &l.target) {
             if target.eq_ignore_ascii_case("Main Page") {
                 continue;
             }
@@ -274,7 +590,7 @@ pub fn extract_tags(doc: &Document, article_id: &str) -> Vec<String> {
             if target.replace('_', " ").to_ascii_lowercase() == article_title {
                 continue;
             }
-            if let Some(t) = normalize_tag(&target) {
+            if let Some(t) = normalize_tag(target) {
                 out.push(t);
             }
         }
@@ -297,66 +613,6 @@ pub fn extract_tags(doc: &Document, article_id: &str) -> Vec<String> {
     out
 }
 
-fn find_top_nav_links(doc: &Document) -> Option<Vec<String>> {
-    for block in &doc.blocks {
-        let BlockKind::Paragraph { content } = &block.kind else {
-            continue;
-        };
-        let mut targets: Vec<String> = Vec::new();
-        let mut saw_main = false;
-        collect_internal_link_targets(content, &mut targets, &mut saw_main);
-        if saw_main {
-            return Some(targets);
-        }
-    }
-    None
-}
-
-fn collect_internal_link_targets(nodes: &[InlineNode], out: &mut Vec<String>, saw_main: &mut bool) {
-    for n in nodes {
-        match &n.kind {
-            InlineKind::InternalLink { link } => {
-                if link.target.eq_ignore_ascii_case("Main Page") {
-                    *saw_main = true;
-                }
-                out.push(link.target.clone());
-                if let Some(t) = &link.text {
-                    collect_internal_link_targets(t, out, saw_main);
-                }
-            }
-            InlineKind::Bold { content }
-            | InlineKind::Italic { content }
-            | InlineKind::BoldItalic { content } => {
-                collect_internal_link_targets(content, out, saw_main)
-            }
-            InlineKind::Ref { node } => {
-                if let Some(c) = &node.content {
-                    collect_internal_link_targets(c, out, saw_main);
-                }
-            }
-            InlineKind::HtmlTag { node } => {
-                collect_internal_link_targets(&node.children, out, saw_main)
-            }
-            InlineKind::Template { node } => {
-                for p in &node.params {
-                    collect_internal_link_targets(&p.value, out, saw_main);
-                }
-            }
-            InlineKind::FileLink { link } => {
-                for p in &link.params {
-                    collect_internal_link_targets(&p.content, out, saw_main);
-                }
-            }
-            InlineKind::ExternalLink { link } => {
-                if let Some(t) = &link.text {
-                    collect_internal_link_targets(t, out, saw_main);
-                }
-            }
-            InlineKind::Text { .. } | InlineKind::LineBreak | InlineKind::Raw { .. } => {}
-        }
-    }
-}
-
 /// Normalize a tag according to Obsidian constraints (for now: no nesting).
 ///
 /// Rules enforced:
@@ -0,0 +1,78 @@
+//! Generation metadata: an HTML comment at the end of a generated `.md` file
+//! recording the parser version, AST schema version, and render options that
+//! produced it, so a bulk run can tell output made by an older version apart.
+//!
+//! ```markdown
+//! <!-- wiki2md parser=0.1.0 schema=1 render=5d41402abc4b2a76b9719d911017c592 -->
+//! ```
+
+use crate::ast::{PARSER_VERSION, SCHEMA_VERSION};
+use crate::render::RenderOptions;
+
+const PREFIX: &str = "<!-- wiki2md ";
+const SUFFIX: &str = " -->";
+
+/// What generated a Markdown file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationInfo {
+    pub parser_version: String,
+    pub schema_version: u32,
+    /// [`RenderOptions::fingerprint`] of the options the file was rendered with.
+    pub render_options: String,
+}
+
+impl GenerationInfo {
+    /// This build, rendering with `opts`.
+    pub fn current(opts: &RenderOptions) -> Self {
+        GenerationInfo {
+            parser_version: PARSER_VERSION.to_string(),
+            schema_version: SCHEMA_VERSION,
+            render_options: opts.fingerprint(),
+        }
+    }
+
+    /// The comment line, without a trailing newline.
+    pub fn to_comment(&self) -> String {
+        format!(
+            "{}parser={} schema={} render={}{}",
+            PREFIX, self.parser_version, self.schema_version, self.render_options, SUFFIX
+        )
+    }
+
+    /// The metadata recorded in a Markdown file (its last such comment), if any.
+    pub fn from_markdown(md: &str) -> Option<Self> {
+        let line = md.lines().rev().find(|line| line.starts_with(PREFIX))?;
+        let fields = line.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+        let (mut parser_version, mut schema_version, mut render_options) = (None, None, None);
+        for field in fields.split_whitespace() {
+            match field.split_once('=') {
+                Some(("parser", v)) => parser_version = Some(v.to_string()),
+                Some(("schema", v)) => schema_version = v.parse().ok(),
+                Some(("render", v)) => render_options = Some(v.to_string()),
+                _ => {}
+            }
+        }
+        Some(GenerationInfo {
+            parser_version: parser_version?,
+            schema_version: schema_version?,
+            render_options: render_options?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_round_trips() {
+        let info = GenerationInfo::current(&RenderOptions::default());
+        let md = format!("# Title\n\nBody.\n\n{}\n", info.to_comment());
+        assert_eq!(GenerationInfo::from_markdown(&md), Some(info));
+        assert_eq!(GenerationInfo::from_markdown("# Title\n\nBody.\n"), None);
+        assert_eq!(
+            GenerationInfo::from_markdown("<!-- wiki2md parser=0.1.0 -->\n"),
+            None
+        );
+    }
+}
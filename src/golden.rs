@@ -0,0 +1,254 @@
+//! Golden tests (`wiki2md golden`): render every `NNN-in-<name>.wiki` file in a
+//! directory and compare the result with `NNN-want-<name>.txt`.
+//!
+//! A case may have a sidecar `NNN-options-<name>.toml` with render overrides,
+//! using the keys of the frontmatter's `wiki2md.render:` section:
+//!
+//! ```toml
+//! [render]
+//! center_tables = true
+//! wrap_width = 80
+//! ```
+//!
+//! When a case fails, its actual output is written to `NNN-out-<name>.txt` next to
+//! the expectation (and removed again once it passes). Blessing overwrites the
+//! expectations with the actual output instead.
+
+use crate::error::{Error, Result};
+use crate::frontmatter::RenderOverrides;
+use crate::parse;
+use crate::render::{self, RenderOptions};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One `NNN-in-<name>.wiki` input and the files that belong to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenCase {
+    /// `NNN-<name>`, e.g. `001-wikitable-text-align`.
+    pub name: String,
+    pub input: PathBuf,
+    pub want: PathBuf,
+    /// Where the actual output goes when the case fails.
+    pub out: PathBuf,
+    /// The sidecar options file, if there is one.
+    pub options: Option<PathBuf>,
+}
+
+/// Sidecar options of a case.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CaseOptions {
+    render: RenderOverrides,
+}
+
+/// What happened to one case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    Passed,
+    /// The output differs from the expectation (or there is none yet).
+    Failed {
+        /// A unified diff from the expected to the actual output.
+        diff: String,
+    },
+    /// The expectation was (re)written with the actual output.
+    Blessed,
+}
+
+/// The outcome of every case in a directory, in case order.
+#[derive(Debug, Clone, Default)]
+pub struct GoldenReport {
+    pub results: Vec<(GoldenCase, GoldenOutcome)>,
+}
+
+impl GoldenReport {
+    pub fn failed(&self) -> impl Iterator<Item = &(GoldenCase, GoldenOutcome)> {
+        self.results
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, GoldenOutcome::Failed { .. }))
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    /// One line per case, the diffs of failed cases, and a summary line.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (case, outcome) in &self.results {
+            match outcome {
+                GoldenOutcome::Passed => out.push_str(&format!("ok       {}\n", case.name)),
+                GoldenOutcome::Blessed => out.push_str(&format!("blessed  {}\n", case.name)),
+                GoldenOutcome::Failed { diff } => {
+                    out.push_str(&format!(
+                        "FAILED   {} (actual output in {})\n",
+                        case.name,
+                        case.out.display()
+                    ));
+                    out.push_str(diff);
+                    if !diff.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        let failed = self.failed().count();
+        out.push_str(&format!(
+            "{} cases: {} passed, {} failed",
+            self.results.len(),
+            self.results.len() - failed,
+            failed
+        ));
+        let blessed = self
+            .results
+            .iter()
+            .filter(|(_, outcome)| *outcome == GoldenOutcome::Blessed)
+            .count();
+        if blessed > 0 {
+            out.push_str(&format!(", {} blessed", blessed));
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Every case in `dir`, sorted by name. Files that don't match `NNN-in-<name>.wiki`
+/// are ignored.
+pub fn discover(dir: &Path) -> Result<Vec<GoldenCase>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir).map_err(Error::io(dir))? {
+        let path = entry.map_err(Error::io(dir))?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some((number, name)) = file_name
+            .strip_suffix(".wiki")
+            .and_then(|stem| stem.split_once("-in-"))
+            .filter(|(number, _)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        else {
+            continue;
+        };
+        let options = dir.join(format!("{}-options-{}.toml", number, name));
+        cases.push(GoldenCase {
+            name: format!("{}-{}", number, name),
+            want: dir.join(format!("{}-want-{}.txt", number, name)),
+            out: dir.join(format!("{}-out-{}.txt", number, name)),
+            options: options.exists().then_some(options),
+            input: path,
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+impl GoldenCase {
+    /// The case's input rendered with its sidecar options applied to `base`.
+    pub fn render(&self, base: &RenderOptions) -> Result<String> {
+        let opts = match &self.options {
+            Some(path) => {
+                let text = fs::read_to_string(path).map_err(Error::io(path))?;
+                let options: CaseOptions = toml::from_str(&text)
+                    .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+                options.render.apply(base)
+            }
+            None => base.clone(),
+        };
+        let bytes = fs::read(&self.input).map_err(Error::io(&self.input))?;
+        let src = String::from_utf8_lossy(&bytes);
+        let ast = parse::parse_wiki(&src);
+        Ok(render::render_doc_with_options(&ast.document, &opts))
+    }
+
+    /// Render the case and compare (or, with `bless`, overwrite) its expectation.
+    pub fn run(&self, base: &RenderOptions, bless: bool) -> Result<GoldenOutcome> {
+        let actual = self.render(base)?;
+        let want = match fs::read(&self.want) {
+            Ok(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(Error::io(&self.want)(e)),
+        };
+
+        let outcome = if want.as_deref() == Some(actual.as_str()) {
+            GoldenOutcome::Passed
+        } else if bless {
+            fs::write(&self.want, &actual).map_err(Error::io(&self.want))?;
+            GoldenOutcome::Blessed
+        } else {
+            fs::write(&self.out, &actual).map_err(Error::io(&self.out))?;
+            let diff = diffy::create_patch(want.as_deref().unwrap_or_default(), &actual);
+            return Ok(GoldenOutcome::Failed {
+                diff: diff.to_string(),
+            });
+        };
+        if self.out.exists() {
+            fs::remove_file(&self.out).map_err(Error::io(&self.out))?;
+        }
+        Ok(outcome)
+    }
+}
+
+/// Run every case in `dir` with default render options.
+pub fn run(dir: &Path, bless: bool) -> Result<GoldenReport> {
+    let base = RenderOptions::default();
+    let mut report = GoldenReport::default();
+    for case in discover(dir)? {
+        let outcome = case.run(&base, bless)?;
+        report.results.push((case, outcome));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_and_blesses_cases_with_sidecar_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::write(dir.join("001-in-plain.wiki"), "'''bold'''\n").unwrap();
+        fs::write(dir.join("001-want-plain.txt"), "**bold**").unwrap();
+        fs::write(
+            dir.join("002-in-caption.wiki"),
+            "{|\n|+ Cap\n|-\n! H\n|-\n| A\n|}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("002-options-caption.toml"),
+            "[render]\ntable_caption_below = true\n",
+        )
+        .unwrap();
+        fs::write(dir.join("002-want-caption.txt"), "stale").unwrap();
+        fs::write(dir.join("README.md"), "not a case").unwrap();
+
+        let cases = discover(dir).unwrap();
+        assert_eq!(
+            cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["001-plain", "002-caption"]
+        );
+        assert!(cases[1].options.is_some());
+
+        let report = run(dir, false).unwrap();
+        assert_eq!(report.results[0].1, GoldenOutcome::Passed);
+        assert!(!report.is_success());
+        assert!(
+            report.to_text().contains("2 cases: 1 passed, 1 failed"),
+            "{}",
+            report.to_text()
+        );
+        let out = fs::read_to_string(dir.join("002-out-caption.txt")).unwrap();
+        assert!(out.ends_with("Cap"), "{out}");
+
+        let report = run(dir, true).unwrap();
+        assert_eq!(report.results[1].1, GoldenOutcome::Blessed);
+        assert!(!dir.join("002-out-caption.txt").exists());
+        assert!(run(dir, false).unwrap().is_success());
+
+        fs::write(
+            dir.join("002-options-caption.toml"),
+            "[render]\nbogus = 1\n",
+        )
+        .unwrap();
+        assert!(matches!(run(dir, false), Err(Error::Config(_))));
+    }
+}
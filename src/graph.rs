@@ -0,0 +1,175 @@
+//! The page-to-page link graph of a set of articles.
+//!
+//! Nodes are article titles (`Ken Thompson`). Only links between articles in the
+//! set become edges; links to pages that weren't mirrored are left out.
+
+use crate::ast::{self, Document};
+use crate::title::Title;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Which pages the graph leaves out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphOptions {
+    /// Leave out `#REDIRECT` pages; links to them point at the redirect target instead.
+    pub drop_redirects: bool,
+    /// Leave out `Category:` pages and links to them.
+    pub drop_categories: bool,
+}
+
+/// Adjacency list: each page and the pages it links to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct LinkGraph {
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkGraph {
+    /// Build the graph from `(article_id, document)` pairs.
+    pub fn build<'a>(
+        pages: impl IntoIterator<Item = (&'a str, &'a Document<'a>)>,
+        opts: &GraphOptions,
+    ) -> Self {
+        let pages: Vec<(String, &Document)> = pages
+            .into_iter()
+            .map(|(id, doc)| (link_title(id), doc))
+            .filter(|(title, _)| !(opts.drop_categories && is_category(title)))
+            .collect();
+
+        // redirect page -> the page it redirects to.
+        let known: BTreeSet<&str> = pages.iter().map(|(t, _)| t.as_str()).collect();
+        let redirects: HashMap<&str, String> = pages
+            .iter()
+            .filter_map(|(title, doc)| {
+                let target = link_title(&doc.redirect.as_ref()?.target);
+                Some((title.as_str(), target))
+            })
+            .collect();
+        let resolve = |title: String| -> Option<String> {
+            let title = match redirects.get(title.as_str()) {
+                Some(target) if opts.drop_redirects => target.clone(),
+                _ => title,
+            };
+            known.contains(title.as_str()).then_some(title)
+        };
+
+        let mut edges: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (title, doc) in &pages {
+            if opts.drop_redirects && redirects.contains_key(title.as_str()) {
+                continue;
+            }
+            let out = edges.entry(title.clone()).or_default();
+            let targets = ast::internal_links(doc)
+                .into_iter()
+                .map(|l| l.target.as_str())
+                .chain(doc.redirect.as_ref().map(|r| r.target.as_str()));
+            for target in targets {
+                if target.trim().is_empty() {
+                    continue;
+                }
+                let target = link_title(target);
+                if opts.drop_categories && is_category(&target) {
+                    continue;
+                }
+                if let Some(target) = resolve(target)
+                    && &target != title
+                {
+                    out.insert(target);
+                }
+            }
+        }
+        LinkGraph { edges }
+    }
+
+    /// Number of links.
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(BTreeSet::len).sum()
+    }
+
+    /// Graphviz DOT (`dot -Tsvg graph.dot -o graph.svg`).
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph wiki {\n");
+        for (from, targets) in &self.edges {
+            out.push_str(&format!("  {};\n", dot_id(from)));
+            for to in targets {
+                out.push_str(&format!("  {} -> {};\n", dot_id(from), dot_id(to)));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// `{"Page": ["Linked page", ...], ...}`
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// The title a link target refers to, normalized as MediaWiki does
+/// ([`Title::parse`]): `perft_results#Start` -> `Perft Results`.
+pub fn link_title(target: &str) -> String {
+    Title::parse(target).to_string()
+}
+
+pub(crate) fn is_category(title: &str) -> bool {
+    title
+        .get(..9)
+        .is_some_and(|p| p.eq_ignore_ascii_case("category:"))
+}
+
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    fn graph(pages: &[(&str, &str)], opts: GraphOptions) -> LinkGraph {
+        let docs: Vec<(&str, Document)> = pages
+            .iter()
+            .map(|(id, src)| (*id, parse_wiki(src).document))
+            .collect();
+        LinkGraph::build(docs.iter().map(|(id, d)| (*id, d)), &opts)
+    }
+
+    #[test]
+    fn links_between_known_pages() {
+        let pages = [
+            (
+                "Alpha-Beta",
+                "See [[minimax]], [[Alpha-Beta]], [[Nowhere]] and [[:Category:Search]].\n",
+            ),
+            ("Minimax", "Back to [[Alpha-Beta#History|alpha-beta]].\n"),
+            ("MiniMax", "#REDIRECT [[Minimax]]\n"),
+            ("Category:Search", "[[Alpha-Beta]]\n"),
+            ("Negamax", "Like [[MiniMax]].\n"),
+        ];
+        let g = graph(&pages, GraphOptions::default());
+        assert_eq!(
+            g.edges["Alpha-Beta"].iter().collect::<Vec<_>>(),
+            ["Category:Search", "Minimax"]
+        );
+        assert_eq!(g.edges["Negamax"].iter().collect::<Vec<_>>(), ["MiniMax"]);
+        assert!(g.to_dot().contains("  \"Minimax\" -> \"Alpha-Beta\";\n"));
+
+        let g = graph(
+            &pages,
+            GraphOptions {
+                drop_redirects: true,
+                drop_categories: true,
+            },
+        );
+        assert!(!g.edges.contains_key("MiniMax"));
+        assert!(!g.edges.contains_key("Category:Search"));
+        assert_eq!(
+            g.edges["Alpha-Beta"].iter().collect::<Vec<_>>(),
+            ["Minimax"]
+        );
+        assert_eq!(g.edges["Negamax"].iter().collect::<Vec<_>>(), ["Minimax"]);
+        assert_eq!(g.edge_count(), 3);
+    }
+}
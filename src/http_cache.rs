@@ -0,0 +1,266 @@
+//! An on-disk cache of HTTP responses (`--http-cache`).
+//!
+//! Each successful GET is stored under the cache directory as two files named by
+//! the MD5 of its URL: `<hash>.json` (URL, status, headers, fetch time) and
+//! `<hash>.body`. This is separate from the `.wiki` files: it also keeps edit pages
+//! of articles whose `.wiki` was deleted, API responses, and downloaded images, so
+//! repeated experiments don't hit the wiki again.
+//!
+//! The cache is process-wide: [`install`] it once, and every fetch (articles,
+//! `mirror` API queries, vault and EPUB images) goes through it.
+
+use crate::error::BoxError;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A directory of cached responses.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+    max_age: Option<Duration>,
+}
+
+/// A response, from the network or the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub status: u16,
+    /// Header names are lowercase; repeated headers are joined with `, `.
+    pub headers: BTreeMap<String, String>,
+    /// When the response was fetched (seconds since the Unix epoch).
+    pub fetched_at: u64,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The body as UTF-8, with invalid sequences replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// `<hash>.json`: everything but the body.
+#[derive(Serialize, Deserialize)]
+struct EntryMeta {
+    url: String,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    fetched_at: u64,
+}
+
+impl HttpCache {
+    /// A cache in `dir` whose entries never expire.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        HttpCache {
+            dir: dir.into(),
+            max_age: None,
+        }
+    }
+
+    /// Fetch again when an entry is older than `max_age` (zero always fetches).
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cached response for `url`, if there is one and it hasn't expired.
+    pub fn lookup(&self, url: &str) -> Option<CachedResponse> {
+        let (meta_path, body_path) = self.entry_paths(url);
+        let meta: EntryMeta = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+        if meta.url != url {
+            return None;
+        }
+        if let Some(max_age) = self.max_age
+            && now().saturating_sub(meta.fetched_at) >= max_age.as_secs()
+        {
+            return None;
+        }
+        let body = fs::read(body_path).ok()?;
+        Some(CachedResponse {
+            status: meta.status,
+            headers: meta.headers,
+            fetched_at: meta.fetched_at,
+            body,
+        })
+    }
+
+    /// Cache `response` as the answer for `url`.
+    pub fn store(&self, url: &str, response: &CachedResponse) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let (meta_path, body_path) = self.entry_paths(url);
+        let meta = EntryMeta {
+            url: url.to_string(),
+            status: response.status,
+            headers: response.headers.clone(),
+            fetched_at: response.fetched_at,
+        };
+        // the body goes first, so a `.json` never points at a missing body.
+        fs::write(body_path, &response.body)?;
+        fs::write(
+            meta_path,
+            serde_json::to_vec_pretty(&meta).map_err(io::Error::other)?,
+        )
+    }
+
+    /// `url` from the cache, or fetched with `client` and cached if successful.
+    pub fn get_with(&self, client: &Client, url: &str) -> Result<CachedResponse, BoxError> {
+        self.get_with_if(client, url, CachedResponse::is_success)
+    }
+
+    /// Like [`Self::get_with`], but only a response `cacheable` accepts is stored,
+    /// for servers that report errors in a `200` body.
+    pub fn get_with_if(
+        &self,
+        client: &Client,
+        url: &str,
+        cacheable: impl Fn(&CachedResponse) -> bool,
+    ) -> Result<CachedResponse, BoxError> {
+        if let Some(response) = self.lookup(url) {
+            tracing::debug!(%url, "http cache hit");
+            return Ok(response);
+        }
+        let response = send(client, url)?;
+        if cacheable(&response)
+            && let Err(e) = self.store(url, &response)
+        {
+            tracing::warn!(%url, error = %e, "could not write http cache entry");
+        }
+        Ok(response)
+    }
+
+    fn entry_paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let hash = format!("{:x}", md5::compute(url.as_bytes()));
+        (
+            self.dir.join(format!("{hash}.json")),
+            self.dir.join(format!("{hash}.body")),
+        )
+    }
+}
+
+static GLOBAL: OnceLock<HttpCache> = OnceLock::new();
+
+/// Route every fetch of this process through `cache`. Returns `false` (and
+/// changes nothing) if a cache was already installed.
+pub fn install(cache: HttpCache) -> bool {
+    GLOBAL.set(cache).is_ok()
+}
+
+/// The installed cache, if any.
+pub fn installed() -> Option<&'static HttpCache> {
+    GLOBAL.get()
+}
+
+/// GET `url`, through the installed cache if there is one.
+pub fn get(url: &str) -> Result<CachedResponse, BoxError> {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    get_with(CLIENT.get_or_init(Client::new), url)
+}
+
+/// Like [`get`], with a configured client (user agent, timeout).
+pub fn get_with(client: &Client, url: &str) -> Result<CachedResponse, BoxError> {
+    match installed() {
+        Some(cache) => cache.get_with(client, url),
+        None => send(client, url),
+    }
+}
+
+/// Like [`get_with`], but only a response `cacheable` accepts is stored.
+pub fn get_with_if(
+    client: &Client,
+    url: &str,
+    cacheable: impl Fn(&CachedResponse) -> bool,
+) -> Result<CachedResponse, BoxError> {
+    match installed() {
+        Some(cache) => cache.get_with_if(client, url, cacheable),
+        None => send(client, url),
+    }
+}
+
+fn send(client: &Client, url: &str) -> Result<CachedResponse, BoxError> {
+    let resp = client.get(url).send()?;
+    let status = resp.status().as_u16();
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in resp.headers() {
+        let Ok(value) = value.to_str() else { continue };
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    let body = resp.bytes()?.to_vec();
+    Ok(CachedResponse {
+        status,
+        headers,
+        fetched_at: now(),
+        body,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_and_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://www.chessprogramming.org/index.php?title=Perft&action=edit";
+        let response = CachedResponse {
+            status: 200,
+            headers: BTreeMap::from([("content-type".to_string(), "text/html".to_string())]),
+            fetched_at: now() - 120,
+            body: b"<textarea>Perft</textarea>".to_vec(),
+        };
+        let cache = HttpCache::new(dir.path());
+        assert_eq!(cache.lookup(url), None);
+        cache.store(url, &response).unwrap();
+
+        let hit = cache.lookup(url).unwrap();
+        assert_eq!(hit, response);
+        assert_eq!(hit.header("Content-Type"), Some("text/html"));
+        assert!(cache.lookup("https://www.chessprogramming.org/").is_none());
+
+        assert!(
+            cache
+                .clone()
+                .max_age(Duration::from_secs(3600))
+                .lookup(url)
+                .is_some()
+        );
+        assert!(
+            cache
+                .clone()
+                .max_age(Duration::from_secs(60))
+                .lookup(url)
+                .is_none()
+        );
+        assert!(cache.max_age(Duration::ZERO).lookup(url).is_none());
+    }
+}
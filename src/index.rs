@@ -0,0 +1,204 @@
+//! Generated navigation pages for the Markdown mirror: an alphabetical `Index.md`
+//! standing in for the wiki's Special:AllPages, and one page per category.
+
+use crate::ast::CategoryTag;
+use crate::graph::link_title;
+use crate::{layout, lower_first_letter_bucket};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+/// Which alphabetical index pages bulk regeneration writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexPages {
+    /// No index pages.
+    #[default]
+    Off,
+    /// A single `Index.md` listing every article, grouped by letter.
+    Single,
+    /// `Index.md` linking to one `Index/<letter>.md` page per letter.
+    PerLetter,
+}
+
+/// A generated page, relative to the Markdown root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedPage {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// The alphabetical index pages for `article_ids`, grouped by bucket letter (the
+/// same letter the `.md` files are bucketed by).
+pub fn alphabetical_index<S: AsRef<str>>(
+    article_ids: &[S],
+    mode: IndexPages,
+) -> Vec<GeneratedPage> {
+    let mut letters: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for id in article_ids {
+        let id = id.as_ref();
+        letters
+            .entry(lower_first_letter_bucket(id).to_uppercase())
+            .or_default()
+            .push(id.replace('_', " "));
+    }
+    for titles in letters.values_mut() {
+        titles.sort_by_cached_key(|t| (t.to_lowercase(), t.clone()));
+        titles.dedup();
+    }
+
+    match mode {
+        IndexPages::Off => Vec::new(),
+        IndexPages::Single => {
+            let mut out = String::from("# Index\n");
+            for (letter, titles) in &letters {
+                out.push_str(&format!("\n## {}\n\n", letter));
+                push_links(&mut out, titles);
+            }
+            vec![GeneratedPage {
+                path: PathBuf::from("Index.md"),
+                content: out,
+            }]
+        }
+        IndexPages::PerLetter => {
+            let mut pages = Vec::with_capacity(letters.len() + 1);
+            let mut out = String::from("# Index\n\n");
+            for (letter, titles) in &letters {
+                let name = layout::safe_file_name(letter);
+                out.push_str(&format!(
+                    "- [[Index/{}|{}]] ({})\n",
+                    name,
+                    letter,
+                    titles.len()
+                ));
+
+                let mut page = format!("# Index: {}\n\n", letter);
+                push_links(&mut page, titles);
+                pages.push(GeneratedPage {
+                    path: PathBuf::from("Index").join(format!("{}.md", name)),
+                    content: page,
+                });
+            }
+            pages.insert(
+                0,
+                GeneratedPage {
+                    path: PathBuf::from("Index.md"),
+                    content: out,
+                },
+            );
+            pages
+        }
+    }
+}
+
+/// One `Categories/<name>.md` page per category listing its member articles, plus
+/// a `Categories.md` page listing the categories. Members are sorted by their
+/// category sort key (`[[Category:Search|Minimax]]`) when they have one.
+pub fn category_pages<'a>(
+    articles: impl IntoIterator<Item = (&'a str, &'a [CategoryTag])>,
+) -> Vec<GeneratedPage> {
+    // category -> (sort key, title) of each member.
+    let mut categories: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for (id, tags) in articles {
+        let title = id.replace('_', " ");
+        for tag in tags {
+            let name = link_title(&tag.name);
+            if name.is_empty() {
+                continue;
+            }
+            let key = tag
+                .sort_key
+                .as_deref()
+                .map(str::trim)
+                .filter(|k| !k.is_empty());
+            let key = key.unwrap_or(&title).to_lowercase();
+            categories
+                .entry(name)
+                .or_default()
+                .push((key, title.clone()));
+        }
+    }
+
+    let mut pages = Vec::with_capacity(categories.len() + 1);
+    let mut out = String::from("# Categories\n\n");
+    for (name, members) in &mut categories {
+        members.sort();
+        let mut seen = HashSet::new();
+        members.retain(|(_, title)| seen.insert(title.clone()));
+        let file_name = layout::safe_file_name(name);
+        out.push_str(&format!(
+            "- [[Categories/{}|{}]] ({})\n",
+            file_name,
+            name,
+            members.len()
+        ));
+
+        let mut page = format!("# Category: {}\n\n", name);
+        let titles: Vec<String> = members.iter().map(|(_, t)| t.clone()).collect();
+        push_links(&mut page, &titles);
+        pages.push(GeneratedPage {
+            path: PathBuf::from("Categories").join(format!("{}.md", file_name)),
+            content: page,
+        });
+    }
+    pages.insert(
+        0,
+        GeneratedPage {
+            path: PathBuf::from("Categories.md"),
+            content: out,
+        },
+    );
+    pages
+}
+
+fn push_links(out: &mut String, titles: &[String]) {
+    for title in titles {
+        out.push_str(&format!("- [[{}]]\n", title));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn groups_articles_by_letter() {
+        let ids = ["Minimax", "alpha-Beta", "Alpha", "Ken_Thompson", "1. e4"];
+        let pages = alphabetical_index(&ids, IndexPages::Single);
+        assert_eq!(
+            pages[0].content,
+            "# Index\n\n## 1\n\n- [[1. e4]]\n\n## A\n\n- [[Alpha]]\n- [[alpha-Beta]]\n\n\
+             ## K\n\n- [[Ken Thompson]]\n\n## M\n\n- [[Minimax]]\n"
+        );
+
+        let pages = alphabetical_index(&ids, IndexPages::PerLetter);
+        assert_eq!(pages.len(), 5);
+        assert!(pages[0].content.contains("- [[Index/A|A]] (2)\n"));
+        assert_eq!(pages[2].path, PathBuf::from("Index").join("A.md"));
+        assert_eq!(
+            pages[2].content,
+            "# Index: A\n\n- [[Alpha]]\n- [[alpha-Beta]]\n"
+        );
+        assert!(alphabetical_index(&ids, IndexPages::Off).is_empty());
+    }
+
+    #[test]
+    fn lists_members_per_category() {
+        let alpha = parse_wiki("Body\n[[Category:Search]]\n[[Category:Search]]\n").document;
+        let minimax =
+            parse_wiki("Body\n[[Category:search|Aaa]]\n[[Category:Chess_Programmers]]\n").document;
+        let pages = category_pages([
+            ("Alpha-Beta", alpha.categories.as_slice()),
+            ("Minimax", minimax.categories.as_slice()),
+        ]);
+        assert_eq!(
+            pages[0].content,
+            "# Categories\n\n- [[Categories/Chess Programmers|Chess Programmers]] (1)\n\
+             - [[Categories/Search|Search]] (2)\n"
+        );
+        assert_eq!(pages[2].path, PathBuf::from("Categories").join("Search.md"));
+        assert_eq!(
+            pages[2].content,
+            "# Category: Search\n\n- [[Minimax]]\n- [[Alpha-Beta]]\n"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Infobox extraction: the named parameters of an infobox-like template
+//! (`{{Infobox person|birth date=1950|country=USA}}`) as frontmatter fields
+//! instead of template braces in the body.
+
+use crate::ast::{self, Document, InlineKind, TemplateInvocation, TemplateNameKind};
+
+/// Which templates count as infoboxes, and where their fields go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoboxOptions {
+    /// Template names. A name also matches longer names that start with it and a
+    /// space, so `Infobox` matches `{{Infobox person}}`. Matched like MediaWiki
+    /// template names: case-insensitively, with `_` and spaces treated alike.
+    pub templates: Vec<String>,
+
+    /// The top-level frontmatter key the fields are written under.
+    pub namespace: String,
+}
+
+impl Default for InfoboxOptions {
+    fn default() -> Self {
+        Self {
+            templates: vec!["Infobox".to_string()],
+            namespace: "infobox".to_string(),
+        }
+    }
+}
+
+impl InfoboxOptions {
+    /// True if `inv` is one of the configured infobox templates.
+    pub fn matches(&self, inv: &TemplateInvocation) -> bool {
+        if inv.name.kind != TemplateNameKind::Template {
+            return false;
+        }
+        let name = normalize_name(&inv.name.raw);
+        self.templates.iter().any(|t| {
+            let t = normalize_name(t);
+            name == t
+                || name
+                    .strip_prefix(&t)
+                    .is_some_and(|rest| rest.starts_with(' '))
+        })
+    }
+}
+
+/// The fields of an article's infobox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Infobox {
+    /// The frontmatter key the fields are written under.
+    pub namespace: String,
+
+    /// Template name as written, e.g. `Infobox person`.
+    pub template: String,
+
+    /// Named parameters in order, keys in `snake_case` (`birth date` ->
+    /// `birth_date`), values as plain text. Positional and empty parameters are
+    /// left out.
+    pub fields: Vec<(String, String)>,
+}
+
+/// The first infobox template in `doc`, if there is one.
+pub fn extract_infobox(doc: &Document, opts: &InfoboxOptions) -> Option<Infobox> {
+    let mut found = None;
+    ast::walk_inlines(&doc.blocks, &mut |node| {
+        if found.is_none()
+            && let InlineKind::Template { node: inv } = &node.kind
+            && opts.matches(inv)
+        {
+            found = Some(inv);
+        }
+    });
+    let inv = found?;
+
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for param in &inv.params {
+        let Some(key) = param
+            .name
+            .as_deref()
+            .map(field_key)
+            .filter(|k| !k.is_empty())
+        else {
+            continue;
+        };
+        let mut value = String::new();
+        ast::plain_text(&param.value, &mut value);
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        if value.is_empty() {
+            continue;
+        }
+        match fields.iter_mut().find(|(k, _)| *k == key) {
+            // like MediaWiki, a repeated parameter keeps its last value.
+            Some(field) => field.1 = value,
+            None => fields.push((key, value)),
+        }
+    }
+    Some(Infobox {
+        namespace: opts.namespace.clone(),
+        template: inv.name.raw.trim().to_string(),
+        fields,
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().replace('_', " ").to_lowercase()
+}
+
+/// `Birth Date` -> `birth_date`.
+fn field_key(name: &str) -> String {
+    let mut key = String::new();
+    for ch in name.trim().chars() {
+        if ch.is_alphanumeric() {
+            key.extend(ch.to_lowercase());
+        } else if !key.is_empty() && !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+    key.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn extracts_named_parameters_of_the_first_infobox() {
+        let src = "{{Infobox_Person|Birth Date=1950|country=[[USA]]|image=|Free text|country=Canada}}\n{{Infobox engine|license=GPL}}\nText.\n";
+        let doc = parse_wiki(src).document;
+        let infobox = extract_infobox(&doc, &InfoboxOptions::default()).unwrap();
+        assert_eq!(infobox.template, "Infobox_Person");
+        assert_eq!(
+            infobox.fields,
+            [
+                ("birth_date".to_string(), "1950".to_string()),
+                ("country".to_string(), "Canada".to_string()),
+            ]
+        );
+
+        let opts = InfoboxOptions {
+            templates: vec!["Engine".to_string()],
+            ..InfoboxOptions::default()
+        };
+        assert!(extract_infobox(&doc, &opts).is_none());
+        let doc = parse_wiki("{{Infoboxes}}\n").document;
+        assert!(extract_infobox(&doc, &InfoboxOptions::default()).is_none());
+    }
+}
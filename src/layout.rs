@@ -0,0 +1,227 @@
+//! Output path templates.
+//!
+//! Generated files are placed by expanding a template such as
+//! `docs/{kind}/{bucket}/{name}.{ext}` (the default layout). Variables:
+//!
+//! - `{kind}`, `{ext}`: `md` or `json`.
+//! - `{bucket}`: lowercased first letter of the article id (`k`).
+//! - `{id}`: the article id (`Ken_Thompson`).
+//! - `{title}`: the id with spaces (`Ken Thompson`).
+//! - `{name}`: `{title}` for Markdown, `{id}` for JSON (the default layout's file names).
+//! - `{slug}`: lowercase ASCII with dashes (`ken-thompson`).
+//! - `{category}`: the article's first category, or `uncategorized`.
+//!
+//! Every value is passed through [`safe_file_name`], so ids like `What?` or
+//! `Dr. No.` still produce paths that are valid on Windows and NTFS mounts.
+
+use crate::ast::Document;
+use deunicode::deunicode;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// The `docs/{md,json}/{bucket}/...` layout.
+pub const DEFAULT_PATH_TEMPLATE: &str = "docs/{kind}/{bucket}/{name}.{ext}";
+
+const VARIABLES: &[&str] = &[
+    "kind", "ext", "bucket", "id", "title", "name", "slug", "category",
+];
+
+/// Check that every `{...}` in `template` is a known variable.
+pub fn validate_path_template(template: &str) -> Result<(), Box<dyn Error>> {
+    for var in template_variables(template)? {
+        if !VARIABLES.contains(&var) {
+            return Err(format!(
+                "unknown path template variable {{{}}} (expected one of: {})",
+                var,
+                VARIABLES.join(", ")
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// True if expanding `template` needs the parsed document (`{category}`).
+pub fn uses_document(template: &str) -> bool {
+    template.contains("{category}")
+}
+
+/// Expand `template` for one output file. `kind` is `md` or `json`; `doc` is only
+/// consulted for `{category}`.
+///
+/// With `slugify_names`, `{name}` and `{title}` expand to `{slug}`.
+pub fn expand_path_template(
+    template: &str,
+    kind: &str,
+    article_id: &str,
+    doc: Option<&Document>,
+    slugify_names: bool,
+) -> Result<PathBuf, Box<dyn Error>> {
+    validate_path_template(template)?;
+
+    let title = article_id.replace('_', " ");
+    let mut out = String::with_capacity(template.len() + article_id.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = open
+            + rest[open..]
+                .find('}')
+                .ok_or("unterminated `{` in path template")?;
+        let value = match &rest[open + 1..close] {
+            "kind" | "ext" => kind.to_string(),
+            "name" | "title" if slugify_names => slugify(article_id),
+            "bucket" => crate::lower_first_letter_bucket(article_id),
+            "id" => article_id.to_string(),
+            "title" => title.clone(),
+            "name" if kind == "md" => title.clone(),
+            "name" => article_id.to_string(),
+            "slug" => slugify(article_id),
+            "category" => doc
+                .and_then(|d| d.categories.first())
+                .map(|c| c.name.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .unwrap_or_else(|| "uncategorized".to_string()),
+            _ => unreachable!("validated above"),
+        };
+        // a value must never introduce extra directories.
+        out.push_str(&safe_file_name(&value.replace(['/', '\\'], "_")));
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+const UNSAFE_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make `name` usable as a file name (or stem) on every platform.
+///
+/// Characters Windows rejects (`<>:"|?*`, control characters), trailing dots and
+/// spaces, and reserved device names (`CON`, `NUL`, `COM1`, ...) are
+/// percent-encoded, so [`decode_file_name`] recovers the original. Names that
+/// are already safe are returned unchanged.
+pub fn safe_file_name(name: &str) -> String {
+    let keep = name.trim_end_matches(['.', ' ']).len();
+    let mut out = String::with_capacity(name.len());
+    for (i, c) in name.char_indices() {
+        if UNSAFE_CHARS.contains(&c) || c.is_control() || i >= keep {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    let base = out.split('.').next().unwrap_or("");
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(base))
+        && let Some(last) = base.chars().last()
+    {
+        // `CON` -> `CO%4E`
+        let n = base.len() - last.len_utf8();
+        out.replace_range(n..base.len(), &format!("%{:02X}", last as u32));
+    }
+    out
+}
+
+/// Undo [`safe_file_name`]. Only the escapes it produces are decoded, so other
+/// `%` sequences in existing file names are left alone.
+pub fn decode_file_name(name: &str) -> String {
+    String::from_utf8_lossy(&crate::percent_decode(name, is_encoded_byte)).into_owned()
+}
+
+fn is_encoded_byte(b: u8) -> bool {
+    b < 0x20
+        || b == 0x7F
+        || b". <>:\"|?*".contains(&b)
+        || b.is_ascii_alphabetic()
+        || b.is_ascii_digit()
+}
+
+/// Lowercase ASCII words joined by dashes: `Zobrist Hashing (C++)` -> `zobrist-hashing-c`.
+pub fn slugify(s: &str) -> String {
+    let mut slug = String::with_capacity(s.len());
+    for c in deunicode(s).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("untitled");
+    }
+    slug
+}
+
+fn template_variables(template: &str) -> Result<Vec<&str>, Box<dyn Error>> {
+    let mut vars = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = open
+            + rest[open..]
+                .find('}')
+                .ok_or("unterminated `{` in path template")?;
+        vars.push(&rest[open + 1..close]);
+        rest = &rest[close + 1..];
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template_matches_the_docs_layout() {
+        let md =
+            expand_path_template(DEFAULT_PATH_TEMPLATE, "md", "Ken_Thompson", None, false).unwrap();
+        assert_eq!(md, PathBuf::from("docs/md/k/Ken Thompson.md"));
+        let json = expand_path_template(DEFAULT_PATH_TEMPLATE, "json", "Ken_Thompson", None, false)
+            .unwrap();
+        assert_eq!(json, PathBuf::from("docs/json/k/Ken_Thompson.json"));
+    }
+
+    #[test]
+    fn slug_and_unknown_variables() {
+        let p = expand_path_template(
+            "content/posts/{slug}.md",
+            "md",
+            "Gödel's_Theorem",
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(p, PathBuf::from("content/posts/godel-s-theorem.md"));
+        assert!(validate_path_template("{nope}.md").is_err());
+        assert!(validate_path_template("{slug.md").is_err());
+
+        let p =
+            expand_path_template(DEFAULT_PATH_TEMPLATE, "md", "Ken_Thompson", None, true).unwrap();
+        assert_eq!(p, PathBuf::from("docs/md/k/ken-thompson.md"));
+    }
+
+    #[test]
+    fn safe_file_names_round_trip() {
+        for (name, safe) in [
+            ("Ken_Thompson", "Ken_Thompson"),
+            ("What_is_a_Bitboard?", "What_is_a_Bitboard%3F"),
+            ("Dr._No.", "Dr._No%2E"),
+            ("A:B \"C\"", "A%3AB %22C%22"),
+            ("CON", "CO%4E"),
+            ("nul.txt", "nu%6C.txt"),
+            ("100%_Done", "100%_Done"),
+        ] {
+            assert_eq!(safe_file_name(name), safe);
+            assert_eq!(decode_file_name(safe), name);
+        }
+    }
+}
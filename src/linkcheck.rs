@@ -0,0 +1,333 @@
+//! Finding dead external links (`check-links`).
+//!
+//! Every `[https://... label]` link in the mirrored articles is checked once with a
+//! HEAD request (GET when the server doesn't allow HEAD), a few at a time. Results
+//! are cached in a JSON file so repeated runs only re-check stale entries.
+
+use crate::ast::{self, Document};
+use crate::error::{Error, Result};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How links are checked.
+#[derive(Debug, Clone)]
+pub struct LinkCheckOptions {
+    /// How many requests are in flight at once.
+    pub concurrency: usize,
+    /// Per-request timeout.
+    pub timeout: Duration,
+    /// Where results are cached between runs; `None` checks everything every time.
+    pub cache_path: Option<PathBuf>,
+    /// Cached results older than this are checked again.
+    pub max_age: Duration,
+}
+
+impl Default for LinkCheckOptions {
+    fn default() -> Self {
+        LinkCheckOptions {
+            concurrency: 8,
+            timeout: Duration::from_secs(15),
+            cache_path: None,
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// The result of checking one URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LinkStatus {
+    /// A 2xx/3xx response (after following redirects).
+    Ok { status: u16 },
+    /// A 4xx/5xx response.
+    Broken { status: u16 },
+    /// No response: DNS failure, refused connection, timeout, ...
+    Error { message: String },
+}
+
+impl LinkStatus {
+    pub fn is_dead(&self) -> bool {
+        !matches!(self, LinkStatus::Ok { .. })
+    }
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkStatus::Ok { status } | LinkStatus::Broken { status } => write!(f, "{}", status),
+            LinkStatus::Error { message } => write!(f, "error: {}", message),
+        }
+    }
+}
+
+/// A dead link and why it's dead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeadLink {
+    pub url: String,
+    pub status: LinkStatus,
+}
+
+/// Dead links per article.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkReport {
+    /// Number of distinct URLs checked (or found in the cache).
+    pub checked: usize,
+    /// Articles with at least one dead link, by article id.
+    pub articles: BTreeMap<String, Vec<DeadLink>>,
+}
+
+impl LinkReport {
+    pub fn dead_count(&self) -> usize {
+        self.articles.values().map(Vec::len).sum()
+    }
+
+    /// One `article<TAB>status<TAB>url` line per dead link.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (article, links) in &self.articles {
+            for link in links {
+                out.push_str(&format!("{}\t{}\t{}\n", article, link.status, link.url));
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    status: LinkStatus,
+    /// Unix seconds.
+    checked_at: u64,
+}
+
+/// Check the external links of `(article_id, document)` pairs.
+pub fn check_links<'a>(
+    pages: impl IntoIterator<Item = (&'a str, &'a Document<'a>)>,
+    opts: &LinkCheckOptions,
+) -> Result<LinkReport> {
+    let mut by_article: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (id, doc) in pages {
+        let urls = ast::external_links(doc)
+            .into_iter()
+            .map(|l| l.url.trim())
+            .filter(|u| u.starts_with("http://") || u.starts_with("https://"));
+        by_article.entry(id).or_default().extend(urls);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut cache = load_cache(opts);
+    let stale: Vec<&str> = by_article
+        .values()
+        .flatten()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|url| {
+            cache
+                .get(*url)
+                .is_none_or(|e| now.saturating_sub(e.checked_at) >= opts.max_age.as_secs())
+        })
+        .collect();
+
+    let client = Client::builder()
+        .timeout(opts.timeout)
+        .user_agent(concat!(
+            "wiki2md/",
+            env!("CARGO_PKG_VERSION"),
+            " (link checker)"
+        ))
+        .build()
+        .map_err(|e| Error::Config(format!("failed to create HTTP client: {}", e)))?;
+    for (url, status) in check_urls(&client, &stale, opts.concurrency) {
+        cache.insert(
+            url.to_string(),
+            CacheEntry {
+                status,
+                checked_at: now,
+            },
+        );
+    }
+    save_cache(opts, &cache)?;
+
+    let mut report = LinkReport::default();
+    let mut checked = BTreeSet::new();
+    for (id, urls) in by_article {
+        let dead: Vec<DeadLink> = urls
+            .into_iter()
+            .inspect(|url| {
+                checked.insert(*url);
+            })
+            .filter_map(|url| {
+                let status = &cache.get(url)?.status;
+                status.is_dead().then(|| DeadLink {
+                    url: url.to_string(),
+                    status: status.clone(),
+                })
+            })
+            .collect();
+        if !dead.is_empty() {
+            report.articles.insert(id.to_string(), dead);
+        }
+    }
+    report.checked = checked.len();
+    Ok(report)
+}
+
+/// Check `urls` on `concurrency` worker threads.
+fn check_urls<'u>(
+    client: &Client,
+    urls: &[&'u str],
+    concurrency: usize,
+) -> Vec<(&'u str, LinkStatus)> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(urls.len()));
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, urls.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(url) = urls.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let status = check_url(client, url);
+                    tracing::info!(%url, %status, "checked");
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push((*url, status));
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+fn check_url(client: &Client, url: &str) -> LinkStatus {
+    let resp = match client.head(url).send() {
+        // some servers don't implement HEAD.
+        Ok(resp) if matches!(resp.status().as_u16(), 405 | 501) => client.get(url).send(),
+        other => other,
+    };
+    match resp {
+        Ok(resp) if resp.status().is_client_error() || resp.status().is_server_error() => {
+            LinkStatus::Broken {
+                status: resp.status().as_u16(),
+            }
+        }
+        Ok(resp) => LinkStatus::Ok {
+            status: resp.status().as_u16(),
+        },
+        Err(e) => LinkStatus::Error {
+            message: e.to_string(),
+        },
+    }
+}
+
+fn load_cache(opts: &LinkCheckOptions) -> HashMap<String, CacheEntry> {
+    let Some(path) = &opts.cache_path else {
+        return HashMap::new();
+    };
+    let Ok(text) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        // a broken cache only costs a re-check.
+        tracing::warn!(path = %path.display(), error = %e, "ignoring invalid link cache");
+        HashMap::new()
+    })
+}
+
+fn save_cache(opts: &LinkCheckOptions, cache: &HashMap<String, CacheEntry>) -> Result<()> {
+    let Some(path) = &opts.cache_path else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(Error::io(parent))?;
+    }
+    let sorted: BTreeMap<&String, &CacheEntry> = cache.iter().collect();
+    let json = serde_json::to_string_pretty(&sorted).unwrap_or_default();
+    fs::write(path, json).map_err(Error::io(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// A server answering `/ok` with 200, HEAD with 405 on `/get-only`, and 404
+    /// everywhere else. Returns its base URL and a request counter.
+    fn serve() -> (String, &'static AtomicUsize) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits: &'static AtomicUsize = Box::leak(Box::new(AtomicUsize::new(0)));
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                hits.fetch_add(1, Ordering::SeqCst);
+                let status = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+                    [_, "/ok", ..] | ["GET", "/get-only", ..] => "200 OK",
+                    ["HEAD", "/get-only", ..] => "405 Method Not Allowed",
+                    _ => "404 Not Found",
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+            }
+        });
+        (base, hits)
+    }
+
+    #[test]
+    fn reports_dead_links_per_article_and_caches_results() {
+        let (base, hits) = serve();
+        let alpha_src =
+            format!("[{base}/ok fine], [{base}/gone gone] and [{base}/get-only old server].\n");
+        let alpha = parse_wiki(&alpha_src).document;
+        let beta_src = format!("[{base}/ok again] [mailto:x@example.com mail]\n");
+        let beta = parse_wiki(&beta_src).document;
+        let pages = [("Alpha", &alpha), ("Beta", &beta)];
+
+        let dir = tempfile::tempdir().unwrap();
+        let opts = LinkCheckOptions {
+            concurrency: 2,
+            cache_path: Some(dir.path().join("links.json")),
+            ..Default::default()
+        };
+        let report = check_links(pages, &opts).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.dead_count(), 1);
+        assert_eq!(report.to_text(), format!("Alpha\t404\t{base}/gone\n"));
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+
+        // everything is cached now.
+        let report = check_links(pages, &opts).unwrap();
+        assert_eq!(report.dead_count(), 1);
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+}
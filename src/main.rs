@@ -1,50 +1,1697 @@
-use clap::Parser;
-use wiki2md::render::RenderOptions;
-use wiki2md::{WriteOptions, regenerate_all_with_options, run_with_options};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use wiki2md::ast_filter::AstFilter;
+use wiki2md::book::{self, BookOptions};
+use wiki2md::cache::{self, CacheDirs};
+use wiki2md::filter::{ArticleFilter, parse_date};
+use wiki2md::golden;
+use wiki2md::graph::GraphOptions;
+use wiki2md::http_cache::{self, HttpCache};
+use wiki2md::index::IndexPages;
+use wiki2md::infobox::InfoboxOptions;
+use wiki2md::linkcheck::LinkCheckOptions;
+use wiki2md::mirror::{self, MirrorOptions};
+use wiki2md::parse;
+use wiki2md::render::{
+    BreadcrumbStyle, ChessDiagramStyle, CodeNormalization, ExternalLinkNumbering,
+    FootnotePlacement, HardBreakStyle, HeadingAnchorStyle, HtmlPolicy, MagicLinkUrls, PoemStyle,
+    QuoteAttributionStyle, RawBlockPolicy, RenderOptions, TableAlignOverride, TableAlignment,
+    TableCaptionPosition, TableCaptionStyle, TableHeaderRows, TemplateHandlers, TextRule,
+    TextRules, TimelineStyle, UrlRewriteRule, UrlRewrites, WideTables, render_section,
+};
+use wiki2md::serve::{self, ServeFormat, ServeOptions};
+use wiki2md::tables::TableFormat;
+use wiki2md::vault::{self, Vault};
+use wiki2md::watch;
+use wiki2md::{
+    Config, ProgressMode, Verbosity, WriteOptions, article_tables, build_link_graph,
+    check_external_links, convert_file, convert_wikitext, corpus_stats, read_ast_file,
+    regenerate_all_with_config, render_article, render_article_section, render_ast_file,
+    run_with_config,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    /// The title of the page (e.g., "Perft" or "Move Generation").
-    /// Required unless --regenerate-all is used.
-    #[arg(required_unless_present = "regenerate_all")]
-    title: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The titles of the pages (e.g., "Perft" "Move Generation"), converted one
+    /// after another. Required unless --regenerate-all, --stdin, or --from-json is used.
+    #[arg(value_name = "TITLE", required_unless_present_any = ["regenerate_all", "stdin", "from_json"])]
+    titles: Vec<String>,
 
     /// Regenerate all .md files from existing .wiki files in ./docs/wiki
     #[arg(long, short = 'r')]
     regenerate_all: bool,
 
-    /// Center wikitable captions and tables using an HTML wrapper.
+    /// Read wikitext from stdin and write Markdown to stdout. Nothing is cached or written.
+    #[arg(long, conflicts_with_all = ["titles", "regenerate_all"])]
+    stdin: bool,
+
+    /// Render a `.json` AST file (as written by --json, possibly edited) and write the
+    /// Markdown to stdout. No `.wiki` file is needed and nothing is written.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["titles", "regenerate_all", "stdin"])]
+    from_json: Option<PathBuf>,
+
+    /// Print the article's Markdown without creating `docs/` or writing any files.
+    #[arg(long, conflicts_with = "regenerate_all")]
+    stdout: bool,
+
+    /// Print only the blocks under this heading (implies --stdout). Nested headings
+    /// are separated by `>`, e.g. "Publications > 1990s".
+    #[arg(long, value_name = "HEADING", conflicts_with = "regenerate_all")]
+    section: Option<String>,
+
+    /// Fail instead of fetching articles missing from ./docs/wiki.
     #[arg(long, default_value_t = false)]
-    center_tables: bool,
+    offline: bool,
+
+    /// Replace `{{:Other Page}}` with the content of that page, if it is in
+    /// ./docs/wiki (transcluded pages are never fetched).
+    #[arg(long, default_value_t = false)]
+    transclude: bool,
+
+    /// Pipe each article's AST through this command (repeatable, run in order): it
+    /// reads the JSON AST on stdin and prints the modified AST on stdout. The
+    /// command is split on whitespace; no shell is involved.
+    #[arg(
+        long = "ast-filter",
+        value_name = "COMMAND",
+        value_parser = AstFilter::parse,
+        conflicts_with_all = ["stdin", "from_json", "stdout", "section"]
+    )]
+    ast_filters: Vec<AstFilter>,
+
+    /// Write Markdown into this Obsidian vault (a folder with a `.obsidian` folder),
+    /// under --vault-folder. Images are downloaded into the vault's attachments folder.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["stdin", "from_json", "stdout", "section", "path_template"])]
+    vault: Option<PathBuf>,
+
+    /// The folder inside the vault generated notes go into, so they never
+    /// overwrite your own notes.
+    #[arg(long, value_name = "FOLDER", default_value = vault::DEFAULT_FOLDER, requires = "vault")]
+    vault_folder: PathBuf,
 
     /// Regenerate YAML frontmatter during regeneration.
     #[arg(long, default_value_t = false)]
     regenerate_frontmatter: bool,
+
+    /// Keep the previous version of each changed `.md` file as `<name>.md.bak`.
+    #[arg(long, default_value_t = false)]
+    backup: bool,
+
+    /// Copy previous versions of changed `.md` files into this directory (implies --backup).
+    #[arg(long, value_name = "DIR")]
+    backup_dir: Option<PathBuf>,
+
+    /// Write nothing; print a unified diff of what would change in each `.md` file.
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Where generated files go, e.g. "content/posts/{slug}.{ext}". Variables: {kind},
+    /// {ext}, {bucket}, {id}, {title}, {name}, {slug}, {category}.
+    #[arg(long, value_name = "TEMPLATE")]
+    path_template: Option<String>,
+
+    /// With --regenerate-all, only convert articles matching this glob (or `re:<regex>`);
+    /// matched against the id, the title, and `bucket/id`. Repeatable.
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// With --regenerate-all, skip articles matching this glob (or `re:<regex>`). Repeatable.
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// With --regenerate-all, only convert articles whose .wiki changed on or after this date.
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    since: Option<String>,
+
+    /// With --regenerate-all, leave `.md` files that already exist untouched.
+    #[arg(long, default_value_t = false)]
+    skip_existing: bool,
+
+    /// End each `.md` file with a comment recording the parser version, schema
+    /// version, and render options hash. With --skip-existing, files generated by
+    /// another version or with other options are regenerated anyway.
+    #[arg(long, default_value_t = false)]
+    generation_metadata: bool,
+
+    /// With --regenerate-all, start at this article and skip everything before it.
+    #[arg(long, value_name = "ARTICLE_ID")]
+    resume_from: Option<String>,
+
+    /// With --regenerate-all, record files that fail to convert and carry on, then
+    /// print a summary and list them in --failures-file.
+    #[arg(long, short = 'k', default_value_t = false)]
+    keep_going: bool,
+
+    /// Where --keep-going writes the list of failed files.
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "failures.json",
+        requires = "keep_going"
+    )]
+    failures_file: PathBuf,
+
+    /// With --regenerate-all, exit non-zero if the articles' diagnostics add up to
+    /// more than N warnings or include any error, listing the worst articles and codes.
+    #[arg(long, value_name = "N")]
+    max_warnings: Option<usize>,
+
+    /// With --regenerate-all, also write a lunr-compatible JSON search index
+    /// (title, headings, plain-text body, tags) of every article to PATH.
+    #[arg(long, value_name = "PATH")]
+    search_index: Option<PathBuf>,
+
+    /// With --regenerate-all, also write a JSON map of the section anchors linked
+    /// to (`[[Page#Section]]`) to the Markdown fragments they became, per article.
+    #[arg(long, value_name = "PATH")]
+    anchor_map: Option<PathBuf>,
+
+    /// With --regenerate-all, also write a manifest of every article's source and
+    /// output files with their content hashes (default: docs/manifest.json).
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "docs/manifest.json")]
+    manifest: Option<PathBuf>,
+
+    /// Also write each article's parsed AST as JSON (to ./docs/json/<bucket>/, or
+    /// where --path-template puts `json` files), in single file and --regenerate-all mode.
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// With --json, write the AST without indentation (smaller, faster to write).
+    #[arg(long, default_value_t = false, requires = "json")]
+    json_compact: bool,
+
+    /// With --json, gzip the AST and write it as <name>.json.gz.
+    #[arg(long, default_value_t = false, requires = "json")]
+    json_gzip: bool,
+
+    /// With --regenerate-all, the alphabetical index written to docs/md/Index.md.
+    #[arg(long, value_enum, default_value_t = IndexMode::Single)]
+    index: IndexMode,
+
+    /// With --regenerate-all, also write one page per category listing its articles
+    /// (docs/md/Categories/<name>.md) and a docs/md/Categories.md overview.
+    #[arg(long, default_value_t = false)]
+    category_pages: bool,
+
+    /// Only print errors.
+    #[arg(long, short = 'q', conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print per-file parse/render timing.
+    #[arg(long, short = 'v')]
+    verbose: bool,
+
+    /// With --regenerate-all, end with the time spent per phase (read, parse,
+    /// render, frontmatter, write) and the slowest files.
+    #[arg(long, default_value_t = false)]
+    timings: bool,
+
+    /// Print one progress line per file instead of a progress bar (the default when
+    /// stderr is not a terminal).
+    #[arg(long, default_value_t = false)]
+    no_tty: bool,
+
+    /// Emit structured fetch/parse/render/write events on stderr in this format.
+    /// Events are also enabled (as text) when `RUST_LOG` is set.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    log_format: Option<LogFormat>,
+
+    /// Cache HTTP responses (edit pages, API queries, images) in this directory and
+    /// answer repeated requests from it.
+    #[arg(long, value_name = "DIR", global = true)]
+    http_cache: Option<PathBuf>,
+
+    /// Fetch again when a cached response is older than this many seconds
+    /// (default: never expire).
+    #[arg(long, value_name = "SECS", requires = "http_cache", global = true)]
+    http_max_age: Option<u64>,
+
+    /// Name generated files by the lowercase ASCII slug of the article (`ken-thompson.md`).
+    #[arg(long, default_value_t = false)]
+    slugify: bool,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+/// Options that control how articles are rendered.
+#[derive(Args)]
+struct RenderArgs {
+    /// Center wikitable captions and tables using an HTML wrapper.
+    #[arg(long, default_value_t = false)]
+    center_tables: bool,
+
+    /// Wrap wide wikitables in a horizontally scrolling `<div>`.
+    #[arg(long, default_value_t = false)]
+    scroll_tables: bool,
+
+    /// Tables with more columns than this scroll (default 10). Implies `--scroll-tables`.
+    #[arg(long, value_name = "N")]
+    scroll_table_columns: Option<usize>,
+
+    /// Tables wider than this many characters scroll (default 160). Implies `--scroll-tables`.
+    #[arg(long, value_name = "CHARS")]
+    scroll_table_width: Option<usize>,
+
+    /// Right-align wikitable columns that are predominantly numbers or percentages,
+    /// whatever their attributes say.
+    #[arg(long, default_value_t = false)]
+    align_numeric: bool,
+
+    /// With --align-numeric, the share of a column's data cells (in percent) that
+    /// must be numbers. Empty cells and placeholders like `-` or `n/a` don't count.
+    #[arg(long, value_name = "PERCENT", default_value_t = 80, value_parser = clap::value_parser!(u8).range(1..=100))]
+    numeric_threshold: u8,
+
+    /// Always left-align the first wikitable column.
+    #[arg(long, default_value_t = false)]
+    align_first_left: bool,
+
+    /// Set the column alignments of matching tables: `caption=TEXT=>l,r,c` or
+    /// `ATTR=VALUE=>...` (e.g. `class=sortable=>l,r`). Repeatable; the first match wins.
+    #[arg(long, value_name = "SPEC")]
+    table_align: Vec<String>,
+
+    /// How wikitable captions are styled.
+    #[arg(long, value_enum, default_value_t = CaptionMode::Plain)]
+    table_caption: CaptionMode,
+
+    /// Put wikitable captions below the table instead of above it.
+    #[arg(long, default_value_t = false)]
+    table_caption_below: bool,
+
+    /// Which rows become the Markdown header row of a wikitable.
+    #[arg(long, value_enum, default_value_t = HeaderMode::First)]
+    table_header: HeaderMode,
+
+    /// With --table-header join, how many rows are joined into the header.
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    table_header_join: u64,
+
+    /// Put an anchor before each wikitable, named by its id, caption, or position
+    /// (`table-perft-results`, `table-2`), so `[[Page#table-2]]` links reach it.
+    #[arg(long, default_value_t = false)]
+    table_anchors: bool,
+
+    /// How `<br/>` line breaks are written to Markdown.
+    #[arg(long, value_enum, default_value_t = BreakStyle::Html)]
+    hard_break_style: BreakStyle,
+
+    /// Render `<poem>` verse inside a blockquote.
+    #[arg(long, default_value_t = false)]
+    poem_blockquote: bool,
+
+    /// What becomes of source the parser couldn't make sense of. `--regenerate-all
+    /// --manifest` and `stats` count these raw blocks per article.
+    #[arg(long, value_enum, default_value_t = RawMode::Fenced)]
+    raw_blocks: RawMode,
+
+    /// Follow quotes and tables with an Obsidian block id (`^3f2a9c`) that stays the
+    /// same across regeneration, so other notes can embed them.
+    #[arg(long, default_value_t = false)]
+    block_ids: bool,
+
+    /// Which raw HTML tags from the wikitext are kept in the Markdown.
+    #[arg(long, value_enum, default_value_t = HtmlMode::All)]
+    html: HtmlMode,
+
+    /// How chess diagram templates are rendered.
+    #[arg(long, value_enum, default_value_t = DiagramMode::Ascii)]
+    chess_diagrams: DiagramMode,
+
+    /// Show chess piece images and chess symbol entities in text as Unicode
+    /// symbols (♔♕♖...) instead of image links.
+    #[arg(long, default_value_t = false)]
+    chess_symbols: bool,
+
+    /// How EasyTimeline `<timeline>` charts are rendered.
+    #[arg(long, value_enum, default_value_t = TimelineMode::Fenced)]
+    timeline: TimelineMode,
+
+    /// Dedent code blocks, expand tabs to 4 spaces, and trim trailing whitespace.
+    #[arg(long, default_value_t = false)]
+    normalize_code: bool,
+
+    /// Collapse code blocks longer than LINES lines into a `<details>` element.
+    #[arg(long, value_name = "LINES")]
+    collapse_code: Option<usize>,
+
+    /// Reflow paragraph text at this many columns.
+    #[arg(long)]
+    wrap_width: Option<usize>,
+
+    /// How unlabeled external links (`[https://example.com]`) are rendered.
+    #[arg(long, value_enum, default_value_t = LinkNumbering::Inline)]
+    bare_links: LinkNumbering,
+
+    /// Where footnote definitions go.
+    #[arg(long, value_enum, default_value_t = Footnotes::References)]
+    footnotes: Footnotes,
+
+    /// Refs with identical content share one footnote.
+    #[arg(long, default_value_t = false)]
+    dedupe_refs: bool,
+
+    /// Where `ISBN`, `RFC`, or `PMID` magic links point: `KIND=URL`, with `{id}` in
+    /// the URL replaced by the identifier, e.g. `isbn=https://openlibrary.org/isbn/{id}`.
+    /// Repeatable.
+    #[arg(long, value_name = "KIND=URL")]
+    magic_link_url: Vec<String>,
+
+    /// How the `Home * People * Name` breadcrumb at the top of a page is rendered.
+    #[arg(long, value_enum, default_value_t = BreadcrumbMode::Paragraph)]
+    breadcrumb: BreadcrumbMode,
+
+    /// How `<span id="...">` link targets are emitted.
+    #[arg(long, value_enum, default_value_t = AnchorMode::Html)]
+    heading_anchors: AnchorMode,
+
+    /// Point image and file URLs here instead of the wiki's `/images` (a CDN, or a
+    /// local server with the downloaded files): `<URL>/a/a9/Board.jpg`.
+    #[arg(long, value_name = "URL")]
+    image_base_url: Option<String>,
+
+    /// Leave out every invocation of this template (e.g. a navigation box).
+    /// Repeatable.
+    #[arg(long, value_name = "NAME")]
+    drop_template: Vec<String>,
+
+    /// Leave out navigation templates: templates named like `navbox`, and
+    /// template-only paragraphs at the bottom of a page.
+    #[arg(long, default_value_t = false)]
+    drop_navboxes: bool,
+
+    /// Leave the "See also" section out of the body; its links still go into the
+    /// frontmatter `related` list.
+    #[arg(long, default_value_t = false)]
+    drop_see_also: bool,
+
+    /// Move the named parameters of an infobox template (`{{Infobox ...}}`) into
+    /// the frontmatter instead of the body.
+    #[arg(long, default_value_t = false)]
+    infobox: bool,
+
+    /// Template name treated as an infobox (also matches longer names starting
+    /// with it). Repeatable; replaces the default `Infobox`. Implies `--infobox`.
+    #[arg(long, value_name = "NAME")]
+    infobox_template: Vec<String>,
+
+    /// Frontmatter key the infobox fields are written under. Implies `--infobox`.
+    #[arg(long, value_name = "KEY")]
+    infobox_namespace: Option<String>,
+
+    /// Rewrite external link URLs: `PATTERN=>REPLACEMENT`, a regex and its
+    /// replacement (`$1` for groups). Repeatable; applied in order.
+    #[arg(long, value_name = "RULE")]
+    rewrite_url: Vec<String>,
+
+    /// File of URL rewrite rules, one `PATTERN=>REPLACEMENT` per line (`#` starts
+    /// a comment). Applied before any --rewrite-url rules.
+    #[arg(long, value_name = "PATH")]
+    url_rewrites: Option<PathBuf>,
+
+    /// Rewrite running text: `PATTERN=>REPLACEMENT`, a regex and its replacement
+    /// (`$0` for the match), e.g. '\b[a-h][1-8][a-h][1-8]\b=>`$0`'. Links and code
+    /// are left alone. Repeatable; applied in order.
+    #[arg(long, value_name = "RULE")]
+    text_rule: Vec<String>,
+
+    /// File of text rules, one `PATTERN=>REPLACEMENT` per line (`#` starts a
+    /// comment). Applied before any --text-rule rules.
+    #[arg(long, value_name = "PATH")]
+    text_rules: Option<PathBuf>,
+
+    /// rhai script registering template handlers and text transforms (repeatable;
+    /// later scripts win).
+    #[cfg(feature = "scripting")]
+    #[arg(long = "script", value_name = "PATH")]
+    scripts: Vec<PathBuf>,
+
+    /// How indented (leading-space) blocks are rendered.
+    #[arg(long, value_enum, default_value_t = LeadingSpaceMode::Auto)]
+    leading_space: LeadingSpaceMode,
+
+    /// Where a quote's "By ..., 1997:" intro line goes.
+    #[arg(long, value_enum, default_value_t = AttributionMode::Intro)]
+    quote_attribution: AttributionMode,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HeaderMode {
+    /// The first row with a header cell.
+    First,
+    /// An empty header; every row is data.
+    Empty,
+    /// The first --table-header-join rows, joined column by column.
+    Join,
+    /// The first row with a header cell; later all-header rows become bold data rows.
+    Demote,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CaptionMode {
+    /// The caption text as-is.
+    Plain,
+    /// `*caption*`
+    Italic,
+    /// `**caption**`
+    Bold,
+}
+
+impl From<CaptionMode> for TableCaptionStyle {
+    fn from(m: CaptionMode) -> Self {
+        match m {
+            CaptionMode::Plain => TableCaptionStyle::Plain,
+            CaptionMode::Italic => TableCaptionStyle::Italic,
+            CaptionMode::Bold => TableCaptionStyle::Bold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AttributionMode {
+    /// Keep the intro line above the quote.
+    Intro,
+    /// An em-dash line at the end of the quote.
+    Trailer,
+    /// An Obsidian quote callout titled with the source.
+    Callout,
+}
+
+impl From<AttributionMode> for QuoteAttributionStyle {
+    fn from(m: AttributionMode) -> Self {
+        match m {
+            AttributionMode::Intro => QuoteAttributionStyle::Intro,
+            AttributionMode::Trailer => QuoteAttributionStyle::Trailer,
+            AttributionMode::Callout => QuoteAttributionStyle::CalloutTitle,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LeadingSpaceMode {
+    /// Decide per block whether it is quoted prose or code.
+    Auto,
+    /// Always a blockquote.
+    Quote,
+    /// Always a fenced code block.
+    Code,
+}
+
+impl LeadingSpaceMode {
+    fn as_override(self) -> Option<bool> {
+        match self {
+            LeadingSpaceMode::Auto => None,
+            LeadingSpaceMode::Quote => Some(true),
+            LeadingSpaceMode::Code => Some(false),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IndexMode {
+    /// No index page.
+    Off,
+    /// One Index.md listing every article by letter.
+    Single,
+    /// Index.md linking to one Index/<letter>.md page per letter.
+    PerLetter,
+}
+
+impl From<IndexMode> for IndexPages {
+    fn from(m: IndexMode) -> Self {
+        match m {
+            IndexMode::Off => IndexPages::Off,
+            IndexMode::Single => IndexPages::Single,
+            IndexMode::PerLetter => IndexPages::PerLetter,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiagramMode {
+    /// Piece letters in a text block.
+    Ascii,
+    /// Unicode chess symbols in a text block.
+    Unicode,
+    /// An image of the position.
+    Image,
+}
+
+impl From<DiagramMode> for ChessDiagramStyle {
+    fn from(m: DiagramMode) -> Self {
+        match m {
+            DiagramMode::Ascii => ChessDiagramStyle::Ascii,
+            DiagramMode::Unicode => ChessDiagramStyle::Unicode,
+            DiagramMode::Image => ChessDiagramStyle::Image,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TimelineMode {
+    /// The EasyTimeline script in a code block.
+    Fenced,
+    /// A Mermaid gantt chart.
+    Gantt,
+    /// A Mermaid timeline diagram.
+    Timeline,
+}
+
+impl From<TimelineMode> for TimelineStyle {
+    fn from(m: TimelineMode) -> Self {
+        match m {
+            TimelineMode::Fenced => TimelineStyle::Fenced,
+            TimelineMode::Gantt => TimelineStyle::MermaidGantt,
+            TimelineMode::Timeline => TimelineStyle::MermaidTimeline,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RawMode {
+    /// In a ```text code block.
+    Fenced,
+    /// In an HTML comment.
+    Comment,
+    /// As is.
+    Passthrough,
+    /// Left out.
+    Drop,
+}
+
+impl From<RawMode> for RawBlockPolicy {
+    fn from(m: RawMode) -> Self {
+        match m {
+            RawMode::Fenced => RawBlockPolicy::Fenced,
+            RawMode::Comment => RawBlockPolicy::Comment,
+            RawMode::Passthrough => RawBlockPolicy::Passthrough,
+            RawMode::Drop => RawBlockPolicy::Drop,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HtmlMode {
+    /// Keep all tags and attributes.
+    All,
+    /// Keep common presentational tags; drop scripts, styles, and event handlers.
+    Safe,
+    /// Remove all tags, keeping their text.
+    Strip,
+}
+
+impl From<HtmlMode> for HtmlPolicy {
+    fn from(m: HtmlMode) -> Self {
+        match m {
+            HtmlMode::All => HtmlPolicy::AllowAll,
+            HtmlMode::Safe => HtmlPolicy::safe_default(),
+            HtmlMode::Strip => HtmlPolicy::StripToText,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BreakStyle {
+    /// `<br/>`
+    Html,
+    /// Trailing backslash.
+    Backslash,
+    /// Two trailing spaces.
+    Spaces,
+}
+
+impl From<BreakStyle> for HardBreakStyle {
+    fn from(s: BreakStyle) -> Self {
+        match s {
+            BreakStyle::Html => HardBreakStyle::Html,
+            BreakStyle::Backslash => HardBreakStyle::Backslash,
+            BreakStyle::Spaces => HardBreakStyle::TrailingSpaces,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LinkNumbering {
+    /// `[1]`, `[2]`, ... linking to the URL.
+    Inline,
+    /// Numbered footnotes listing the URL.
+    Footnote,
+    /// The URL itself.
+    Autolink,
+}
+
+impl From<LinkNumbering> for ExternalLinkNumbering {
+    fn from(n: LinkNumbering) -> Self {
+        match n {
+            LinkNumbering::Inline => ExternalLinkNumbering::Inline,
+            LinkNumbering::Footnote => ExternalLinkNumbering::Footnote,
+            LinkNumbering::Autolink => ExternalLinkNumbering::Autolink,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Footnotes {
+    /// At the page's `<references />`.
+    References,
+    /// After the section that references them.
+    Section,
+    /// In a References section at the end of the page.
+    End,
+    /// Inline, as `^[...]`.
+    Inline,
+}
+
+impl From<Footnotes> for FootnotePlacement {
+    fn from(f: Footnotes) -> Self {
+        match f {
+            Footnotes::References => FootnotePlacement::References,
+            Footnotes::Section => FootnotePlacement::Section,
+            Footnotes::End => FootnotePlacement::End,
+            Footnotes::Inline => FootnotePlacement::Inline,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BreadcrumbMode {
+    /// As written, a bold line of links.
+    Paragraph,
+    /// Leave it out.
+    Omit,
+    /// A small heading: `###### Home › People › Name`.
+    Heading,
+    /// A plain line: `Home › People › Name`.
+    Line,
+}
+
+impl From<BreadcrumbMode> for BreadcrumbStyle {
+    fn from(mode: BreadcrumbMode) -> Self {
+        match mode {
+            BreadcrumbMode::Paragraph => BreadcrumbStyle::Paragraph,
+            BreadcrumbMode::Omit => BreadcrumbStyle::Omit,
+            BreadcrumbMode::Heading => BreadcrumbStyle::Heading,
+            BreadcrumbMode::Line => BreadcrumbStyle::Line,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AnchorMode {
+    /// `<a name="id"></a>`.
+    Html,
+    /// pandoc/Kramdown attributes: `## Title {#id}`, `[]{#id}`.
+    Attribute,
+    /// Leave them out.
+    None,
+}
+
+impl From<AnchorMode> for HeadingAnchorStyle {
+    fn from(mode: AnchorMode) -> Self {
+        match mode {
+            AnchorMode::Html => HeadingAnchorStyle::Html,
+            AnchorMode::Attribute => HeadingAnchorStyle::Attribute,
+            AnchorMode::None => HeadingAnchorStyle::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per event.
+    Json,
+}
+
+/// Install a `tracing` subscriber on stderr. Does nothing unless `--log-format`
+/// is given or `RUST_LOG` is set, so the default output is unchanged.
+fn init_logging(format: Option<LogFormat>) {
+    let env_filter = std::env::var("RUST_LOG").ok();
+    let format = match (format, &env_filter) {
+        (Some(f), _) => f,
+        (None, Some(_)) => LogFormat::Text,
+        (None, None) => return,
+    };
+    let filter =
+        tracing_subscriber::EnvFilter::new(env_filter.as_deref().unwrap_or("wiki2md=info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build an EPUB book from a list of articles in ./docs/wiki.
+    Book {
+        /// Article titles, one chapter each, in order.
+        titles: Vec<String>,
+
+        /// File listing article titles, one per line (`#` starts a comment).
+        /// Its articles come before any titles given on the command line.
+        #[arg(long, short = 'i')]
+        index: Option<PathBuf>,
+
+        /// Output EPUB path.
+        #[arg(long, short = 'o', default_value = "book.epub")]
+        output: PathBuf,
+
+        /// Book title.
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Book author.
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Keep images as links to the wiki instead of downloading them into the book.
+        #[arg(long, default_value_t = false)]
+        no_images: bool,
+
+        /// Fail instead of fetching articles missing from ./docs/wiki.
+        #[arg(long, default_value_t = false)]
+        offline: bool,
+    },
+
+    /// Export the page-to-page link graph of the articles in ./docs/wiki.
+    Graph {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+
+        /// Write to this file instead of stdout.
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+
+        /// Leave out redirect pages (links to them point at their targets).
+        #[arg(long, default_value_t = false)]
+        no_redirects: bool,
+
+        /// Leave out `Category:` pages.
+        #[arg(long, default_value_t = false)]
+        no_categories: bool,
+    },
+
+    /// Check the external links of the articles in ./docs/wiki and list dead ones.
+    /// Exits with status 1 if any link is dead.
+    CheckLinks {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// How many requests to make at once.
+        #[arg(long, short = 'j', default_value_t = 8)]
+        concurrency: usize,
+
+        /// Per-request timeout in seconds.
+        #[arg(long, value_name = "SECS", default_value_t = 15)]
+        timeout: u64,
+
+        /// Where results are cached between runs.
+        #[arg(long, value_name = "PATH", default_value = "docs/link-cache.json")]
+        cache: PathBuf,
+
+        /// Re-check cached results older than this many days (0 re-checks everything).
+        #[arg(long, value_name = "DAYS", default_value_t = 7)]
+        max_age: u64,
+    },
+
+    /// Regenerate a `.md` file whenever its `.wiki` file in ./docs/wiki changes, and
+    /// print the parser's diagnostics for it.
+    Watch {
+        /// Name generated files by the lowercase ASCII slug of the article.
+        #[arg(long, default_value_t = false)]
+        slugify: bool,
+
+        /// Where generated files go (see the top-level --path-template).
+        #[arg(long, value_name = "TEMPLATE")]
+        path_template: Option<String>,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Convert a single `.wiki` file from anywhere on disk, outside ./docs.
+    Convert {
+        /// The `.wiki` file. Its name is the article id (`Ken_Thompson.wiki`).
+        input: PathBuf,
+
+        /// Output Markdown path (default: the input with a `.md` extension).
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+
+        /// Also write the parsed AST as JSON next to the output.
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Count node kinds, diagnostics, and templates across the articles in
+    /// ./docs/wiki, with parse times and the largest documents.
+    Stats {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// Read the cached `.json` ASTs under this directory instead of parsing.
+        #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "docs/json")]
+        from_json: Option<PathBuf>,
+
+        /// How many templates and largest documents to list.
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+
+    /// Serve the articles in ./docs/wiki over HTTP for previewing in a browser.
+    /// Pages are rendered on each request, with internal links pointing at other
+    /// pages on the server.
+    Serve {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+
+        /// What article pages show.
+        #[arg(long, value_enum, default_value_t = PreviewFormat::Html)]
+        format: PreviewFormat,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Render every `NNN-in-<name>.wiki` golden test case in a directory and
+    /// compare it with `NNN-want-<name>.txt`.
+    Golden {
+        /// Directory holding the cases.
+        #[arg(default_value = "tests/testdata")]
+        dir: PathBuf,
+
+        /// Overwrite the expectations with the actual output.
+        #[arg(long)]
+        bless: bool,
+    },
+
+    /// Inspect and tidy the ./docs/wiki, ./docs/json, and ./docs/md cache.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Fetch every page of the wiki into ./docs/wiki through the MediaWiki API,
+    /// then convert them all. Cached pages are skipped, so an interrupted mirror
+    /// continues where it stopped.
+    Mirror {
+        /// The wiki's `api.php` endpoint.
+        #[arg(long, default_value = mirror::DEFAULT_API_URL)]
+        api_url: String,
+
+        /// The namespace to mirror (0 is articles).
+        #[arg(long, default_value_t = 0)]
+        namespace: u32,
+
+        /// Minimum pause between two requests, in milliseconds.
+        #[arg(long, value_name = "MS", default_value_t = 1000)]
+        delay_ms: u64,
+
+        /// Pages fetched per request (at most 50).
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u16).range(1..=50))]
+        batch_size: u16,
+
+        /// How often a throttled or failed request is retried.
+        #[arg(long, default_value_t = 5)]
+        max_retries: u32,
+
+        /// Fetch pages that are already cached again.
+        #[arg(long)]
+        refetch: bool,
+
+        /// Only mirror the first N pages.
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Only fetch; don't convert.
+        #[arg(long)]
+        no_convert: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Export the tables of an article as CSV or TSV files, one per table.
+    Tables {
+        /// The title of the page (e.g., "Perft Results").
+        title: String,
+
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = TableExportFormat::Csv)]
+        format: TableExportFormat,
+
+        /// Directory to write `<article>-<n>.csv` files to.
+        #[arg(long, short = 'o', default_value = ".")]
+        out_dir: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// A human-readable report.
+    Text,
+    /// The same report as JSON.
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PreviewFormat {
+    /// The HTML backend's output.
+    Html,
+    /// The converted Markdown, as a Markdown viewer would show it.
+    Markdown,
+}
+
+impl From<PreviewFormat> for ServeFormat {
+    fn from(f: PreviewFormat) -> Self {
+        match f {
+            PreviewFormat::Html => ServeFormat::Html,
+            PreviewFormat::Markdown => ServeFormat::Markdown,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Count the files and bytes of each directory per bucket.
+    Status,
+    /// List `.md`/`.json` files whose `.wiki` source is missing, and `.wiki`
+    /// files without a `.md` file. Exits with status 1 if there are any.
+    Verify,
+    /// Remove `.md` and `.json` files whose `.wiki` source is missing.
+    Clean {
+        /// Only list what would be removed.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TableExportFormat {
+    /// Comma-separated values.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+
+impl From<TableExportFormat> for TableFormat {
+    fn from(format: TableExportFormat) -> Self {
+        match format {
+            TableExportFormat::Csv => TableFormat::Csv,
+            TableExportFormat::Tsv => TableFormat::Tsv,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT.
+    Dot,
+    /// JSON adjacency list: `{"Page": ["Linked page", ...]}`.
+    Json,
 }
 
 fn main() {
     let args = Cli::parse();
+    init_logging(args.log_format);
+    if let Some(dir) = &args.http_cache {
+        let mut cache = HttpCache::new(dir);
+        if let Some(secs) = args.http_max_age {
+            cache = cache.max_age(Duration::from_secs(secs));
+        }
+        http_cache::install(cache);
+    }
 
-    let render_opts = RenderOptions {
-        center_tables_and_captions: args.center_tables,
-        ..Default::default()
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Book {
+                titles,
+                index,
+                output,
+                title,
+                author,
+                no_images,
+                offline,
+            } => run_book(titles, index, output, title, author, no_images, offline),
+            Command::Graph {
+                format,
+                output,
+                no_redirects,
+                no_categories,
+            } => run_graph(format, output, no_redirects, no_categories),
+            Command::CheckLinks {
+                format,
+                concurrency,
+                timeout,
+                cache,
+                max_age,
+            } => {
+                let opts = LinkCheckOptions {
+                    concurrency,
+                    timeout: Duration::from_secs(timeout),
+                    cache_path: Some(cache),
+                    max_age: Duration::from_secs(max_age * 24 * 60 * 60),
+                };
+                run_check_links(format, &opts)
+            }
+            Command::Convert {
+                input,
+                output,
+                json,
+                render,
+            } => run_convert(&input, output, json, &render),
+            Command::Stats {
+                format,
+                from_json,
+                top,
+            } => run_stats(format, from_json, top),
+            Command::Watch {
+                slugify,
+                path_template,
+                render,
+            } => run_watch(slugify, path_template, &render),
+            Command::Serve {
+                addr,
+                format,
+                render,
+            } => run_serve(addr, format, &render),
+            Command::Golden { dir, bless } => run_golden(&dir, bless),
+            Command::Cache { action } => run_cache(action),
+            Command::Mirror {
+                api_url,
+                namespace,
+                delay_ms,
+                batch_size,
+                max_retries,
+                refetch,
+                limit,
+                no_convert,
+                render,
+            } => {
+                let opts = MirrorOptions {
+                    api_url,
+                    namespace,
+                    delay: Duration::from_millis(delay_ms),
+                    batch_size: batch_size.into(),
+                    max_retries,
+                    refetch,
+                    limit,
+                    convert: !no_convert,
+                    ..Default::default()
+                };
+                run_mirror(&opts, &render)
+            }
+            Command::Tables {
+                title,
+                format,
+                out_dir,
+            } => run_tables(&title, format.into(), &out_dir),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let render_opts = match args.render.to_options() {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let filter = match build_filter(&args.include, &args.exclude, args.since.as_deref()) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     };
 
     let write_opts = WriteOptions {
         regenerate_frontmatter: args.regenerate_frontmatter,
+        backup: args.backup,
+        backup_dir: args.backup_dir.clone(),
+        dry_run: args.dry_run,
+        path_template: args.path_template.clone(),
+        slugify_filenames: args.slugify,
+        filter,
+        skip_existing: args.skip_existing,
+        resume_from: args.resume_from.clone(),
+        verbosity: if args.quiet {
+            Verbosity::Quiet
+        } else if args.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        },
+        progress: if args.no_tty || !std::io::stderr().is_terminal() {
+            ProgressMode::Lines
+        } else {
+            ProgressMode::Bar
+        },
+        keep_going: args.keep_going,
+        failures_path: args.keep_going.then(|| args.failures_file.clone()),
+        max_warnings: args.max_warnings,
+        search_index: args.search_index.clone(),
+        anchor_map: args.anchor_map.clone(),
+        manifest: args.manifest.clone(),
+        write_json: args.json,
+        json_compact: args.json_compact,
+        json_gzip: args.json_gzip,
+        generation_metadata: args.generation_metadata,
+        index_pages: args.index.into(),
+        category_pages: args.category_pages,
+        timing_report: args.timings,
+        transclude: args.transclude,
+        ast_filters: args.ast_filters.clone(),
+        vault: None,
     };
+    let mut config = Config::new()
+        .render_options(render_opts.clone())
+        .write_options(write_opts)
+        .write_json(args.json)
+        .offline(args.offline);
+    if let Some(path) = &args.vault {
+        match Vault::open(path, &args.vault_folder) {
+            Ok(vault) => config = config.vault(vault),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    if args.regenerate_all {
-        if let Err(e) = regenerate_all_with_options(&render_opts, &write_opts) {
+    if args.stdin {
+        let mut src = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut src) {
+            eprintln!("Error reading stdin: {}", e);
+            std::process::exit(1);
+        }
+        match &args.section {
+            Some(section) => {
+                let doc = parse::parse_wiki(&src).document;
+                match render_section(&doc, &heading_path(section), &render_opts) {
+                    Some(md) => println!("{}", md),
+                    None => {
+                        eprintln!("Error: section not found: {}", section);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => println!("{}", convert_wikitext(&src, &render_opts)),
+        }
+    } else if let Some(json_path) = &args.from_json {
+        let result = match &args.section {
+            Some(section) => read_ast_file(json_path).and_then(|ast| {
+                render_section(&ast.document, &heading_path(section), &render_opts).ok_or_else(
+                    || wiki2md::Error::Config(format!("section not found: {}", section)),
+                )
+            }),
+            None => render_ast_file(json_path, &render_opts),
+        };
+        match result {
+            Ok(md) => println!("{}", md),
+            Err(e) => {
+                eprintln!("Error rendering '{}': {}", json_path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(section) = &args.section {
+        process_titles(&args.titles, |title| {
+            println!(
+                "{}",
+                render_article_section(title, &heading_path(section), &config)?
+            );
+            Ok(())
+        });
+    } else if args.stdout {
+        process_titles(&args.titles, |title| {
+            println!("{}", render_article(title, &config)?);
+            Ok(())
+        });
+    } else if args.regenerate_all {
+        if let Err(e) = regenerate_all_with_config(&config) {
             eprintln!("Error regenerating all files: {}", e);
             std::process::exit(1);
         }
     } else {
-        let title = args.title.as_ref().unwrap();
-        if let Err(e) = run_with_options(title, false, &render_opts, &write_opts) {
+        process_titles(&args.titles, |title| run_with_config(title, &config));
+    }
+}
+
+/// Run `process` for each title, reporting failures as they happen and, for more
+/// than one title, a summary at the end. Exits with status 1 if any failed.
+fn process_titles(titles: &[String], mut process: impl FnMut(&str) -> wiki2md::Result<()>) {
+    let mut failed: Vec<&str> = Vec::new();
+    for title in titles {
+        if let Err(e) = process(title) {
             eprintln!("Error processing '{}': {}", title, e);
-            std::process::exit(1);
+            failed.push(title);
+        }
+    }
+    if titles.len() > 1 {
+        eprintln!(
+            "Processed {} articles: {} succeeded, {} failed.",
+            titles.len(),
+            titles.len() - failed.len(),
+            failed.len()
+        );
+        if !failed.is_empty() {
+            eprintln!("Failed: {}", failed.join(", "));
+        }
+    }
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// `"Publications > 1990s"` -> `["Publications", "1990s"]`
+fn heading_path(section: &str) -> Vec<&str> {
+    section.split('>').map(str::trim).collect()
+}
+
+fn build_filter(
+    include: &[String],
+    exclude: &[String],
+    since: Option<&str>,
+) -> Result<ArticleFilter, Box<dyn std::error::Error>> {
+    let mut filter = ArticleFilter::new();
+    for p in include {
+        filter = filter.include(p)?;
+    }
+    for p in exclude {
+        filter = filter.exclude(p)?;
+    }
+    if let Some(since) = since {
+        filter = filter.since(parse_date(since)?);
+    }
+    Ok(filter)
+}
+
+impl RenderArgs {
+    fn to_options(&self) -> Result<RenderOptions, Box<dyn std::error::Error>> {
+        #[allow(unused_mut)]
+        let mut opts = RenderOptions {
+            center_tables_and_captions: self.center_tables,
+            scroll_wide_tables: self.wide_tables(),
+            table_alignment: TableAlignment {
+                numeric_right: self.align_numeric,
+                numeric_min_percent: self.numeric_threshold,
+                first_column_left: self.align_first_left,
+                overrides: self
+                    .table_align
+                    .iter()
+                    .map(|spec| TableAlignOverride::parse(spec))
+                    .collect::<Result<_, _>>()?,
+            },
+            table_caption_style: self.table_caption.into(),
+            table_caption_position: if self.table_caption_below {
+                TableCaptionPosition::Below
+            } else {
+                TableCaptionPosition::Above
+            },
+            table_header_rows: match self.table_header {
+                HeaderMode::First => TableHeaderRows::FirstHeaderRow,
+                HeaderMode::Empty => TableHeaderRows::Empty,
+                HeaderMode::Join => TableHeaderRows::Join(self.table_header_join as usize),
+                HeaderMode::Demote => TableHeaderRows::DemoteExtra,
+            },
+            table_anchors: self.table_anchors,
+            hard_break_style: self.hard_break_style.into(),
+            poem_style: if self.poem_blockquote {
+                PoemStyle::BlockQuote
+            } else {
+                PoemStyle::Plain
+            },
+            block_ids: self.block_ids,
+            raw_blocks: self.raw_blocks.into(),
+            wrap_width: self.wrap_width,
+            html_policy: self.html.into(),
+            chess_diagram_style: self.chess_diagrams.into(),
+            chess_symbols: self.chess_symbols,
+            timeline_style: self.timeline.into(),
+            leading_space_as_blockquote: self.leading_space.as_override(),
+            quote_attribution: self.quote_attribution.into(),
+            code_normalization: if self.normalize_code {
+                CodeNormalization::all()
+            } else {
+                CodeNormalization::default()
+            },
+            collapse_code_lines: self.collapse_code,
+            url_rewrites: build_url_rewrites(self.url_rewrites.as_deref(), &self.rewrite_url)?,
+            text_rules: build_text_rules(self.text_rules.as_deref(), &self.text_rule)?,
+            external_link_numbering: self.bare_links.into(),
+            footnote_placement: self.footnotes.into(),
+            dedupe_refs: self.dedupe_refs,
+            magic_link_urls: build_magic_link_urls(&self.magic_link_url)?,
+            breadcrumb_style: self.breadcrumb.into(),
+            heading_anchor_style: self.heading_anchors.into(),
+            image_base_url: self.image_base_url.clone(),
+            template_handlers: self.template_handlers(),
+            drop_navboxes: self.drop_navboxes,
+            drop_see_also: self.drop_see_also,
+            infobox: self.infobox_options(),
+            ..Default::default()
+        };
+        #[cfg(feature = "scripting")]
+        for path in &self.scripts {
+            wiki2md::scripting::Script::from_file(path)?.apply(&mut opts);
+        }
+        Ok(opts)
+    }
+
+    fn template_handlers(&self) -> TemplateHandlers {
+        let mut handlers = TemplateHandlers::builtin();
+        for name in &self.drop_template {
+            handlers.drop_template(name);
+        }
+        handlers
+    }
+
+    fn wide_tables(&self) -> Option<WideTables> {
+        if !self.scroll_tables
+            && self.scroll_table_columns.is_none()
+            && self.scroll_table_width.is_none()
+        {
+            return None;
+        }
+        let defaults = WideTables::default();
+        Some(WideTables {
+            max_columns: self.scroll_table_columns.unwrap_or(defaults.max_columns),
+            max_width: self.scroll_table_width.unwrap_or(defaults.max_width),
+        })
+    }
+
+    fn infobox_options(&self) -> Option<InfoboxOptions> {
+        if !self.infobox && self.infobox_template.is_empty() && self.infobox_namespace.is_none() {
+            return None;
+        }
+        let mut opts = InfoboxOptions::default();
+        if !self.infobox_template.is_empty() {
+            opts.templates = self.infobox_template.clone();
+        }
+        if let Some(namespace) = &self.infobox_namespace {
+            opts.namespace = namespace.clone();
+        }
+        Some(opts)
+    }
+}
+
+fn build_magic_link_urls(specs: &[String]) -> Result<MagicLinkUrls, Box<dyn std::error::Error>> {
+    let mut urls = MagicLinkUrls::default();
+    for spec in specs {
+        let (kind, url) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("invalid --magic-link-url {:?}: expected KIND=URL", spec))?;
+        let slot = match kind.trim().to_ascii_lowercase().as_str() {
+            "isbn" => &mut urls.isbn,
+            "rfc" => &mut urls.rfc,
+            "pmid" => &mut urls.pmid,
+            other => {
+                return Err(format!(
+                    "unknown magic link kind {:?} (expected isbn, rfc, or pmid)",
+                    other
+                )
+                .into());
+            }
+        };
+        *slot = url.trim().to_string();
+    }
+    Ok(urls)
+}
+
+fn build_url_rewrites(
+    file: Option<&Path>,
+    rules: &[String],
+) -> Result<UrlRewrites, Box<dyn std::error::Error>> {
+    let mut rewrites = match file {
+        Some(path) => UrlRewrites::parse_rules(&std::fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => UrlRewrites::new(),
+    };
+    for rule in rules {
+        rewrites = rewrites.push(UrlRewriteRule::parse(rule)?);
+    }
+    Ok(rewrites)
+}
+
+fn build_text_rules(
+    file: Option<&Path>,
+    rules: &[String],
+) -> Result<TextRules, Box<dyn std::error::Error>> {
+    let mut text_rules = match file {
+        Some(path) => TextRules::parse_rules(&std::fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => TextRules::new(),
+    };
+    for rule in rules {
+        text_rules = text_rules.push(TextRule::parse(rule)?);
+    }
+    Ok(text_rules)
+}
+
+fn run_graph(
+    format: GraphFormat,
+    output: Option<PathBuf>,
+    no_redirects: bool,
+    no_categories: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let opts = GraphOptions {
+        drop_redirects: no_redirects,
+        drop_categories: no_categories,
+    };
+    let graph = build_link_graph(&Config::new(), &opts)?;
+    let text = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Json => graph.to_json(),
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(&path, text)?;
+            eprintln!(
+                "Wrote {} pages and {} links to {}",
+                graph.edges.len(),
+                graph.edge_count(),
+                path.display()
+            );
         }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn run_check_links(
+    format: ReportFormat,
+    opts: &LinkCheckOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = check_external_links(&Config::new(), opts)?;
+    match format {
+        ReportFormat::Text => print!("{}", report.to_text()),
+        ReportFormat::Json => print!("{}", report.to_json()),
     }
+    let dead = report.dead_count();
+    eprintln!(
+        "Checked {} links: {} dead in {} articles.",
+        report.checked,
+        dead,
+        report.articles.len()
+    );
+    if dead > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_watch(
+    slugify: bool,
+    path_template: Option<String>,
+    render: &RenderArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::new()
+        .render_options(render.to_options()?)
+        .write_options(WriteOptions {
+            path_template,
+            slugify_filenames: slugify,
+            ..Default::default()
+        });
+    watch::watch(&config)?;
+    Ok(())
+}
+
+fn run_convert(
+    input: &Path,
+    output: Option<PathBuf>,
+    json: bool,
+    render: &RenderArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = output.unwrap_or_else(|| input.with_extension("md"));
+    let config = Config::new()
+        .render_options(render.to_options()?)
+        .write_json(json);
+    convert_file(input, &output, &config)?;
+    eprintln!("Wrote {}", output.display());
+    Ok(())
+}
+
+fn run_mirror(opts: &MirrorOptions, render: &RenderArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::new().render_options(render.to_options()?);
+    let report = mirror::mirror(&config, opts)?;
+    eprintln!(
+        "Mirrored {} pages: {} fetched, {} already cached.",
+        report.listed, report.fetched, report.cached
+    );
+    if !report.missing.is_empty() {
+        eprintln!(
+            "{} pages were deleted since listing: {}",
+            report.missing.len(),
+            report.missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn run_serve(
+    addr: String,
+    format: PreviewFormat,
+    render: &RenderArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::new().render_options(render.to_options()?);
+    let opts = ServeOptions {
+        addr,
+        format: format.into(),
+    };
+    serve::serve(&config, &opts)?;
+    Ok(())
+}
+
+fn run_golden(dir: &Path, bless: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let report = golden::run(dir, bless)?;
+    print!("{}", report.to_text());
+    if !report.is_success() {
+        return Err(format!("{} golden cases failed", report.failed().count()).into());
+    }
+    Ok(())
+}
+
+fn run_cache(action: CacheAction) -> Result<(), Box<dyn std::error::Error>> {
+    let dirs = CacheDirs::default();
+    match action {
+        CacheAction::Status => print!("{}", cache::status(&dirs)?.to_text()),
+        CacheAction::Verify => {
+            let report = cache::verify(&dirs)?;
+            print!("{}", report.to_text());
+            if !report.is_clean() {
+                return Err(format!(
+                    "{} orphaned .md, {} orphaned .json, {} unconverted .wiki files",
+                    report.orphaned_md.len(),
+                    report.orphaned_json.len(),
+                    report.unconverted.len()
+                )
+                .into());
+            }
+        }
+        CacheAction::Clean { dry_run } => {
+            let removed = cache::clean(&dirs, dry_run)?;
+            for path in &removed {
+                println!("{}", path.display());
+            }
+            let verb = if dry_run { "Would remove" } else { "Removed" };
+            eprintln!("{} {} orphaned files.", verb, removed.len());
+        }
+    }
+    Ok(())
+}
+
+fn run_tables(
+    title: &str,
+    format: TableFormat,
+    out_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (article_id, tables) = article_tables(title, &Config::new())?;
+    if tables.is_empty() {
+        eprintln!("No tables in {}", article_id);
+        return Ok(());
+    }
+    std::fs::create_dir_all(out_dir)?;
+    for (n, table) in tables.iter().enumerate() {
+        let path = out_dir.join(format!("{}-{}.{}", article_id, n + 1, format.extension()));
+        std::fs::write(&path, table.to_delimited(format))?;
+        match &table.caption {
+            Some(caption) => eprintln!("Wrote {} ({})", path.display(), caption),
+            None => eprintln!("Wrote {}", path.display()),
+        }
+    }
+    Ok(())
+}
+
+fn run_stats(
+    format: ReportFormat,
+    from_json: Option<PathBuf>,
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stats = corpus_stats(&Config::new(), from_json.as_deref())?;
+    match format {
+        ReportFormat::Text => print!("{}", stats.to_text(top)),
+        ReportFormat::Json => print!("{}", stats.to_json(top)),
+    }
+    Ok(())
+}
+
+fn run_book(
+    titles: Vec<String>,
+    index: Option<PathBuf>,
+    output: PathBuf,
+    title: Option<String>,
+    author: Option<String>,
+    no_images: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut all = match index {
+        Some(path) => book::parse_book_index(&std::fs::read_to_string(path)?),
+        None => Vec::new(),
+    };
+    all.extend(titles);
+
+    let defaults = BookOptions::default();
+    let opts = BookOptions {
+        title: title.unwrap_or(defaults.title),
+        author: author.unwrap_or(defaults.author),
+        embed_images: !no_images,
+        fetch_missing: !offline,
+        ..defaults
+    };
+    let report = book::build_epub(&all, &Config::new(), &output, &opts)?;
+    eprintln!(
+        "Wrote {} ({} chapters, {} images).",
+        output.display(),
+        report.chapters,
+        report.images
+    );
+    Ok(())
 }
@@ -0,0 +1,165 @@
+//! Build manifest (`manifest.json`): what a bulk run generated from which source,
+//! with content hashes, so downstream builds and sync scripts can tell what
+//! changed without hashing everything themselves.
+//!
+//! ```json
+//! {
+//!   "generated_by": "wiki2md 0.1.0",
+//!   "articles": [
+//!     {
+//!       "article_id": "Ken_Thompson",
+//!       "source": "docs/wiki/k/Ken_Thompson.wiki",
+//!       "source_hash": "5d41402abc4b2a76b9719d911017c592",
+//!       "outputs": [{ "path": "docs/md/k/Ken Thompson.md", "hash": "..." }],
+//!       "diagnostics": 2,
+//!       "render_options": "..."
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Hashes are lowercase hex MD5 digests.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The manifest of a bulk run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub generated_by: String,
+    /// Sorted by article id.
+    pub articles: Vec<ManifestEntry>,
+}
+
+/// One converted article.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub article_id: String,
+    /// The `.wiki` file, with `/` separators.
+    pub source: String,
+    pub source_hash: String,
+    pub outputs: Vec<ManifestOutput>,
+    /// Number of parser diagnostics.
+    pub diagnostics: usize,
+    /// Number of raw blocks (see [`raw_block_count`](crate::ast::raw_block_count)).
+    #[serde(default)]
+    pub raw_blocks: usize,
+    /// [`RenderOptions::fingerprint`](crate::render::RenderOptions::fingerprint) of
+    /// the options the article was rendered with (including its frontmatter overrides).
+    pub render_options: String,
+}
+
+/// One file generated for an article.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestOutput {
+    /// With `/` separators.
+    pub path: String,
+    pub hash: String,
+}
+
+impl ManifestOutput {
+    pub fn new(path: &Path, contents: &[u8]) -> Self {
+        ManifestOutput {
+            path: manifest_path(path),
+            hash: content_hash(contents),
+        }
+    }
+
+    /// An output whose [`content_hash`] was computed while it was written.
+    pub fn hashed(path: &Path, hash: String) -> Self {
+        ManifestOutput {
+            path: manifest_path(path),
+            hash,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn new(articles: Vec<ManifestEntry>) -> Self {
+        let mut manifest = Manifest {
+            generated_by: format!("wiki2md {}", env!("CARGO_PKG_VERSION")),
+            articles,
+        };
+        manifest
+            .articles
+            .sort_by(|a, b| a.article_id.cmp(&b.article_id));
+        manifest
+    }
+
+    /// Read a manifest written by an earlier run; `None` if there is none.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::io(path)(e)),
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Replace (or add) the entries of `articles`, keeping the others. Used when a
+    /// run only converted some articles.
+    pub fn merge(mut self, articles: Vec<ManifestEntry>) -> Self {
+        self.articles
+            .retain(|old| !articles.iter().any(|new| new.article_id == old.article_id));
+        self.articles.extend(articles);
+        Manifest::new(self.articles)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// Lowercase hex MD5 of `contents`.
+pub fn content_hash(contents: &[u8]) -> String {
+    format!("{:x}", md5::compute(contents))
+}
+
+/// `path` with `/` separators, as written in the manifest.
+pub(crate) fn manifest_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, hash: &str) -> ManifestEntry {
+        ManifestEntry {
+            article_id: id.to_string(),
+            source: format!("docs/wiki/x/{}.wiki", id),
+            source_hash: hash.to_string(),
+            outputs: vec![ManifestOutput::new(Path::new("docs/md/x/A.md"), b"hello")],
+            diagnostics: 0,
+            raw_blocks: 0,
+            render_options: String::new(),
+        }
+    }
+
+    #[test]
+    fn merge_replaces_entries_by_article_id() {
+        let manifest = Manifest::new(vec![entry("B", "1"), entry("A", "1")]);
+        assert_eq!(manifest.articles[0].article_id, "A");
+        assert_eq!(
+            manifest.articles[0].outputs[0].hash,
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+
+        let merged = manifest.merge(vec![entry("B", "2"), entry("C", "2")]);
+        let ids: Vec<(&str, &str)> = merged
+            .articles
+            .iter()
+            .map(|e| (e.article_id.as_str(), e.source_hash.as_str()))
+            .collect();
+        assert_eq!(ids, [("A", "1"), ("B", "2"), ("C", "2")]);
+
+        let json = merged.to_json();
+        assert_eq!(serde_json::from_str::<Manifest>(&json).unwrap(), merged);
+    }
+}
@@ -0,0 +1,383 @@
+//! Mirroring a whole wiki (`mirror`): list every page through the MediaWiki API
+//! (`list=allpages`), fetch the wikitext of the pages that aren't cached yet into
+//! the wiki directory, then convert everything in bulk.
+//!
+//! Requests go out one at a time, at least [`MirrorOptions::delay`] apart, with
+//! up to 50 pages per content request. Throttled requests (`429`, `503`, or a
+//! `maxlag` error) are retried after the server's `Retry-After`. Cached pages are
+//! skipped, so running an interrupted mirror again continues where it stopped.
+
+use crate::driver::{wiki_path_for, write_atomic};
+use crate::error::{BoxError, Error, Result};
+use crate::http_cache;
+use crate::progress::Progress;
+use crate::{Config, regenerate_all_with_config, sanitize_article_id};
+use reqwest::blocking::Client;
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+use std::cell::Cell;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The chessprogramming.org API endpoint.
+pub const DEFAULT_API_URL: &str = "https://www.chessprogramming.org/api.php";
+
+/// What to mirror and how politely.
+#[derive(Debug, Clone)]
+pub struct MirrorOptions {
+    /// The wiki's `api.php` endpoint.
+    pub api_url: String,
+    /// The namespace to list (0 is articles).
+    pub namespace: u32,
+    /// The minimum pause between two requests.
+    pub delay: Duration,
+    /// Pages fetched per request (the API allows at most 50).
+    pub batch_size: usize,
+    /// How often a throttled or failed request is retried before giving up.
+    pub max_retries: u32,
+    /// Per-request timeout.
+    pub timeout: Duration,
+    /// Fetch cached pages again instead of skipping them.
+    pub refetch: bool,
+    /// Stop after listing this many pages (to try things out on a big wiki).
+    pub limit: Option<usize>,
+    /// Convert the mirrored pages (`regenerate_all_with_config`) after fetching.
+    pub convert: bool,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        MirrorOptions {
+            api_url: DEFAULT_API_URL.to_string(),
+            namespace: 0,
+            delay: Duration::from_secs(1),
+            batch_size: 50,
+            max_retries: 5,
+            timeout: Duration::from_secs(30),
+            refetch: false,
+            limit: None,
+            convert: true,
+        }
+    }
+}
+
+/// What a mirror run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// Pages the wiki listed.
+    pub listed: usize,
+    /// Pages skipped because they were already cached.
+    pub cached: usize,
+    /// Pages fetched and written to the wiki directory.
+    pub fetched: usize,
+    /// Listed pages the API returned no content for (deleted since listing).
+    pub missing: Vec<String>,
+}
+
+/// Mirror every page of the wiki at `opts.api_url` into `config`'s wiki directory,
+/// then (with `opts.convert`) convert them with `config`.
+pub fn mirror(config: &Config, opts: &MirrorOptions) -> Result<MirrorReport> {
+    let api = Api::new(opts)?;
+
+    let mut titles = api.all_pages().map_err(|source| Error::Fetch {
+        title: "list=allpages".to_string(),
+        source,
+    })?;
+    if let Some(limit) = opts.limit {
+        titles.truncate(limit);
+    }
+    let mut report = MirrorReport {
+        listed: titles.len(),
+        ..Default::default()
+    };
+    let pending: Vec<&String> = titles
+        .iter()
+        .filter(|title| {
+            opts.refetch || !wiki_path_for(&config.wiki_dir, &sanitize_article_id(title)).exists()
+        })
+        .collect();
+    report.cached = titles.len() - pending.len();
+    tracing::info!(
+        listed = report.listed,
+        cached = report.cached,
+        pending = pending.len(),
+        "listed pages"
+    );
+    let progress = Progress::new(pending.len(), config.write.verbosity, config.write.progress);
+    progress.summary(&format!(
+        "{} pages listed, {} cached, {} to fetch",
+        report.listed,
+        report.cached,
+        pending.len()
+    ));
+
+    for batch in pending.chunks(opts.batch_size.clamp(1, 50)) {
+        let pages = api.contents(batch).map_err(|source| Error::Fetch {
+            title: batch[0].to_string(),
+            source,
+        })?;
+        for (title, content) in pages {
+            let Some(content) = content else {
+                report.missing.push(title);
+                continue;
+            };
+            let path = wiki_path_for(&config.wiki_dir, &sanitize_article_id(&title));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(Error::io(parent))?;
+            }
+            // resume skips any `.wiki` that exists, so never leave a partial one.
+            write_atomic(&path, content.as_bytes()).map_err(Error::io(&path))?;
+            report.fetched += 1;
+        }
+        let done = report.fetched + report.missing.len();
+        tracing::debug!(done, pending = pending.len(), "fetched batch");
+        progress.advance(done, &format!("Fetched {}/{}", done, pending.len()));
+    }
+    progress.finish();
+
+    if opts.convert {
+        regenerate_all_with_config(config)?;
+    }
+    Ok(report)
+}
+
+/// A rate-limited MediaWiki API client.
+struct Api<'a> {
+    client: Client,
+    opts: &'a MirrorOptions,
+    last_request: Cell<Option<Instant>>,
+}
+
+impl<'a> Api<'a> {
+    fn new(opts: &'a MirrorOptions) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(opts.timeout)
+            .user_agent(concat!("wiki2md/", env!("CARGO_PKG_VERSION"), " (mirror)"))
+            .build()
+            .map_err(|e| Error::Config(format!("failed to create HTTP client: {}", e)))?;
+        Ok(Api {
+            client,
+            opts,
+            last_request: Cell::new(None),
+        })
+    }
+
+    /// Every title in the namespace, following `apcontinue`.
+    fn all_pages(&self) -> Result<Vec<String>, BoxError> {
+        let namespace = self.opts.namespace.to_string();
+        let mut titles = Vec::new();
+        let mut from: Option<String> = None;
+        loop {
+            let mut params = vec![
+                ("list", "allpages"),
+                ("apnamespace", namespace.as_str()),
+                ("aplimit", "max"),
+            ];
+            if let Some(from) = &from {
+                params.push(("apcontinue", from));
+            }
+            let (page, next) = parse_all_pages(&self.query(&params)?)?;
+            titles.extend(page);
+            match next {
+                Some(next) if self.opts.limit.is_none_or(|limit| titles.len() < limit) => {
+                    from = Some(next)
+                }
+                _ => return Ok(titles),
+            }
+        }
+    }
+
+    /// The current wikitext of `titles`; `None` for pages that no longer exist.
+    fn contents(&self, titles: &[&String]) -> Result<Vec<(String, Option<String>)>, BoxError> {
+        let titles: Vec<&str> = titles.iter().map(|t| t.as_str()).collect();
+        let joined = titles.join("|");
+        let params = [
+            ("prop", "revisions"),
+            ("rvprop", "content"),
+            ("rvslots", "main"),
+            ("titles", joined.as_str()),
+        ];
+        parse_contents(&self.query(&params)?)
+    }
+
+    /// `action=query` with `params`, waiting out the delay and retrying throttled
+    /// or failed requests.
+    fn query(&self, params: &[(&str, &str)]) -> Result<Value, BoxError> {
+        let mut attempt = 0;
+        loop {
+            if let Some(last) = self.last_request.get() {
+                thread::sleep(self.opts.delay.saturating_sub(last.elapsed()));
+            }
+            self.last_request.set(Some(Instant::now()));
+            let retry_after = match self.send(params) {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(retry_after)) => retry_after,
+                Err(e) if attempt >= self.opts.max_retries => return Err(e),
+                Err(e) => {
+                    tracing::warn!(error = %e, attempt, "API request failed, retrying");
+                    None
+                }
+            };
+            if attempt >= self.opts.max_retries {
+                return Err("still throttled after retrying".into());
+            }
+            attempt += 1;
+            // without a Retry-After, back off exponentially.
+            let wait = retry_after.unwrap_or(self.opts.delay * 2u32.saturating_pow(attempt));
+            tracing::info!(wait_secs = wait.as_secs(), attempt, "throttled, waiting");
+            thread::sleep(wait);
+        }
+    }
+
+    /// One request. `Ok(Err(retry_after))` means the server asked us to slow down.
+    fn send(&self, params: &[(&str, &str)]) -> Result<Result<Value, Option<Duration>>, BoxError> {
+        let mut url = Url::parse(&self.opts.api_url)?;
+        url.query_pairs_mut()
+            .extend_pairs([
+                ("action", "query"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("maxlag", "5"),
+            ])
+            .extend_pairs(params);
+        // a `maxlag` error comes with a `200`, and must not be replayed from the cache.
+        let resp = http_cache::get_with_if(&self.client, url.as_str(), |resp| {
+            resp.is_success()
+                && serde_json::from_slice::<Value>(&resp.body)
+                    .is_ok_and(|v| v.get("error").is_none())
+        })?;
+        let retry_after = resp
+            .header("retry-after")
+            .and_then(|v| v.trim().parse().ok())
+            .map(Duration::from_secs);
+        let status = StatusCode::from_u16(resp.status)?;
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            return Ok(Err(retry_after));
+        }
+        if !status.is_success() {
+            return Err(format!("request failed: {}", status).into());
+        }
+        let value: Value = serde_json::from_slice(&resp.body)?;
+        match value["error"]["code"].as_str() {
+            Some("maxlag") => Ok(Err(retry_after)),
+            Some(code) => Err(format!(
+                "API error {}: {}",
+                code,
+                value["error"]["info"].as_str().unwrap_or("")
+            )
+            .into()),
+            None => Ok(Ok(value)),
+        }
+    }
+}
+
+/// The titles of an `allpages` response and its `apcontinue`, if there are more.
+fn parse_all_pages(value: &Value) -> Result<(Vec<String>, Option<String>), BoxError> {
+    let pages = value["query"]["allpages"]
+        .as_array()
+        .ok_or("response has no query.allpages")?;
+    let titles = pages
+        .iter()
+        .filter_map(|p| p["title"].as_str().map(str::to_string))
+        .collect();
+    Ok((
+        titles,
+        value["continue"]["apcontinue"].as_str().map(str::to_string),
+    ))
+}
+
+/// The titles and main-slot wikitext of a `prop=revisions` response.
+fn parse_contents(value: &Value) -> Result<Vec<(String, Option<String>)>, BoxError> {
+    let pages = value["query"]["pages"]
+        .as_array()
+        .ok_or("response has no query.pages")?;
+    Ok(pages
+        .iter()
+        .filter_map(|p| {
+            let title = p["title"].as_str()?.to_string();
+            let content = p["revisions"][0]["slots"]["main"]["content"]
+                .as_str()
+                .map(str::to_string);
+            Some((title, content))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_cache::HttpCache;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn maxlag_errors_are_retried_and_not_cached() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_url = format!("http://{}/api.php", listener.local_addr().unwrap());
+        let hits: &'static AtomicUsize = Box::leak(Box::new(AtomicUsize::new(0)));
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                let body = match hits.fetch_add(1, Ordering::SeqCst) {
+                    0 => r#"{"error":{"code":"maxlag","info":"Waiting for a database server"}}"#,
+                    _ => r#"{"query":{"allpages":[{"pageid":2,"ns":0,"title":"Belle"}]}}"#,
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nRetry-After: 0\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        // the cache lives as long as the process, like `--http-cache`.
+        http_cache::install(HttpCache::new(tempfile::tempdir().unwrap().keep()));
+
+        let opts = MirrorOptions {
+            api_url,
+            delay: Duration::ZERO,
+            max_retries: 1,
+            ..Default::default()
+        };
+        let api = Api::new(&opts).unwrap();
+        assert_eq!(api.all_pages().unwrap(), ["Belle"]);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn parses_api_responses() {
+        let list: Value = serde_json::from_str(
+            r#"{"continue":{"apcontinue":"Bitboards","continue":"-||"},
+                "query":{"allpages":[{"pageid":1,"ns":0,"title":"Alpha-Beta"},{"pageid":2,"ns":0,"title":"Belle"}]}}"#,
+        )
+        .unwrap();
+        let (titles, next) = parse_all_pages(&list).unwrap();
+        assert_eq!(titles, ["Alpha-Beta", "Belle"]);
+        assert_eq!(next.as_deref(), Some("Bitboards"));
+
+        let contents: Value = serde_json::from_str(
+            r#"{"batchcomplete":true,"query":{"pages":[
+                {"pageid":2,"ns":0,"title":"Belle","revisions":[{"slots":{"main":{"contentmodel":"wikitext","content":"'''Belle'''"}}}]},
+                {"ns":0,"title":"Gone","missing":true}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_contents(&contents).unwrap(),
+            [
+                ("Belle".to_string(), Some("'''Belle'''".to_string())),
+                ("Gone".to_string(), None)
+            ]
+        );
+        assert!(parse_all_pages(&serde_json::json!({"error": {}})).is_err());
+    }
+}
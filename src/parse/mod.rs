@@ -11,7 +11,9 @@
 //! links, refs, basic HTML tags, templates, and MediaWiki tables).
 
 mod table;
-mod util;
+mod timeline;
+mod trivia;
+pub(crate) mod util;
 
 use crate::ast::*;
 
@@ -19,13 +21,82 @@ use util::{collect_lines, line_trimmed_start, parse_html_attrs, strip_cr};
 
 /// Result of parsing a document.
 #[derive(Debug, Clone)]
-pub struct ParseOutput {
-    pub document: Document,
+pub struct ParseOutput<'a> {
+    pub document: Document<'a>,
     pub diagnostics: Vec<Diagnostic>,
     pub byte_len: usize,
 }
 
-fn create_envelope(src: String, parse_out: ParseOutput) -> AstFile {
+/// Limits that keep the parser robust on pathological or adversarial input.
+///
+/// The defaults are generous enough for real articles; embedders parsing
+/// untrusted input can tighten them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Lists nested deeper than this are clamped to this depth.
+    ///
+    /// Untrusted / fuzzed input can use an extremely long list of marker prefixes
+    /// (e.g. `:::::::::::::::::`), building a deeply nested list AST that exceeds
+    /// `serde_json`'s recursion limit when the AST is round-tripped through JSON.
+    pub max_list_depth: usize,
+
+    /// Templates nested deeper than this (`{{a|{{b|{{c|...}}}}}}`) keep their
+    /// innermost arguments as plain text.
+    pub max_template_depth: usize,
+
+    /// Inline markup (links, emphasis, refs, spans, templates) nested deeper than
+    /// this is kept as plain text.
+    pub max_inline_depth: usize,
+
+    /// Runs of at least this many `{` or `[` are treated as plain text instead of
+    /// searching for a matching close at every offset (which is quadratic).
+    pub max_delimiter_run: usize,
+
+    /// Merge diagnostics with the same code and message into one, counting
+    /// [`Diagnostic::occurrences`]. A single broken table can otherwise report the
+    /// same warning thousands of times.
+    pub dedup_diagnostics: bool,
+
+    /// Keep at most this many diagnostics per document (after merging), followed
+    /// by one `wiki2md.diagnostics.truncated` diagnostic counting the rest. `None`
+    /// keeps all.
+    pub max_diagnostics: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_list_depth: 20,
+            max_template_depth: 40,
+            max_inline_depth: 100,
+            max_delimiter_run: 16,
+            dedup_diagnostics: true,
+            max_diagnostics: Some(1000),
+        }
+    }
+}
+
+/// State threaded through the parser: the options, the diagnostics so far, and
+/// how deeply the current position is nested.
+pub(crate) struct ParseContext<'a> {
+    pub opts: &'a ParseOptions,
+    pub diagnostics: Vec<Diagnostic>,
+    pub inline_depth: usize,
+    pub template_depth: usize,
+}
+
+impl<'a> ParseContext<'a> {
+    fn new(opts: &'a ParseOptions) -> Self {
+        ParseContext {
+            opts,
+            diagnostics: Vec::new(),
+            inline_depth: 0,
+            template_depth: 0,
+        }
+    }
+}
+
+fn create_envelope(src: String, parse_out: ParseOutput<'_>) -> AstFile {
     AstFile {
         schema_version: SCHEMA_VERSION,
         parser: ParserInfo {
@@ -39,7 +110,7 @@ fn create_envelope(src: String, parse_out: ParseOutput) -> AstFile {
             byte_len: src.len() as u64,
         },
         diagnostics: parse_out.diagnostics,
-        document: parse_out.document,
+        document: parse_out.document.into_owned(),
     }
 }
 
@@ -49,10 +120,26 @@ pub fn parse_wiki_to_envelope(src: &str) -> AstFile {
     create_envelope(src.to_string(), doc)
 }
 
+/// Parse a `.wiki` file (Wikitext) into an AST `Document`, recording trivia.
+///
+/// This is the opt-in lossless mode: in addition to the regular AST, the
+/// document carries whitespace, blank-line counts, and original delimiter forms
+/// (see [`Trivia`]) so that `render::wikitext::serialize_doc` reproduces `src`
+/// byte-for-byte for unedited nodes.
+pub fn parse_wiki_lossless<'a>(src: &'a str) -> ParseOutput<'a> {
+    let mut out = parse_wiki(src);
+    trivia::attach_trivia(src, &mut out.document);
+    out
+}
+
 /// Parse a `.wiki` file (Wikitext) into an AST `Document`.
-pub fn parse_wiki(src: &str) -> ParseOutput {
-    let mut diagnostics: Vec<Diagnostic> = Vec::new();
-    let mut blocks: Vec<BlockNode> = Vec::new();
+pub fn parse_wiki<'a>(src: &'a str) -> ParseOutput<'a> {
+    parse_wiki_with_options(src, &ParseOptions::default())
+}
+
+/// Like [`parse_wiki`], with explicit nesting and diagnostic limits.
+pub fn parse_wiki_with_options<'a>(src: &'a str, opts: &ParseOptions) -> ParseOutput<'a> {
+    let mut cx = ParseContext::new(opts);
     let mut categories: Vec<CategoryTag> = Vec::new();
     let mut redirect: Option<Redirect> = None;
     let byte_len = src.len();
@@ -75,6 +162,42 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
         break;
     }
 
+    let blocks = parse_blocks(src, lines[i..].to_vec(), &mut cx, &mut categories);
+
+    let doc = Document {
+        span: Span::new(0, src.len() as u64),
+        breadcrumb: crate::ast::find_breadcrumb(&blocks),
+        blocks,
+        categories,
+        redirect,
+        trivia: Vec::new(),
+        parts: Vec::new(),
+    };
+
+    let mut diagnostics = if opts.dedup_diagnostics {
+        dedup_diagnostics(cx.diagnostics)
+    } else {
+        cx.diagnostics
+    };
+    if let Some(max) = opts.max_diagnostics {
+        cap_diagnostics(&mut diagnostics, max);
+    }
+    ParseOutput {
+        document: doc,
+        diagnostics,
+        byte_len,
+    }
+}
+
+/// Parse `lines` of `src` into blocks, collecting categories as metadata.
+fn parse_blocks<'a>(
+    src: &'a str,
+    mut lines: Vec<util::LineRange>,
+    cx: &mut ParseContext,
+    categories: &mut Vec<CategoryTag>,
+) -> Vec<BlockNode<'a>> {
+    let mut blocks: Vec<BlockNode> = Vec::new();
+    let mut i = 0;
     while i < lines.len() {
         let line = lines[i];
         let raw = &src[line.start..line.end];
@@ -86,6 +209,21 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
             continue;
         }
 
+        // <noinclude>, <includeonly>, and <onlyinclude> sections
+        if let Some((node, next_i)) = try_parse_inclusion_block(src, &mut lines, i, cx, categories)
+        {
+            blocks.push(node);
+            i = next_i;
+            continue;
+        }
+
+        // <blockquote>...</blockquote>
+        if let Some((node, next_i)) = try_parse_blockquote(src, &mut lines, i, cx, categories) {
+            blocks.push(node);
+            i = next_i;
+            continue;
+        }
+
         // categories as metadata (often at the bottom of the page)
         if let Some(cat) = try_parse_category(line, text) {
             categories.push(cat);
@@ -93,13 +231,13 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
             continue;
         }
 
-        // block-level <references />
-        if let Some(node) = try_parse_references(line, text) {
+        // block-level <references />, or <references>...</references> with list-defined refs
+        if let Some((node, end, next_i)) = try_parse_references(src, &lines, i, cx) {
             blocks.push(BlockNode {
-                span: Span::new(line.start as u64, line.end as u64),
+                span: Span::new(line.start as u64, end as u64),
                 kind: BlockKind::References { node },
             });
-            i += 1;
+            i = next_i;
             continue;
         }
 
@@ -126,7 +264,7 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
         // headings
         if let Some((level, inner_start, inner_end)) = try_parse_heading(src, line, text) {
             let content_slice = &src[inner_start..inner_end];
-            let inlines = util::parse_inlines(src, inner_start, content_slice, &mut diagnostics);
+            let inlines = util::parse_inlines(src, inner_start, content_slice, cx);
             blocks.push(BlockNode {
                 span: Span::new(line.start as u64, line.end as u64),
                 kind: BlockKind::Heading {
@@ -140,24 +278,27 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
 
         // tables
         if line_trimmed_start(src, line).starts_with("{|") {
-            match table::parse_table(src, &lines, i, &mut diagnostics) {
+            match table::parse_table(src, &lines, i, cx) {
                 Ok((node, next_i)) => {
                     blocks.push(node);
                     assert!(
                         next_i > i,
-                        "BUG: table parser made no progress (i={}, next_i={})", i, next_i,
+                        "BUG: table parser made no progress (i={}, next_i={})",
+                        i,
+                        next_i,
                     );
                     i = next_i;
                     continue;
                 }
                 Err(e) => {
-                    diagnostics.push(Diagnostic {
+                    cx.diagnostics.push(Diagnostic {
                         severity: Severity::Warning,
                         phase: Some(DiagnosticPhase::Parse),
                         code: Some("wikitext.table.parse_failed".to_string()),
                         message: format!("Failed to parse table: {e}"),
                         span: Some(Span::new(line.start as u64, line.end as u64)),
                         notes: vec![],
+                        occurrences: 1,
                     });
                     // fall back to raw block.
                     blocks.push(BlockNode {
@@ -172,15 +313,17 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
             }
         }
 
-        // <pre> and <syntaxhighlight> code blocks.
-        if let Some(res) = try_parse_code_block(src, &lines, i, &mut diagnostics) {
+        // <pre> and <syntaxhighlight> code blocks, <timeline> scripts, and <poem>s.
+        if let Some(res) = try_parse_code_block(src, &lines, i, cx) {
             blocks.push(res.node);
             if let Some(tail) = res.tail {
                 blocks.push(tail);
             }
             assert!(
                 res.next_i > i,
-                "BUG: code block parser made no progress (i={}, next_i={})", i, res.next_i
+                "BUG: code block parser made no progress (i={}, next_i={})",
+                i,
+                res.next_i
             );
             i = res.next_i;
             continue;
@@ -188,11 +331,12 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
 
         // leading-space preformatted blocks.
         if text.starts_with(' ') {
-            let (node, next_i) = parse_leading_space_block(src, &lines, i, &mut diagnostics);
+            let (node, next_i) = parse_leading_space_block(src, &lines, i, cx);
             blocks.push(node);
             assert!(
                 next_i > i,
-                "BUG: leading-space code block parser made no progress (i={i}, next_i={})", next_i,
+                "BUG: leading-space code block parser made no progress (i={i}, next_i={})",
+                next_i,
             );
             i = next_i;
             continue;
@@ -200,11 +344,13 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
 
         // lists
         if is_list_line(text) {
-            let (node, next_i) = parse_list_block(src, &lines, i, &mut diagnostics);
+            let (node, next_i) = parse_list_block(src, &lines, i, cx);
             blocks.push(node);
             assert!(
                 next_i > i,
-                "BUG: list parser made no progress (i={}, next_i={})", i, next_i,
+                "BUG: list parser made no progress (i={}, next_i={})",
+                i,
+                next_i,
             );
             i = next_i;
             continue;
@@ -234,7 +380,7 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
 
         let para_end = lines[end_i - 1].end;
         let slice = &src[para_start..para_end];
-        let inlines = util::parse_inlines(src, para_start, slice, &mut diagnostics);
+        let inlines = util::parse_inlines(src, para_start, slice, cx);
         blocks.push(BlockNode {
             span: Span::new(para_start as u64, para_end as u64),
             kind: BlockKind::Paragraph { content: inlines },
@@ -242,17 +388,215 @@ pub fn parse_wiki(src: &str) -> ParseOutput {
         i = end_i;
     }
 
-    let doc = Document {
-        span: Span::new(0, src.len() as u64),
-        blocks,
-        categories,
-        redirect,
+    attach_quote_attributions(&mut blocks);
+    blocks
+}
+
+/// A `<noinclude>`/`<includeonly>`/`<onlyinclude>` section whose opening tag
+/// starts line `i`, up to its closing tag (or the end of `lines` if it isn't
+/// closed). Text after the closing tag is left in `lines` for the next block.
+fn try_parse_inclusion_block<'a>(
+    src: &'a str,
+    lines: &mut [util::LineRange],
+    i: usize,
+    cx: &mut ParseContext,
+    categories: &mut Vec<CategoryTag>,
+) -> Option<(BlockNode<'a>, usize)> {
+    let line = lines[i];
+    let text = strip_cr(&src[line.start..line.end]);
+    let open_start = line.start + (text.len() - text.trim_start().len());
+    let (mode, open_len) = util::inclusion_open_tag(&src[open_start..line.end])?;
+    let content_start = open_start + open_len;
+    let region_end = lines.last().map_or(line.end, |l| l.end);
+
+    let close_tag = format!("</{}>", mode.tag());
+    let (content_end, close_end) =
+        match util::find_ignore_ascii_case(&src[content_start..region_end], &close_tag) {
+            Some(rel) => (content_start + rel, content_start + rel + close_tag.len()),
+            None => {
+                cx.diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    phase: Some(DiagnosticPhase::Parse),
+                    code: Some("wikitext.inclusion.unclosed".to_string()),
+                    message: format!("Unclosed <{}> tag", mode.tag()),
+                    span: Some(Span::new(open_start as u64, content_start as u64)),
+                    notes: vec![],
+                    occurrences: 1,
+                });
+                (region_end, region_end)
+            }
+        };
+
+    let inner = util::collect_lines_in(src, content_start, content_end);
+    let node = BlockNode {
+        span: Span::new(open_start as u64, close_end as u64),
+        kind: BlockKind::Inclusion {
+            mode,
+            blocks: parse_blocks(src, inner, cx, categories),
+        },
     };
 
-    ParseOutput {
-        document: doc,
-        diagnostics,
-        byte_len,
+    let close_i = (i..lines.len())
+        .find(|&j| close_end <= lines[j].end)
+        .unwrap_or(lines.len() - 1);
+    let rest = strip_cr(&src[close_end..lines[close_i].end]);
+    if rest.trim().is_empty() {
+        Some((node, close_i + 1))
+    } else {
+        lines[close_i].start = close_end + (rest.len() - rest.trim_start().len());
+        Some((node, close_i))
+    }
+}
+
+/// An HTML `<blockquote>` whose opening tag starts line `i`, up to its matching
+/// closing tag (or the end of `lines` if it isn't closed). A `<cite>` ending the
+/// quote, or a last line starting with a dash (`— Ken Thompson`), becomes its
+/// trailing attribution. Text after the closing tag is left in `lines`.
+fn try_parse_blockquote<'a>(
+    src: &'a str,
+    lines: &mut [util::LineRange],
+    i: usize,
+    cx: &mut ParseContext,
+    categories: &mut Vec<CategoryTag>,
+) -> Option<(BlockNode<'a>, usize)> {
+    const OPEN: &str = "<blockquote";
+    const CLOSE: &str = "</blockquote>";
+    let line = lines[i];
+    let text = strip_cr(&src[line.start..line.end]);
+    let open_start = line.start + (text.len() - text.trim_start().len());
+    let rest = &src[open_start..line.end];
+    if !util::starts_with_ignore_ascii_case(rest, OPEN)
+        || !rest[OPEN.len()..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace())
+    {
+        return None;
+    }
+    let content_start = open_start + rest.find('>')? + 1;
+    let region_end = lines.last().map_or(line.end, |l| l.end);
+
+    // nested quotes close in order.
+    let mut depth = 1;
+    let mut pos = content_start;
+    let close = loop {
+        let region = &src[pos..region_end];
+        let next_close = util::find_ignore_ascii_case(region, CLOSE);
+        match (util::find_ignore_ascii_case(region, OPEN), next_close) {
+            (Some(open), Some(close)) if open < close => {
+                depth += 1;
+                pos += open + OPEN.len();
+            }
+            (_, Some(close)) if depth > 1 => {
+                depth -= 1;
+                pos += close + CLOSE.len();
+            }
+            (_, close) => break close.map(|c| pos + c),
+        }
+    };
+    let (content_end, close_end) = match close {
+        Some(c) => (c, c + CLOSE.len()),
+        None => {
+            cx.diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                phase: Some(DiagnosticPhase::Parse),
+                code: Some("wikitext.html.unclosed".to_string()),
+                message: "Unclosed <blockquote> tag".to_string(),
+                span: Some(Span::new(open_start as u64, content_start as u64)),
+                notes: vec![],
+                occurrences: 1,
+            });
+            (region_end, region_end)
+        }
+    };
+
+    let (content_end, attribution) = match trailing_quote_source(src, content_start, content_end) {
+        Some((source_start, source, span)) => {
+            let content = util::parse_inlines(src, source.start, &src[source], cx);
+            let attribution = QuoteAttribution {
+                span,
+                content,
+                trailing: true,
+            };
+            (source_start, Some(attribution))
+        }
+        None => (content_end, None),
+    };
+    let inner = util::collect_lines_in(src, content_start, content_end);
+    let node = BlockNode {
+        span: Span::new(open_start as u64, close_end as u64),
+        kind: BlockKind::BlockQuote {
+            blocks: parse_blocks(src, inner, cx, categories),
+            attribution,
+            html: true,
+        },
+    };
+
+    let close_i = (i..lines.len())
+        .find(|&j| close_end <= lines[j].end)
+        .unwrap_or(lines.len() - 1);
+    let rest = strip_cr(&src[close_end..lines[close_i].end]);
+    if rest.trim().is_empty() {
+        Some((node, close_i + 1))
+    } else {
+        lines[close_i].start = close_end + (rest.len() - rest.trim_start().len());
+        Some((node, close_i))
+    }
+}
+
+/// The source closing the quote in `src[start..end]`: a final `<cite>...</cite>`,
+/// or a last line starting with a dash after at least one other line. Returns
+/// where the quote text ends, the range of the source's text, and its span.
+fn trailing_quote_source(
+    src: &str,
+    start: usize,
+    end: usize,
+) -> Option<(usize, std::ops::Range<usize>, Span)> {
+    const DASHES: [&str; 4] = ["—", "―", "–", "--"];
+    let inner = &src[start..end];
+    let trimmed_end = start + inner.trim_end().len();
+    let inner = &src[start..trimmed_end];
+
+    // compare bytes: the text before `</cite>` needn't end on a char boundary.
+    if inner.len() >= "</cite>".len()
+        && inner.as_bytes()[inner.len() - "</cite>".len()..].eq_ignore_ascii_case(b"</cite>")
+    {
+        let lower = inner.to_ascii_lowercase();
+        let cite = lower.rfind("<cite")?;
+        let text_start = start + cite + inner[cite..].find('>')? + 1;
+        let text_end = trimmed_end - "</cite>".len();
+        if text_start > text_end {
+            return None;
+        }
+        let text = &src[text_start..text_end];
+        let text_start = text_start + (text.len() - strip_dash(text, &DASHES).len());
+        return Some((
+            start + cite,
+            text_start..text_end,
+            Span::new((start + cite) as u64, trimmed_end as u64),
+        ));
+    }
+
+    let last_line_start = start + inner.rfind('\n')? + 1;
+    let last = &src[last_line_start..trimmed_end];
+    let source = last.trim_start();
+    if !src[start..last_line_start].trim().is_empty()
+        && !source.starts_with("---")
+        && DASHES.iter().any(|d| source.starts_with(d))
+    {
+        let source_start = trimmed_end - strip_dash(source, &DASHES).len();
+        return Some((
+            last_line_start,
+            source_start..trimmed_end,
+            Span::new(last_line_start as u64, trimmed_end as u64),
+        ));
+    }
+    None
+}
+
+/// `text` without a leading dash and the whitespace around it.
+fn strip_dash<'t>(text: &'t str, dashes: &[&str]) -> &'t str {
+    let trimmed = text.trim_start();
+    match dashes.iter().find(|d| trimmed.starts_with(**d)) {
+        Some(d) => trimmed[d.len()..].trim_start(),
+        None => trimmed,
     }
 }
 
@@ -310,28 +654,63 @@ fn try_parse_category(line: util::LineRange, text: &str) -> Option<CategoryTag>
     })
 }
 
-fn try_parse_references(_line: util::LineRange, text: &str) -> Option<ReferencesNode> {
+/// A `<references />` line, or `<references>` up to a line ending in
+/// `</references>` with the `<ref>`s in between. Returns the node, the end of its
+/// span, and the index of the line after it.
+fn try_parse_references<'a>(
+    src: &'a str,
+    lines: &[util::LineRange],
+    i: usize,
+    cx: &mut ParseContext,
+) -> Option<(ReferencesNode<'a>, usize, usize)> {
+    let line = lines[i];
+    let text = strip_cr(&src[line.start..line.end]);
     let trimmed = text.trim();
-    let lower = trimmed.to_ascii_lowercase();
-    if !lower.starts_with("<references") {
+    if !trimmed.to_ascii_lowercase().starts_with("<references") {
         return None;
     }
-    if !lower.ends_with("/>") && !lower.ends_with("></references>") {
+    // parse attributes in the opening tag.
+    let open_end = trimmed.find('>')?;
+    let open = &trimmed[..=open_end];
+    let attrs_str = open
+        .trim_start_matches('<')
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+        .trim();
+    let attrs_str = attrs_str.trim_end_matches("/>").trim_end_matches('>');
+    let attrs = parse_html_attrs(attrs_str);
+
+    if open.ends_with("/>") {
+        return (open_end + 1 == trimmed.len()).then(|| {
+            (
+                ReferencesNode {
+                    attrs,
+                    refs: vec![],
+                },
+                line.end,
+                i + 1,
+            )
+        });
+    }
+
+    const CLOSE: &str = "</references>";
+    let content_start = line.start + (text.len() - text.trim_start().len()) + open_end + 1;
+    let region_end = lines.last().map_or(line.end, |l| l.end);
+    let close_rel = util::find_ignore_ascii_case(&src[content_start..region_end], CLOSE)?;
+    let close_end = content_start + close_rel + CLOSE.len();
+    let close_i = (i..lines.len()).find(|&j| close_end <= lines[j].end)?;
+    if !strip_cr(&src[close_end..lines[close_i].end])
+        .trim()
+        .is_empty()
+    {
         return None;
     }
-    // parse attributes in the opening tag.
-    // <references ... />
-    if let Some(end) = trimmed.find('>') {
-        let open = &trimmed[..=end];
-        let attrs_str = open
-            .trim_start_matches('<')
-            .trim_start_matches(|c: char| c.is_ascii_alphabetic())
-            .trim();
-        let attrs_str = attrs_str.trim_end_matches("/>").trim_end_matches('>');
-        let attrs = parse_html_attrs(attrs_str);
-        return Some(ReferencesNode { attrs });
-    }
-    Some(ReferencesNode { attrs: vec![] })
+
+    let content = &src[content_start..content_start + close_rel];
+    let refs = util::parse_inlines(src, content_start, content, cx)
+        .into_iter()
+        .filter(|n| matches!(n.kind, InlineKind::Ref { .. }))
+        .collect();
+    Some((ReferencesNode { attrs, refs }, close_end, close_i + 1))
 }
 
 fn try_parse_magic_word(trimmed: &str) -> Option<String> {
@@ -409,32 +788,25 @@ fn try_parse_heading(src: &str, line: util::LineRange, _text: &str) -> Option<(u
     Some((level, inner_start, inner_end))
 }
 
-struct CodeBlockParseResult {
-    node: BlockNode,
+struct CodeBlockParseResult<'a> {
+    node: BlockNode<'a>,
     next_i: usize,
     /// Trailing text that appears after the closing tag on the same line.
     /// This must be preserved; it is parsed as a paragraph fragment.
-    tail: Option<BlockNode>,
+    tail: Option<BlockNode<'a>>,
 }
 
-fn try_parse_code_block(
-    src: &str,
+fn try_parse_code_block<'a>(
+    src: &'a str,
     lines: &[util::LineRange],
     start_i: usize,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Option<CodeBlockParseResult> {
+    cx: &mut ParseContext,
+) -> Option<CodeBlockParseResult<'a>> {
     let line = lines[start_i];
     let trimmed = line_trimmed_start(src, line);
     let lower = trimmed.to_ascii_lowercase();
     if lower.starts_with("<pre") {
-        return parse_tagged_code_block(
-            src,
-            lines,
-            start_i,
-            "pre",
-            CodeBlockKind::PreTag,
-            diagnostics,
-        );
+        return parse_tagged_code_block(src, lines, start_i, "pre", CodeBlockKind::PreTag, cx);
     }
     if lower.starts_with("<syntaxhighlight") {
         return parse_tagged_code_block(
@@ -443,20 +815,76 @@ fn try_parse_code_block(
             start_i,
             "syntaxhighlight",
             CodeBlockKind::SyntaxHighlight,
-            diagnostics,
+            cx,
         );
     }
+    if lower.starts_with("<timeline") {
+        // EasyTimeline scripts are verbatim like `<pre>`; parse the script afterwards.
+        let mut res =
+            parse_tagged_code_block(src, lines, start_i, "timeline", CodeBlockKind::PreTag, cx)?;
+        if let BlockKind::CodeBlock { block } = &res.node.kind {
+            res.node.kind = BlockKind::Timeline {
+                timeline: timeline::parse_timeline(&block.text),
+            };
+        }
+        return Some(res);
+    }
+    if lower.starts_with("<poem") {
+        // verbatim like `<pre>` too, except that each line is parsed as wikitext.
+        let mut res =
+            parse_tagged_code_block(src, lines, start_i, "poem", CodeBlockKind::PreTag, cx)?;
+        if let BlockKind::CodeBlock { block } = &res.node.kind {
+            let start = res.node.span.start as usize;
+            let content_start = start + src[start..].find('>').map_or(0, |i| i + 1);
+            res.node.kind = BlockKind::Poem {
+                lines: parse_poem_lines(
+                    src,
+                    content_start,
+                    &src[content_start..content_start + block.text.len()],
+                    cx,
+                ),
+            };
+        }
+        return Some(res);
+    }
     None
 }
 
-fn parse_tagged_code_block(
-    src: &str,
+/// The lines of a `<poem>`, parsed as inlines. Blank lines (stanza breaks) are
+/// empty; blank lines at the start and end are dropped.
+fn parse_poem_lines<'a>(
+    src: &'a str,
+    base_abs: usize,
+    text: &'a str,
+    cx: &mut ParseContext,
+) -> Vec<Vec<InlineNode<'a>>> {
+    let mut out = Vec::new();
+    let mut offset = base_abs;
+    for raw in text.split('\n') {
+        let line = strip_cr(raw);
+        if line.trim().is_empty() {
+            out.push(Vec::new());
+        } else {
+            out.push(util::parse_inlines(src, offset, line, cx));
+        }
+        offset += raw.len() + 1;
+    }
+    let first = out.iter().position(|l| !l.is_empty()).unwrap_or(out.len());
+    let last = out
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(first, |i| i + 1);
+    out.drain(first..last).collect()
+}
+
+fn parse_tagged_code_block<'a>(
+    src: &'a str,
     lines: &[util::LineRange],
     start_i: usize,
     tag: &str,
     kind: CodeBlockKind,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Option<CodeBlockParseResult> {
+    cx: &mut ParseContext,
+) -> Option<CodeBlockParseResult<'a>> {
     let start_line = lines[start_i];
     // IMPORTANT: compute the "trimmed start" offset without being affected by a trailing CR.
     //
@@ -502,17 +930,8 @@ fn parse_tagged_code_block(
         .and_then(|a| a.value.clone());
 
     let close_pat = format!("</{}>", tag);
-    let search_haystack = &src[open_end_abs..];
-
-    // search using byte windows
-    // this replaces .to_ascii_lowercase().find() without the allocation
-    let close_rel = search_haystack
-        .as_bytes()
-        .windows(close_pat.len())
-        .position(|window| window.eq_ignore_ascii_case(close_pat.as_bytes()));
-
-    let Some(close_rel) = close_rel else {
-        diagnostics.push(Diagnostic {
+    let Some(close_rel) = util::find_ignore_ascii_case(&src[open_end_abs..], &close_pat) else {
+        cx.diagnostics.push(Diagnostic {
             severity: Severity::Warning,
             phase: Some(DiagnosticPhase::Parse),
             code: Some("wikitext.codeblock.unclosed".to_string()),
@@ -522,6 +941,7 @@ fn parse_tagged_code_block(
                 (start_abs + open_end_rel + 1) as u64,
             )),
             notes: vec![],
+            occurrences: 1,
         });
         // consume only this line.
         let node = BlockNode {
@@ -562,7 +982,7 @@ fn parse_tagged_code_block(
             let raw_tail = &src[close_end_abs..line_end_abs];
             let tail_text = strip_cr(raw_tail);
             if !tail_text.trim().is_empty() {
-                let inlines = util::parse_inlines(src, close_end_abs, tail_text, diagnostics);
+                let inlines = util::parse_inlines(src, close_end_abs, tail_text, cx);
                 tail = Some(BlockNode {
                     span: Span::new(close_end_abs as u64, line_end_abs as u64),
                     kind: BlockKind::Paragraph { content: inlines },
@@ -584,13 +1004,12 @@ fn parse_tagged_code_block(
     Some(CodeBlockParseResult { node, next_i, tail })
 }
 
-fn parse_leading_space_block(
-    src: &str,
+fn parse_leading_space_block<'a>(
+    src: &'a str,
     lines: &[util::LineRange],
     start_i: usize,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> (BlockNode, usize) {
-
+    cx: &mut ParseContext,
+) -> (BlockNode<'a>, usize) {
     // on chessprogramming pages, a single leading space is commonly used for
     // quote-like blocks (even though MediaWiki treats it as preformatted text).
     //
@@ -600,9 +1019,13 @@ fn parse_leading_space_block(
     // - the renderer can emit clean Markdown blockquotes.
 
     #[derive(Debug)]
-    enum QLine {
-        Content { line_i: usize, content_start_abs: usize, content: String },
-        Blank { },
+    enum QLine<'s> {
+        Content {
+            line_i: usize,
+            content_start_abs: usize,
+            content: &'s str,
+        },
+        Blank {},
     }
 
     let mut i = start_i;
@@ -617,7 +1040,7 @@ fn parse_leading_space_block(
         if text.starts_with(' ') {
             end_abs = lr.end;
             let content_start_abs = lr.start + 1;
-            let content = text.strip_prefix(' ').unwrap_or(text).to_string();
+            let content = text.strip_prefix(' ').unwrap_or(text);
             qlines.push(QLine::Content {
                 line_i: i,
                 content_start_abs,
@@ -647,7 +1070,7 @@ fn parse_leading_space_block(
                 if t2.starts_with(' ') {
                     // include this blank line as a paragraph separator within the quote.
                     end_abs = lr.end;
-                    qlines.push(QLine::Blank { });
+                    qlines.push(QLine::Blank {});
                     i += 1;
                     continue;
                 }
@@ -658,12 +1081,39 @@ fn parse_leading_space_block(
         break;
     }
 
+    // real pages mix quoted prose and indented code; keep code verbatim.
+    let raw_lines: Vec<&str> = qlines
+        .iter()
+        .map(|ql| match ql {
+            QLine::Content { content, .. } => *content,
+            QLine::Blank {} => "",
+        })
+        .collect();
+    if leading_space_lines_look_like_code(&raw_lines) {
+        let text = raw_lines.join("\n");
+        return (
+            BlockNode {
+                span: Span::new(start_abs as u64, end_abs as u64),
+                kind: BlockKind::CodeBlock {
+                    block: CodeBlock {
+                        kind: CodeBlockKind::LeadingSpace,
+                        lang: None,
+                        text,
+                    },
+                },
+            },
+            i,
+        );
+    }
+
     // convert quote lines into paragraph blocks inside the BlockQuote.
     let mut blocks: Vec<BlockNode> = Vec::new();
     let mut cur_para: Vec<InlineNode> = Vec::new();
     let mut cur_span: Option<Span> = None;
 
-    let flush_para = |blocks: &mut Vec<BlockNode>, cur_para: &mut Vec<InlineNode>, cur_span: &mut Option<Span>| {
+    let flush_para = |blocks: &mut Vec<BlockNode<'a>>,
+                      cur_para: &mut Vec<InlineNode<'a>>,
+                      cur_span: &mut Option<Span>| {
         if cur_para.is_empty() {
             *cur_span = None;
             return;
@@ -694,13 +1144,11 @@ fn parse_leading_space_block(
                     let lr_prev = lines[line_i.saturating_sub(1)];
                     cur_para.push(InlineNode {
                         span: Span::new(lr_prev.end as u64, lr_prev.end_with_newline as u64),
-                        kind: InlineKind::Text {
-                            value: "\n".to_string(),
-                        },
+                        kind: InlineKind::Text { value: "\n".into() },
                     });
                 }
 
-                let nodes = util::parse_inlines(src, content_start_abs, &content, diagnostics);
+                let nodes = util::parse_inlines(src, content_start_abs, content, cx);
                 if let Some(first) = nodes.first() {
                     cur_span = Some(match cur_span {
                         Some(s) => s.cover(first.span),
@@ -723,36 +1171,157 @@ fn parse_leading_space_block(
     (
         BlockNode {
             span: Span::new(start_abs as u64, end_abs as u64),
-            kind: BlockKind::BlockQuote { blocks },
+            kind: BlockKind::BlockQuote {
+                blocks,
+                attribution: None,
+                html: false,
+            },
         },
         i,
     )
 }
 
+/// Heuristic: does a leading-space block hold code rather than quoted prose?
+///
+/// Lines count as code-ish when they end in `;`, `{` or `}`, start with a
+/// comment or preprocessor directive, start with a common keyword, or contain
+/// operators that rarely appear in prose. Lines count as prose when they read
+/// like sentences or contain wiki markup (links, refs, bold/italic).
+fn leading_space_lines_look_like_code(lines: &[&str]) -> bool {
+    const KEYWORDS: [&str; 16] = [
+        "if", "for", "while", "return", "int", "void", "else", "case", "switch", "break", "U64",
+        "unsigned", "const", "static", "struct", "typedef",
+    ];
+    const OPERATORS: [&str; 10] = ["==", "!=", "&&", "||", "<<", ">>", "++", "+=", "->", "0x"];
+
+    let mut code = 0usize;
+    let mut prose = 0usize;
+    let mut total = 0usize;
+    let mut symbols = 0usize;
+    let mut chars = 0usize;
+
+    for line in lines {
+        let t = line.trim();
+        if t.is_empty() {
+            continue;
+        }
+        total += 1;
+        chars += t.chars().filter(|c| !c.is_whitespace()).count();
+        symbols += t
+            .chars()
+            .filter(|c| matches!(c, '{' | '}' | ';' | '(' | ')' | '='))
+            .count();
+
+        if t.contains("[[") || t.contains("<ref") || t.contains("''") {
+            prose += 1;
+            continue;
+        }
+
+        let first_word: String = t
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        let code_ish = t.ends_with(';')
+            || t.ends_with('{')
+            || t.ends_with('}')
+            || t.starts_with("//")
+            || t.starts_with("/*")
+            || t.starts_with("#include")
+            || t.starts_with("#define")
+            || KEYWORDS.contains(&first_word.as_str())
+            || OPERATORS.iter().any(|op| t.contains(op));
+        if code_ish {
+            code += 1;
+            continue;
+        }
+
+        let words = t.split_whitespace().count();
+        if words >= 6 && t.ends_with(['.', '?', '!', '"', ':', ',']) {
+            prose += 1;
+        }
+    }
+
+    if total == 0 || prose * 3 >= total {
+        return false;
+    }
+    // at least half the lines look like code, or the block is symbol-dense.
+    code * 2 >= total || (chars > 0 && symbols * 10 >= chars)
+}
+
+/// Move a `By [[Someone]], 1997:` paragraph directly above a quote into the quote's
+/// `attribution`, so the renderer can place it (the quote's span grows to cover it).
+fn attach_quote_attributions(blocks: &mut Vec<BlockNode>) {
+    let mut out: Vec<BlockNode> = Vec::with_capacity(blocks.len());
+    for mut block in blocks.drain(..) {
+        if let BlockKind::BlockQuote { attribution, .. } = &mut block.kind
+            && attribution.is_none()
+            && let Some(BlockNode {
+                span,
+                kind: BlockKind::Paragraph { content },
+            }) = out.last()
+            && looks_like_quote_attribution(content)
+        {
+            let span = *span;
+            let Some(BlockNode {
+                kind: BlockKind::Paragraph { content },
+                ..
+            }) = out.pop()
+            else {
+                unreachable!()
+            };
+            *attribution = Some(QuoteAttribution {
+                span,
+                content,
+                trailing: false,
+            });
+            block.span.start = span.start;
+        }
+        out.push(block);
+    }
+    *blocks = out;
+}
+
+fn looks_like_quote_attribution(content: &[InlineNode]) -> bool {
+    let starts_with_by = match content.first().map(|n| &n.kind) {
+        Some(InlineKind::Text { value }) => {
+            let lower = value.trim_start().to_ascii_lowercase();
+            lower.starts_with("by ")
+                || lower.starts_with("quote by ")
+                || lower.starts_with("quotes by ")
+        }
+        _ => false,
+    };
+    // a trailing `<ref>` may follow the colon.
+    let ends_with_colon = content
+        .iter()
+        .rev()
+        .find(|n| match &n.kind {
+            InlineKind::Ref { .. } => false,
+            InlineKind::Text { value } => !value.trim().is_empty(),
+            _ => true,
+        })
+        .is_some_and(
+            |n| matches!(&n.kind, InlineKind::Text { value } if value.trim_end().ends_with(':')),
+        );
+    let one_line = !content.iter().any(|n| match &n.kind {
+        InlineKind::Text { value } => value.contains('\n'),
+        InlineKind::LineBreak => true,
+        _ => false,
+    });
+    starts_with_by && ends_with_colon && one_line
+}
+
 fn is_list_line(text: &str) -> bool {
     let trimmed = text.trim_start();
     matches!(trimmed.chars().next(), Some('*' | '#' | ';' | ':'))
 }
 
-/// Hard cap on list nesting depth.
-///
-/// MediaWiki itself supports fairly deep nesting, but in practice real articles
-/// rarely exceed a small handful of levels. Untrusted / fuzzed input can use an
-/// extremely long list of marker prefixes (e.g. `:::::::::::::::::`) which causes
-/// this parser to build a deeply nested list AST. That, in turn, can exceed
-/// `serde_json`'s recursion limit when we round-trip the AST through pretty
-/// printed JSON.
-///
-/// Clamping the depth keeps the parser robust while still producing a
-/// reasonable best-effort structure for pathological inputs.
-const MAX_LIST_DEPTH: usize = 20;
-
-fn parse_list_block(
-    src: &str,
+fn parse_list_block<'a>(
+    src: &'a str,
     lines: &[util::LineRange],
     start_i: usize,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> (BlockNode, usize) {
+    cx: &mut ParseContext,
+) -> (BlockNode<'a>, usize) {
     // collect contiguous list lines.
     let mut i = start_i;
     let mut list_lines: Vec<(util::LineRange, String, usize, String)> = Vec::new();
@@ -792,11 +1361,11 @@ fn parse_list_block(
 
     // build nested lists with a stack of contexts.
     #[derive(Debug)]
-    struct ListCtx {
-        items: Vec<ListItem>,
+    struct ListCtx<'a> {
+        items: Vec<ListItem<'a>>,
     }
 
-    fn attach_child_list(parent: &mut ListItem, child: ListCtx) {
+    fn attach_child_list<'a>(parent: &mut ListItem<'a>, child: ListCtx<'a>) {
         if child.items.is_empty() {
             return;
         }
@@ -816,18 +1385,22 @@ fn parse_list_block(
 
     for (lr, prefix, content_start_abs, _content_owned) in list_lines {
         let depth_raw = prefix.chars().count().max(1);
-        let depth = depth_raw.min(MAX_LIST_DEPTH);
-        if depth_raw > MAX_LIST_DEPTH {
-            diagnostics.push(Diagnostic {
+        // MediaWiki itself supports fairly deep nesting, but real articles rarely
+        // exceed a handful of levels; clamping keeps a best-effort structure.
+        let max_depth = cx.opts.max_list_depth.max(1);
+        let depth = depth_raw.min(max_depth);
+        if depth_raw > max_depth {
+            cx.diagnostics.push(Diagnostic {
                 severity: Severity::Warning,
                 phase: Some(DiagnosticPhase::Parse),
                 code: Some("wikitext.list.depth_clamped".to_string()),
                 message: format!(
                     "List nesting depth {} exceeds max {}; clamping",
-                    depth_raw, MAX_LIST_DEPTH
+                    depth_raw, max_depth
                 ),
                 span: Some(Span::new(lr.start as u64, lr.end as u64)),
                 notes: vec![],
+                occurrences: 1,
             });
         }
 
@@ -860,13 +1433,14 @@ fn parse_list_block(
             if let Some(parent_ctx) = stack.last_mut()
                 && parent_ctx.items.is_empty()
             {
-                diagnostics.push(Diagnostic {
+                cx.diagnostics.push(Diagnostic {
                     severity: Severity::Warning,
                     phase: Some(DiagnosticPhase::Parse),
                     code: Some("wikitext.list.missing_parent".to_string()),
                     message: "Nested list item without a parent; inserting dummy item".to_string(),
                     span: Some(Span::new(lr.start as u64, lr.end as u64)),
                     notes: vec![],
+                    occurrences: 1,
                 });
                 parent_ctx.items.push(ListItem {
                     span: Span::new(lr.start as u64, lr.start as u64),
@@ -881,7 +1455,7 @@ fn parse_list_block(
         let content_slice = &src[content_start_abs..lr.end];
         let mut item_blocks: Vec<BlockNode> = Vec::new();
         if !content_slice.trim().is_empty() {
-            let inlines = util::parse_inlines(src, content_start_abs, content_slice, diagnostics);
+            let inlines = util::parse_inlines(src, content_start_abs, content_slice, cx);
             if !inlines.is_empty() {
                 item_blocks.push(BlockNode {
                     span: Span::new(content_start_abs as u64, lr.end as u64),
@@ -958,10 +1532,17 @@ fn is_block_start(src: &str, line: util::LineRange, text: &str) -> bool {
         return true;
     }
     let t = trimmed.to_ascii_lowercase();
-    if t.starts_with("<pre") || t.starts_with("<syntaxhighlight") {
+    if t.starts_with("<pre")
+        || t.starts_with("<syntaxhighlight")
+        || t.starts_with("<timeline")
+        || t.starts_with("<poem")
+    {
+        return true;
+    }
+    if t.starts_with("<references") || util::starts_with_ignore_ascii_case(trimmed, "<blockquote") {
         return true;
     }
-    if t.starts_with("<references") {
+    if util::inclusion_open_tag(trimmed).is_some() {
         return true;
     }
     if try_parse_magic_word(trimmed).is_some() {
@@ -1032,11 +1613,59 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn keeps_empty_positional_template_params() {
+        let out = parse_wiki("{{Diagram|| |c|}}\n");
+        let BlockKind::Paragraph { content } = &out.document.blocks[0].kind else {
+            panic!("expected paragraph: {:?}", out.document.blocks);
+        };
+        let InlineKind::Template { node } = &content[0].kind else {
+            panic!("expected template: {:?}", content);
+        };
+        let values: Vec<&[InlineNode]> = node.params.iter().map(|p| p.value.as_slice()).collect();
+        assert_eq!(values.len(), 4, "{:?}", node.params);
+        assert!(values[0].is_empty() && values[1].is_empty() && values[3].is_empty());
+        assert!(matches!(&values[2][0].kind, InlineKind::Text { value } if value == "c"));
+        assert_eq!(node.params[1].span, Span::new(11, 12));
+
+        let json = serde_json::to_value(node).unwrap();
+        assert_eq!(json["params"][0]["value"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn parses_template_arg_placeholders() {
+        let src = "{{Infobox|name={{{name|[[Perft]]}}}}} and {{{1}}}.";
+        let opts = ParseOptions::default();
+        let mut cx = ParseContext::new(&opts);
+        let inlines = util::parse_inlines(src, 0, src, &mut cx);
+        let InlineKind::Template { node } = &inlines[0].kind else {
+            panic!("expected template: {:?}", inlines);
+        };
+        assert_eq!(inlines[0].span, Span::new(0, 37));
+        let InlineKind::TemplateArg {
+            name,
+            default: Some(default),
+        } = &node.params[0].value[0].kind
+        else {
+            panic!("expected placeholder: {:?}", node.params);
+        };
+        assert_eq!(name, "name");
+        assert!(matches!(default[0].kind, InlineKind::InternalLink { .. }));
+        assert!(
+            matches!(&inlines[2].kind, InlineKind::TemplateArg { name, default: None } if name == "1")
+        );
+        assert_eq!(
+            &src[inlines[2].span.start as usize..inlines[2].span.end as usize],
+            "{{{1}}}"
+        );
+    }
+
     #[test]
     fn parses_file_link() {
         let src = "[[FILE:Example.jpg|thumb|An example]]";
-        let mut diagnostics = Vec::new();
-        let inlines = util::parse_inlines(src, 0, src, &mut diagnostics);
+        let opts = ParseOptions::default();
+        let mut cx = ParseContext::new(&opts);
+        let inlines = util::parse_inlines(src, 0, src, &mut cx);
         assert!(
             inlines
                 .iter()
@@ -1058,4 +1687,224 @@ mod tests {
         assert_eq!(table.rows[0].cells[0].kind, TableCellKind::Header);
         assert_eq!(table.rows[1].cells[0].kind, TableCellKind::Data);
     }
+
+    #[test]
+    fn leading_space_blocks_are_classified_per_block() {
+        let src = "Quote:\n Problem is, no one else has stepped forward in [[WCCC 1977|1977]].\n\nCode:\n U64 b = 0;\n for (int i = 0; i < 64; i++) {\n   b |= 1;\n }\n";
+        let out = parse_wiki(src);
+        let kinds: Vec<&BlockKind> = out.document.blocks.iter().map(|b| &b.kind).collect();
+        assert!(
+            matches!(kinds[1], BlockKind::BlockQuote { .. }),
+            "{kinds:?}"
+        );
+        let BlockKind::CodeBlock { block } = kinds[3] else {
+            panic!("expected code block: {kinds:?}");
+        };
+        assert_eq!(block.kind, CodeBlockKind::LeadingSpace);
+        assert_eq!(
+            block.text,
+            "U64 b = 0;\nfor (int i = 0; i < 64; i++) {\n  b |= 1;\n}"
+        );
+    }
+
+    #[test]
+    fn quote_intro_line_becomes_attribution() {
+        let src = "By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:\n Problem is, no one else has stepped forward.\n";
+        let out = parse_wiki(src);
+        assert_eq!(out.document.blocks.len(), 1);
+        let block = &out.document.blocks[0];
+        assert_eq!(block.span.start, 0);
+        let BlockKind::BlockQuote {
+            attribution: Some(a),
+            ..
+        } = &block.kind
+        else {
+            panic!("expected attributed quote: {:?}", block.kind);
+        };
+        assert!(matches!(&a.content[0].kind, InlineKind::Text { value } if value == "By "));
+
+        let out = parse_wiki("Some text:\n Problem is, no one else has stepped forward.\n");
+        assert_eq!(out.document.blocks.len(), 2);
+    }
+
+    #[test]
+    fn html_blockquotes_become_quotes() {
+        let src = "Before.\n<blockquote>\nOuter.\n<blockquote>Inner.</blockquote>\nStill outer.\n— [[Ken Thompson]], 1983\n</blockquote> After.\n<blockquote class=\"q\">Quote.<cite>Hyatt</cite></blockquote>\n";
+        let out = parse_wiki(src);
+        let blocks = &out.document.blocks;
+        assert_eq!(blocks.len(), 4, "{blocks:?}");
+        let BlockKind::BlockQuote {
+            blocks: inner,
+            attribution: Some(a),
+            html: true,
+        } = &blocks[1].kind
+        else {
+            panic!("expected attributed quote: {:?}", blocks[1].kind);
+        };
+        assert!(a.trailing);
+        assert!(
+            matches!(&a.content[0].kind, InlineKind::InternalLink { link } if link.target == "Ken Thompson")
+        );
+        assert!(
+            matches!(
+                inner[1].kind,
+                BlockKind::BlockQuote {
+                    html: true,
+                    attribution: None,
+                    ..
+                }
+            ),
+            "{inner:?}"
+        );
+        assert!(
+            src[blocks[1].span.start as usize..blocks[1].span.end as usize]
+                .ends_with("</blockquote>")
+        );
+        assert!(
+            matches!(&blocks[2].kind, BlockKind::Paragraph { content } if matches!(&content[0].kind, InlineKind::Text { value } if value == "After."))
+        );
+        let BlockKind::BlockQuote {
+            attribution: Some(a),
+            ..
+        } = &blocks[3].kind
+        else {
+            panic!("expected cite: {:?}", blocks[3].kind);
+        };
+        assert!(matches!(&a.content[0].kind, InlineKind::Text { value } if value == "Hyatt"));
+    }
+
+    #[test]
+    fn html_blockquotes_ending_in_multibyte_text() {
+        for src in [
+            "<blockquote>\nZitat von Stefan Müller “Grüße”\n</blockquote>\n",
+            "<blockquote>Grüße<cite>Müller</cite></blockquote>\n",
+            "<blockquote>\nÄ\n</blockquote>\n",
+        ] {
+            let out = parse_wiki(src);
+            assert!(
+                matches!(
+                    out.document.blocks[0].kind,
+                    BlockKind::BlockQuote { html: true, .. }
+                ),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn inclusion_tags_become_sections() {
+        let src = "Shown<includeonly> elsewhere</includeonly>.\n<noinclude>\n== Usage ==\n[[Category:Templates]]\n</noinclude> Tail\n";
+        let out = parse_wiki(src);
+        let blocks = &out.document.blocks;
+        assert_eq!(blocks.len(), 3, "{blocks:?}");
+
+        let BlockKind::Paragraph { content } = &blocks[0].kind else {
+            panic!("expected paragraph: {:?}", blocks[0].kind);
+        };
+        assert!(matches!(
+            &content[1].kind,
+            InlineKind::Inclusion { mode: InclusionMode::IncludeOnly, content } if content.len() == 1
+        ));
+
+        let BlockKind::Inclusion {
+            mode,
+            blocks: inner,
+        } = &blocks[1].kind
+        else {
+            panic!("expected inclusion: {:?}", blocks[1].kind);
+        };
+        assert_eq!(*mode, InclusionMode::NoInclude);
+        assert!(matches!(
+            inner[..],
+            [BlockNode {
+                kind: BlockKind::Heading { .. },
+                ..
+            }]
+        ));
+        let section = &src[blocks[1].span.start as usize..blocks[1].span.end as usize];
+        assert!(
+            section.starts_with("<noinclude>") && section.ends_with("</noinclude>"),
+            "{section}"
+        );
+        assert_eq!(out.document.categories[0].name, "Templates");
+        assert!(
+            matches!(&blocks[2].kind, BlockKind::Paragraph { content } if matches!(&content[0].kind, InlineKind::Text { value } if value == "Tail"))
+        );
+
+        assert_eq!(
+            crate::render::render_doc(&out.document),
+            "Shown.\n\n### Usage\n\nTail"
+        );
+    }
+
+    #[test]
+    fn list_defined_references_match_named_uses() {
+        let src = "A<ref name=a /> B<ref name=\"b\">Inline</ref> C<ref name=a/>.\n<references>\n<ref name=a>Defined [[Here]]</ref>\n</references>\n";
+        let out = parse_wiki(src);
+        let blocks = &out.document.blocks;
+        assert_eq!(blocks.len(), 2, "{blocks:?}");
+        let BlockKind::References { node } = &blocks[1].kind else {
+            panic!("expected references: {:?}", blocks[1].kind);
+        };
+        assert_eq!(blocks[1].span.end as usize, src.len() - 1);
+        let [
+            InlineNode {
+                kind: InlineKind::Ref { node: def },
+                ..
+            },
+        ] = &node.refs[..]
+        else {
+            panic!("expected one ref: {:?}", node.refs);
+        };
+        assert_eq!(def.name(), Some("a"));
+
+        assert_eq!(
+            crate::render::render_doc(&out.document),
+            "A[^1] B[^2] C[^1].\n\n<br/>\n\n## References\n\n[^1]: Defined [[Here]]\n[^2]: Inline"
+        );
+    }
+
+    #[test]
+    fn inline_tags_match_in_any_case() {
+        let src = "A<REF Name=a>Body</Ref> b<BR/>c <Span id=x>s</SPAN> 1 < 2.\n";
+        let out = parse_wiki(src);
+        let BlockKind::Paragraph { content } = &out.document.blocks[0].kind else {
+            panic!("expected paragraph");
+        };
+        let kinds: Vec<_> = content.iter().map(|n| n.kind.name()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "text",
+                "ref",
+                "text",
+                "line_break",
+                "text",
+                "html_tag",
+                "text"
+            ]
+        );
+        assert!(matches!(&content[6].kind, InlineKind::Text { value } if value == " 1 < 2."));
+    }
+
+    #[test]
+    fn text_borrows_from_the_source() {
+        let src = "{|\n| cell [[Link|label]]\n|}\nPlain ''text''.\n";
+        let out = parse_wiki(src);
+        let mut texts = Vec::new();
+        walk_inlines(&out.document.blocks, &mut |node| {
+            if let InlineKind::Text { value } = &node.kind {
+                texts.push(value);
+            }
+        });
+        assert_eq!(texts, ["cell ", "label", "Plain ", "text", "."]);
+        assert!(
+            texts
+                .iter()
+                .all(|v| matches!(v, std::borrow::Cow::Borrowed(_)))
+        );
+
+        let owned: Document<'static> = out.document.clone().into_owned();
+        assert_eq!(owned, out.document);
+    }
 }
@@ -3,12 +3,13 @@ use crate::ast::{
     TableCaption, TableCell, TableCellKind, TableRow,
 };
 
-use super::util::{parse_html_attrs, strip_cr, LineRange};
+use super::ParseContext;
 use super::util;
+use super::util::{LineRange, parse_html_attrs, strip_cr};
 
 // NOTE: This is a deliberately conservative implementation of the MediaWiki
 // table grammar. It focuses on the common "wikitable" patterns found in the
-// https://chessprogramming.org wiki export and aims to preserve spans for 
+// https://chessprogramming.org wiki export and aims to preserve spans for
 // debugging.
 
 #[derive(Debug)]
@@ -20,25 +21,41 @@ struct CellBuilder {
     rowspan: Option<u32>,
     colspan: Option<u32>,
     content_abs_start: usize,
-    content: String,
 }
 
 #[derive(Debug)]
-struct RowBuilder {
+struct RowBuilder<'a> {
     span_start: usize,
     span_end: usize,
     attrs: Vec<HtmlAttr>,
-    cells: Vec<TableCell>,
+    cells: Vec<TableCell<'a>>,
 }
 
-fn finish_cell(
-    src: &str,
+fn finish_cell<'a>(
+    src: &'a str,
     cell: &mut Option<CellBuilder>,
-    row: &mut Option<RowBuilder>,
-    diagnostics: &mut Vec<Diagnostic>,
+    row: &mut Option<RowBuilder<'a>>,
+    cx: &mut ParseContext,
 ) {
-    let Some(b) = cell.take() else { return; };
-    let blocks = cell_content_to_blocks(src, b.content_abs_start, &b.content, diagnostics);
+    let Some(b) = cell.take() else {
+        return;
+    };
+    // a cell's continuation lines are consecutive, so its content is one slice of `src`.
+    let content = &src[b.content_abs_start..b.span_end];
+    let blocks = if content.contains('\r') {
+        // CRLF input: parse the lines without their `\r`, owning the result.
+        let joined = content
+            .split('\n')
+            .map(strip_cr)
+            .collect::<Vec<_>>()
+            .join("\n");
+        cell_content_to_blocks(src, b.content_abs_start, &joined, cx)
+            .into_iter()
+            .map(BlockNode::into_owned)
+            .collect()
+    } else {
+        cell_content_to_blocks(src, b.content_abs_start, content, cx)
+    };
     let span = Span::new(b.span_start as u64, b.span_end as u64);
     let cell = TableCell {
         kind: b.kind,
@@ -62,8 +79,10 @@ fn finish_cell(
     }
 }
 
-fn finish_row(row: &mut Option<RowBuilder>, table: &mut Table) {
-    let Some(rb) = row.take() else { return; };
+fn finish_row<'a>(row: &mut Option<RowBuilder<'a>>, table: &mut Table<'a>) {
+    let Some(rb) = row.take() else {
+        return;
+    };
     let span = Span::new(rb.span_start as u64, rb.span_end as u64);
     table.rows.push(TableRow {
         span,
@@ -72,12 +91,12 @@ fn finish_row(row: &mut Option<RowBuilder>, table: &mut Table) {
     });
 }
 
-pub fn parse_table(
-    src: &str,
+pub fn parse_table<'a>(
+    src: &'a str,
     lines: &[LineRange],
     start_i: usize,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Result<(BlockNode, usize), String> {
+    cx: &mut ParseContext,
+) -> Result<(BlockNode<'a>, usize), String> {
     let start_line = lines[start_i];
     let start_text = strip_cr(&src[start_line.start..start_line.end]);
     let trimmed = start_text.trim_start();
@@ -113,7 +132,6 @@ pub fn parse_table(
             depth += 1;
             if depth > 1 {
                 if let Some(cell) = current_cell.as_mut() {
-                    append_line(&mut cell.content, line_raw);
                     cell.span_end = lr.end;
                 }
                 i += 1;
@@ -123,7 +141,7 @@ pub fn parse_table(
         if trimmed_start.starts_with("|}") {
             if depth == 1 {
                 // end of this table.
-                finish_cell(src, &mut current_cell, &mut current_row, diagnostics);
+                finish_cell(src, &mut current_cell, &mut current_row, cx);
                 finish_row(&mut current_row, &mut table);
                 table_end_abs = lr.end;
                 i += 1;
@@ -131,7 +149,6 @@ pub fn parse_table(
             } else {
                 depth -= 1;
                 if let Some(cell) = current_cell.as_mut() {
-                    append_line(&mut cell.content, line_raw);
                     cell.span_end = lr.end;
                 }
                 i += 1;
@@ -142,7 +159,6 @@ pub fn parse_table(
         if depth > 1 {
             // inside a nested table; treat as raw content.
             if let Some(cell) = current_cell.as_mut() {
-                append_line(&mut cell.content, line_raw);
                 cell.span_end = lr.end;
             }
             i += 1;
@@ -152,12 +168,13 @@ pub fn parse_table(
         // caption
         if trimmed_start.starts_with("|+") {
             // finish any pending cell/row (caption should precede rows).
-            finish_cell(src, &mut current_cell, &mut current_row, diagnostics);
+            finish_cell(src, &mut current_cell, &mut current_row, cx);
             finish_row(&mut current_row, &mut table);
 
             let after = trimmed_start.strip_prefix("|+").unwrap_or("");
-            let (attrs, content, content_abs) = split_attrs_content(after, lr.start + (line_raw.len() - trimmed_start.len()) + 2);
-            let cap_nodes = util::parse_inlines(src, content_abs, content, diagnostics);
+            let (attrs, content, content_abs) =
+                split_attrs_content(after, lr.start + (line_raw.len() - trimmed_start.len()) + 2);
+            let cap_nodes = util::parse_inlines(src, content_abs, content, cx);
             let span = Span::new(lr.start as u64, lr.end as u64);
             table.caption = Some(TableCaption {
                 span,
@@ -170,7 +187,7 @@ pub fn parse_table(
 
         // row separator
         if trimmed_start.starts_with("|-") {
-            finish_cell(src, &mut current_cell, &mut current_row, diagnostics);
+            finish_cell(src, &mut current_cell, &mut current_row, cx);
             finish_row(&mut current_row, &mut table);
 
             let after = trimmed_start.strip_prefix("|-").unwrap_or("");
@@ -189,11 +206,15 @@ pub fn parse_table(
         // cell line (header or data)
         if trimmed_start.starts_with('!') || trimmed_start.starts_with('|') {
             // finish any pending cell (a new cell line implies a previous cell ended).
-            finish_cell(src, &mut current_cell, &mut current_row, diagnostics);
+            finish_cell(src, &mut current_cell, &mut current_row, cx);
 
             let is_header = trimmed_start.starts_with('!');
             let marker = if is_header { '!' } else { '|' };
-            let kind = if is_header { TableCellKind::Header } else { TableCellKind::Data };
+            let kind = if is_header {
+                TableCellKind::Header
+            } else {
+                TableCellKind::Data
+            };
 
             let line_abs_start = lr.start + (line_raw.len() - trimmed_start.len());
             let rest = trimmed_start.strip_prefix(marker).unwrap_or("");
@@ -219,11 +240,10 @@ pub fn parse_table(
                         rowspan,
                         colspan,
                         content_abs_start: content_abs,
-                        content: content.to_string(),
                     });
                 } else {
                     // immediate cell.
-                    let blocks = cell_content_to_blocks(src, content_abs, content, diagnostics);
+                    let blocks = cell_content_to_blocks(src, content_abs, content, cx);
                     let cell = TableCell {
                         kind,
                         span: Span::new(seg_abs_start as u64, seg_abs_end as u64),
@@ -252,7 +272,6 @@ pub fn parse_table(
 
         // continuation line for current cell content.
         if let Some(cell) = current_cell.as_mut() {
-            append_line(&mut cell.content, line_raw);
             cell.span_end = lr.end;
             i += 1;
             continue;
@@ -260,13 +279,14 @@ pub fn parse_table(
 
         // otherwise: ignore stray lines inside the table and record a diagnostic.
         if !trimmed_start.is_empty() {
-            diagnostics.push(Diagnostic {
+            cx.diagnostics.push(Diagnostic {
                 severity: Severity::Warning,
                 phase: Some(DiagnosticPhase::Parse),
                 code: Some("wikitext.table.unexpected_line".to_string()),
                 message: "Unexpected line inside table".to_string(),
                 span: Some(Span::new(lr.start as u64, lr.end as u64)),
                 notes: vec![line_raw.to_string()],
+                occurrences: 1,
             });
         }
         i += 1;
@@ -279,13 +299,6 @@ pub fn parse_table(
     Ok((node, i))
 }
 
-fn append_line(buf: &mut String, line: &str) {
-    if !buf.is_empty() {
-        buf.push('\n');
-    }
-    buf.push_str(line);
-}
-
 struct Segment {
     start: usize,
     end: usize,
@@ -315,13 +328,16 @@ fn split_cell_segments(rest: &str, sep: &str) -> Vec<Segment> {
             i += 2;
             continue;
         }
-        if rem.starts_with("]]" ) {
+        if rem.starts_with("]]") {
             link_depth = (link_depth - 1).max(0);
             i += 2;
             continue;
         }
         if tpl_depth == 0 && link_depth == 0 && rem.starts_with(sep) {
-            out.push(Segment { start: last, end: i });
+            out.push(Segment {
+                start: last,
+                end: i,
+            });
             i += sep.len();
             last = i;
             continue;
@@ -380,7 +396,7 @@ fn find_attr_separator(seg_str: &str) -> Option<usize> {
             i += 2;
             continue;
         }
-        if rem.starts_with("]]" ) {
+        if rem.starts_with("]]") {
             link_depth = (link_depth - 1).max(0);
             i += 2;
             continue;
@@ -412,30 +428,32 @@ fn extract_spans(attrs: &[HtmlAttr]) -> (Option<u32>, Option<u32>) {
     let mut colspan = None;
     for a in attrs {
         if a.name.eq_ignore_ascii_case("rowspan")
-            && let Some(v) = a.value.as_deref().and_then(|s| s.parse::<u32>().ok()) {
-                rowspan = Some(v);
-            }
+            && let Some(v) = a.value.as_deref().and_then(|s| s.parse::<u32>().ok())
+        {
+            rowspan = Some(v);
+        }
         if a.name.eq_ignore_ascii_case("colspan")
-            && let Some(v) = a.value.as_deref().and_then(|s| s.parse::<u32>().ok()) {
-                colspan = Some(v);
-            }
+            && let Some(v) = a.value.as_deref().and_then(|s| s.parse::<u32>().ok())
+        {
+            colspan = Some(v);
+        }
     }
     (rowspan, colspan)
 }
 
-fn cell_content_to_blocks(
-    src: &str,
+fn cell_content_to_blocks<'a>(
+    src: &'a str,
     abs_start: usize,
-    content: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Vec<BlockNode> {
+    content: &'a str,
+    cx: &mut ParseContext,
+) -> Vec<BlockNode<'a>> {
     let trimmed = content.trim();
     if trimmed.is_empty() {
         return vec![];
     }
     let lead = content.len() - content.trim_start().len();
     let abs = abs_start + lead;
-    let inlines = util::parse_inlines(src, abs, trimmed, diagnostics);
+    let inlines = util::parse_inlines(src, abs, trimmed, cx);
     vec![BlockNode {
         span: Span::new(abs as u64, (abs + trimmed.len()) as u64),
         kind: BlockKind::Paragraph { content: inlines },
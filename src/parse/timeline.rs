@@ -0,0 +1,139 @@
+//! EasyTimeline (`<timeline>`) scripts.
+//!
+//! Only what a Markdown renderer can use is parsed: `DateFormat`, `Period`,
+//! `BarData`, and `PlotData`. Layout (`ImageSize`, `Colors`, `ScaleMajor`, ...)
+//! is ignored and stays available in [`Timeline::source`].
+
+use crate::ast::{Timeline, TimelineBar, TimelineEntry, TimelinePeriod};
+
+pub(crate) fn parse_timeline(source: &str) -> Timeline {
+    let mut timeline = Timeline {
+        source: source.to_string(),
+        date_format: None,
+        period: None,
+        bars: Vec::new(),
+        entries: Vec::new(),
+    };
+    let mut section = String::new();
+    // a `bar:X` line without dates sets the bar for the lines after it.
+    let mut current_bar: Option<String> = None;
+
+    for line in source.lines() {
+        let data = match setting(line) {
+            Some((name, value)) => {
+                section = name.to_ascii_lowercase();
+                current_bar = None;
+                value
+            }
+            None => line.trim(),
+        };
+        if data.is_empty() || data.starts_with('#') {
+            continue;
+        }
+        let attrs = attributes(data);
+        let get = |key: &str| {
+            attrs
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.clone())
+        };
+        match section.as_str() {
+            "dateformat" => timeline.date_format = Some(data.to_string()),
+            "period" => {
+                if let (Some(from), Some(till)) = (get("from"), get("till")) {
+                    timeline.period = Some(TimelinePeriod { from, till });
+                }
+            }
+            "bardata" => {
+                if let Some(id) = get("bar") {
+                    timeline.bars.push(TimelineBar {
+                        id,
+                        text: get("text").map(|t| clean_text(&t)),
+                    });
+                }
+            }
+            "plotdata" => {
+                let (from, till, at) = (get("from"), get("till"), get("at"));
+                if from.is_none() && till.is_none() && at.is_none() {
+                    if let Some(bar) = get("bar") {
+                        current_bar = Some(bar);
+                    }
+                    continue;
+                }
+                timeline.entries.push(TimelineEntry {
+                    bar: get("bar").or_else(|| current_bar.clone()),
+                    from,
+                    till,
+                    at,
+                    text: get("text")
+                        .map(|t| clean_text(&t))
+                        .filter(|t| !t.is_empty()),
+                });
+            }
+            _ => {}
+        }
+    }
+    timeline
+}
+
+/// `Name = value` at the start of a line (settings are never indented).
+fn setting(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| (name, value.trim()))
+}
+
+/// The `key:value` attributes of a data line. Values may be double-quoted.
+fn attributes(line: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let Some(colon) = rest[..word_end].find(':') else {
+            rest = rest[word_end..].trim_start();
+            continue;
+        };
+        let key = rest[..colon].to_string();
+        let after = &rest[colon + 1..];
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        attrs.push((key, value.to_string()));
+        rest = next.trim_start();
+    }
+    attrs
+}
+
+/// Label text: `~` (a line break) becomes a space and `[[Target|label]]` its label.
+fn clean_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("[[") {
+        out.push_str(&rest[..open]);
+        let inner = &rest[open + 2..];
+        let Some(close) = inner.find("]]") else {
+            out.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+        let link = &inner[..close];
+        out.push_str(link.rsplit_once('|').map_or(link, |(_, label)| label));
+        rest = &inner[close + 2..];
+    }
+    out.push_str(rest);
+    out.replace('~', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,82 @@
+use crate::ast::{Document, Span, Trivia, TriviaKind};
+use crate::render::wikitext;
+
+/// Attach [`Trivia`] records for every top-level item of `doc`.
+///
+/// Top-level items (redirect, blocks, categories) are visited in source order.
+/// The bytes between two items become the `leading` trivia of the second one,
+/// and the item's own source text is kept as `original` whenever it differs
+/// from the canonical serialization.
+pub fn attach_trivia(src: &str, doc: &mut Document) {
+    let mut items: Vec<(TriviaKind, Span, String)> = Vec::new();
+    if let Some(r) = &doc.redirect {
+        items.push((
+            TriviaKind::Redirect,
+            r.span,
+            wikitext::serialize_redirect(r),
+        ));
+    }
+    for b in &doc.blocks {
+        items.push((TriviaKind::Block, b.span, wikitext::serialize_block(b)));
+    }
+    for c in &doc.categories {
+        items.push((
+            TriviaKind::Category,
+            c.span,
+            wikitext::serialize_category(c),
+        ));
+    }
+    items.sort_by_key(|(kind, span, _)| (span.start, *kind == TriviaKind::Category));
+
+    let mut trivia: Vec<Trivia> = Vec::with_capacity(items.len() + 1);
+    let mut cursor = 0usize;
+    for (kind, span, canonical) in items {
+        let (start, end) = match kind {
+            // the redirect span only covers the `[[...]]` link; keep the whole line
+            // so `#REDIRECT` and anything after the link survive.
+            TriviaKind::Redirect => {
+                let s = span.start as usize;
+                let line_start = src[..s].rfind('\n').map(|p| p + 1).unwrap_or(0);
+                let line_end = src[s..].find('\n').map(|p| s + p).unwrap_or(src.len());
+                (line_start, line_end)
+            }
+            _ => (span.start as usize, span.end as usize),
+        };
+        let start = start.max(cursor).min(src.len());
+        let end = end.max(start).min(src.len());
+
+        let leading = &src[cursor..start];
+        let original = &src[start..end];
+        trivia.push(Trivia {
+            kind,
+            span,
+            leading: leading.to_string(),
+            blank_lines: count_blank_lines(leading, cursor == 0),
+            original: (original != canonical).then(|| original.to_string()),
+            fingerprint: wikitext::fingerprint(&canonical),
+        });
+        cursor = end;
+    }
+
+    let tail = &src[cursor..];
+    trivia.push(Trivia {
+        kind: TriviaKind::Eof,
+        span: Span::new(src.len() as u64, src.len() as u64),
+        leading: tail.to_string(),
+        blank_lines: count_blank_lines(tail, cursor == 0),
+        original: None,
+        fingerprint: String::new(),
+    });
+
+    doc.trivia = trivia;
+}
+
+fn count_blank_lines(leading: &str, at_document_start: bool) -> u32 {
+    let newlines = leading.bytes().filter(|b| *b == b'\n').count() as u32;
+    if at_document_start {
+        newlines
+    } else {
+        // the first newline terminates the previous item's line.
+        newlines.saturating_sub(1)
+    }
+}
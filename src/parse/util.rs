@@ -1,8 +1,13 @@
 use crate::ast::{
-    Diagnostic, DiagnosticPhase, ExternalLink, FileLink, FileNamespace, FileParam, HtmlAttr, HtmlTag,
-    InlineKind, InlineNode, InternalLink, RefNode, Severity, Span, TemplateInvocation, TemplateName,
-    TemplateNameKind, TemplateParam,
+    Diagnostic, DiagnosticPhase, ExternalLink, FileLink, FileNamespace, FileParam, HtmlAttr,
+    HtmlTag, InclusionMode, InlineKind, InlineNode, InternalLink, MagicLink, MagicLinkKind,
+    RefNode, Severity, Span, TemplateInvocation, TemplateName, TemplateNameKind, TemplateParam,
 };
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use super::ParseContext;
 
 /// A byte range for a single line in the source.
 ///
@@ -43,10 +48,37 @@ pub fn strip_cr(s: &str) -> &str {
     s.strip_suffix('\r').unwrap_or(s)
 }
 
+/// Like [`collect_lines`], for the part of `src` between `start` and `end`.
+pub fn collect_lines_in(src: &str, start: usize, end: usize) -> Vec<LineRange> {
+    let mut lines = collect_lines(&src[start..end]);
+    for line in &mut lines {
+        line.start += start;
+        line.end += start;
+        line.end_with_newline += start;
+    }
+    lines
+}
+
 pub fn line_trimmed_start(src: &str, line: LineRange) -> &str {
     strip_cr(&src[line.start..line.end]).trim_start()
 }
 
+/// True if `s` starts with `prefix`, ignoring ASCII case.
+pub(crate) fn starts_with_ignore_ascii_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// Byte offset of the first `needle` in `haystack`, ignoring ASCII case.
+///
+/// Compares in place instead of lowercasing a copy of `haystack`, which the inline
+/// parser would otherwise do at every `<`.
+pub(crate) fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
 /// Parse a sequence of HTML-like attributes (small subset).
 ///
 /// Example: `style="text-align:center;" rowspan="2"`.
@@ -124,12 +156,12 @@ pub fn parse_html_attrs(mut s: &str) -> Vec<HtmlAttr> {
 /// Parse inline content for paragraphs, headings, etc.
 ///
 /// `base_abs` is the absolute byte offset of `slice` within the original source.
-pub fn parse_inlines(
-    full_src: &str,
+pub fn parse_inlines<'a>(
+    full_src: &'a str,
     base_abs: usize,
-    slice: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Vec<InlineNode> {
+    slice: &'a str,
+    cx: &mut ParseContext,
+) -> Vec<InlineNode<'a>> {
     // Pathological inputs can contain extremely long runs of opening delimiters like
     // "{{{{{{{{..." or "[[[[[[[[...".
     //
@@ -138,8 +170,9 @@ pub fn parse_inlines(
     // delimiter run, we can devolve into quadratic behavior (O(n^2)).
     //
     // To keep parsing robust on adversarial / fuzzed inputs, we treat very long runs
-    // of these delimiters as plain text and skip over them in one step.
-    const MAX_OPEN_DELIM_RUN: usize = 16;
+    // of these delimiters (`max_delimiter_run`) as plain text and skip over them in
+    // one step.
+    let max_open_delim_run = cx.opts.max_delimiter_run.max(2);
 
     #[inline]
     fn count_ascii_prefix_byte(s: &str, byte: u8) -> usize {
@@ -152,19 +185,36 @@ pub fn parse_inlines(
         n
     }
 
+    // every nested link label, emphasis, ref, or template argument recurses here.
+    if cx.inline_depth >= cx.opts.max_inline_depth {
+        cx.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            phase: Some(DiagnosticPhase::Parse),
+            code: Some("wikitext.inline.depth_exceeded".to_string()),
+            message: format!(
+                "Inline nesting exceeds max depth {}; treating as text",
+                cx.opts.max_inline_depth
+            ),
+            span: Some(Span::new(base_abs as u64, (base_abs + slice.len()) as u64)),
+            notes: vec![],
+            occurrences: 1,
+        });
+        return vec![InlineNode {
+            span: Span::new(base_abs as u64, (base_abs + slice.len()) as u64),
+            kind: InlineKind::Text {
+                value: Cow::Borrowed(slice),
+            },
+        }];
+    }
+    cx.inline_depth += 1;
+
     let mut out: Vec<InlineNode> = Vec::new();
     let mut i: usize = 0;
     let mut text_start: usize = 0;
 
-    let flush_text = |out: &mut Vec<InlineNode>, text_start: &mut usize, i: usize| {
+    let flush_text = |out: &mut Vec<InlineNode<'a>>, text_start: &mut usize, i: usize| {
         if *text_start < i {
-            let txt = &slice[*text_start..i];
-            out.push(InlineNode {
-                span: Span::new((base_abs + *text_start) as u64, (base_abs + i) as u64),
-                kind: InlineKind::Text {
-                    value: txt.to_string(),
-                },
-            });
+            push_text(out, base_abs + *text_start, &slice[*text_start..i]);
         }
         *text_start = i;
     };
@@ -182,8 +232,8 @@ pub fn parse_inlines(
         // every byte offset within the run, leading to O(n^2) scanning.
         if rem.starts_with("{{") {
             let run = count_ascii_prefix_byte(rem, b'{');
-            if run >= MAX_OPEN_DELIM_RUN {
-                diagnostics.push(Diagnostic {
+            if run >= max_open_delim_run {
+                cx.diagnostics.push(Diagnostic {
                     severity: Severity::Warning,
                     phase: Some(DiagnosticPhase::Parse),
                     code: Some("wikitext.inline.pathological_delim_run".to_string()),
@@ -191,8 +241,12 @@ pub fn parse_inlines(
                         "Pathological run of '{{' characters (len={}); treating as text",
                         run
                     ),
-                    span: Some(Span::new((base_abs + i) as u64, (base_abs + i + run) as u64)),
+                    span: Some(Span::new(
+                        (base_abs + i) as u64,
+                        (base_abs + i + run) as u64,
+                    )),
                     notes: vec![],
+                    occurrences: 1,
                 });
                 i += run;
                 continue;
@@ -200,8 +254,8 @@ pub fn parse_inlines(
         }
         if rem.starts_with("[[") {
             let run = count_ascii_prefix_byte(rem, b'[');
-            if run >= MAX_OPEN_DELIM_RUN {
-                diagnostics.push(Diagnostic {
+            if run >= max_open_delim_run {
+                cx.diagnostics.push(Diagnostic {
                     severity: Severity::Warning,
                     phase: Some(DiagnosticPhase::Parse),
                     code: Some("wikitext.inline.pathological_delim_run".to_string()),
@@ -209,8 +263,12 @@ pub fn parse_inlines(
                         "Pathological run of '[' characters (len={}); treating as text",
                         run
                     ),
-                    span: Some(Span::new((base_abs + i) as u64, (base_abs + i + run) as u64)),
+                    span: Some(Span::new(
+                        (base_abs + i) as u64,
+                        (base_abs + i + run) as u64,
+                    )),
                     notes: vec![],
+                    occurrences: 1,
                 });
                 i += run;
                 continue;
@@ -219,34 +277,48 @@ pub fn parse_inlines(
 
         // <br>, <br/>, <br />
         if rem.starts_with('<')
-            && let Some((node, consumed)) = try_parse_line_break(base_abs + i, rem) {
-                flush_text(&mut out, &mut text_start, i);
-                out.push(node);
-                i += consumed;
-                text_start = i;
-                continue;
-            }
+            && let Some((node, consumed)) = try_parse_line_break(base_abs + i, rem)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(node);
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // <ref ...> ... </ref>
         if rem.starts_with('<')
-            && let Some((node, consumed)) = try_parse_ref_tag(full_src, base_abs + i, rem, diagnostics) {
-                flush_text(&mut out, &mut text_start, i);
-                out.push(node);
-                i += consumed;
-                text_start = i;
-                continue;
-            }
+            && let Some((node, consumed)) = try_parse_ref_tag(full_src, base_abs + i, rem, cx)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(node);
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // <span ...></span>
         if rem.starts_with('<')
-            && let Some((node, consumed)) = try_parse_simple_html_tag(full_src, base_abs + i, rem, "span", diagnostics)
-            {
-                flush_text(&mut out, &mut text_start, i);
-                out.push(node);
-                i += consumed;
-                text_start = i;
-                continue;
-            }
+            && let Some((node, consumed)) =
+                try_parse_simple_html_tag(full_src, base_abs + i, rem, "span", cx)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(node);
+            i += consumed;
+            text_start = i;
+            continue;
+        }
+
+        // <noinclude>, <includeonly>, <onlyinclude>
+        if rem.starts_with('<')
+            && let Some((node, consumed)) = try_parse_inclusion_tag(full_src, base_abs + i, rem, cx)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(node);
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // internal links and file links [[...]]
         //
@@ -254,73 +326,128 @@ pub fn parse_inlines(
         // (e.g. `[[File:X|... [[Other]] ...]]`). A naive `find("]]" )` would stop at
         // the first nested link and truncate the outer link.
         if rem.starts_with("[[")
-            && let Some(consumed) = find_matching_double_brackets(rem) {
-                // consumed includes the closing `]]`.
-                if consumed >= 4 {
-                    let inner = &rem[2..consumed - 2];
-                    flush_text(&mut out, &mut text_start, i);
-                    out.push(parse_bracket_link(full_src, base_abs + i, base_abs + i + 2, inner, diagnostics));
-                    i += consumed;
-                    text_start = i;
-                    continue;
-                }
+            && let Some(consumed) = find_matching_double_brackets(rem)
+        {
+            // consumed includes the closing `]]`.
+            if consumed >= 4 {
+                let inner = &rem[2..consumed - 2];
+                flush_text(&mut out, &mut text_start, i);
+                out.push(parse_bracket_link(
+                    full_src,
+                    base_abs + i,
+                    base_abs + i + 2,
+                    inner,
+                    cx,
+                ));
+                i += consumed;
+                text_start = i;
+                continue;
             }
+        }
 
         // external links [https://... label]
-        if rem.starts_with('[') && !rem.starts_with("[[")
-            && let Some(end_rel) = rem[1..].find(']') {
-                let inner_end = 1 + end_rel;
-                let inner = &rem[1..inner_end];
-                let inner_trim = inner.trim_start();
-                if inner_trim.starts_with("http://") || inner_trim.starts_with("https://") {
-                    flush_text(&mut out, &mut text_start, i);
-
-                    let (url, label) = split_first_ws(inner_trim);
-                    let url_abs_start = base_abs + i + 1 + (inner.len() - inner_trim.len());
-
-                    let text_nodes = label.map(|lbl| {
-                        // label start is after url + whitespace.
-                        let label_pos = inner_trim.find(lbl).unwrap_or(inner_trim.len());
-                        let abs = url_abs_start + label_pos;
-                        parse_inlines(full_src, abs, lbl, diagnostics)
-                    });
-
-                    out.push(InlineNode {
-                        span: Span::new((base_abs + i) as u64, (base_abs + i + inner_end + 1) as u64),
-                        kind: InlineKind::ExternalLink {
-                            link: ExternalLink {
-                                url: url.to_string(),
-                                text: text_nodes,
-                            },
+        if rem.starts_with('[')
+            && !rem.starts_with("[[")
+            && let Some(end_rel) = rem[1..].find(']')
+        {
+            let inner_end = 1 + end_rel;
+            let inner = &rem[1..inner_end];
+            let inner_trim = inner.trim_start();
+            if inner_trim.starts_with("http://") || inner_trim.starts_with("https://") {
+                flush_text(&mut out, &mut text_start, i);
+
+                let (url, label) = split_first_ws(inner_trim);
+                let url_abs_start = base_abs + i + 1 + (inner.len() - inner_trim.len());
+
+                let text_nodes = label.map(|lbl| {
+                    // label start is after url + whitespace.
+                    let label_pos = inner_trim.find(lbl).unwrap_or(inner_trim.len());
+                    let abs = url_abs_start + label_pos;
+                    link_label(parse_inlines(full_src, abs, lbl, cx))
+                });
+
+                out.push(InlineNode {
+                    span: Span::new((base_abs + i) as u64, (base_abs + i + inner_end + 1) as u64),
+                    kind: InlineKind::ExternalLink {
+                        link: ExternalLink {
+                            url: url.to_string(),
+                            text: text_nodes,
                         },
-                    });
+                    },
+                });
 
-                    i += inner_end + 1;
-                    text_start = i;
-                    continue;
-                }
+                i += inner_end + 1;
+                text_start = i;
+                continue;
             }
+        }
+
+        // template parameter placeholders {{{name|default}}}
+        if is_template_arg_open(rem)
+            && let Some(consumed) = find_matching_template_arg(rem)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(parse_template_arg(
+                full_src,
+                base_abs + i,
+                &rem[3..consumed - 3],
+                cx,
+            ));
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // templates {{...}}
         if rem.starts_with("{{")
-            && let Some(consumed) = find_matching_braces(rem) {
-                let inner = &rem[2..consumed - 2];
-                flush_text(&mut out, &mut text_start, i);
-                out.push(parse_template(full_src, base_abs + i, base_abs + i + 2, inner, diagnostics));
+            && let Some(consumed) = find_matching_braces(rem)
+        {
+            if cx.template_depth >= cx.opts.max_template_depth {
+                // too deeply nested: keep the whole invocation as text.
+                cx.diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    phase: Some(DiagnosticPhase::Parse),
+                    code: Some("wikitext.template.depth_exceeded".to_string()),
+                    message: format!(
+                        "Template nesting exceeds max depth {}; treating as text",
+                        cx.opts.max_template_depth
+                    ),
+                    span: Some(Span::new(
+                        (base_abs + i) as u64,
+                        (base_abs + i + consumed) as u64,
+                    )),
+                    notes: vec![],
+                    occurrences: 1,
+                });
                 i += consumed;
-                text_start = i;
                 continue;
             }
+            let inner = &rem[2..consumed - 2];
+            flush_text(&mut out, &mut text_start, i);
+            cx.template_depth += 1;
+            out.push(parse_template(
+                full_src,
+                base_abs + i,
+                base_abs + i + 2,
+                inner,
+                cx,
+            ));
+            cx.template_depth -= 1;
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // emphasis: `''italic''`, `'''bold'''`, `'''''bold italic'''''`.
         if rem.starts_with("''")
-            && let Some((node, consumed)) = try_parse_emphasis(full_src, base_abs + i, slice, i, diagnostics) {
-                flush_text(&mut out, &mut text_start, i);
-                out.push(node);
-                i += consumed;
-                text_start = i;
-                continue;
-            }
+            && let Some((node, consumed)) = try_parse_emphasis(full_src, base_abs + i, slice, i, cx)
+        {
+            flush_text(&mut out, &mut text_start, i);
+            out.push(node);
+            i += consumed;
+            text_start = i;
+            continue;
+        }
 
         // default: advance by one char.
         let ch_len = rem.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
@@ -328,20 +455,91 @@ pub fn parse_inlines(
     }
 
     flush_text(&mut out, &mut text_start, i);
+    cx.inline_depth -= 1;
     out
 }
 
-fn try_parse_line_break(abs_start: usize, rem: &str) -> Option<(InlineNode, usize)> {
-    let lower = rem.to_ascii_lowercase();
-    if !lower.starts_with("<br") {
-        return None;
+/// Push `text` (starting at `abs_start`) as Text nodes, with `ISBN`, `RFC`, and
+/// `PMID` magic links split out.
+fn push_text<'a>(out: &mut Vec<InlineNode<'a>>, abs_start: usize, text: &'a str) {
+    static MAGIC_LINK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"\b(?:ISBN[ \x{a0}]+(?P<isbn>(?:97[89][- ]?)?(?:[0-9][- ]?){9}[0-9Xx])|RFC[ \x{a0}]+(?P<rfc>[0-9]+)|PMID[ \x{a0}]+(?P<pmid>[0-9]+))\b",
+        )
+        .expect("valid regex")
+    });
+
+    let push = |out: &mut Vec<InlineNode<'a>>, start: usize, end: usize, kind: InlineKind<'a>| {
+        out.push(InlineNode {
+            span: Span::new((abs_start + start) as u64, (abs_start + end) as u64),
+            kind,
+        });
+    };
+    let mut last = 0;
+    for caps in MAGIC_LINK.captures_iter(text) {
+        let whole = caps.get(0).expect("group 0 always matches");
+        let (kind, id) = match (caps.name("isbn"), caps.name("rfc"), caps.name("pmid")) {
+            (Some(m), _, _) => (
+                MagicLinkKind::Isbn,
+                m.as_str().replace(['-', ' '], "").to_ascii_uppercase(),
+            ),
+            (_, Some(m), _) => (MagicLinkKind::Rfc, m.as_str().to_string()),
+            (_, _, Some(m)) => (MagicLinkKind::Pmid, m.as_str().to_string()),
+            _ => continue,
+        };
+        if last < whole.start() {
+            push(
+                out,
+                last,
+                whole.start(),
+                InlineKind::Text {
+                    value: Cow::Borrowed(&text[last..whole.start()]),
+                },
+            );
+        }
+        let link = MagicLink {
+            kind,
+            id,
+            text: whole.as_str().to_string(),
+        };
+        push(
+            out,
+            whole.start(),
+            whole.end(),
+            InlineKind::MagicLink { link },
+        );
+        last = whole.end();
     }
-    let end = rem.find('>')?;
-    // accept <br>, <br/>, <br /> ...
-    let tag = &lower[..=end];
-    if !tag.starts_with("<br") {
+    if last < text.len() {
+        push(
+            out,
+            last,
+            text.len(),
+            InlineKind::Text {
+                value: Cow::Borrowed(&text[last..]),
+            },
+        );
+    }
+}
+
+/// `nodes` with magic links turned back into text, since links don't nest.
+fn link_label<'a>(mut nodes: Vec<InlineNode<'a>>) -> Vec<InlineNode<'a>> {
+    for node in &mut nodes {
+        if let InlineKind::MagicLink { link } = &mut node.kind {
+            node.kind = InlineKind::Text {
+                value: std::mem::take(&mut link.text).into(),
+            };
+        }
+    }
+    nodes
+}
+
+fn try_parse_line_break<'a>(abs_start: usize, rem: &'a str) -> Option<(InlineNode<'a>, usize)> {
+    if !starts_with_ignore_ascii_case(rem, "<br") {
         return None;
     }
+    // accept <br>, <br/>, <br /> ...
+    let end = rem.find('>')?;
     let consumed = end + 1;
     Some((
         InlineNode {
@@ -352,20 +550,16 @@ fn try_parse_line_break(abs_start: usize, rem: &str) -> Option<(InlineNode, usiz
     ))
 }
 
-fn try_parse_emphasis(
-    full_src: &str,
+fn try_parse_emphasis<'a>(
+    full_src: &'a str,
     abs_start: usize,
-    full_slice: &str,
+    full_slice: &'a str,
     rel_i: usize,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Option<(InlineNode, usize)> {
+    cx: &mut ParseContext,
+) -> Option<(InlineNode<'a>, usize)> {
     let rem = &full_slice[rel_i..];
     // prefer longer delimiters.
-    for (delim, kind) in [
-        ("'''''", "bi"),
-        ("'''", "b"),
-        ("''", "i"),
-    ] {
+    for (delim, kind) in [("'''''", "bi"), ("'''", "b"), ("''", "i")] {
         if rem.starts_with(delim) {
             let delim_len = delim.len();
             let after = &rem[delim_len..];
@@ -373,7 +567,7 @@ fn try_parse_emphasis(
                 let inner_rel_start = rel_i + delim_len;
                 let inner_rel_end = inner_rel_start + close_rel;
                 let inner = &full_slice[inner_rel_start..inner_rel_end];
-                let children = parse_inlines(full_src, abs_start + delim_len, inner, diagnostics);
+                let children = parse_inlines(full_src, abs_start + delim_len, inner, cx);
                 let consumed = delim_len + close_rel + delim_len;
                 let span = Span::new(abs_start as u64, (abs_start + consumed) as u64);
                 let inline_kind = match kind {
@@ -381,29 +575,37 @@ fn try_parse_emphasis(
                     "b" => InlineKind::Bold { content: children },
                     "i" => InlineKind::Italic { content: children },
                     _ => InlineKind::Text {
-                        value: rem[..consumed].to_string(),
+                        value: Cow::Borrowed(&rem[..consumed]),
                     },
                 };
-                return Some((InlineNode { span, kind: inline_kind }, consumed));
+                return Some((
+                    InlineNode {
+                        span,
+                        kind: inline_kind,
+                    },
+                    consumed,
+                ));
             }
         }
     }
     None
 }
 
-fn parse_bracket_link(
-    full_src: &str,
+fn parse_bracket_link<'a>(
+    full_src: &'a str,
     abs_start: usize,
     abs_inner_start: usize,
-    inner: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> InlineNode {
+    inner: &'a str,
+    cx: &mut ParseContext,
+) -> InlineNode<'a> {
     let inner_trim = inner.trim_start();
-    let lower = inner_trim.to_ascii_lowercase();
-    if lower.starts_with("file:") || lower.starts_with("image:") || lower.starts_with("media:") {
-        return parse_file_link(full_src, abs_start, abs_inner_start, inner, diagnostics);
+    if ["file:", "image:", "media:"]
+        .iter()
+        .any(|ns| starts_with_ignore_ascii_case(inner_trim, ns))
+    {
+        return parse_file_link(full_src, abs_start, abs_inner_start, inner, cx);
     }
-    parse_internal_link(full_src, abs_start, abs_inner_start, inner, diagnostics)
+    parse_internal_link(full_src, abs_start, abs_inner_start, inner, cx)
 }
 
 fn split_target_anchor(s: &str) -> (&str, Option<&str>) {
@@ -414,13 +616,13 @@ fn split_target_anchor(s: &str) -> (&str, Option<&str>) {
     }
 }
 
-fn parse_internal_link(
-    full_src: &str,
+fn parse_internal_link<'a>(
+    full_src: &'a str,
     abs_start: usize,
     abs_inner_start: usize,
-    inner: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> InlineNode {
+    inner: &'a str,
+    cx: &mut ParseContext,
+) -> InlineNode<'a> {
     let span = Span::new(abs_start as u64, (abs_start + 2 + inner.len() + 2) as u64);
     let (target_part, label_part) = match inner.split_once('|') {
         Some((a, b)) => (a, Some(b)),
@@ -436,7 +638,7 @@ fn parse_internal_link(
         } else {
             let rel = inner.find(lbl).unwrap_or(0);
             let abs = abs_inner_start + rel;
-            Some(parse_inlines(full_src, abs, lbl_trim, diagnostics))
+            Some(link_label(parse_inlines(full_src, abs, lbl_trim, cx)))
         }
     } else {
         None
@@ -454,20 +656,20 @@ fn parse_internal_link(
     }
 }
 
-fn parse_file_link(
-    full_src: &str,
+fn parse_file_link<'a>(
+    full_src: &'a str,
     abs_start: usize,
     abs_inner_start: usize,
-    inner: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> InlineNode {
+    inner: &'a str,
+    cx: &mut ParseContext,
+) -> InlineNode<'a> {
     let span = Span::new(abs_start as u64, (abs_start + 2 + inner.len() + 2) as u64);
     let parts = split_top_level(inner, '|');
     if parts.is_empty() {
         return InlineNode {
             span,
             kind: InlineKind::Text {
-                value: format!("[[{}]]", inner),
+                value: format!("[[{}]]", inner).into(),
             },
         };
     }
@@ -494,9 +696,12 @@ fn parse_file_link(
         }
         let leading = raw.len() - raw.trim_start().len();
         let abs = abs_inner_start + seg.0 + leading;
-        let nodes = parse_inlines(full_src, abs, trimmed, diagnostics);
+        let nodes = parse_inlines(full_src, abs, trimmed, cx);
         params.push(FileParam {
-            span: Span::new((abs_inner_start + seg.0) as u64, (abs_inner_start + seg.1) as u64),
+            span: Span::new(
+                (abs_inner_start + seg.0) as u64,
+                (abs_inner_start + seg.1) as u64,
+            ),
             content: nodes,
         });
     }
@@ -513,14 +718,13 @@ fn parse_file_link(
     }
 }
 
-fn try_parse_ref_tag(
-    full_src: &str,
+fn try_parse_ref_tag<'a>(
+    full_src: &'a str,
     abs_start: usize,
-    rem: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Option<(InlineNode, usize)> {
-    let lower = rem.to_ascii_lowercase();
-    if !lower.starts_with("<ref") {
+    rem: &'a str,
+    cx: &mut ParseContext,
+) -> Option<(InlineNode<'a>, usize)> {
+    if !starts_with_ignore_ascii_case(rem, "<ref") {
         return None;
     }
     let open_end = rem.find('>')?;
@@ -530,7 +734,7 @@ fn try_parse_ref_tag(
         .trim_start_matches('<')
         .trim_start_matches(|c: char| c.is_ascii_alphabetic())
         .trim();
-    let attrs_str = attrs_str.trim_end_matches('>').trim_end_matches("/>").trim();
+    let attrs_str = attrs_str.trim_end_matches('>').trim_end_matches('/').trim();
     let attrs = parse_html_attrs(attrs_str);
 
     if self_closing {
@@ -551,14 +755,18 @@ fn try_parse_ref_tag(
     }
 
     let close_pat = "</ref>";
-    let Some(close_rel) = lower[open_end + 1..].find(close_pat) else {
-        diagnostics.push(Diagnostic {
+    let Some(close_rel) = find_ignore_ascii_case(&rem[open_end + 1..], close_pat) else {
+        cx.diagnostics.push(Diagnostic {
             severity: Severity::Warning,
             phase: Some(DiagnosticPhase::Parse),
             code: Some("wikitext.ref.unclosed".to_string()),
             message: "Unclosed <ref> tag".to_string(),
-            span: Some(Span::new(abs_start as u64, (abs_start + open_end + 1) as u64)),
+            span: Some(Span::new(
+                abs_start as u64,
+                (abs_start + open_end + 1) as u64,
+            )),
             notes: vec![],
+            occurrences: 1,
         });
         return None;
     };
@@ -569,7 +777,12 @@ fn try_parse_ref_tag(
     let content_nodes = if content.trim().is_empty() {
         None
     } else {
-        Some(parse_inlines(full_src, abs_start + content_start_rel, content, diagnostics))
+        Some(parse_inlines(
+            full_src,
+            abs_start + content_start_rel,
+            content,
+            cx,
+        ))
     };
     let consumed = close_start_rel + close_pat.len();
 
@@ -588,16 +801,17 @@ fn try_parse_ref_tag(
     ))
 }
 
-fn try_parse_simple_html_tag(
-    full_src: &str,
+fn try_parse_simple_html_tag<'a>(
+    full_src: &'a str,
     abs_start: usize,
-    rem: &str,
+    rem: &'a str,
     tag_name: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Option<(InlineNode, usize)> {
-    let lower = rem.to_ascii_lowercase();
-    let open_pat = format!("<{}", tag_name);
-    if !lower.starts_with(&open_pat) {
+    cx: &mut ParseContext,
+) -> Option<(InlineNode<'a>, usize)> {
+    if !rem
+        .strip_prefix('<')
+        .is_some_and(|r| starts_with_ignore_ascii_case(r, tag_name))
+    {
         return None;
     }
     let open_end = rem.find('>')?;
@@ -630,14 +844,18 @@ fn try_parse_simple_html_tag(
     }
 
     let close_pat = format!("</{}>", tag_name);
-    let Some(close_rel) = lower[open_end + 1..].find(&close_pat) else {
-        diagnostics.push(Diagnostic {
+    let Some(close_rel) = find_ignore_ascii_case(&rem[open_end + 1..], &close_pat) else {
+        cx.diagnostics.push(Diagnostic {
             severity: Severity::Warning,
             phase: Some(DiagnosticPhase::Parse),
             code: Some("wikitext.html.unclosed".to_string()),
             message: format!("Unclosed <{}> tag", tag_name),
-            span: Some(Span::new(abs_start as u64, (abs_start + open_end + 1) as u64)),
+            span: Some(Span::new(
+                abs_start as u64,
+                (abs_start + open_end + 1) as u64,
+            )),
             notes: vec![],
+            occurrences: 1,
         });
         return None;
     };
@@ -648,7 +866,7 @@ fn try_parse_simple_html_tag(
     let children = if content.is_empty() {
         vec![]
     } else {
-        parse_inlines(full_src, abs_start + content_start_rel, content, diagnostics)
+        parse_inlines(full_src, abs_start + content_start_rel, content, cx)
     };
     let consumed = close_start_rel + close_pat.len();
 
@@ -668,20 +886,85 @@ fn try_parse_simple_html_tag(
     ))
 }
 
-fn parse_template(
-    full_src: &str,
+/// The mode and length of an inclusion-control opening tag (`<noinclude>`) at the
+/// start of `s`.
+pub(crate) fn inclusion_open_tag(s: &str) -> Option<(InclusionMode, usize)> {
+    let rest = s.strip_prefix('<')?;
+    // the longest tag name is 11 bytes; don't scan further for the `>`.
+    let end = rest.bytes().take(16).position(|b| b == b'>')?;
+    let mode = InclusionMode::from_tag(rest[..end].trim())?;
+    Some((mode, end + 2))
+}
+
+fn try_parse_inclusion_tag<'a>(
+    full_src: &'a str,
+    abs_start: usize,
+    rem: &'a str,
+    cx: &mut ParseContext,
+) -> Option<(InlineNode<'a>, usize)> {
+    let (mode, open_len) = inclusion_open_tag(rem)?;
+    let close_pat = format!("</{}>", mode.tag());
+    let Some(close_rel) = find_ignore_ascii_case(&rem[open_len..], &close_pat) else {
+        cx.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            phase: Some(DiagnosticPhase::Parse),
+            code: Some("wikitext.inclusion.unclosed".to_string()),
+            message: format!("Unclosed <{}> tag", mode.tag()),
+            span: Some(Span::new(abs_start as u64, (abs_start + open_len) as u64)),
+            notes: vec![],
+            occurrences: 1,
+        });
+        return None;
+    };
+
+    let content = &rem[open_len..open_len + close_rel];
+    let consumed = open_len + close_rel + close_pat.len();
+    Some((
+        InlineNode {
+            span: Span::new(abs_start as u64, (abs_start + consumed) as u64),
+            kind: InlineKind::Inclusion {
+                mode,
+                content: parse_inlines(full_src, abs_start + open_len, content, cx),
+            },
+        },
+        consumed,
+    ))
+}
+
+/// `{{{name}}}` / `{{{name|default}}}`; `inner` is what's between the braces.
+/// Like MediaWiki, only the text up to a second `|` is the default.
+fn parse_template_arg<'a>(
+    full_src: &'a str,
+    abs_start: usize,
+    inner: &'a str,
+    cx: &mut ParseContext,
+) -> InlineNode<'a> {
+    let abs_inner_start = abs_start + 3;
+    let parts = split_top_level(inner, '|');
+    let name = inner[parts[0].0..parts[0].1].trim().to_string();
+    let default = parts.get(1).map(|&(start, end)| {
+        parse_inlines(full_src, abs_inner_start + start, &inner[start..end], cx)
+    });
+    InlineNode {
+        span: Span::new(abs_start as u64, (abs_inner_start + inner.len() + 3) as u64),
+        kind: InlineKind::TemplateArg { name, default },
+    }
+}
+
+fn parse_template<'a>(
+    full_src: &'a str,
     abs_start: usize,
     abs_inner_start: usize,
-    inner: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> InlineNode {
+    inner: &'a str,
+    cx: &mut ParseContext,
+) -> InlineNode<'a> {
     let span = Span::new(abs_start as u64, (abs_start + 2 + inner.len() + 2) as u64);
     let parts = split_top_level(inner, '|');
     if parts.is_empty() {
         return InlineNode {
             span,
             kind: InlineKind::Text {
-                value: format!("{{{{{}}}}}", inner),
+                value: format!("{{{{{}}}}}", inner).into(),
             },
         };
     }
@@ -700,9 +983,11 @@ fn parse_template(
             if !rest_trim.is_empty() {
                 // abs offset of `rest_trim` within the original `inner`.
                 let part0 = &inner[parts[0].0..parts[0].1];
-                let rel = part0.find(rest_trim).unwrap_or(part0.len() - rest_trim.len());
+                let rel = part0
+                    .find(rest_trim)
+                    .unwrap_or(part0.len() - rest_trim.len());
                 let abs = abs_inner_start + parts[0].0 + rel;
-                let nodes = parse_inlines(full_src, abs, rest_trim, diagnostics);
+                let nodes = parse_inlines(full_src, abs, rest_trim, cx);
                 params.push(TemplateParam {
                     span: Span::new(abs as u64, (abs + rest_trim.len()) as u64),
                     name: None,
@@ -716,6 +1001,13 @@ fn parse_template(
         let raw = &inner[seg.0..seg.1];
         let trimmed = raw.trim();
         if trimmed.is_empty() {
+            // empty positional params still count towards numbering (`{{a||c}}` sets 1 and 3).
+            let raw_abs = abs_inner_start + seg.0;
+            params.push(TemplateParam {
+                span: Span::new(raw_abs as u64, (abs_inner_start + seg.1) as u64),
+                name: None,
+                value: Vec::new(),
+            });
             continue;
         }
 
@@ -728,7 +1020,7 @@ fn parse_template(
             let raw_abs = abs_inner_start + seg.0;
             let v_rel = raw.find(v_trim).unwrap_or(raw.len() - v_trim.len());
             let v_abs = raw_abs + v_rel;
-            let value_nodes = parse_inlines(full_src, v_abs, v_trim, diagnostics);
+            let value_nodes = parse_inlines(full_src, v_abs, v_trim, cx);
             params.push(TemplateParam {
                 span: Span::new(raw_abs as u64, (abs_inner_start + seg.1) as u64),
                 name: Some(n_trim.to_string()),
@@ -738,7 +1030,7 @@ fn parse_template(
             let raw_abs = abs_inner_start + seg.0;
             let lead = raw.len() - raw.trim_start().len();
             let v_abs = raw_abs + lead;
-            let value_nodes = parse_inlines(full_src, v_abs, trimmed, diagnostics);
+            let value_nodes = parse_inlines(full_src, v_abs, trimmed, cx);
             params.push(TemplateParam {
                 span: Span::new(raw_abs as u64, (abs_inner_start + seg.1) as u64),
                 name: None,
@@ -763,22 +1055,38 @@ fn parse_template(
 
 fn find_matching_braces(s: &str) -> Option<usize> {
     // `s` starts with "{{".
-    let mut depth = 0usize;
-    let mut i = 0usize;
+    match_braces(s, 2)
+}
+
+/// Find the end of a `{{{...}}}` parameter placeholder starting at `s[0..]`,
+/// including the closing `}}}`.
+fn find_matching_template_arg(s: &str) -> Option<usize> {
+    match_braces(s, 3)
+}
+
+/// Match the `open`-brace run at the start of `s` (2 for a template, 3 for a
+/// placeholder). Nested placeholders need `}}}` to close, so `{{A|{{{1}}}}}` ends
+/// after the last brace rather than one short.
+fn match_braces(s: &str, open: usize) -> Option<usize> {
+    let mut stack: Vec<usize> = vec![open];
+    let mut i = open;
     while i < s.len() {
         let rem = &s[i..];
         if rem.starts_with("{{") {
-            depth += 1;
-            i += 2;
+            let n = if is_template_arg_open(rem) { 3 } else { 2 };
+            stack.push(n);
+            i += n;
             continue;
         }
         if rem.starts_with("}}") {
-            if depth == 0 {
-                return None;
-            }
-            depth -= 1;
-            i += 2;
-            if depth == 0 {
+            let n = match stack.pop() {
+                Some(3) if rem.starts_with("}}}") => 3,
+                // a lone `}}` can't close a placeholder.
+                Some(3) if stack.is_empty() => return None,
+                _ => 2,
+            };
+            i += n;
+            if stack.is_empty() {
                 return Some(i);
             }
             continue;
@@ -789,6 +1097,11 @@ fn find_matching_braces(s: &str) -> Option<usize> {
     None
 }
 
+/// `{{{` not followed by another `{` (`{{{{` is a template inside a template).
+fn is_template_arg_open(s: &str) -> bool {
+    s.starts_with("{{{") && !s.starts_with("{{{{")
+}
+
 /// Find the end (in bytes) of a balanced `[[...]]` sequence starting at `s[0..]`.
 ///
 /// This is more robust than a naive `find("]]" )` because MediaWiki file links
@@ -875,19 +1188,21 @@ pub fn split_top_level(s: &str, delim: char) -> Vec<(usize, usize)> {
             i += 2;
             continue;
         }
-        if rem.starts_with("]]" ) {
+        if rem.starts_with("]]") {
             link_depth = link_depth.saturating_sub(1);
             i += 2;
             continue;
         }
-        if tpl_depth == 0 && link_depth == 0
+        if tpl_depth == 0
+            && link_depth == 0
             && let Some(ch) = rem.chars().next()
-                && ch == delim {
-                    out.push((seg_start, i));
-                    i += ch.len_utf8();
-                    seg_start = i;
-                    continue;
-                }
+            && ch == delim
+        {
+            out.push((seg_start, i));
+            i += ch.len_utf8();
+            seg_start = i;
+            continue;
+        }
         let ch_len = rem.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
         i += ch_len;
     }
@@ -917,16 +1232,18 @@ fn find_top_level_eq(s: &str) -> Option<usize> {
             i += 2;
             continue;
         }
-        if rem.starts_with("]]" ) {
+        if rem.starts_with("]]") {
             link_depth = link_depth.saturating_sub(1);
             i += 2;
             continue;
         }
-        if tpl_depth == 0 && link_depth == 0
+        if tpl_depth == 0
+            && link_depth == 0
             && let Some(ch) = rem.chars().next()
-                && ch == '=' {
-                    return Some(i);
-                }
+            && ch == '='
+        {
+            return Some(i);
+        }
         let ch_len = rem.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
         i += ch_len;
     }
@@ -0,0 +1,296 @@
+//! Progress reporting for bulk regeneration.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How much bulk regeneration reports on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Errors only.
+    Quiet,
+    /// Progress and a summary.
+    #[default]
+    Normal,
+    /// Progress, a summary, and per-file parse/render timing.
+    Verbose,
+}
+
+/// How per-file progress is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// One `[  12/1157] [00:00.031] Regenerated: ...` line per file (log-friendly).
+    #[default]
+    Lines,
+    /// A single progress bar redrawn in place. Verbose per-file lines print above it.
+    Bar,
+}
+
+/// Time spent on one file, per phase.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FileTiming {
+    pub read: Duration,
+    pub parse: Duration,
+    pub render: Duration,
+    /// Building (or keeping) the frontmatter and assembling the file.
+    pub frontmatter: Duration,
+    /// Reading the existing `.md` file, backing it up, and writing the new one.
+    pub write: Duration,
+}
+
+impl FileTiming {
+    const PHASES: [&'static str; 5] = ["read", "parse", "render", "frontmatter", "write"];
+
+    fn phases(&self) -> [Duration; 5] {
+        [
+            self.read,
+            self.parse,
+            self.render,
+            self.frontmatter,
+            self.write,
+        ]
+    }
+
+    pub(crate) fn total(&self) -> Duration {
+        self.phases().iter().sum()
+    }
+}
+
+/// Per-phase totals and the slowest files of a bulk run.
+#[derive(Debug, Default)]
+pub(crate) struct TimingReport {
+    files: Vec<(PathBuf, FileTiming)>,
+}
+
+impl TimingReport {
+    pub(crate) fn add(&mut self, md_path: &Path, timing: FileTiming) {
+        self.files.push((md_path.to_path_buf(), timing));
+    }
+
+    /// The report, listing the `top` slowest files.
+    pub(crate) fn to_text(&self, top: usize) -> String {
+        let mut totals = [Duration::ZERO; 5];
+        for (_, timing) in &self.files {
+            for (total, phase) in totals.iter_mut().zip(timing.phases()) {
+                *total += phase;
+            }
+        }
+        let sum: Duration = totals.iter().sum();
+        let mut out = format!("Time per phase ({} files):\n", self.files.len());
+        for (name, total) in FileTiming::PHASES.iter().zip(totals) {
+            let share = if sum.is_zero() {
+                0.0
+            } else {
+                total.as_secs_f64() / sum.as_secs_f64() * 100.0
+            };
+            out.push_str(&format!(
+                "  {:<12} {:>9.3}s {:>5.1}%\n",
+                name,
+                total.as_secs_f64(),
+                share
+            ));
+        }
+
+        let mut slowest: Vec<&(PathBuf, FileTiming)> = self.files.iter().collect();
+        slowest.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.total()));
+        out.push_str("Slowest files:\n");
+        for (path, timing) in slowest.into_iter().take(top) {
+            let phases: Vec<String> = FileTiming::PHASES
+                .iter()
+                .zip(timing.phases())
+                .map(|(name, d)| format!("{} {:.1}ms", name, ms(d)))
+                .collect();
+            out.push_str(&format!(
+                "  {:>9.1}ms  {} ({})\n",
+                ms(timing.total()),
+                path.display(),
+                phases.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+pub(crate) struct Progress {
+    verbosity: Verbosity,
+    bar: Option<ProgressBar>,
+    total: usize,
+    start: Instant,
+}
+
+impl Progress {
+    pub(crate) fn new(total: usize, verbosity: Verbosity, mode: ProgressMode) -> Self {
+        let bar = (mode == ProgressMode::Bar && verbosity != Verbosity::Quiet).then(|| {
+            let bar =
+                ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr());
+            bar.set_style(
+                ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            bar
+        });
+        Progress {
+            verbosity,
+            bar,
+            total,
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Report file number `n` (1-based) as done.
+    pub(crate) fn file_done(&self, n: usize, verb: &str, md_path: &Path, timing: FileTiming) {
+        if self.verbosity == Verbosity::Quiet {
+            return;
+        }
+        let mut line = format!(
+            "[{:>4}/{:>4}] [{}] {}: {:?}",
+            n,
+            self.total,
+            format_elapsed(self.elapsed()),
+            verb,
+            md_path
+        );
+        if self.verbosity == Verbosity::Verbose {
+            line.push_str(&format!(
+                " (parse {:.1}ms, render {:.1}ms)",
+                ms(timing.parse),
+                ms(timing.render)
+            ));
+        }
+        match &self.bar {
+            Some(bar) => {
+                if self.verbosity == Verbosity::Verbose {
+                    bar.println(line);
+                }
+                bar.set_position(n as u64);
+                bar.set_message(
+                    md_path
+                        .file_name()
+                        .map(|f| f.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            None => eprintln!("{}", line),
+        }
+    }
+
+    /// Report file number `n` as failed (printed even when quiet).
+    pub(crate) fn file_failed(&self, n: usize, wiki_path: &Path, stage: &str, error: &str) {
+        let line = format!(
+            "[{:>4}/{:>4}] [{}] Failed ({}): {:?}: {}",
+            n,
+            self.total,
+            format_elapsed(self.elapsed()),
+            stage,
+            wiki_path,
+            error
+        );
+        self.error(&line);
+        if let Some(bar) = &self.bar {
+            bar.set_position(n as u64);
+        }
+    }
+
+    /// Advance past a file without reporting it.
+    pub(crate) fn file_skipped(&self, n: usize) {
+        if let Some(bar) = &self.bar {
+            bar.set_position(n as u64);
+        }
+    }
+
+    /// Report `n` of the `total` items as done: `msg` as a line of its own, or the
+    /// bar moved along (suppressed when quiet).
+    pub(crate) fn advance(&self, n: usize, msg: &str) {
+        match &self.bar {
+            Some(bar) => bar.set_position(n as u64),
+            None => self.summary(msg),
+        }
+    }
+
+    /// Print a summary line (suppressed when quiet).
+    pub(crate) fn summary(&self, msg: &str) {
+        if self.verbosity == Verbosity::Quiet {
+            return;
+        }
+        match &self.bar {
+            Some(bar) => bar.println(msg),
+            None => eprintln!("{}", msg),
+        }
+    }
+
+    /// Print an error message regardless of verbosity.
+    pub(crate) fn error(&self, msg: &str) {
+        match &self.bar {
+            Some(bar) => bar.println(msg),
+            None => eprintln!("{}", msg),
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// `mm:ss.mmm`
+fn format_elapsed(elapsed: Duration) -> String {
+    let total_ms = elapsed.as_millis();
+    let mins = total_ms / 60_000;
+    let secs = (total_ms % 60_000) / 1_000;
+    let ms = total_ms % 1_000;
+    format!("{:02}:{:02}.{:03}", mins, secs, ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(read: u64, parse: u64, render: u64) -> FileTiming {
+        FileTiming {
+            read: Duration::from_millis(read),
+            parse: Duration::from_millis(parse),
+            render: Duration::from_millis(render),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timing_report_totals_phases_and_lists_the_slowest_files() {
+        let mut report = TimingReport::default();
+        report.add(Path::new("a/Alpha.md"), timing(100, 200, 200));
+        report.add(Path::new("b/Beta.md"), timing(0, 1500, 0));
+        report.add(Path::new("c/Gamma.md"), timing(0, 300, 700));
+
+        assert_eq!(
+            report.to_text(2),
+            "Time per phase (3 files):\n\
+             \x20 read             0.100s   3.3%\n\
+             \x20 parse            2.000s  66.7%\n\
+             \x20 render           0.900s  30.0%\n\
+             \x20 frontmatter      0.000s   0.0%\n\
+             \x20 write            0.000s   0.0%\n\
+             Slowest files:\n\
+             \x20    1500.0ms  b/Beta.md (read 0.0ms, parse 1500.0ms, render 0.0ms, frontmatter 0.0ms, write 0.0ms)\n\
+             \x20    1000.0ms  c/Gamma.md (read 0.0ms, parse 300.0ms, render 700.0ms, frontmatter 0.0ms, write 0.0ms)\n"
+        );
+    }
+
+    #[test]
+    fn timing_report_without_time_has_no_shares() {
+        let mut report = TimingReport::default();
+        report.add(Path::new("a/Alpha.md"), FileTiming::default());
+        let text = report.to_text(0);
+        assert!(text.starts_with("Time per phase (1 files):\n"), "{text}");
+        assert_eq!(text.matches("0.000s   0.0%").count(), 5, "{text}");
+        assert!(text.ends_with("Slowest files:\n"), "{text}");
+    }
+}
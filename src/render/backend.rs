@@ -0,0 +1,352 @@
+//! Markup backend trait.
+//!
+//! Every block and inline construct is emitted through a [`MarkupBackend`]. The
+//! default methods produce Obsidian-flavored Markdown, so an implementation only
+//! needs to override the constructs it wants to change. Overrides can still
+//! delegate to the stock emitters (`render_table`, `render_heading`, ...) and
+//! recurse through `render_block` / `render_inlines`, which dispatch back into
+//! the backend configured in [`RenderOptions::backend`].
+
+use super::*;
+
+/// Emits markup for individual AST constructs.
+///
+/// `ctx` carries per-document state (collected footnotes) and `opts` is the
+/// options the document is being rendered with.
+pub trait MarkupBackend: std::fmt::Debug + Send + Sync {
+    /// Dispatch a block to the construct-specific method.
+    fn block(&self, block: &BlockNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        match &block.kind {
+            BlockKind::Heading { level, content } => self.heading(*level, content, ctx, opts),
+            BlockKind::Paragraph { content } => self.paragraph(content, ctx, opts),
+            BlockKind::List { items } => self.list(items, 0, ctx, opts),
+            BlockKind::CodeBlock { block } => self.code_block(block, ctx, opts),
+            BlockKind::Poem { lines } => self.poem(lines, ctx, opts),
+            BlockKind::Timeline { timeline } => self.timeline(timeline, ctx, opts),
+            BlockKind::Table { table } => self.table(table, ctx, opts),
+            BlockKind::BlockQuote {
+                blocks,
+                attribution,
+                html,
+            } => self.block_quote(blocks, attribution.as_ref(), *html, ctx, opts),
+            BlockKind::HorizontalRule => self.horizontal_rule(ctx, opts),
+            // most documents render references via `render_doc_with_options` so that
+            // we can decide whether to emit a heading based on the surrounding context.
+            BlockKind::References { .. } => self.references(true, ctx, opts),
+            BlockKind::HtmlBlock { node } => self.html_block(node, ctx, opts),
+            BlockKind::MagicWord { name } => self.magic_word(name, ctx, opts),
+            BlockKind::Inclusion { mode, blocks } => self.inclusion(*mode, blocks, ctx, opts),
+            BlockKind::Raw { text } => self.raw_block(text, ctx, opts),
+        }
+    }
+
+    fn heading(
+        &self,
+        level: u8,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_heading(level, content, ctx, opts)
+    }
+
+    fn paragraph(
+        &self,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_paragraph(content, ctx, opts)
+    }
+
+    /// `indent` is the number of spaces nested list items are indented by.
+    fn list(
+        &self,
+        items: &[ListItem],
+        indent: usize,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_list(items, ctx, opts, indent)
+    }
+
+    fn code_block(
+        &self,
+        block: &CodeBlock,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_code_block(block.kind, block.lang.as_deref(), &block.text, ctx, opts)
+    }
+
+    fn poem(
+        &self,
+        lines: &[Vec<InlineNode>],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_poem(lines, ctx, opts)
+    }
+
+    fn timeline(
+        &self,
+        timeline: &Timeline,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_timeline(timeline, ctx, opts)
+    }
+
+    fn table(&self, table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        render_table(table, ctx, opts)
+    }
+
+    /// `html` is true for `<blockquote>` tags, false for leading-space quotes.
+    fn block_quote(
+        &self,
+        blocks: &[BlockNode],
+        attribution: Option<&QuoteAttribution>,
+        html: bool,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_block_quote(blocks, attribution, html, ctx, opts)
+    }
+
+    fn horizontal_rule(&self, _ctx: &mut RenderContext, _opts: &RenderOptions) -> String {
+        "---".to_string()
+    }
+
+    /// Emit the footnotes collected so far in `ctx`.
+    fn references(
+        &self,
+        emit_heading: bool,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_references(ctx, opts, emit_heading)
+    }
+
+    fn html_block(
+        &self,
+        node: &HtmlBlock,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_html_block(node, ctx, opts)
+    }
+
+    fn magic_word(&self, name: &str, _ctx: &mut RenderContext, _opts: &RenderOptions) -> String {
+        format!("<!-- {} -->", name)
+    }
+
+    /// A `<noinclude>`/`<includeonly>`/`<onlyinclude>` section, as shown on the page.
+    fn inclusion(
+        &self,
+        mode: InclusionMode,
+        blocks: &[BlockNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_inclusion(mode, blocks, ctx, opts)
+    }
+
+    fn raw_block(&self, text: &str, _ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        let text = text.trim_end_matches('\n');
+        match opts.raw_blocks {
+            // keep raw blocks visible but non-destructive.
+            RawBlockPolicy::Fenced => format!("```text\n{}\n```", text),
+            RawBlockPolicy::Comment => format!("<!-- {} -->", text.replace("-->", "--&gt;")),
+            RawBlockPolicy::Passthrough => text.to_string(),
+            RawBlockPolicy::Drop => String::new(),
+        }
+    }
+
+    /// Dispatch an inline node to the construct-specific method.
+    fn inline(&self, node: &InlineNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        match &node.kind {
+            InlineKind::Text { value } => self.text(value, ctx, opts),
+            InlineKind::Bold { content } => self.bold(content, ctx, opts),
+            InlineKind::Italic { content } => self.italic(content, ctx, opts),
+            InlineKind::BoldItalic { content } => self.bold_italic(content, ctx, opts),
+            InlineKind::LineBreak => self.line_break(ctx, opts),
+            InlineKind::InternalLink { link } => self.internal_link(link, ctx, opts),
+            InlineKind::ExternalLink { link } => self.external_link(link, ctx, opts),
+            InlineKind::FileLink { link } => self.file_link(link, ctx, opts),
+            InlineKind::MagicLink { link } => self.magic_link(link, ctx, opts),
+            InlineKind::Template { node } => self.template(node, ctx, opts),
+            InlineKind::TemplateArg { name, default } => {
+                self.template_arg(name, default.as_deref(), ctx, opts)
+            }
+            InlineKind::Ref { node } => self.footnote_ref(node, ctx, opts),
+            InlineKind::HtmlTag { node } => self.html_tag(node, ctx, opts),
+            InlineKind::Inclusion { mode, content } => {
+                self.inline_inclusion(*mode, content, ctx, opts)
+            }
+            InlineKind::Raw { text } => self.raw_inline(text, ctx, opts),
+        }
+    }
+
+    fn text(&self, value: &str, _ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        let value = match opts.chess_symbols {
+            true => chess::decode_chess_entities(value),
+            false => value.into(),
+        };
+        // normalize raw newlines into spaces for Markdown paragraphs.
+        value.replace(['\r', '\n'], " ")
+    }
+
+    fn bold(
+        &self,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        format!("**{}**", render_inlines(content, ctx, opts))
+    }
+
+    fn italic(
+        &self,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        format!("*{}*", render_inlines(content, ctx, opts))
+    }
+
+    fn bold_italic(
+        &self,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        format!("***{}***", render_inlines(content, ctx, opts))
+    }
+
+    fn line_break(&self, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        // table rows cannot span lines, so cells always use the HTML form.
+        let style = if ctx.in_table_cell() {
+            HardBreakStyle::Html
+        } else {
+            opts.hard_break_style
+        };
+        match style {
+            // emit a real newline after the HTML break so that Markdown renderers (e.g., Obsidian)
+            // don't treat the following text as part of the same visual line.
+            HardBreakStyle::Html => "<br/>\n".to_string(),
+            HardBreakStyle::Backslash => "\\\n".to_string(),
+            HardBreakStyle::TrailingSpaces => "  \n".to_string(),
+        }
+    }
+
+    fn internal_link(
+        &self,
+        link: &InternalLink,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_internal_link(link, ctx, opts)
+    }
+
+    fn external_link(
+        &self,
+        link: &ExternalLink,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_external_link(link, ctx, opts)
+    }
+
+    fn file_link(&self, link: &FileLink, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        render_file_link(link, ctx, opts)
+    }
+
+    fn magic_link(
+        &self,
+        link: &MagicLink,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_magic_link(link, ctx, opts)
+    }
+
+    fn template(
+        &self,
+        inv: &TemplateInvocation,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        render_template(inv, ctx, opts)
+    }
+
+    /// An unsubstituted `{{{name|default}}}` placeholder, kept as written.
+    fn template_arg(
+        &self,
+        name: &str,
+        default: Option<&[InlineNode]>,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        match default {
+            Some(default) => format!(
+                "{{{{{{{}|{}}}}}}}",
+                name,
+                render_inlines(default, ctx, opts)
+            ),
+            None => format!("{{{{{{{}}}}}}}", name),
+        }
+    }
+
+    /// Emit a footnote marker; the footnote body is collected into `ctx`.
+    fn footnote_ref(
+        &self,
+        node: &RefNode,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        let content = match (&node.content, node.name()) {
+            (Some(c), _) => render_inlines(c, ctx, opts),
+            // `<ref name=a />` whose body is defined in `<references>`.
+            (None, Some(name)) => match ctx.ref_definition(name).map(<[_]>::to_vec) {
+                Some(c) => render_inlines(&c, ctx, opts),
+                None => String::new(),
+            },
+            (None, None) => String::new(),
+        };
+        if opts.footnote_placement == FootnotePlacement::Inline {
+            return format!("^[{}]", content.trim());
+        }
+        let n = match node.name() {
+            Some(name) => ctx.push_named_ref(name, content),
+            None => ctx.push_ref(content),
+        };
+        format!("[^{}]", n)
+    }
+
+    fn html_tag(&self, tag: &HtmlTag, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+        render_html_tag(tag, ctx, opts)
+    }
+
+    fn inline_inclusion(
+        &self,
+        mode: InclusionMode,
+        content: &[InlineNode],
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> String {
+        match mode.shown_on_page() {
+            true => render_inlines(content, ctx, opts),
+            false => String::new(),
+        }
+    }
+
+    fn raw_inline(&self, text: &str, _ctx: &mut RenderContext, _opts: &RenderOptions) -> String {
+        text.to_string()
+    }
+}
+
+/// The default backend: Obsidian-flavored Markdown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ObsidianMarkdown;
+
+impl MarkupBackend for ObsidianMarkdown {}
@@ -0,0 +1,328 @@
+//! Chess diagram templates.
+//!
+//! Two template families are understood:
+//!
+//! - FEN templates (`{{FEN|<fen>}}`, `{{ChessBoard|fen=<fen>|caption=...}}`).
+//! - Wikipedia-style square lists: `{{Chess diagram|align|header|<64 squares>|footer}}`,
+//!   where each square is empty or a piece letter plus `l`/`d` for white/black
+//!   (`kl` = white king, `rd` = black rook), listed from a8 to h1.
+//!
+//! The board is emitted as an ASCII or Unicode diagram in a fenced block, or as
+//! an image URL, depending on [`RenderOptions::chess_diagram_style`].
+
+use super::*;
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+/// How chess diagram templates are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChessDiagramStyle {
+    /// A fenced text block with piece letters (`K`, `q`, ...) and `.` for empty squares.
+    #[default]
+    Ascii,
+    /// A fenced text block with Unicode chess symbols (`♔`, `♛`, ...).
+    Unicode,
+    /// A Markdown image built from [`RenderOptions::chess_diagram_image_url`].
+    Image,
+}
+
+pub(crate) const DEFAULT_DIAGRAM_IMAGE_URL: &str = "https://lichess1.org/export/fen.gif?fen={fen}";
+
+/// Board squares from a8 to h1 (rank 8 first); pieces use FEN letters.
+struct Board {
+    squares: [[Option<char>; 8]; 8],
+}
+
+impl Board {
+    fn from_fen(fen: &str) -> Option<Board> {
+        let placement = fen.split_whitespace().next()?;
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return None;
+        }
+        let mut squares = [[None; 8]; 8];
+        for (r, rank) in ranks.iter().enumerate() {
+            let mut file = 0usize;
+            for c in rank.chars() {
+                if let Some(n) = c.to_digit(10) {
+                    file += n as usize;
+                } else if "KQRBNPkqrbnp".contains(c) {
+                    if file >= 8 {
+                        return None;
+                    }
+                    squares[r][file] = Some(c);
+                    file += 1;
+                } else {
+                    return None;
+                }
+            }
+            if file != 8 {
+                return None;
+            }
+        }
+        Some(Board { squares })
+    }
+
+    fn from_square_list(tokens: &[String]) -> Option<Board> {
+        if tokens.len() != 64 {
+            return None;
+        }
+        let mut squares = [[None; 8]; 8];
+        for (i, t) in tokens.iter().enumerate() {
+            let t = t.trim();
+            let mut chars = t.chars();
+            let (Some(piece), Some(color)) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            let piece = piece.to_ascii_lowercase();
+            if !"kqrbnp".contains(piece) {
+                continue;
+            }
+            squares[i / 8][i % 8] = match color {
+                'l' => Some(piece.to_ascii_uppercase()),
+                'd' => Some(piece),
+                _ => None,
+            };
+        }
+        Some(Board { squares })
+    }
+
+    fn placement(&self) -> String {
+        let mut out = String::new();
+        for (r, rank) in self.squares.iter().enumerate() {
+            if r > 0 {
+                out.push('/');
+            }
+            let mut empty = 0;
+            for sq in rank {
+                match sq {
+                    Some(c) => {
+                        if empty > 0 {
+                            out.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        out.push(*c);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+            }
+        }
+        out
+    }
+
+    fn text_diagram(&self, unicode: bool) -> String {
+        let mut out = String::from("  +-----------------+\n");
+        for (r, rank) in self.squares.iter().enumerate() {
+            out.push_str(&format!("{} |", 8 - r));
+            for sq in rank {
+                out.push(' ');
+                out.push(match (sq, unicode) {
+                    (Some(c), true) => unicode_piece(*c),
+                    (Some(c), false) => *c,
+                    (None, true) => '·',
+                    (None, false) => '.',
+                });
+            }
+            out.push_str(" |\n");
+        }
+        out.push_str("  +-----------------+\n    a b c d e f g h");
+        out
+    }
+}
+
+fn unicode_piece(c: char) -> char {
+    match c {
+        'K' => '♔',
+        'Q' => '♕',
+        'R' => '♖',
+        'B' => '♗',
+        'N' => '♘',
+        'P' => '♙',
+        'k' => '♚',
+        'q' => '♛',
+        'r' => '♜',
+        'b' => '♝',
+        'n' => '♞',
+        _ => '♟',
+    }
+}
+
+fn param_text(p: &TemplateParam) -> String {
+    inlines_plain_text(&p.value).unwrap_or_default()
+}
+
+fn named_param<'a>(inv: &'a TemplateInvocation, name: &str) -> Option<&'a TemplateParam<'a>> {
+    inv.params.iter().find(|p| {
+        p.name
+            .as_deref()
+            .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// `{{FEN|<fen>}}`, `{{ChessBoard|fen=<fen>|caption=...}}`
+pub(crate) fn fen_template(
+    inv: &TemplateInvocation,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> Option<String> {
+    let fen_param =
+        named_param(inv, "fen").or_else(|| inv.params.iter().find(|p| p.name.is_none()))?;
+    let fen = param_text(fen_param).trim().to_string();
+    let board = Board::from_fen(&fen)?;
+    let caption = named_param(inv, "caption")
+        .map(|p| render_inlines(&p.value, ctx, opts).trim().to_string())
+        .unwrap_or_default();
+    Some(render_board(&board, Some(&fen), &caption, ctx, opts))
+}
+
+/// `{{Chess diagram|align|header|<64 squares>|footer}}`
+pub(crate) fn square_list_template(
+    inv: &TemplateInvocation,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> Option<String> {
+    let positional: Vec<&TemplateParam> = inv.params.iter().filter(|p| p.name.is_none()).collect();
+    if positional.len() < 66 {
+        return None;
+    }
+    let tokens: Vec<String> = positional[2..66].iter().map(|p| param_text(p)).collect();
+    let board = Board::from_square_list(&tokens)?;
+
+    let mut caption = render_inlines(&positional[1].value, ctx, opts)
+        .trim()
+        .to_string();
+    if let Some(footer) = positional.get(66) {
+        let footer = render_inlines(&footer.value, ctx, opts).trim().to_string();
+        if !footer.is_empty() {
+            if !caption.is_empty() {
+                caption.push_str(" — ");
+            }
+            caption.push_str(&footer);
+        }
+    }
+    Some(render_board(&board, None, &caption, ctx, opts))
+}
+
+fn render_board(
+    board: &Board,
+    fen: Option<&str>,
+    caption: &str,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let fen = match fen {
+        Some(f) if f.split_whitespace().count() > 1 => f.to_string(),
+        _ => format!("{} w - - 0 1", board.placement()),
+    };
+
+    let style = match opts.chess_diagram_style {
+        // a fenced block can't live inside a table row.
+        ChessDiagramStyle::Ascii | ChessDiagramStyle::Unicode if ctx.in_table_cell() => {
+            return format!("`{}`", fen);
+        }
+        s => s,
+    };
+
+    let body = match style {
+        ChessDiagramStyle::Image => {
+            let url = opts
+                .chess_diagram_image_url
+                .replace("{fen}", &encode_fen(&fen));
+            let alt = if caption.is_empty() { &fen } else { caption };
+            return format!("![{}]({})", alt, url);
+        }
+        ChessDiagramStyle::Ascii => board.text_diagram(false),
+        ChessDiagramStyle::Unicode => board.text_diagram(true),
+    };
+
+    let mut out = format!("```text\n{}\n```", body);
+    if !caption.is_empty() {
+        out.push_str(&format!("\n*{}*", caption));
+    }
+    out
+}
+
+/// The symbol shown by a Wikimedia piece image: `Chess_klt45.svg` is a white
+/// (`l`) king, `Chess_ndt26.png` a black (`d`) knight.
+pub(crate) fn piece_image_symbol(target: &str) -> Option<char> {
+    static PIECE_IMAGE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?i)^chess[_ ]([kqrbnp])([ld])[a-z]?\d*\.(?:svg|png|gif)$").unwrap()
+    });
+    let caps = PIECE_IMAGE.captures(target.trim())?;
+    let piece = caps[1].chars().next()?.to_ascii_lowercase();
+    let white = caps[2].eq_ignore_ascii_case("l");
+    Some(unicode_piece(if white {
+        piece.to_ascii_uppercase()
+    } else {
+        piece
+    }))
+}
+
+/// `text` with numeric entities for chess symbols (`&#9812;`, `&#x265E;`) as the
+/// characters themselves.
+pub(crate) fn decode_chess_entities(text: &str) -> Cow<'_, str> {
+    static CHESS_ENTITY: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"&#(?:(98(?:1[2-9]|2[0-3]))|[xX](265[4-9a-fA-F]));").unwrap());
+    if !text.contains("&#") {
+        return Cow::Borrowed(text);
+    }
+    CHESS_ENTITY.replace_all(text, |caps: &regex::Captures| {
+        let code = match (caps.get(1), caps.get(2)) {
+            (Some(dec), _) => dec.as_str().parse().ok(),
+            (_, Some(hex)) => u32::from_str_radix(hex.as_str(), 16).ok(),
+            _ => None,
+        };
+        code.and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+}
+
+fn encode_fen(fen: &str) -> String {
+    let mut out = String::with_capacity(fen.len());
+    for b in fen.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_round_trips_through_board() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR";
+        assert_eq!(Board::from_fen(fen).unwrap().placement(), fen);
+        assert!(Board::from_fen("8/8/8").is_none());
+        assert!(Board::from_fen("9/8/8/8/8/8/8/8").is_none());
+    }
+
+    #[test]
+    fn square_list_uses_light_and_dark_suffixes() {
+        let mut tokens = vec![String::new(); 64];
+        tokens[4] = "kd".to_string(); // e8
+        tokens[60] = "kl".to_string(); // e1
+        tokens[52] = "pl".to_string(); // e2
+        let board = Board::from_square_list(&tokens).unwrap();
+        assert_eq!(board.placement(), "4k3/8/8/8/8/8/4P3/4K3");
+    }
+
+    #[test]
+    fn piece_images_and_entities_become_symbols() {
+        assert_eq!(piece_image_symbol("Chess_klt45.svg"), Some('♔'));
+        assert_eq!(piece_image_symbol("Chess ndt26.png"), Some('♞'));
+        assert_eq!(piece_image_symbol("Chess_board.svg"), None);
+        assert_eq!(
+            decode_chess_entities("1.e4 &#9823; &#x2658;f3 &#9824; &amp;"),
+            "1.e4 ♟ ♘f3 &#9824; &amp;"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Whitespace normalization for code blocks.
+
+/// How code block text is normalized before it is fenced. All steps are off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CodeNormalization {
+    /// Strip the leading whitespace shared by all non-blank lines.
+    pub dedent: bool,
+
+    /// Expand tabs to the next multiple of this many columns (applied before dedenting).
+    pub tab_width: Option<usize>,
+
+    /// Trim trailing whitespace from every line.
+    pub trim_trailing_whitespace: bool,
+}
+
+impl CodeNormalization {
+    /// Dedent, expand tabs to 4 columns, and trim trailing whitespace.
+    pub fn all() -> Self {
+        Self {
+            dedent: true,
+            tab_width: Some(4),
+            trim_trailing_whitespace: true,
+        }
+    }
+
+    fn is_noop(&self) -> bool {
+        !self.dedent && self.tab_width.is_none() && !self.trim_trailing_whitespace
+    }
+
+    pub(crate) fn apply(&self, text: &str) -> String {
+        if self.is_noop() {
+            return text.to_string();
+        }
+
+        let mut lines: Vec<String> = text
+            .split('\n')
+            .map(|l| {
+                let l = l.strip_suffix('\r').unwrap_or(l);
+                match self.tab_width {
+                    Some(w) if w > 0 => expand_tabs(l, w),
+                    _ => l.to_string(),
+                }
+            })
+            .collect();
+
+        if self.trim_trailing_whitespace {
+            for l in &mut lines {
+                let trimmed = l.trim_end().len();
+                l.truncate(trimmed);
+            }
+        }
+
+        if self.dedent {
+            let indent = common_indent(&lines);
+            if !indent.is_empty() {
+                for l in &mut lines {
+                    if l.starts_with(&indent) {
+                        l.drain(..indent.len());
+                    } else {
+                        // blank (whitespace-only) lines shorter than the indent.
+                        l.clear();
+                    }
+                }
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+fn expand_tabs(line: &str, width: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut col = 0usize;
+    for c in line.chars() {
+        if c == '\t' {
+            let n = width - (col % width);
+            out.extend(std::iter::repeat_n(' ', n));
+            col += n;
+        } else {
+            out.push(c);
+            col += 1;
+        }
+    }
+    out
+}
+
+/// Longest whitespace prefix shared by every non-blank line.
+fn common_indent(lines: &[String]) -> String {
+    let mut common: Option<&str> = None;
+    for l in lines.iter().filter(|l| !l.trim().is_empty()) {
+        let ws = &l[..l.len() - l.trim_start().len()];
+        common = Some(match common {
+            None => ws,
+            Some(c) => {
+                let n = c
+                    .char_indices()
+                    .zip(ws.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map(|((i, a), _)| i + a.len_utf8())
+                    .unwrap_or(0);
+                &c[..n]
+            }
+        });
+    }
+    common.unwrap_or("").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedents_expands_tabs_and_trims() {
+        let text = "    int x;  \n\n\tif (x) {\n\t    y();\n    }";
+        assert_eq!(
+            CodeNormalization::all().apply(text),
+            "int x;\n\nif (x) {\n    y();\n}"
+        );
+    }
+
+    #[test]
+    fn mixed_indent_without_tab_expansion_keeps_only_the_shared_prefix() {
+        let norm = CodeNormalization {
+            dedent: true,
+            ..Default::default()
+        };
+        assert_eq!(norm.apply("  \ta\n  b"), "\ta\nb");
+        assert_eq!(CodeNormalization::default().apply(" a \n b"), " a \n b");
+    }
+}
@@ -0,0 +1,150 @@
+//! Code block language tags.
+//!
+//! MediaWiki's `<syntaxhighlight lang="...">` accepts Pygments names and aliases
+//! (`c++`, `csharp`, `pas`) that Markdown highlighters often don't recognize.
+//! Languages are mapped through [`RenderOptions::code_lang_aliases`]; `<pre>`
+//! blocks without a language can optionally get one inferred from their content.
+
+use std::collections::HashMap;
+
+/// The default alias table (keys are lowercase).
+pub fn default_code_lang_aliases() -> HashMap<String, String> {
+    [
+        ("c++", "cpp"),
+        ("cxx", "cpp"),
+        ("cplusplus", "cpp"),
+        ("csharp", "cs"),
+        ("c#", "cs"),
+        ("pas", "pascal"),
+        ("delphi", "pascal"),
+        ("objectpascal", "pascal"),
+        ("asm", "nasm"),
+        ("masm", "nasm"),
+        ("x86asm", "nasm"),
+        ("py", "python"),
+        ("python3", "python"),
+        ("js", "javascript"),
+        ("sh", "bash"),
+        ("shell", "bash"),
+        ("vb", "vbnet"),
+        ("vb.net", "vbnet"),
+        ("f90", "fortran"),
+        ("golang", "go"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+/// Map `lang` through `aliases` (case-insensitively). Unknown languages are kept as-is.
+pub(crate) fn resolve_alias(lang: &str, aliases: &HashMap<String, String>) -> String {
+    let lang = lang.trim();
+    aliases
+        .get(&lang.to_ascii_lowercase())
+        .cloned()
+        .unwrap_or_else(|| lang.to_string())
+}
+
+/// Best-effort language guess for untagged code. Returns `None` unless the
+/// content clearly looks like one of the languages common on chessprogramming.org.
+pub(crate) fn infer_lang(text: &str) -> Option<&'static str> {
+    let has = |needle: &str| text.contains(needle);
+    let count = |needles: &[&str]| needles.iter().filter(|n| text.contains(*n)).count();
+
+    if has("using System") || has("Console.Write") {
+        return Some("cs");
+    }
+    if has("public static void") || has("System.out.") {
+        return Some("java");
+    }
+    let lower = text.to_ascii_lowercase();
+    if lower.contains(":=") && lower.contains("begin") && lower.contains("end;") {
+        return Some("pascal");
+    }
+    if (has("def ") || has("import ")) && !has(";") && has(":\n") {
+        return Some("python");
+    }
+
+    let cpp_only = count(&[
+        "std::",
+        "template<",
+        "template <",
+        "nullptr",
+        "public:",
+        "cout <<",
+        "::",
+    ]);
+    let c_like = count(&[
+        "#include",
+        "#define",
+        "return ",
+        "for (",
+        "if (",
+        "while (",
+        "int ",
+        "void ",
+        "U64",
+        "uint64_t",
+        "unsigned ",
+        "const ",
+        "struct ",
+        ");",
+        "};",
+    ]);
+    if c_like >= 2 && (has("{") || has(";")) {
+        return Some(if cpp_only > 0 { "cpp" } else { "c" });
+    }
+
+    let asm_lines = text
+        .lines()
+        .map(|l| l.trim_start().to_ascii_lowercase())
+        .filter(|l| {
+            [
+                "mov ", "xor ", "and ", "shr ", "shl ", "jmp ", "jnz ", "jz ", "lea ", "imul ",
+                "popcnt ", "bsf ", "bsr ", "ret",
+            ]
+            .iter()
+            .any(|op| l.starts_with(op))
+        })
+        .count();
+    if asm_lines >= 2 {
+        return Some("nasm");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_map_case_insensitively() {
+        let aliases = default_code_lang_aliases();
+        assert_eq!(resolve_alias("C++", &aliases), "cpp");
+        assert_eq!(resolve_alias("csharp", &aliases), "cs");
+        assert_eq!(resolve_alias("pas", &aliases), "pascal");
+        assert_eq!(resolve_alias("rust", &aliases), "rust");
+    }
+
+    #[test]
+    fn infers_common_languages_and_declines_otherwise() {
+        assert_eq!(
+            infer_lang("U64 x = 0;\nfor (int i = 0; i < 64; i++) {\n}\n"),
+            Some("c")
+        );
+        assert_eq!(
+            infer_lang("std::vector<int> v;\nint n = v.size();\n"),
+            Some("cpp")
+        );
+        assert_eq!(
+            infer_lang("x := 1;\nbegin\n  y := x;\nend;\n"),
+            Some("pascal")
+        );
+        assert_eq!(
+            infer_lang("mov rax, rbx\nxor rcx, rcx\nret\n"),
+            Some("nasm")
+        );
+        assert_eq!(infer_lang("1. e4 e5 2. Nf3 Nc6\n"), None);
+        assert_eq!(infer_lang("rnbqkbnr\npppppppp\n"), None);
+    }
+}
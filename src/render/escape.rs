@@ -0,0 +1,170 @@
+//! Markdown escaping for literal text.
+//!
+//! Text and Raw inline nodes are plain text in the wiki, but characters such as
+//! `*`, `_`, `#`, `[`, or backticks can turn into emphasis, tags, links, or code
+//! once written to Markdown. An [`EscapePolicy`] decides, per character, how such
+//! characters are neutralized. Markup the renderer emits itself (emphasis
+//! markers, link brackets, ...) is never escaped.
+
+use std::collections::BTreeMap;
+
+/// How a single character is escaped in literal text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharEscape {
+    /// Emit the character unchanged.
+    Keep,
+    /// Prefix a backslash where the character could be read as Markdown syntax
+    /// (e.g. `_` only at word boundaries, `~` only when doubled).
+    Backslash,
+    /// Prefix a backslash on every occurrence.
+    BackslashAlways,
+    /// Replace every occurrence with the given text (e.g. an HTML entity).
+    Replace(String),
+}
+
+/// Per-character escaping rules for literal text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapePolicy {
+    rules: BTreeMap<char, CharEscape>,
+}
+
+impl Default for EscapePolicy {
+    fn default() -> Self {
+        Self::obsidian()
+    }
+}
+
+impl EscapePolicy {
+    /// No escaping at all: text is written as-is.
+    pub fn none() -> Self {
+        Self {
+            rules: BTreeMap::new(),
+        }
+    }
+
+    /// CommonMark / GitHub Flavored Markdown.
+    pub fn commonmark() -> Self {
+        Self::none()
+            .with('\\', CharEscape::Backslash)
+            .with('*', CharEscape::BackslashAlways)
+            .with('_', CharEscape::Backslash)
+            .with('`', CharEscape::BackslashAlways)
+            .with('[', CharEscape::BackslashAlways)
+            .with(']', CharEscape::BackslashAlways)
+            .with('~', CharEscape::Backslash)
+            .with('#', CharEscape::Backslash)
+    }
+
+    /// Obsidian-flavored Markdown (the default).
+    ///
+    /// Obsidian can misinterpret literal `*` characters as emphasis markers even
+    /// when surrounded by spaces, and backslash-escaped asterisks are not always
+    /// honored, so `*` is replaced with `&middot;`. `#` followed by a word would
+    /// become a tag, and `[[` a wiki link.
+    pub fn obsidian() -> Self {
+        Self::commonmark().with('*', CharEscape::Replace("&middot;".to_string()))
+    }
+
+    /// Set the rule for `c`.
+    pub fn with(mut self, c: char, rule: CharEscape) -> Self {
+        match rule {
+            CharEscape::Keep => {
+                self.rules.remove(&c);
+            }
+            _ => {
+                self.rules.insert(c, rule);
+            }
+        }
+        self
+    }
+
+    /// The rule for `c` (`Keep` if none was set).
+    pub fn rule(&self, c: char) -> &CharEscape {
+        self.rules.get(&c).unwrap_or(&CharEscape::Keep)
+    }
+
+    /// Escape literal text according to this policy.
+    pub fn escape(&self, text: &str) -> String {
+        if self.rules.is_empty() || !text.chars().any(|c| self.rules.contains_key(&c)) {
+            return text.to_string();
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len() + 8);
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            match self.rule(c) {
+                CharEscape::Keep => out.push(c),
+                CharEscape::BackslashAlways => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                CharEscape::Backslash => {
+                    if is_syntax_here(c, prev, &chars[i + 1..]) {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+                CharEscape::Replace(r) => out.push_str(r),
+            }
+        }
+        out
+    }
+}
+
+/// Context check for [`CharEscape::Backslash`]; `rest` is the text after `c`.
+fn is_syntax_here(c: char, prev: Option<char>, rest: &[char]) -> bool {
+    let alnum = |ch: Option<char>| ch.is_some_and(char::is_alphanumeric);
+    let next = rest.first().copied();
+    match c {
+        // intraword underscores (`Move_Generation`) never start emphasis.
+        '_' => !(alnum(prev) && alnum(next)),
+        '~' => prev == Some('~') || next == Some('~'),
+        // a heading at line start, or an Obsidian tag (`#word`) anywhere;
+        // numeric entities (`&#8968;`) are left alone.
+        '#' if prev == Some('&') => false,
+        '#' => prev.is_none() || is_tag(rest),
+        // only a backslash before ASCII punctuation is an escape itself.
+        '\\' => next.is_some_and(|n| n.is_ascii_punctuation()),
+        _ => true,
+    }
+}
+
+/// Whether `#` followed by `rest` makes an Obsidian tag: a run of letters,
+/// digits, `_`, `-`, and `/` that isn't all digits (`#1` is no tag).
+fn is_tag(rest: &[char]) -> bool {
+    let len = rest
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        .count();
+    len > 0 && !rest[..len].iter().all(char::is_ascii_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obsidian_policy_escapes_markdown_syntax_in_text() {
+        let p = EscapePolicy::obsidian();
+        assert_eq!(p.escape("A * B"), "A &middot; B");
+        assert_eq!(p.escape("Move_Generation"), "Move_Generation");
+        assert_eq!(p.escape("_start and end_"), "\\_start and end\\_");
+        assert_eq!(p.escape("see [1] and `x`"), "see \\[1\\] and \\`x\\`");
+        assert_eq!(p.escape("C# and #tag"), "C# and \\#tag");
+        assert_eq!(p.escape("C#, C#. and C#"), "C#, C#. and C#");
+        assert_eq!(p.escape("Possible Move #1"), "Possible Move #1");
+        assert_eq!(p.escape("not #1984 but #e4"), "not #1984 but \\#e4");
+        assert_eq!(p.escape("&#8968;n/2&#8969;"), "&#8968;n/2&#8969;");
+        assert_eq!(p.escape("~200 and ~~gone~~"), "~200 and \\~\\~gone\\~\\~");
+    }
+
+    #[test]
+    fn rules_can_be_overridden_per_character() {
+        let p = EscapePolicy::commonmark()
+            .with('*', CharEscape::Replace("&ast;".to_string()))
+            .with('[', CharEscape::Keep);
+        assert_eq!(p.escape("[a*b]"), "[a&ast;b\\]");
+        assert_eq!(EscapePolicy::none().escape("*_[]"), "*_[]");
+    }
+}
@@ -0,0 +1,111 @@
+//! Render hooks: callbacks that see the Markdown rendered for each block, inline,
+//! or whole document and may replace it.
+//!
+//! A hook returns `Some(markdown)` to replace the rendered string, or `None` to
+//! keep it. Unlike a [`MarkupBackend`], hooks don't change how anything is
+//! rendered, so they suit small decorations and special cases:
+//!
+//! ```
+//! use wiki2md::ast::{BlockKind, InlineKind};
+//! use wiki2md::render::{RenderHooks, RenderOptions};
+//!
+//! let opts = RenderOptions {
+//!     hooks: RenderHooks::new()
+//!         .on_inline(|node, md| match &node.kind {
+//!             InlineKind::InternalLink { link } if link.target.starts_with("Stockfish") => {
+//!                 Some(format!("🐟 {}", md))
+//!             }
+//!             _ => None,
+//!         })
+//!         .on_block(|block, md| {
+//!             matches!(block.kind, BlockKind::CodeBlock { .. }).then(|| format!("{}\n^code", md))
+//!         }),
+//!     ..RenderOptions::default()
+//! };
+//! let md = wiki2md::convert_wikitext("See [[Stockfish]].\n", &opts);
+//! assert_eq!(md, "See 🐟 [[Stockfish]].");
+//! ```
+
+use super::*;
+use std::fmt;
+
+/// Called with a block and the Markdown rendered for it.
+pub type BlockHook = Arc<dyn Fn(&BlockNode, &str) -> Option<String> + Send + Sync>;
+
+/// Called with an inline node and the Markdown rendered for it (text already escaped).
+pub type InlineHook = Arc<dyn Fn(&InlineNode, &str) -> Option<String> + Send + Sync>;
+
+/// Called with the document and the Markdown rendered for its body.
+pub type DocumentHook = Arc<dyn Fn(&Document, &str) -> Option<String> + Send + Sync>;
+
+/// The hooks [`RenderOptions`] runs while rendering. None are set by default.
+#[derive(Clone, Default)]
+pub struct RenderHooks {
+    pub on_block: Option<BlockHook>,
+    pub on_inline: Option<InlineHook>,
+    pub post_document: Option<DocumentHook>,
+}
+
+impl fmt::Debug for RenderHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderHooks")
+            .field("on_block", &self.on_block.is_some())
+            .field("on_inline", &self.on_inline.is_some())
+            .field("post_document", &self.post_document.is_some())
+            .finish()
+    }
+}
+
+impl RenderHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hook run for every block, nested ones included.
+    pub fn on_block<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&BlockNode, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.on_block = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook run for every inline node, nested ones included.
+    pub fn on_inline<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&InlineNode, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.on_inline = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook run once for the rendered document body.
+    pub fn post_document<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Document, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.post_document = Some(Arc::new(hook));
+        self
+    }
+
+    pub(crate) fn block(&self, block: &BlockNode, rendered: String) -> String {
+        match &self.on_block {
+            Some(hook) => hook(block, &rendered).unwrap_or(rendered),
+            None => rendered,
+        }
+    }
+
+    pub(crate) fn inline(&self, node: &InlineNode, rendered: String) -> String {
+        match &self.on_inline {
+            Some(hook) => hook(node, &rendered).unwrap_or(rendered),
+            None => rendered,
+        }
+    }
+
+    pub(crate) fn document(&self, doc: &Document, rendered: String) -> String {
+        match &self.post_document {
+            Some(hook) => hook(doc, &rendered).unwrap_or(rendered),
+            None => rendered,
+        }
+    }
+}
@@ -0,0 +1,732 @@
+//! AST -> HTML renderer.
+//!
+//! Produces a standalone HTML page from the same `Document` the Markdown renderer
+//! consumes, so converted pages can be previewed in a browser without a Markdown
+//! engine. Unlike Markdown, HTML can express tables with row/column spans, real
+//! figures, and linked footnotes, so those are emitted natively.
+//!
+//! Plain text is passed through unescaped (MediaWiki allows inline HTML such as
+//! `<sub>` and entities like `&#8968;` in running text); attribute values,
+//! code, and raw blocks are always escaped.
+
+use crate::ast::*;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use std::collections::HashMap;
+
+use super::{
+    MagicLinkUrls, UrlRewrites, extract_standalone_file_link, file_link_width_px,
+    file_param_is_option_like, image_base, mediawiki_file_thumb_url, mediawiki_file_url,
+};
+
+/// Options controlling HTML output.
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// If true, wrap the body in `<!DOCTYPE html><html><head>...</head><body>...`.
+    pub standalone: bool,
+
+    /// Page title. Used for `<title>` and rendered as the top-level `<h1>`.
+    pub title: Option<String>,
+
+    /// CSS inserted into a `<style>` element of standalone pages.
+    pub css: Option<String>,
+
+    /// Base URL used for MediaWiki file resolution (images, `File:` pages).
+    pub mediawiki_base_url: String,
+
+    /// Where image URLs point instead of `<mediawiki_base_url>/images` (see
+    /// [`RenderOptions::image_base_url`](super::RenderOptions::image_base_url)).
+    pub image_base_url: Option<String>,
+
+    /// Prefix for internal link targets, e.g. `https://www.chessprogramming.org/`
+    /// or `/articles/` for a local preview server.
+    pub internal_link_prefix: String,
+
+    /// Suffix for internal link targets, e.g. `.html`.
+    pub internal_link_suffix: String,
+
+    /// Explicit hrefs for specific link targets, keyed by article id (spaces as
+    /// underscores). Takes precedence over the prefix/suffix; the EPUB builder uses
+    /// this to point links at chapters inside the book.
+    pub internal_link_targets: HashMap<String, String>,
+
+    /// Default width (in pixels) to request for embedded images.
+    pub default_image_width_px: u32,
+
+    /// If true, prefer a `NNNpx` option from the wikitext file params.
+    pub respect_wikitext_image_width: bool,
+
+    /// Rules applied to external link URLs before they are emitted.
+    pub url_rewrites: UrlRewrites,
+
+    /// Where `ISBN`, `RFC`, and `PMID` magic links point.
+    pub magic_link_urls: MagicLinkUrls,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            standalone: true,
+            title: None,
+            css: Some(DEFAULT_CSS.to_string()),
+            mediawiki_base_url: "https://www.chessprogramming.org".to_string(),
+            image_base_url: None,
+            internal_link_prefix: "https://www.chessprogramming.org/".to_string(),
+            internal_link_suffix: String::new(),
+            internal_link_targets: HashMap::new(),
+            default_image_width_px: 300,
+            respect_wikitext_image_width: false,
+            url_rewrites: UrlRewrites::default(),
+            magic_link_urls: MagicLinkUrls::default(),
+        }
+    }
+}
+
+const DEFAULT_CSS: &str =
+    "body { max-width: 60em; margin: 2em auto; font-family: sans-serif; line-height: 1.5; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #aaa; padding: 0.2em 0.5em; }
+figure { margin: 1em 0; }
+blockquote { border-left: 3px solid #ccc; margin-left: 0; padding-left: 1em; }
+pre { background: #f6f6f6; padding: 0.5em; overflow-x: auto; }";
+
+#[derive(Debug, Default)]
+struct HtmlContext<'a> {
+    refs: Vec<String>,
+    /// Number of refs already emitted by a `<references />` block.
+    emitted_refs: usize,
+    /// Footnote numbers of the named refs seen so far.
+    named_refs: HashMap<String, usize>,
+    /// Contents of list-defined refs, by name.
+    ref_definitions: HashMap<String, Vec<InlineNode<'a>>>,
+}
+
+/// Render a document into HTML.
+pub fn render_doc(doc: &Document, opts: &HtmlOptions) -> String {
+    let mut ctx = HtmlContext {
+        ref_definitions: crate::ast::list_defined_refs(&doc.blocks),
+        ..Default::default()
+    };
+    let mut body = String::new();
+
+    if let Some(title) = &opts.title {
+        body.push_str(&format!("<h1>{}</h1>\n", encode_text(title.trim())));
+    }
+
+    for block in &doc.blocks {
+        let rendered = render_block(block, &mut ctx, opts);
+        if !rendered.is_empty() {
+            body.push_str(&rendered);
+            body.push('\n');
+        }
+    }
+
+    // refs without a `<references />` block still get a footnote list.
+    if ctx.emitted_refs < ctx.refs.len() {
+        body.push_str(&render_references(&mut ctx));
+        body.push('\n');
+    }
+
+    if !opts.standalone {
+        return body;
+    }
+    standalone_page(opts.title.as_deref(), opts.css.as_deref(), &body)
+}
+
+/// Wrap an HTML fragment in `<!DOCTYPE html><html><head>...</head><body>...`.
+pub fn standalone_page(title: Option<&str>, css: Option<&str>, body: &str) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    if let Some(title) = title {
+        out.push_str(&format!("<title>{}</title>\n", encode_text(title.trim())));
+    }
+    if let Some(css) = css {
+        out.push_str(&format!("<style>\n{}\n</style>\n", css));
+    }
+    out.push_str("</head>\n<body>\n");
+    out.push_str(body);
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_block(block: &BlockNode, ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    match &block.kind {
+        BlockKind::Heading { level, content } => render_heading(*level, content, ctx, opts),
+        BlockKind::Paragraph { content } => {
+            if let Some(link) = extract_standalone_file_link(content)
+                && matches!(link.namespace, FileNamespace::File | FileNamespace::Image)
+            {
+                return render_figure(link, ctx, opts);
+            }
+            let inner = render_inlines(content, ctx, opts);
+            if inner.trim().is_empty() {
+                return String::new();
+            }
+            format!("<p>{}</p>", inner.trim())
+        }
+        BlockKind::List { items } => render_list(items, ctx, opts),
+        BlockKind::Table { table } => render_table(table, ctx, opts),
+        BlockKind::CodeBlock { block } => {
+            let class = block
+                .lang
+                .as_deref()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(|l| format!(" class=\"language-{}\"", encode_double_quoted_attribute(l)))
+                .unwrap_or_default();
+            format!(
+                "<pre><code{}>{}</code></pre>",
+                class,
+                encode_text(block.text.trim_matches('\n'))
+            )
+        }
+        BlockKind::Poem { lines } => {
+            let stanzas: Vec<String> = lines
+                .split(Vec::is_empty)
+                .filter(|stanza| !stanza.is_empty())
+                .map(|stanza| {
+                    let lines: Vec<String> = stanza
+                        .iter()
+                        .map(|l| render_inlines(l, ctx, opts))
+                        .collect();
+                    format!("<p>{}</p>", lines.join("<br/>\n"))
+                })
+                .collect();
+            format!("<div class=\"poem\">\n{}\n</div>", stanzas.join("\n"))
+        }
+        BlockKind::Timeline { timeline } => format!(
+            "<pre class=\"timeline\">{}</pre>",
+            encode_text(timeline.source.trim_matches('\n'))
+        ),
+        BlockKind::References { .. } => render_references(ctx),
+        BlockKind::HtmlBlock { node } => {
+            let open = format!("<{}{}", node.name, render_attrs(&node.attrs));
+            if node.self_closing {
+                return format!("{} />", open);
+            }
+            let mut out = format!("{}>\n", open);
+            for b in &node.children {
+                out.push_str(&render_block(b, ctx, opts));
+                out.push('\n');
+            }
+            out.push_str(&format!("</{}>", node.name));
+            out
+        }
+        BlockKind::MagicWord { name } => format!("<!-- {} -->", encode_text(name)),
+        BlockKind::HorizontalRule => "<hr />".to_string(),
+        BlockKind::BlockQuote {
+            blocks,
+            attribution,
+            ..
+        } => {
+            let mut out = String::new();
+            if let Some(a) = attribution.as_ref().filter(|a| !a.trailing) {
+                let intro = render_inlines(&a.content, ctx, opts);
+                out.push_str(&format!("<p>{}</p>\n", intro.trim()));
+            }
+            out.push_str("<blockquote>\n");
+            for b in blocks {
+                out.push_str(&render_block(b, ctx, opts));
+                out.push('\n');
+            }
+            if let Some(a) = attribution.as_ref().filter(|a| a.trailing) {
+                let source = render_inlines(&a.content, ctx, opts);
+                out.push_str(&format!(
+                    "<footer>— <cite>{}</cite></footer>\n",
+                    source.trim()
+                ));
+            }
+            out.push_str("</blockquote>");
+            out
+        }
+        BlockKind::Inclusion { mode, blocks } => match mode.shown_on_page() {
+            true => blocks
+                .iter()
+                .map(|b| render_block(b, ctx, opts))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false => String::new(),
+        },
+        BlockKind::Raw { text } => format!("<pre class=\"raw\">{}</pre>", encode_text(text)),
+    }
+}
+
+fn render_heading(
+    level: u8,
+    content: &[InlineNode],
+    ctx: &mut HtmlContext,
+    opts: &HtmlOptions,
+) -> String {
+    // prefer an explicit leading `<span id="...">` anchor, else derive a MediaWiki-style id.
+    let mut content_slice = content;
+    let mut id: Option<String> = None;
+    if let Some(first) = content.first()
+        && let InlineKind::HtmlTag { node } = &first.kind
+        && node.name.eq_ignore_ascii_case("span")
+        && node.children.is_empty()
+        && let Some(v) = html_attr(&node.attrs, "id")
+    {
+        id = Some(v.to_string());
+        content_slice = &content[1..];
+    }
+    let inner = render_inlines(content_slice, ctx, opts);
+    let id = id.unwrap_or_else(|| heading_id(content_slice));
+
+    // the page title is `<h1>`, so demote headings from the AST by one level.
+    let lvl = level.saturating_add(1).clamp(2, 6);
+    format!(
+        "<h{lvl} id=\"{}\">{}</h{lvl}>",
+        encode_double_quoted_attribute(&id),
+        inner.trim()
+    )
+}
+
+/// MediaWiki-style section id: the heading's plain text with spaces as underscores.
+fn heading_id(content: &[InlineNode]) -> String {
+    let mut text = String::new();
+    plain_text(content, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+fn render_list(items: &[ListItem], ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    // wikitext allows mixing markers at one level; group consecutive runs of
+    // compatible items into one HTML list element each.
+    let mut out = String::new();
+    let mut i = 0;
+    while i < items.len() {
+        let (list_tag, item_tag_for) = list_tags(items[i].marker);
+        let mut j = i;
+        while j < items.len() && list_tags(items[j].marker).0 == list_tag {
+            j += 1;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("<{}>\n", list_tag));
+        for item in &items[i..j] {
+            let item_tag = item_tag_for(item.marker);
+            let mut inner = String::new();
+            for (bi, b) in item.blocks.iter().enumerate() {
+                match &b.kind {
+                    BlockKind::Paragraph { content } if bi == 0 => {
+                        inner.push_str(render_inlines(content, ctx, opts).trim())
+                    }
+                    _ => {
+                        inner.push('\n');
+                        inner.push_str(&render_block(b, ctx, opts));
+                    }
+                }
+            }
+            out.push_str(&format!("<{}>{}</{}>\n", item_tag, inner, item_tag));
+        }
+        out.push_str(&format!("</{}>", list_tag));
+        i = j;
+    }
+    out
+}
+
+type ItemTagFn = fn(ListMarker) -> &'static str;
+
+fn list_tags(marker: ListMarker) -> (&'static str, ItemTagFn) {
+    match marker {
+        ListMarker::Unordered => ("ul", |_| "li"),
+        ListMarker::Ordered => ("ol", |_| "li"),
+        ListMarker::Term | ListMarker::Definition => {
+            ("dl", |m| if m == ListMarker::Term { "dt" } else { "dd" })
+        }
+    }
+}
+
+fn render_table(table: &Table, ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    let mut out = format!("<table{}>\n", render_attrs(&table.attrs));
+    if let Some(cap) = &table.caption {
+        out.push_str(&format!(
+            "<caption{}>{}</caption>\n",
+            render_attrs(&cap.attrs),
+            render_inlines(&cap.content, ctx, opts).trim()
+        ));
+    }
+    for row in &table.rows {
+        out.push_str(&format!("<tr{}>", render_attrs(&row.attrs)));
+        for cell in &row.cells {
+            let tag = match cell.kind {
+                TableCellKind::Header => "th",
+                TableCellKind::Data => "td",
+            };
+            // `rowspan`/`colspan` are kept in `attrs`, so they pass through here.
+            out.push_str(&format!("<{}{}>", tag, render_attrs(&cell.attrs)));
+            let single_paragraph = cell.blocks.len() == 1
+                && matches!(cell.blocks[0].kind, BlockKind::Paragraph { .. });
+            for b in &cell.blocks {
+                match &b.kind {
+                    BlockKind::Paragraph { content } if single_paragraph => {
+                        out.push_str(render_inlines(content, ctx, opts).trim())
+                    }
+                    _ => out.push_str(&render_block(b, ctx, opts)),
+                }
+            }
+            out.push_str(&format!("</{}>", tag));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>");
+    out
+}
+
+fn render_figure(link: &FileLink, ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    let width = if opts.respect_wikitext_image_width {
+        file_link_width_px(link).unwrap_or(opts.default_image_width_px)
+    } else {
+        opts.default_image_width_px
+    };
+    let images = image_base(&opts.mediawiki_base_url, opts.image_base_url.as_deref());
+    let url = mediawiki_file_thumb_url(&images, &link.target, width);
+
+    let caption_param = link
+        .params
+        .iter()
+        .rev()
+        .find(|p| !file_param_is_option_like(p));
+    let caption = caption_param
+        .map(|p| render_inlines(&p.content, ctx, opts).trim().to_string())
+        .unwrap_or_default();
+    let mut alt = String::new();
+    if let Some(p) = caption_param {
+        plain_text(&p.content, &mut alt);
+    }
+    let alt = if alt.trim().is_empty() {
+        link.target.trim().to_string()
+    } else {
+        alt.trim().to_string()
+    };
+
+    let mut out = format!(
+        "<figure>\n<img src=\"{}\" alt=\"{}\" width=\"{}\" />\n",
+        encode_double_quoted_attribute(&url),
+        encode_double_quoted_attribute(&alt),
+        width
+    );
+    if !caption.is_empty() {
+        out.push_str(&format!("<figcaption>{}</figcaption>\n", caption));
+    }
+    out.push_str("</figure>");
+    out
+}
+
+fn render_references(ctx: &mut HtmlContext) -> String {
+    if ctx.emitted_refs >= ctx.refs.len() {
+        return String::new();
+    }
+    let mut out = format!(
+        "<ol class=\"references\" start=\"{}\">\n",
+        ctx.emitted_refs + 1
+    );
+    for (i, body) in ctx.refs.iter().enumerate().skip(ctx.emitted_refs) {
+        let n = i + 1;
+        out.push_str(&format!(
+            "<li id=\"cite_note-{n}\"><a href=\"#cite_ref-{n}\">&uarr;</a> {}</li>\n",
+            body.trim()
+        ));
+    }
+    out.push_str("</ol>");
+    ctx.emitted_refs = ctx.refs.len();
+    out
+}
+
+fn render_inlines(inlines: &[InlineNode], ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    let mut out = String::new();
+    for node in inlines {
+        // footnote markers attach to the preceding token.
+        if matches!(node.kind, InlineKind::Ref { .. }) {
+            while matches!(out.as_bytes().last(), Some(b' ' | b'\t')) {
+                out.pop();
+            }
+        }
+        out.push_str(&render_inline(node, ctx, opts));
+    }
+    out
+}
+
+fn render_inline(node: &InlineNode, ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    match &node.kind {
+        InlineKind::Text { value } => value.to_string(),
+        InlineKind::Bold { content } => format!("<b>{}</b>", render_inlines(content, ctx, opts)),
+        InlineKind::Italic { content } => format!("<i>{}</i>", render_inlines(content, ctx, opts)),
+        InlineKind::BoldItalic { content } => {
+            format!("<b><i>{}</i></b>", render_inlines(content, ctx, opts))
+        }
+        InlineKind::LineBreak => "<br />".to_string(),
+        InlineKind::InternalLink { link } => {
+            let label = match &link.text {
+                Some(nodes) => render_inlines(nodes, ctx, opts),
+                None => {
+                    let mut s = link.target.replace('_', " ");
+                    if let Some(a) = &link.anchor
+                        && s.trim().is_empty()
+                    {
+                        s = a.clone();
+                    }
+                    encode_text(s.trim()).to_string()
+                }
+            };
+            let mut href = String::new();
+            let target = link.target.trim();
+            if !target.is_empty() {
+                let id = target.replace(' ', "_");
+                match lookup_link_target(&opts.internal_link_targets, &id) {
+                    Some(mapped) => href.push_str(mapped),
+                    None => {
+                        href.push_str(&opts.internal_link_prefix);
+                        href.push_str(&id);
+                        href.push_str(&opts.internal_link_suffix);
+                    }
+                }
+            }
+            if let Some(a) = &link.anchor {
+                href.push('#');
+                href.push_str(&a.trim().replace(' ', "_"));
+            }
+            format!(
+                "<a href=\"{}\">{}</a>",
+                encode_double_quoted_attribute(&href),
+                label.trim()
+            )
+        }
+        InlineKind::ExternalLink { link } => {
+            let url = opts.url_rewrites.apply(&link.url);
+            let label = match &link.text {
+                Some(nodes) => render_inlines(nodes, ctx, opts).trim().to_string(),
+                None => encode_text(&url).to_string(),
+            };
+            format!(
+                "<a href=\"{}\">{}</a>",
+                encode_double_quoted_attribute(&url),
+                label
+            )
+        }
+        InlineKind::MagicLink { link } => {
+            let url = opts.magic_link_urls.url(link);
+            format!(
+                "<a href=\"{}\">{}</a>",
+                encode_double_quoted_attribute(&opts.url_rewrites.apply(&url)),
+                encode_text(&link.text)
+            )
+        }
+        InlineKind::FileLink { link } => {
+            let base = opts.mediawiki_base_url.trim_end_matches('/');
+            let page = match link.namespace {
+                FileNamespace::Media => {
+                    let images = image_base(base, opts.image_base_url.as_deref());
+                    mediawiki_file_url(&images, &link.target)
+                }
+                FileNamespace::File | FileNamespace::Image => {
+                    format!("{}/File:{}", base, link.target.replace(' ', "_"))
+                }
+            };
+            let caption = link
+                .params
+                .iter()
+                .rev()
+                .find(|p| !file_param_is_option_like(p))
+                .map(|p| render_inlines(&p.content, ctx, opts))
+                .unwrap_or_else(|| encode_text(&link.target).to_string());
+            format!(
+                "<a href=\"{}\">{}</a>",
+                encode_double_quoted_attribute(&page),
+                caption.trim()
+            )
+        }
+        InlineKind::Ref { node } => {
+            if let Some(&n) = node.name().and_then(|name| ctx.named_refs.get(name)) {
+                return format!("<sup><a href=\"#cite_note-{n}\">[{n}]</a></sup>");
+            }
+            let body = match (&node.content, node.name()) {
+                (Some(c), _) => render_inlines(c, ctx, opts),
+                (None, Some(name)) => match ctx.ref_definitions.get(name).cloned() {
+                    Some(c) => render_inlines(&c, ctx, opts),
+                    None => String::new(),
+                },
+                (None, None) => String::new(),
+            };
+            ctx.refs.push(body);
+            let n = ctx.refs.len();
+            if let Some(name) = node.name() {
+                ctx.named_refs.insert(name.to_string(), n);
+            }
+            format!("<sup id=\"cite_ref-{n}\"><a href=\"#cite_note-{n}\">[{n}]</a></sup>")
+        }
+        InlineKind::HtmlTag { node } => {
+            let open = format!("<{}{}", node.name, render_attrs(&node.attrs));
+            if node.self_closing {
+                format!("{} />", open)
+            } else {
+                format!(
+                    "{}>{}</{}>",
+                    open,
+                    render_inlines(&node.children, ctx, opts),
+                    node.name
+                )
+            }
+        }
+        InlineKind::Template { node } => render_template(node, ctx, opts),
+        InlineKind::TemplateArg { name, default } => match default {
+            Some(default) => format!(
+                "{{{{{{{}|{}}}}}}}",
+                encode_text(name),
+                render_inlines(default, ctx, opts)
+            ),
+            None => format!("{{{{{{{}}}}}}}", encode_text(name)),
+        },
+        InlineKind::Inclusion { mode, content } => match mode.shown_on_page() {
+            true => render_inlines(content, ctx, opts),
+            false => String::new(),
+        },
+        InlineKind::Raw { text } => encode_text(text).to_string(),
+    }
+}
+
+fn render_template(inv: &TemplateInvocation, ctx: &mut HtmlContext, opts: &HtmlOptions) -> String {
+    if inv.name.kind == TemplateNameKind::ParserFunction
+        && inv.name.raw.eq_ignore_ascii_case("#evu")
+    {
+        let url = inv
+            .params
+            .first()
+            .map(|p| render_inlines(&p.value, ctx, opts))
+            .unwrap_or_default();
+        if url.trim().is_empty() {
+            return String::new();
+        }
+        return format!(
+            "<a href=\"{}\">Video</a>",
+            encode_double_quoted_attribute(url.trim())
+        );
+    }
+    // keep unknown templates visible but clearly marked.
+    let mut s = format!("{{{{{}", inv.name.raw);
+    for p in &inv.params {
+        s.push('|');
+        if let Some(n) = &p.name {
+            s.push_str(n);
+            s.push('=');
+        }
+        s.push_str(&render_inlines(&p.value, ctx, opts));
+    }
+    s.push_str("}}");
+    format!("<code class=\"template\">{}</code>", s)
+}
+
+/// MediaWiki titles are case-insensitive in their first letter, so `[[perft]]`
+/// still resolves to the `Perft` entry.
+fn lookup_link_target<'a>(targets: &'a HashMap<String, String>, id: &str) -> Option<&'a str> {
+    if targets.is_empty() {
+        return None;
+    }
+    if let Some(t) = targets.get(id) {
+        return Some(t);
+    }
+    let mut chars = id.chars();
+    let first = chars.next()?;
+    let upper: String = first.to_uppercase().chain(chars).collect();
+    targets.get(&upper).map(String::as_str)
+}
+
+fn render_attrs(attrs: &[HtmlAttr]) -> String {
+    let mut out = String::new();
+    for a in attrs {
+        out.push(' ');
+        out.push_str(&a.name);
+        if let Some(v) = &a.value {
+            out.push_str("=\"");
+            out.push_str(&encode_double_quoted_attribute(v));
+            out.push('"');
+        }
+    }
+    out
+}
+
+fn html_attr<'a>(attrs: &'a [HtmlAttr], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name))
+        .and_then(|a| a.value.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    fn body(src: &str) -> String {
+        let opts = HtmlOptions {
+            standalone: false,
+            ..Default::default()
+        };
+        render_doc(&parse_wiki(src).document, &opts)
+    }
+
+    #[test]
+    fn headings_get_ids_and_are_demoted() {
+        let html = body("=Move Generation=\n==<span id=\"Pseudo\"></span>Pseudo-legal==\n");
+        assert!(
+            html.contains("<h2 id=\"Move_Generation\">Move Generation</h2>"),
+            "{html}"
+        );
+        assert!(
+            html.contains("<h3 id=\"Pseudo\">Pseudo-legal</h3>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn refs_become_linked_footnotes() {
+        let html = body("Text<ref>Body</ref>.\n<references />\n");
+        assert!(
+            html.contains("Text<sup id=\"cite_ref-1\"><a href=\"#cite_note-1\">[1]</a></sup>."),
+            "{html}"
+        );
+        assert!(
+            html.contains("<li id=\"cite_note-1\"><a href=\"#cite_ref-1\">&uarr;</a> Body</li>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn tables_keep_row_and_column_spans() {
+        let html = body(
+            "{| class=\"wikitable\"\n|+ Cap\n|-\n! colspan=\"2\" | H\n|-\n| rowspan=\"2\" | A || B\n|}\n",
+        );
+        assert!(html.contains("<caption>Cap</caption>"), "{html}");
+        assert!(html.contains("<th colspan=\"2\">H</th>"), "{html}");
+        assert!(
+            html.contains("<td rowspan=\"2\">A</td><td>B</td>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn standalone_file_links_become_figures() {
+        let html = body("[[File:Board.png|thumb|The ''board'']]\n");
+        assert!(
+            html.starts_with("<figure>\n<img src=\"https://www.chessprogramming.org/images/thumb/"),
+            "{html}"
+        );
+        assert!(html.contains("alt=\"The board\""), "{html}");
+        assert!(
+            html.contains("<figcaption>The <i>board</i></figcaption>"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn standalone_page_has_document_shell() {
+        let opts = HtmlOptions {
+            title: Some("Perft".to_string()),
+            ..Default::default()
+        };
+        let html = render_doc(&parse_wiki("Body\n").document, &opts);
+        assert!(html.starts_with("<!DOCTYPE html>"), "{html}");
+        assert!(html.contains("<title>Perft</title>"), "{html}");
+        assert!(html.contains("<h1>Perft</h1>\n<p>Body</p>"), "{html}");
+        assert!(html.ends_with("</body>\n</html>\n"), "{html}");
+    }
+}
@@ -0,0 +1,267 @@
+//! Passthrough policy for raw HTML in wikitext.
+//!
+//! MediaWiki pages may contain arbitrary HTML tags (`<span>`, `<div>`, `<font>`,
+//! ...). The Markdown renderer passes them through verbatim by default, which is
+//! unsafe when the output is published to the web. An [`HtmlPolicy`] restricts
+//! which tags and attributes survive.
+//!
+//! Only a few tags become `HtmlTag`/`HtmlBlock` nodes; the rest stay inside Text
+//! nodes, so restrictive policies also sanitize tags found in literal text.
+
+use crate::ast::HtmlAttr;
+use crate::parse::util::parse_html_attrs;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What happens to `HtmlTag` / `HtmlBlock` nodes during rendering.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HtmlPolicy {
+    /// Emit every tag and attribute as written (the historical behavior).
+    #[default]
+    AllowAll,
+    /// Emit only the listed tags/attributes; other tags are replaced by their content.
+    Allowlist(HtmlAllowlist),
+    /// Drop all tags and keep only their text content.
+    StripToText,
+}
+
+/// Allowed tags, each with its allowed attributes. Names are ASCII case-insensitive.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HtmlAllowlist {
+    tags: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl HtmlAllowlist {
+    /// An empty allowlist (equivalent to stripping every tag).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `tag` with the given attributes.
+    pub fn allow(mut self, tag: &str, attrs: &[&str]) -> Self {
+        let entry = self.tags.entry(tag.to_ascii_lowercase()).or_default();
+        entry.extend(attrs.iter().map(|a| a.to_ascii_lowercase()));
+        self
+    }
+
+    /// Presentational tags commonly found on chessprogramming.org, without
+    /// `style` attributes or anything that can run script.
+    pub fn safe_default() -> Self {
+        let plain = [
+            "b",
+            "big",
+            "blockquote",
+            "br",
+            "center",
+            "code",
+            "del",
+            "em",
+            "hr",
+            "i",
+            "ins",
+            "kbd",
+            "p",
+            "pre",
+            "q",
+            "s",
+            "samp",
+            "small",
+            "strike",
+            "strong",
+            "sub",
+            "sup",
+            "tt",
+            "u",
+            "var",
+        ];
+        plain
+            .iter()
+            .fold(Self::new(), |l, t| l.allow(t, &[]))
+            .allow("a", &["href", "name", "title"])
+            .allow("abbr", &["title"])
+            .allow("div", &["id", "class", "title"])
+            .allow("span", &["id", "class", "title"])
+            .allow("font", &["color", "size"])
+    }
+
+    fn allows_tag(&self, tag: &str) -> bool {
+        self.tags.contains_key(&tag.to_ascii_lowercase())
+    }
+
+    fn allows_attr(&self, tag: &str, attr: &str) -> bool {
+        self.tags
+            .get(&tag.to_ascii_lowercase())
+            .is_some_and(|attrs| attrs.contains(&attr.to_ascii_lowercase()))
+    }
+}
+
+/// How the renderer should emit a particular tag.
+pub(crate) enum TagAction<'a> {
+    /// Emit the tag with these attributes.
+    Keep(Vec<&'a HtmlAttr>),
+    /// Emit only the tag's content.
+    Unwrap,
+    /// Emit nothing (neither tag nor content).
+    Drop,
+}
+
+impl HtmlPolicy {
+    /// Shorthand for `HtmlPolicy::Allowlist(HtmlAllowlist::safe_default())`.
+    pub fn safe_default() -> Self {
+        HtmlPolicy::Allowlist(HtmlAllowlist::safe_default())
+    }
+
+    pub(crate) fn action<'a>(&self, tag: &str, attrs: &'a [HtmlAttr]) -> TagAction<'a> {
+        match self {
+            HtmlPolicy::AllowAll => TagAction::Keep(attrs.iter().collect()),
+            _ if is_script_like(tag) => TagAction::Drop,
+            HtmlPolicy::StripToText => TagAction::Unwrap,
+            HtmlPolicy::Allowlist(list) => {
+                if !list.allows_tag(tag) {
+                    return TagAction::Unwrap;
+                }
+                TagAction::Keep(
+                    attrs
+                        .iter()
+                        .filter(|a| list.allows_attr(tag, &a.name) && !is_unsafe_attr(a))
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Apply the policy to tags embedded in literal text.
+    ///
+    /// Comments are removed, and a `<` that does not start a tag is escaped.
+    pub(crate) fn sanitize_text(&self, text: &str) -> String {
+        if *self == HtmlPolicy::AllowAll || !text.contains('<') {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find('<') {
+            out.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+
+            if tail.starts_with("<!--") {
+                rest = tail.find("-->").map(|e| &tail[e + 3..]).unwrap_or("");
+                continue;
+            }
+
+            let Some(tag) = parse_text_tag(tail) else {
+                out.push_str("&lt;");
+                rest = &tail[1..];
+                continue;
+            };
+            rest = &tail[tag.len..];
+
+            let attrs = parse_html_attrs(tag.attrs);
+            match self.action(tag.name, &attrs) {
+                TagAction::Keep(kept) => {
+                    out.push('<');
+                    if tag.closing {
+                        out.push('/');
+                    }
+                    out.push_str(tag.name);
+                    for a in kept {
+                        out.push(' ');
+                        out.push_str(&a.name);
+                        if let Some(v) = &a.value {
+                            out.push_str("=\"");
+                            out.push_str(v);
+                            out.push('"');
+                        }
+                    }
+                    out.push_str(if tag.self_closing { " />" } else { ">" });
+                }
+                TagAction::Unwrap => {}
+                TagAction::Drop => {
+                    // skip everything up to and including the matching close tag.
+                    if !tag.closing && !tag.self_closing {
+                        let close = format!("</{}", tag.name.to_ascii_lowercase());
+                        rest = match rest.to_ascii_lowercase().find(&close) {
+                            Some(c) => rest[c..]
+                                .find('>')
+                                .map(|e| &rest[c + e + 1..])
+                                .unwrap_or(""),
+                            None => "",
+                        };
+                    }
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Whether the renderer may emit `<a name="...">` anchors of its own.
+    pub(crate) fn allows_anchors(&self) -> bool {
+        match self {
+            HtmlPolicy::AllowAll => true,
+            HtmlPolicy::Allowlist(list) => list.allows_attr("a", "name"),
+            HtmlPolicy::StripToText => false,
+        }
+    }
+}
+
+fn is_script_like(tag: &str) -> bool {
+    ["script", "style", "iframe", "object", "embed"]
+        .iter()
+        .any(|t| tag.eq_ignore_ascii_case(t))
+}
+
+fn is_unsafe_attr(a: &HtmlAttr) -> bool {
+    if a.name.to_ascii_lowercase().starts_with("on") {
+        return true;
+    }
+    a.value.as_deref().is_some_and(|v| {
+        let v: String = v
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        v.starts_with("javascript:") || v.starts_with("vbscript:") || v.starts_with("data:")
+    })
+}
+
+struct TextTag<'a> {
+    name: &'a str,
+    attrs: &'a str,
+    closing: bool,
+    self_closing: bool,
+    /// Byte length of the whole tag, including `<` and `>`.
+    len: usize,
+}
+
+/// Recognize `<name ...>`, `</name>`, or `<name ... />` at the start of `s`.
+fn parse_text_tag(s: &str) -> Option<TextTag<'_>> {
+    let body = s.strip_prefix('<')?;
+    let (closing, body) = match body.strip_prefix('/') {
+        Some(b) => (true, b),
+        None => (false, body),
+    };
+    if !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name_len = body
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(body.len());
+    let name = &body[..name_len];
+    let after = &body[name_len..];
+    if !after.starts_with(['>', '/', ' ', '\t', '\n']) {
+        return None;
+    }
+    let end = after.find('>')?;
+    if after[..end].contains('<') {
+        return None;
+    }
+    let inner = after[..end].trim();
+    let self_closing = inner.ends_with('/');
+    Some(TextTag {
+        name,
+        attrs: inner.trim_end_matches('/').trim(),
+        closing,
+        self_closing,
+        len: s.len() - after.len() + end + 1,
+    })
+}
@@ -3,26 +3,60 @@
 //! This module intentionally operates **only** on the parsed AST (typically loaded
 //! from JSON) and does not inspect raw `.wiki` text.
 
+mod backend;
+mod chess;
+mod code_indent;
+mod code_lang;
+mod escape;
+mod hooks;
+pub mod html;
+mod html_policy;
+mod table_align;
+mod templates;
+mod text_rules;
+mod timeline;
+mod url_rewrite;
+pub mod wikitext;
+mod wrap;
+
+pub use backend::{MarkupBackend, ObsidianMarkdown};
+pub use chess::ChessDiagramStyle;
+pub use code_indent::CodeNormalization;
+pub use code_lang::default_code_lang_aliases;
+pub use escape::{CharEscape, EscapePolicy};
+pub use hooks::{BlockHook, DocumentHook, InlineHook, RenderHooks};
+pub use html_policy::{HtmlAllowlist, HtmlPolicy};
+pub use table_align::{TableAlignOverride, TableAlignment, TableColumnAlign, TableMatch};
+pub use templates::{TemplateHandler, TemplateHandlers};
+pub use text_rules::{TextRule, TextRules};
+pub use timeline::{TimelineStyle, render_timeline};
+pub use url_rewrite::{UrlRewriteRule, UrlRewrites};
+
+use html_policy::TagAction;
+
 use crate::ast::*;
+use crate::infobox::InfoboxOptions;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Rendering options that control formatting decisions.
 #[derive(Debug, Clone)]
 pub struct RenderOptions {
-    /// If true, render `CodeBlockKind::LeadingSpace` as a Markdown blockquote rather than
-    /// a fenced code block.
-    pub leading_space_as_blockquote: bool,
+    /// Override for leading-space (indented) blocks. The parser classifies each block as
+    /// quoted prose or code; `None` renders that classification as-is, `Some(true)` forces
+    /// every such block to a Markdown blockquote and `Some(false)` forces a fenced code block.
+    pub leading_space_as_blockquote: Option<bool>,
 
-    /// Obsidian's Markdown renderer can misinterpret literal `*` characters
-    /// in normal text as emphasis markers, even when surrounded by spaces.
-    ///
-    /// When enabled, any literal `*` that would otherwise be rendered as text
-    /// (i.e., from plain text/Raw nodes, not the emphasis markers we emit for
-    /// Bold/Italic) is replaced with `obsidian_text_asterisk_replacement`.
-    pub obsidian_text_asterisk_workaround: bool,
+    /// Where a quote's `By [[Someone]], 1997:` intro line is emitted.
+    pub quote_attribution: QuoteAttributionStyle,
 
-    /// Text to replace `*` with when `obsidian_text_asterisk_workaround` is true.
-    /// The default value is `&middot;`.
-    pub obsidian_text_asterisk_replacement: String,
+    /// How Markdown-significant characters in literal text (Text/Raw nodes) are
+    /// escaped. Markers emitted by the renderer itself are never escaped.
+    ///
+    /// Defaults to [`EscapePolicy::obsidian`], which replaces `*` with `&middot;`
+    /// and backslash-escapes `_`, `#`, `[`, `]`, `~`, and backticks where needed.
+    pub text_escapes: EscapePolicy,
 
     /// If true, render standalone `[[File:...]]` links as Markdown images.
     pub render_file_links_as_images: bool,
@@ -32,6 +66,11 @@ pub struct RenderOptions {
     /// For chessprogramming.org, this should be `https://www.chessprogramming.org`.
     pub mediawiki_base_url: String,
 
+    /// Where image and file URLs point instead of `<mediawiki_base_url>/images`,
+    /// e.g. a CDN or a local server with the downloaded files:
+    /// `/images/a/a9/Board.jpg` becomes `<image_base_url>/a/a9/Board.jpg`.
+    pub image_base_url: Option<String>,
+
     /// Default width (in pixels) to request for embedded images.
     pub default_image_width_px: u32,
 
@@ -52,31 +91,585 @@ pub struct RenderOptions {
     /// separate it from preceding content.
     pub emit_br_before_references: bool,
 
-    /// If true, render tables and table captions (above) centered using HTML.
+    /// If true, render tables and table captions centered using HTML.
     pub center_tables_and_captions: bool,
+
+    /// If set, tables with too many columns or too wide a layout are wrapped in a
+    /// horizontally scrolling `<div style="overflow-x:auto">`. Composes with
+    /// `center_tables_and_captions` (the scroll container goes outside).
+    pub scroll_wide_tables: Option<WideTables>,
+
+    /// Adjustments to the column alignment of tables (numeric columns, the first
+    /// column, per-table overrides).
+    pub table_alignment: TableAlignment,
+
+    /// How `|+` table captions are styled. (The HTML renderer always emits `<caption>`.)
+    pub table_caption_style: TableCaptionStyle,
+
+    /// Whether table captions go above or below the table.
+    pub table_caption_position: TableCaptionPosition,
+
+    /// Which rows of a table make up its Markdown header row.
+    pub table_header_rows: TableHeaderRows,
+
+    /// Emit an anchor before each table, named by its `id`, its caption, or its
+    /// position (see [`crate::anchors::table_ids`]), so `[[Page#table-perft-results]]`
+    /// links reach it.
+    pub table_anchors: bool,
+
+    /// Handlers for specific templates; unhandled templates are passed through
+    /// as `{{name|...}}`.
+    pub template_handlers: TemplateHandlers,
+
+    /// Maps code block languages (lowercase) to the tag written after the fence,
+    /// e.g. `c++` -> `cpp`. Languages not in the table are kept as-is.
+    pub code_lang_aliases: HashMap<String, String>,
+
+    /// If true, guess a language for `<pre>`/`<syntaxhighlight>` blocks that don't
+    /// declare one. Blocks that don't clearly look like code stay untagged.
+    pub infer_code_lang: bool,
+
+    /// Whitespace cleanup (dedent, tab expansion, trailing whitespace) applied to
+    /// fenced code blocks.
+    pub code_normalization: CodeNormalization,
+
+    /// If set, fenced code blocks longer than this many lines are wrapped in a
+    /// collapsed `<details>` element summarized as `lang · N lines`.
+    pub collapse_code_lines: Option<usize>,
+
+    /// How chess diagram templates (`{{FEN|...}}`, `{{Chess diagram|...}}`) are rendered.
+    pub chess_diagram_style: ChessDiagramStyle,
+
+    /// If true, chess piece images (`[[File:Chess_qlt45.svg]]`) and numeric chess
+    /// entities (`&#9813;`) in running text become Unicode symbols (`♕`), so inline
+    /// notation reads without fetching images.
+    pub chess_symbols: bool,
+
+    /// Image URL used by [`ChessDiagramStyle::Image`]; `{fen}` is replaced with the
+    /// URL-encoded FEN of the position.
+    pub chess_diagram_image_url: String,
+
+    /// How EasyTimeline (`<timeline>`) charts are rendered.
+    pub timeline_style: TimelineStyle,
+
+    /// Which raw HTML tags and attributes from the wikitext are passed through.
+    pub html_policy: HtmlPolicy,
+
+    /// How `<br/>` line breaks in running text are emitted.
+    ///
+    /// Table cells always use `<br/>`, since a Markdown table row cannot span lines.
+    pub hard_break_style: HardBreakStyle,
+
+    /// How `<poem>` verse is laid out. Its lines always end in hard line breaks
+    /// ([`RenderOptions::hard_break_style`]).
+    pub poem_style: PoemStyle,
+
+    /// What becomes of blocks the parser couldn't make sense of ([`BlockKind::Raw`]).
+    pub raw_blocks: RawBlockPolicy,
+
+    /// If set, reflow paragraph text at this many columns. Links, code spans,
+    /// HTML tags, and footnote markers are never split across lines.
+    pub wrap_width: Option<usize>,
+
+    /// Rules applied to external link URLs before they are emitted.
+    pub url_rewrites: UrlRewrites,
+
+    /// Regex rules applied to running text (see [`TextRules`]). Text inside links
+    /// and code is left alone.
+    pub text_rules: TextRules,
+
+    /// How unlabeled bracketed external links (`[https://example.com]`) are rendered.
+    pub external_link_numbering: ExternalLinkNumbering,
+
+    /// Where footnote definitions (`[^1]: ...`) go.
+    pub footnote_placement: FootnotePlacement,
+
+    /// If true, refs whose rendered bodies are identical share one footnote.
+    pub dedupe_refs: bool,
+
+    /// Where `ISBN`, `RFC`, and `PMID` magic links point.
+    pub magic_link_urls: MagicLinkUrls,
+
+    /// How the page's breadcrumb ([`Document::breadcrumb`]) is rendered.
+    pub breadcrumb_style: BreadcrumbStyle,
+
+    /// How `<span id="...">` anchors (in headings and elsewhere) are emitted.
+    pub heading_anchor_style: HeadingAnchorStyle,
+
+    /// If true, top-level quotes and tables are followed by an Obsidian block id
+    /// (`^3f2a9c`) so other notes can embed them with `![[Page#^3f2a9c]]`. The id
+    /// is a hash of the block's wikitext, so it survives regeneration.
+    pub block_ids: bool,
+
+    /// If true, navigation templates are dropped: templates named like `navbox`,
+    /// and paragraphs made only of templates at the bottom of the page (after
+    /// the last text, references, and rules).
+    pub drop_navboxes: bool,
+
+    /// If true, the "See also" section is left out of the body. Its links are in
+    /// the frontmatter `related` list either way.
+    pub drop_see_also: bool,
+
+    /// If set, infobox templates are left out of the body; their fields go into
+    /// the frontmatter instead (see [`crate::infobox`]).
+    pub infobox: Option<InfoboxOptions>,
+
+    /// Callbacks that may replace the Markdown rendered for a block, an inline
+    /// node, or the whole document (see [`RenderHooks`]).
+    pub hooks: RenderHooks,
+
+    /// Emits the markup for each construct. Defaults to [`ObsidianMarkdown`];
+    /// supply a custom [`MarkupBackend`] to override individual constructs.
+    pub backend: Arc<dyn MarkupBackend>,
 }
 
 impl Default for RenderOptions {
     fn default() -> Self {
         Self {
-            leading_space_as_blockquote: true,
-            obsidian_text_asterisk_workaround: true,
-            obsidian_text_asterisk_replacement: "&middot;".to_string(),
+            leading_space_as_blockquote: None,
+            quote_attribution: QuoteAttributionStyle::Intro,
+            text_escapes: EscapePolicy::obsidian(),
             render_file_links_as_images: true,
             mediawiki_base_url: "https://www.chessprogramming.org".to_string(),
+            image_base_url: None,
             default_image_width_px: 300,
             respect_wikitext_image_width: false,
             insert_hr_after_top_image: true,
             emit_references_heading: true,
             emit_br_before_references: true,
             center_tables_and_captions: false,
+            scroll_wide_tables: None,
+            table_alignment: TableAlignment::default(),
+            table_caption_style: TableCaptionStyle::Plain,
+            table_caption_position: TableCaptionPosition::Above,
+            table_header_rows: TableHeaderRows::default(),
+            table_anchors: false,
+            template_handlers: TemplateHandlers::builtin(),
+            code_lang_aliases: default_code_lang_aliases(),
+            infer_code_lang: true,
+            code_normalization: CodeNormalization::default(),
+            collapse_code_lines: None,
+            chess_diagram_style: ChessDiagramStyle::Ascii,
+            chess_symbols: false,
+            chess_diagram_image_url: chess::DEFAULT_DIAGRAM_IMAGE_URL.to_string(),
+            timeline_style: TimelineStyle::Fenced,
+            html_policy: HtmlPolicy::AllowAll,
+            hard_break_style: HardBreakStyle::Html,
+            poem_style: PoemStyle::Plain,
+            raw_blocks: RawBlockPolicy::Fenced,
+            wrap_width: None,
+            url_rewrites: UrlRewrites::default(),
+            text_rules: TextRules::default(),
+            external_link_numbering: ExternalLinkNumbering::Inline,
+            footnote_placement: FootnotePlacement::References,
+            dedupe_refs: false,
+            magic_link_urls: MagicLinkUrls::default(),
+            breadcrumb_style: BreadcrumbStyle::Paragraph,
+            heading_anchor_style: HeadingAnchorStyle::Html,
+            block_ids: false,
+            drop_navboxes: false,
+            drop_see_also: false,
+            infobox: None,
+            hooks: RenderHooks::default(),
+            backend: Arc::new(ObsidianMarkdown),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// A hash of every option (hex MD5), equal for options that render alike.
+    /// Template handlers and the backend count by name, not by behavior.
+    pub fn fingerprint(&self) -> String {
+        let mut aliases: Vec<(&String, &String)> = self.code_lang_aliases.iter().collect();
+        aliases.sort();
+        let rest = RenderOptions {
+            code_lang_aliases: HashMap::new(),
+            ..self.clone()
+        };
+        format!("{:x}", md5::compute(format!("{:?}{:?}", rest, aliases)))
+    }
+
+    /// The URL files are under: [`Self::image_base_url`], or the wiki's `/images`.
+    pub fn image_base(&self) -> String {
+        image_base(&self.mediawiki_base_url, self.image_base_url.as_deref())
+    }
+}
+
+/// Markdown hard line break styles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HardBreakStyle {
+    /// `<br/>` followed by a newline.
+    #[default]
+    Html,
+    /// A trailing backslash (CommonMark).
+    Backslash,
+    /// Two trailing spaces. Invisible, but understood by almost every renderer.
+    TrailingSpaces,
+}
+
+/// How raw blocks ([`BlockKind::Raw`]) are rendered. Their number per article is
+/// in the [manifest](crate::manifest) and the corpus [stats](crate::stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawBlockPolicy {
+    /// In a ```` ```text ```` fence, visible but inert.
+    #[default]
+    Fenced,
+    /// In an HTML comment: kept in the file, hidden when it is displayed.
+    Comment,
+    /// As is, for the Markdown renderer to make of it what it can.
+    Passthrough,
+    /// Left out.
+    Drop,
+}
+
+/// How `<poem>` blocks are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoemStyle {
+    /// One paragraph per stanza.
+    #[default]
+    Plain,
+    /// The stanzas inside a blockquote.
+    BlockQuote,
+}
+
+/// How the `Home * People * Name` breadcrumb at the top of a page is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreadcrumbStyle {
+    /// As written: usually a bold line of links separated by `*`.
+    #[default]
+    Paragraph,
+    /// Left out.
+    Omit,
+    /// `###### [[Main Page|Home]] › [[People]] › Name`, a level-6 heading so it
+    /// stays below every section in the outline.
+    Heading,
+    /// `[[Main Page|Home]] › [[People]] › Name` on a line of its own.
+    Line,
+}
+
+/// How `<span id="x">` link targets are emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadingAnchorStyle {
+    /// `<a name="x"></a>` (only if the [`HtmlPolicy`] allows `<a name>`).
+    #[default]
+    Html,
+    /// pandoc/Kramdown attributes: `## Title {#x}` on headings, `[]{#x}` and
+    /// `[text]{#x}` elsewhere.
+    Attribute,
+    /// Left out, for targets that generate heading ids themselves.
+    None,
+}
+
+/// How unlabeled bracketed external links (`[https://example.com]`) are rendered.
+/// MediaWiki shows them as `[1]`, `[2]`, ... numbered per page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExternalLinkNumbering {
+    /// `[\[1\]](https://example.com)`, numbered in order of appearance.
+    #[default]
+    Inline,
+    /// A footnote marker whose body is the URL, numbered along with the refs.
+    Footnote,
+    /// `<https://example.com>`, unnumbered.
+    Autolink,
+}
+
+/// Where footnote definitions are emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnotePlacement {
+    /// At the page's `<references />`; pages without one get no definitions.
+    #[default]
+    References,
+    /// After the section that first references them, before the next heading.
+    Section,
+    /// In a `## References` section at the end of the page, wherever (and
+    /// whether) the page has `<references />`.
+    End,
+    /// Inline, as Obsidian inline footnotes (`^[Knuth 1975]`); nothing is
+    /// numbered.
+    Inline,
+}
+
+/// URL templates for magic links. `{id}` is replaced with the identifier
+/// (`0201896834` for `ISBN 0-201-89683-4`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagicLinkUrls {
+    pub isbn: String,
+    pub rfc: String,
+    pub pmid: String,
+}
+
+impl Default for MagicLinkUrls {
+    fn default() -> Self {
+        MagicLinkUrls {
+            isbn: "https://www.bookfinder.com/search/?isbn={id}".to_string(),
+            rfc: "https://datatracker.ietf.org/doc/html/rfc{id}".to_string(),
+            pmid: "https://pubmed.ncbi.nlm.nih.gov/{id}/".to_string(),
+        }
+    }
+}
+
+impl MagicLinkUrls {
+    /// The URL `link` points to.
+    pub fn url(&self, link: &MagicLink) -> String {
+        let template = match link.kind {
+            MagicLinkKind::Isbn => &self.isbn,
+            MagicLinkKind::Rfc => &self.rfc,
+            MagicLinkKind::Pmid => &self.pmid,
+        };
+        template.replace("{id}", &link.id)
+    }
+}
+
+/// Markup for table captions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableCaptionStyle {
+    /// The caption text as its own paragraph.
+    #[default]
+    Plain,
+    /// `*caption*`
+    Italic,
+    /// `**caption**`
+    Bold,
+}
+
+/// Placement of table captions relative to the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableCaptionPosition {
+    #[default]
+    Above,
+    Below,
+}
+
+/// How the header row of a Markdown table is chosen. Markdown tables have exactly
+/// one header row; wikitables can have none or several.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableHeaderRows {
+    /// The first row with a header cell (or the first row); other rows stay data
+    /// rows, header cells and all.
+    #[default]
+    FirstHeaderRow,
+    /// An empty header row, with every row of the table as a data row.
+    Empty,
+    /// The first N rows, their cells joined column by column with a space.
+    Join(usize),
+    /// The first row with a header cell; rows after it made only of header cells
+    /// become data rows with their cells in bold.
+    DemoteExtra,
+}
+
+/// When a table counts as wide enough to scroll (see
+/// [`RenderOptions::scroll_wide_tables`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WideTables {
+    /// Tables with more columns than this scroll.
+    pub max_columns: usize,
+    /// Tables wider than this many characters scroll, estimated as the widest
+    /// cell of each column plus three characters of padding and separator.
+    pub max_width: usize,
+}
+
+impl Default for WideTables {
+    fn default() -> Self {
+        WideTables {
+            max_columns: 10,
+            max_width: 160,
+        }
+    }
+}
+
+impl WideTables {
+    /// True if a table of these rendered cells is too wide.
+    fn matches(&self, rows: &[Vec<String>], col_count: usize) -> bool {
+        if col_count > self.max_columns {
+            return true;
+        }
+        let width: usize = (0..col_count)
+            .map(|c| {
+                let widest = rows
+                    .iter()
+                    .filter_map(|r| r.get(c))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                widest + 3
+            })
+            .sum();
+        width + 1 > self.max_width
+    }
+}
+
+/// Placement of a quote's attribution line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteAttributionStyle {
+    /// The intro line as written, as a paragraph above the quote.
+    #[default]
+    Intro,
+    /// A `— Robert Hyatt, 1997` line at the end of the quote.
+    Trailer,
+    /// An Obsidian `> [!quote] Robert Hyatt, 1997` callout title.
+    CalloutTitle,
+}
+
+/// Per-document state shared by all emitters while rendering.
+#[derive(Debug, Default)]
+pub struct RenderContext<'a> {
+    refs: Vec<String>,
+    /// Number of footnote definitions already emitted by a section.
+    refs_emitted: usize,
+    /// Footnote numbers of the named refs seen so far.
+    named_refs: HashMap<String, usize>,
+    /// Footnote numbers by body, when identical refs share a footnote.
+    ref_bodies: Option<HashMap<String, usize>>,
+    /// Contents of list-defined refs, by name.
+    ref_definitions: HashMap<String, Vec<InlineNode<'a>>>,
+    /// Unlabeled external links numbered so far.
+    external_links: usize,
+    in_table_cell: bool,
+    /// Depth of links and code tags being rendered; text rules only apply at 0.
+    protected_text: usize,
+    /// How often each block id has been handed out.
+    block_ids: HashMap<String, usize>,
+    /// The document's `<span id>` anchors, so in-page links to them can be told
+    /// from links to headings.
+    span_anchors: HashSet<String>,
+    /// The document's table ids, in the order tables are rendered, and how many
+    /// have been rendered so far.
+    table_ids: Vec<String>,
+    tables: usize,
+}
+
+impl<'a> RenderContext<'a> {
+    /// A context for rendering `doc`, with its list-defined refs and span anchors
+    /// registered.
+    ///
+    /// Its table ids assume tables are rendered in document order.
+    pub fn for_document(doc: &Document<'a>) -> Self {
+        RenderContext {
+            ref_definitions: crate::ast::list_defined_refs(&doc.blocks),
+            span_anchors: crate::ast::span_anchors(doc)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            table_ids: crate::anchors::table_ids(doc),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `id` is one of the document's `<span id>` anchors (rendered as
+    /// `<a name>`) rather than, say, a heading.
+    pub fn is_span_anchor(&self, id: &str) -> bool {
+        self.span_anchors.contains(id)
+    }
+
+    /// Whether `id` is the anchor of one of the document's tables.
+    pub fn is_table_anchor(&self, id: &str) -> bool {
+        self.table_ids.iter().any(|t| t == id)
+    }
+
+    /// The id of the next table to be rendered.
+    fn next_table_id(&mut self) -> Option<String> {
+        self.tables += 1;
+        self.table_ids.get(self.tables - 1).cloned()
+    }
+
+    /// Record a footnote body and return its 1-based footnote number. With
+    /// [`RenderOptions::dedupe_refs`], a body seen before gets its earlier number.
+    pub fn push_ref(&mut self, content: String) -> usize {
+        let key = content.trim();
+        if let Some(bodies) = &mut self.ref_bodies
+            && !key.is_empty()
+        {
+            if let Some(&n) = bodies.get(key) {
+                return n;
+            }
+            bodies.insert(key.to_string(), self.refs.len() + 1);
+        }
+        self.refs.push(content);
+        self.refs.len()
+    }
+
+    /// Like [`push_ref`](Self::push_ref) for a ref with a `name`: every use of the
+    /// name gets the number of its first use. A later use with content fills in
+    /// an empty body.
+    pub fn push_named_ref(&mut self, name: &str, content: String) -> usize {
+        match self.named_refs.get(name) {
+            Some(&n) => {
+                if self.refs[n - 1].trim().is_empty() {
+                    if let Some(bodies) = &mut self.ref_bodies
+                        && !content.trim().is_empty()
+                    {
+                        bodies.entry(content.trim().to_string()).or_insert(n);
+                    }
+                    self.refs[n - 1] = content;
+                }
+                n
+            }
+            None => {
+                let n = self.push_ref(content);
+                self.named_refs.insert(name.to_string(), n);
+                n
+            }
+        }
+    }
+
+    /// Number the next unlabeled external link and return its 1-based number.
+    pub fn next_external_link(&mut self) -> usize {
+        self.external_links += 1;
+        self.external_links
+    }
+
+    /// The content of the list-defined ref called `name`.
+    pub fn ref_definition(&self, name: &str) -> Option<&[InlineNode<'a>]> {
+        self.ref_definitions.get(name).map(Vec::as_slice)
+    }
+
+    /// Footnote bodies collected so far, in order.
+    pub fn refs(&self) -> &[String] {
+        &self.refs
+    }
+
+    /// True while the content of a table cell is being rendered.
+    pub fn in_table_cell(&self) -> bool {
+        self.in_table_cell
+    }
+
+    /// Like [`for_document`](Self::for_document), reusing the collections
+    /// `pool` kept from an earlier document.
+    fn from_pool(doc: &Document<'a>, pool: &mut RenderPool) -> Self {
+        RenderContext {
+            refs: std::mem::take(&mut pool.refs),
+            named_refs: std::mem::take(&mut pool.named_refs),
+            block_ids: std::mem::take(&mut pool.block_ids),
+            ..Self::for_document(doc)
         }
     }
+
+    /// Empty the context's collections and hand them back to `pool`.
+    fn release(mut self, pool: &mut RenderPool) {
+        self.refs.clear();
+        self.named_refs.clear();
+        self.block_ids.clear();
+        pool.refs = self.refs;
+        pool.named_refs = self.named_refs;
+        pool.block_ids = self.block_ids;
+    }
 }
 
+/// Allocations reused from one document to the next by a bulk run: the context's
+/// footnote and block id tables keep their capacity, and each output buffer starts
+/// at the size of the previous document's.
 #[derive(Debug, Default)]
-struct RenderContext {
+pub struct RenderPool {
     refs: Vec<String>,
+    named_refs: HashMap<String, usize>,
+    block_ids: HashMap<String, usize>,
+    output_len: usize,
+}
+
+impl RenderPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
 }
 
 pub fn render_doc(doc: &Document) -> String {
@@ -84,22 +677,54 @@ pub fn render_doc(doc: &Document) -> String {
 }
 
 pub fn render_doc_with_options(doc: &Document, opts: &RenderOptions) -> String {
-    let mut ctx = RenderContext::default();
-    let mut out = String::new();
+    render_doc_pooled(doc, opts, &mut RenderPool::default())
+}
+
+/// Like [`render_doc_with_options`], reusing `pool`'s allocations; bulk runs keep
+/// one pool for every document.
+pub fn render_doc_pooled(doc: &Document, opts: &RenderOptions, pool: &mut RenderPool) -> String {
+    let mut ctx = RenderContext::from_pool(doc, pool);
+    if opts.dedupe_refs {
+        ctx.ref_bodies = Some(HashMap::new());
+    }
+    let mut out = String::with_capacity(pool.output_len);
     let mut inserted_top_image_hr = false;
     let mut seen_heading = false;
+    let navbox_tail = if opts.drop_navboxes {
+        trailing_navbox_start(&doc.blocks)
+    } else {
+        doc.blocks.len()
+    };
+
+    let see_also = opts
+        .drop_see_also
+        .then(|| crate::ast::see_also_section(&doc.blocks))
+        .flatten()
+        .unwrap_or_default();
 
     for (bi, block) in doc.blocks.iter().enumerate() {
-        if !out.is_empty() {
-            // separate blocks with a single blank line.
-            out.push_str("\n\n");
+        if bi >= navbox_tail && is_template_paragraph(block) {
+            continue;
+        }
+        if see_also.contains(&bi) {
+            continue;
         }
-
         let is_top_image = !seen_heading
             && opts.insert_hr_after_top_image
             && !inserted_top_image_hr
             && block_is_standalone_image_paragraph(block, opts);
 
+        if opts.footnote_placement == FootnotePlacement::Section
+            && matches!(block.kind, BlockKind::Heading { .. })
+        {
+            push_block(&mut out, &render_section_footnotes(&mut ctx));
+        }
+        if opts.footnote_placement != FootnotePlacement::References
+            && is_references_section(&doc.blocks, bi, opts)
+        {
+            continue;
+        }
+
         let rendered = match &block.kind {
             BlockKind::References { .. } => {
                 let prev_is_refs_heading = bi
@@ -108,11 +733,40 @@ pub fn render_doc_with_options(doc: &Document, opts: &RenderOptions) -> String {
                     .map(|b| heading_is_named_references(b, opts))
                     .unwrap_or(false);
 
-                render_references(&mut ctx, opts, /*emit_heading*/ !prev_is_refs_heading)
+                let refs = opts.backend.references(
+                    /*emit_heading*/ !prev_is_refs_heading,
+                    &mut ctx,
+                    opts,
+                );
+                opts.hooks.block(block, refs)
             }
-            _ => render_block(block, &mut ctx, opts),
+            _ => match doc.breadcrumb.as_ref().filter(|b| b.is_block(block)) {
+                Some(breadcrumb) => {
+                    let crumbs = render_breadcrumb(block, breadcrumb, &mut ctx, opts);
+                    opts.hooks.block(block, crumbs)
+                }
+                None => render_block(block, &mut ctx, opts),
+            },
         };
 
+        // sections hidden on the page (`<includeonly>`), paragraphs holding only an
+        // infobox, and dropped raw blocks leave no gap.
+        if rendered.is_empty()
+            && matches!(
+                block.kind,
+                BlockKind::Inclusion { .. } | BlockKind::Paragraph { .. } | BlockKind::Raw { .. }
+            )
+        {
+            continue;
+        }
+        let rendered = match opts.block_ids.then(|| block_id(block, &mut ctx)).flatten() {
+            Some(id) if !rendered.is_empty() => format!("{}\n\n^{}", rendered, id),
+            _ => rendered,
+        };
+        if !out.is_empty() {
+            // separate blocks with a single blank line.
+            out.push_str("\n\n");
+        }
         out.push_str(&rendered);
 
         if is_top_image {
@@ -125,43 +779,212 @@ pub fn render_doc_with_options(doc: &Document, opts: &RenderOptions) -> String {
         }
     }
 
+    match opts.footnote_placement {
+        FootnotePlacement::Section => push_block(&mut out, &render_section_footnotes(&mut ctx)),
+        FootnotePlacement::End => {
+            let refs = opts
+                .backend
+                .references(/*emit_heading*/ true, &mut ctx, opts);
+            push_block(&mut out, &refs);
+        }
+        FootnotePlacement::References | FootnotePlacement::Inline => {}
+    }
+
     // trim trailing whitespace/newlines for stable output.
     while matches!(out.as_bytes().last(), Some(b'\n' | b' ' | b'\t' | b'\r')) {
         out.pop();
     }
-    out
+    pool.output_len = out.len();
+    ctx.release(pool);
+    opts.hooks.document(doc, out)
 }
 
-fn render_block(block: &BlockNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
-    match &block.kind {
-        BlockKind::Heading { level, content } => render_heading(*level, content, ctx, opts),
-        BlockKind::Paragraph { content } => render_paragraph(content, ctx, opts),
-        BlockKind::List { items } => render_list(items, ctx, opts, 0),
-        BlockKind::CodeBlock { block } => {
-            render_code_block(block.kind, block.lang.as_deref(), &block.text, ctx, opts)
-        }
-        BlockKind::Table { table } => render_table(table, ctx, opts),
-        BlockKind::BlockQuote { blocks } => {
-            let mut inner = String::new();
-            for (i, b) in blocks.iter().enumerate() {
-                if i > 0 {
-                    inner.push_str("\n\n");
-                }
-                inner.push_str(&render_block(b, ctx, opts));
+/// Append a rendered block to `out`, separated by a blank line; empty ones are skipped.
+fn push_block(out: &mut String, block: &str) {
+    if block.is_empty() {
+        return;
+    }
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(block);
+}
+
+/// True for a `<references />` block, and for a `References` heading directly
+/// followed by one, which are left out when footnotes are placed elsewhere.
+fn is_references_section(blocks: &[BlockNode], i: usize, opts: &RenderOptions) -> bool {
+    match &blocks[i].kind {
+        BlockKind::References { .. } => true,
+        BlockKind::Heading { .. } => {
+            heading_is_named_references(&blocks[i], opts)
+                && blocks
+                    .get(i + 1)
+                    .is_some_and(|b| matches!(b.kind, BlockKind::References { .. }))
+        }
+        _ => false,
+    }
+}
+
+/// The definitions of the footnotes referenced since the last call.
+fn render_section_footnotes(ctx: &mut RenderContext) -> String {
+    let start = ctx.refs_emitted;
+    ctx.refs_emitted = ctx.refs.len();
+    footnote_definitions(&ctx.refs, start)
+}
+
+/// The block id of a quote or table: the start of the MD5 of its wikitext, with
+/// `-2`, `-3`, ... added for repeats of the same block on the page.
+fn block_id(block: &BlockNode, ctx: &mut RenderContext) -> Option<String> {
+    if !matches!(
+        block.kind,
+        BlockKind::BlockQuote { .. } | BlockKind::Table { .. }
+    ) {
+        return None;
+    }
+    let hash = format!("{:x}", md5::compute(wikitext::serialize_block(block)));
+    let id = hash[..6].to_string();
+    let seen = ctx.block_ids.entry(id.clone()).or_default();
+    *seen += 1;
+    Some(match *seen {
+        1 => id,
+        n => format!("{}-{}", id, n),
+    })
+}
+
+/// Render only the blocks under the heading reached by `heading_path` (see
+/// [`crate::ast::section_blocks`]), followed by the footnotes they reference.
+/// `None` if there is no such section.
+pub fn render_section(
+    doc: &Document,
+    heading_path: &[&str],
+    opts: &RenderOptions,
+) -> Option<String> {
+    let mut blocks = crate::ast::section_blocks(doc, heading_path)?.to_vec();
+    if !blocks
+        .iter()
+        .any(|b| matches!(b.kind, BlockKind::References { .. }))
+    {
+        // refs in the section still need their footnote bodies, including the
+        // ones defined in the page's `<references>` list.
+        let mut refs = Vec::new();
+        crate::ast::walk_blocks(&doc.blocks, &mut |b| {
+            if let BlockKind::References { node } = &b.kind {
+                refs.extend(node.refs.iter().cloned());
             }
-            prefix_lines(&inner, "> ")
+        });
+        let end = blocks.last().map_or(doc.span.end, |b| b.span.end);
+        blocks.push(BlockNode {
+            span: Span::new(end, end),
+            kind: BlockKind::References {
+                node: ReferencesNode {
+                    attrs: Vec::new(),
+                    refs,
+                },
+            },
+        });
+    }
+    let section = Document {
+        span: doc.span,
+        blocks,
+        categories: Vec::new(),
+        redirect: None,
+        breadcrumb: None,
+        trivia: Vec::new(),
+        parts: Vec::new(),
+    };
+    Some(render_doc_with_options(&section, opts))
+}
+
+/// Render a block through the configured backend, then its `on_block` hook.
+pub fn render_block(block: &BlockNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+    opts.hooks
+        .block(block, opts.backend.block(block, ctx, opts))
+}
+
+pub fn render_block_quote(
+    blocks: &[BlockNode],
+    attribution: Option<&QuoteAttribution>,
+    html: bool,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let forced_code = !html && opts.leading_space_as_blockquote == Some(false);
+    let style = match attribution {
+        Some(_) if forced_code => QuoteAttributionStyle::Intro,
+        // a source written after the quote has no intro line to keep.
+        Some(a) if a.trailing && opts.quote_attribution == QuoteAttributionStyle::Intro => {
+            QuoteAttributionStyle::Trailer
+        }
+        _ => opts.quote_attribution,
+    };
+    let mut out = String::new();
+    if let Some(a) = attribution
+        && style == QuoteAttributionStyle::Intro
+    {
+        let intro = opts.backend.paragraph(&a.content, ctx, opts);
+        if !intro.is_empty() {
+            out.push_str(&intro);
+            out.push_str("\n\n");
         }
-        BlockKind::HorizontalRule => "---".to_string(),
-        // most documents render references via `render_doc_with_options` so that
-        // we can decide whether to emit a heading based on the surrounding context.
-        BlockKind::References { .. } => render_references(ctx, opts, /*emit_heading*/ true),
-        BlockKind::HtmlBlock { node } => render_html_block(node, ctx, opts),
-        BlockKind::MagicWord { name } => format!("<!-- {} -->", name),
-        BlockKind::Raw { text } => {
-            // keep raw blocks visible but non-destructive.
-            format!("```text\n{}\n```", text.trim_end_matches('\n'))
+    }
+
+    if forced_code {
+        // forced code: keep the source of the quoted blocks verbatim.
+        let text: Vec<String> = blocks.iter().map(wikitext::serialize_block).collect();
+        out.push_str(&render_code_block(
+            CodeBlockKind::LeadingSpace,
+            None,
+            &text.join("\n\n"),
+            ctx,
+            opts,
+        ));
+        return out;
+    }
+
+    let source = attribution
+        .filter(|_| style != QuoteAttributionStyle::Intro)
+        .map(|a| attribution_source(&render_inlines(&a.content, ctx, opts)));
+    let mut inner = String::new();
+    if let Some(source) = &source
+        && style == QuoteAttributionStyle::CalloutTitle
+    {
+        inner.push_str(&format!("[!quote] {}\n", source));
+    }
+    for (i, b) in blocks.iter().enumerate() {
+        if i > 0 {
+            inner.push_str("\n\n");
         }
+        inner.push_str(&render_block(b, ctx, opts));
+    }
+    if let Some(source) = &source
+        && style == QuoteAttributionStyle::Trailer
+    {
+        inner.push_str(&format!("\n\n— {}", source));
+    }
+    out.push_str(&prefix_lines(&inner, "> "));
+    out
+}
+
+/// `By [[Robert Hyatt]], 1997:` => `[[Robert Hyatt]], 1997`
+fn attribution_source(intro: &str) -> String {
+    let intro = intro.trim();
+    let lower = intro.to_ascii_lowercase();
+    let rest = ["quotes by ", "quote by ", "by "]
+        .iter()
+        .find(|p| lower.starts_with(*p))
+        .map(|p| &intro[p.len()..])
+        .unwrap_or(intro);
+    let mut rest = rest.trim();
+    // footnote markers may follow the colon; keep them after the name.
+    let mut notes = String::new();
+    while rest.ends_with(']')
+        && let Some(i) = rest.rfind("[^")
+    {
+        notes.insert_str(0, &rest[i..]);
+        rest = rest[..i].trim_end();
     }
+    let rest = rest.strip_suffix(':').unwrap_or(rest).trim_end();
+    format!("{}{}", rest, notes)
 }
 
 fn heading_is_named_references(block: &BlockNode, opts: &RenderOptions) -> bool {
@@ -176,6 +999,76 @@ fn heading_is_named_references(block: &BlockNode, opts: &RenderOptions) -> bool
     }
 }
 
+/// The breadcrumb paragraph `block` in the configured [`BreadcrumbStyle`].
+fn render_breadcrumb(
+    block: &BlockNode,
+    breadcrumb: &Breadcrumb,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let mut path: Vec<InlineNode> = Vec::new();
+    let steps = breadcrumb
+        .links
+        .iter()
+        .map(|link| InlineKind::InternalLink { link: link.clone() })
+        .chain(breadcrumb.current.iter().map(|text| InlineKind::Text {
+            value: text.as_str().into(),
+        }));
+    for kind in steps {
+        if !path.is_empty() {
+            path.push(InlineNode {
+                span: breadcrumb.span,
+                kind: InlineKind::Text {
+                    value: " › ".into(),
+                },
+            });
+        }
+        path.push(InlineNode {
+            span: breadcrumb.span,
+            kind,
+        });
+    }
+    match opts.breadcrumb_style {
+        BreadcrumbStyle::Paragraph => render_block(block, ctx, opts),
+        BreadcrumbStyle::Omit => String::new(),
+        BreadcrumbStyle::Heading => opts.backend.heading(6, &path, ctx, opts),
+        BreadcrumbStyle::Line => opts.backend.paragraph(&path, ctx, opts),
+    }
+}
+
+/// A paragraph holding nothing but templates, like a navigation box.
+fn is_template_paragraph(block: &BlockNode) -> bool {
+    let BlockKind::Paragraph { content } = &block.kind else {
+        return false;
+    };
+    content
+        .iter()
+        .any(|n| matches!(n.kind, InlineKind::Template { .. }))
+        && content.iter().all(|n| match &n.kind {
+            InlineKind::Template { .. } | InlineKind::LineBreak => true,
+            InlineKind::Text { value } => value.trim().is_empty(),
+            _ => false,
+        })
+}
+
+/// Where the bottom-of-page navigation starts: the index of the first of the
+/// trailing blocks that are template-only paragraphs, references, magic words,
+/// or rules.
+fn trailing_navbox_start(blocks: &[BlockNode]) -> usize {
+    blocks
+        .iter()
+        .rposition(|block| {
+            !is_template_paragraph(block)
+                && !matches!(
+                    block.kind,
+                    BlockKind::References { .. }
+                        | BlockKind::MagicWord { .. }
+                        | BlockKind::HorizontalRule
+                )
+        })
+        .map_or(0, |i| i + 1)
+}
+
 fn block_is_standalone_image_paragraph(block: &BlockNode, opts: &RenderOptions) -> bool {
     if !opts.render_file_links_as_images {
         return false;
@@ -187,7 +1080,7 @@ fn block_is_standalone_image_paragraph(block: &BlockNode, opts: &RenderOptions)
     }
 }
 
-fn render_paragraph(
+pub fn render_paragraph(
     content: &[InlineNode],
     ctx: &mut RenderContext,
     opts: &RenderOptions,
@@ -198,19 +1091,152 @@ fn render_paragraph(
     {
         return render_file_figure(link, ctx, opts);
     }
-    render_inlines(content, ctx, opts)
+    // anchors on lines of their own before or after the text are targets between
+    // paragraphs: they become `<a name>` blocks instead of running into the text.
+    let (before, content, after) = match anchors_enabled(opts) && !ctx.in_table_cell() {
+        true => split_anchor_lines(content),
+        false => (&content[..0], content, &content[..0]),
+    };
+    let content = without_trailing_breaks(content, ctx, opts);
+    let mut text = render_inlines(content, ctx, opts);
+    // a leading template that renders to nothing (an infobox moved to the
+    // frontmatter, a dropped navbox) leaves the space after it behind.
+    if matches!(
+        content.first().map(|n| &n.kind),
+        Some(InlineKind::Template { .. })
+    ) || !before.is_empty()
+    {
+        text = text.trim_start().to_string();
+    }
+    if !after.is_empty() {
+        text.truncate(text.trim_end().len());
+    }
+    let text = match opts.wrap_width {
+        Some(width) if !ctx.in_table_cell() => wrap::wrap_markdown(&text, width),
+        _ => text,
+    };
+    if before.is_empty() && after.is_empty() {
+        return text;
+    }
+    let mut anchor_lines = |anchors: &[InlineNode]| {
+        anchors
+            .iter()
+            .filter(|n| is_anchor_span(n))
+            .map(|n| render_inline(n, ctx, opts))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let before = anchor_lines(before);
+    let after = anchor_lines(after);
+    [before, text, after]
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
 }
 
-fn extract_standalone_file_link(content: &[InlineNode]) -> Option<&FileLink> {
-    let mut file: Option<&FileLink> = None;
-    for node in content {
-        match &node.kind {
-            InlineKind::FileLink { link } => {
-                if file.is_some() {
-                    return None;
-                }
-                file = Some(link);
-            }
+/// `<span id="x"></span>`: an empty span that only marks a link target.
+fn is_anchor_span(node: &InlineNode) -> bool {
+    match &node.kind {
+        InlineKind::HtmlTag { node } => {
+            node.name.eq_ignore_ascii_case("span")
+                && node.attrs.iter().any(|a| a.name.eq_ignore_ascii_case("id"))
+                && node.children.iter().all(
+                    |c| matches!(&c.kind, InlineKind::Text { value } if value.trim().is_empty()),
+                )
+        }
+        _ => false,
+    }
+}
+
+/// `content` without the hard breaks nothing follows: at the end of a block, a
+/// backslash break shows up literally and trailing spaces linger.
+fn without_trailing_breaks<'c, 'a>(
+    content: &'c [InlineNode<'a>],
+    ctx: &RenderContext,
+    opts: &RenderOptions,
+) -> &'c [InlineNode<'a>] {
+    if opts.hard_break_style == HardBreakStyle::Html || ctx.in_table_cell() {
+        return content;
+    }
+    let mut end = content.len();
+    loop {
+        let last = content[..end].iter().rposition(
+            |n| !matches!(&n.kind, InlineKind::Text { value } if value.trim().is_empty()),
+        );
+        match last {
+            Some(i) if matches!(content[i].kind, InlineKind::LineBreak) => end = i,
+            _ => return &content[..end],
+        }
+    }
+}
+
+/// Split `content` into the anchor spans on lines of their own at its start,
+/// the text, and those at its end. An anchor sharing a line with text stays in it.
+fn split_anchor_lines<'c, 'a>(
+    content: &'c [InlineNode<'a>],
+) -> (
+    &'c [InlineNode<'a>],
+    &'c [InlineNode<'a>],
+    &'c [InlineNode<'a>],
+) {
+    let is_blank =
+        |n: &InlineNode| matches!(&n.kind, InlineKind::Text { value } if value.trim().is_empty());
+    let line_edge = |n: Option<&InlineNode>, at_start: bool| match n.map(|n| &n.kind) {
+        None => true,
+        Some(InlineKind::Text { value }) if at_start => {
+            value.trim_start_matches([' ', '\t']).starts_with('\n')
+        }
+        Some(InlineKind::Text { value }) => value.trim_end_matches([' ', '\t']).ends_with('\n'),
+        Some(InlineKind::LineBreak) => true,
+        _ => false,
+    };
+
+    let lead = content
+        .iter()
+        .take_while(|n| is_anchor_span(n) || is_blank(n))
+        .count();
+    let lead = match content[..lead].iter().any(is_anchor_span)
+        && (content[..lead]
+            .iter()
+            .any(|n| matches!(&n.kind, InlineKind::Text { value } if value.contains('\n')))
+            || line_edge(content.get(lead), true))
+    {
+        true => lead,
+        false => 0,
+    };
+    let rest = &content[lead..];
+    let trail = rest
+        .iter()
+        .rev()
+        .take_while(|n| is_anchor_span(n) || is_blank(n))
+        .count();
+    let split = rest.len() - trail;
+    let trail = match rest[split..].iter().any(is_anchor_span)
+        && (rest[split..]
+            .iter()
+            .any(|n| matches!(&n.kind, InlineKind::Text { value } if value.contains('\n')))
+            || line_edge(split.checked_sub(1).map(|i| &rest[i]), false))
+    {
+        true => trail,
+        false => 0,
+    };
+    let split = rest.len() - trail;
+    (&content[..lead], &rest[..split], &rest[split..])
+}
+
+pub(crate) fn extract_standalone_file_link<'a>(
+    content: &'a [InlineNode],
+) -> Option<&'a FileLink<'a>> {
+    let mut file: Option<&FileLink> = None;
+    for node in content {
+        match &node.kind {
+            InlineKind::FileLink { link } => {
+                if file.is_some() {
+                    return None;
+                }
+                file = Some(link);
+            }
             InlineKind::Text { value } => {
                 if !value.trim().is_empty() {
                     return None;
@@ -239,7 +1265,7 @@ fn render_file_figure(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOpt
             vec![InlineNode {
                 span,
                 kind: InlineKind::Text {
-                    value: link.target.clone(),
+                    value: link.target.as_str().into(),
                 },
             }]
         }
@@ -268,7 +1294,7 @@ fn render_file_figure(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOpt
     } else {
         opts.default_image_width_px
     };
-    let url = mediawiki_file_thumb_url(&opts.mediawiki_base_url, &link.target, width);
+    let url = mediawiki_file_thumb_url(&opts.image_base(), &link.target, width);
 
     let mut refs = String::new();
     for rn in ref_nodes {
@@ -279,28 +1305,45 @@ fn render_file_figure(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOpt
     format!("![{}]({})<br />*{}*{}", alt.trim(), url, alt.trim(), refs)
 }
 
-fn mediawiki_file_thumb_url(base: &str, filename: &str, width_px: u32) -> String {
-    let base = base.trim_end_matches('/');
-    let name = canonicalize_mediawiki_filename(filename);
+/// Where files live: `image_base_url`, or `<mediawiki_base_url>/images`.
+pub(crate) fn image_base(mediawiki_base_url: &str, image_base_url: Option<&str>) -> String {
+    match image_base_url.map(|b| b.trim_end_matches('/')) {
+        Some(base) => base.to_string(),
+        None => format!("{}/images", mediawiki_base_url.trim_end_matches('/')),
+    }
+}
 
-    // MediaWiki stores files under /images/<h1>/<h2>/<name>, where <h1> and <h2>
-    // are derived from the MD5 of the canonical filename.
-    let digest = md5::compute(name.as_bytes());
-    let hex = format!("{:x}", digest);
-    let h1 = &hex[0..1];
-    let h2 = &hex[0..2];
+/// A thumbnail of `filename`, under the files root `images` (see [`image_base`]).
+pub(crate) fn mediawiki_file_thumb_url(images: &str, filename: &str, width_px: u32) -> String {
+    if width_px == 0 {
+        // fallback to the original file URL.
+        return mediawiki_file_url(images, filename);
+    }
+    let images = images.trim_end_matches('/');
+    let name = canonicalize_mediawiki_filename(filename);
+    let (h1, h2) = mediawiki_file_hash_dirs(&name);
 
     // match the common MediaWiki thumbnail URL format:
     // /images/thumb/<h1>/<h2>/<name>/<width>px-<name>
-    if width_px > 0 {
-        format!(
-            "{}/images/thumb/{}/{}/{}/{}px-{}",
-            base, h1, h2, name, width_px, name
-        )
-    } else {
-        // fallback to the original file URL.
-        format!("{}/images/{}/{}/{}", base, h1, h2, name)
-    }
+    format!(
+        "{}/thumb/{}/{}/{}/{}px-{}",
+        images, h1, h2, name, width_px, name
+    )
+}
+
+/// The URL of the uploaded file itself: `/images/<h1>/<h2>/<name>`.
+pub(crate) fn mediawiki_file_url(images: &str, filename: &str) -> String {
+    let images = images.trim_end_matches('/');
+    let name = canonicalize_mediawiki_filename(filename);
+    let (h1, h2) = mediawiki_file_hash_dirs(&name);
+    format!("{}/{}/{}/{}", images, h1, h2, name)
+}
+
+/// MediaWiki stores files under /images/<h1>/<h2>/<name>, where <h1> and <h2>
+/// are the first one and two hex digits of the MD5 of the canonical filename.
+fn mediawiki_file_hash_dirs(name: &str) -> (String, String) {
+    let hex = format!("{:x}", md5::compute(name.as_bytes()));
+    (hex[0..1].to_string(), hex[0..2].to_string())
 }
 
 fn canonicalize_mediawiki_filename(filename: &str) -> String {
@@ -317,7 +1360,7 @@ fn canonicalize_mediawiki_filename(filename: &str) -> String {
     out
 }
 
-fn file_link_width_px(link: &FileLink) -> Option<u32> {
+pub(crate) fn file_link_width_px(link: &FileLink) -> Option<u32> {
     for p in &link.params {
         let Some(token) = file_param_plain_text(p) else {
             continue;
@@ -329,9 +1372,14 @@ fn file_link_width_px(link: &FileLink) -> Option<u32> {
     None
 }
 
-fn file_param_plain_text(p: &FileParam) -> Option<String> {
+pub(crate) fn file_param_plain_text(p: &FileParam) -> Option<String> {
+    inlines_plain_text(&p.content)
+}
+
+/// Concatenated Text/Raw values, or `None` if `nodes` contains any markup.
+fn inlines_plain_text(nodes: &[InlineNode]) -> Option<String> {
     let mut s = String::new();
-    for n in &p.content {
+    for n in nodes {
         match &n.kind {
             InlineKind::Text { value } => s.push_str(value),
             InlineKind::Raw { text } => s.push_str(text),
@@ -341,7 +1389,7 @@ fn file_param_plain_text(p: &FileParam) -> Option<String> {
     Some(s)
 }
 
-fn file_param_is_option_like(p: &FileParam) -> bool {
+pub(crate) fn file_param_is_option_like(p: &FileParam) -> bool {
     let Some(raw) = file_param_plain_text(p) else {
         return false;
     };
@@ -376,7 +1424,7 @@ fn parse_px(s: &str) -> Option<u32> {
     s.parse::<u32>().ok().filter(|n| *n > 0 && *n <= 4096)
 }
 
-fn render_heading(
+pub fn render_heading(
     level: u8,
     content: &[InlineNode],
     ctx: &mut RenderContext,
@@ -384,18 +1432,18 @@ fn render_heading(
 ) -> String {
     // special-case: leading <span id="..."></span> anchors are better emitted on their own line.
     let mut content_slice = content;
-    let mut prefix = String::new();
+    let mut anchor = None;
     if let Some(first) = content.first()
         && let InlineKind::HtmlTag { node } = &first.kind
         && node.name.eq_ignore_ascii_case("span")
+        && anchors_enabled(opts)
         && let Some(id_attr) = node
             .attrs
             .iter()
             .find(|a| a.name.eq_ignore_ascii_case("id"))
             .and_then(|a| a.value.as_ref())
     {
-        // emit a stable HTML anchor.
-        prefix.push_str(&format!("<a name=\"{}\"></a>\n", id_attr));
+        anchor = Some(id_attr);
         content_slice = &content[1..];
     }
 
@@ -405,14 +1453,35 @@ fn render_heading(
     let shifted = level.saturating_add(1).clamp(2, 6);
     let hashes = "#".repeat(shifted as usize);
     let title = render_inlines(content_slice, ctx, opts).trim().to_string();
-    if prefix.is_empty() {
-        format!("{} {}", hashes, title)
-    } else {
-        format!("{}{} {}", prefix, hashes, title)
+    match (anchor, opts.heading_anchor_style) {
+        // emit a stable HTML anchor.
+        (Some(id), HeadingAnchorStyle::Html) => {
+            format!("<a name=\"{}\"></a>\n{} {}", id, hashes, title)
+        }
+        (Some(id), HeadingAnchorStyle::Attribute) => format!("{} {} {{#{}}}", hashes, title, id),
+        _ => format!("{} {}", hashes, title),
+    }
+}
+
+/// Whether `<span id>` anchors are handled specially rather than like other
+/// spans. With [`HeadingAnchorStyle::None`] they are dropped this way.
+fn anchors_enabled(opts: &RenderOptions) -> bool {
+    match opts.heading_anchor_style {
+        HeadingAnchorStyle::Html => opts.html_policy.allows_anchors(),
+        HeadingAnchorStyle::Attribute | HeadingAnchorStyle::None => true,
+    }
+}
+
+/// The link target `id` around the rendered `inner`, in the configured style.
+fn render_anchor(id: &str, inner: &str, opts: &RenderOptions) -> String {
+    match opts.heading_anchor_style {
+        HeadingAnchorStyle::Html => format!("<a name=\"{}\">{}</a>", id, inner),
+        HeadingAnchorStyle::Attribute => format!("[{}]{{#{}}}", inner, id),
+        HeadingAnchorStyle::None => inner.to_string(),
     }
 }
 
-fn render_list(
+pub fn render_list(
     items: &[ListItem],
     ctx: &mut RenderContext,
     opts: &RenderOptions,
@@ -436,6 +1505,7 @@ fn render_list(
             match &first.kind {
                 BlockKind::Paragraph { content: inlines } => {
                     out.push_str(&prefix);
+                    let inlines = without_trailing_breaks(inlines, ctx, opts);
                     out.push_str(render_inlines(inlines, ctx, opts).trim());
 
                     // render remaining blocks (including nested lists) indented.
@@ -473,7 +1543,7 @@ fn render_list(
     out
 }
 
-fn render_code_block(
+pub fn render_code_block(
     kind: CodeBlockKind,
     lang: Option<&str>,
     text: &str,
@@ -481,39 +1551,127 @@ fn render_code_block(
     opts: &RenderOptions,
 ) -> String {
     match kind {
-        CodeBlockKind::LeadingSpace if opts.leading_space_as_blockquote => {
+        CodeBlockKind::LeadingSpace if opts.leading_space_as_blockquote == Some(true) => {
             // treat as quoted text (matches the legacy behavior for chessprogramming pages).
             prefix_lines(text.trim_end_matches('\n'), "> ")
         }
         _ => {
+            let lang = match lang.map(str::trim).filter(|l| !l.is_empty()) {
+                Some(l) => Some(code_lang::resolve_alias(l, &opts.code_lang_aliases)),
+                None if opts.infer_code_lang => code_lang::infer_lang(text).map(str::to_string),
+                None => None,
+            };
             let mut out = String::new();
             out.push_str("```");
-            if let Some(l) = lang
-                && !l.trim().is_empty()
-            {
-                out.push_str(l.trim());
+            if let Some(l) = &lang {
+                out.push_str(l);
             }
             out.push('\n');
-            out.push_str(text.trim_end_matches('\n'));
+            let text = opts.code_normalization.apply(text);
+            let text = text.trim_end_matches('\n');
+            out.push_str(text);
             out.push_str("\n```");
-            out
+
+            let lines = text.lines().count();
+            match opts.collapse_code_lines {
+                Some(max) if lines > max => {
+                    let summary = match &lang {
+                        Some(l) => format!("{} · {} lines", l, lines),
+                        None => format!("{} lines", lines),
+                    };
+                    format!(
+                        "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+                        summary, out
+                    )
+                }
+                _ => out,
+            }
+        }
+    }
+}
+
+/// A `<poem>`: one paragraph per stanza, with a hard break after every line but
+/// the last. Leading spaces (indented lines) become `&nbsp;`.
+pub fn render_poem(
+    lines: &[Vec<InlineNode>],
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let mut stanzas = Vec::new();
+    for stanza in lines
+        .split(Vec::is_empty)
+        .filter(|stanza| !stanza.is_empty())
+    {
+        let mut out = String::new();
+        for (i, line) in stanza.iter().enumerate() {
+            if i > 0 {
+                out.push_str(&opts.backend.line_break(ctx, opts));
+            }
+            let rendered = render_inlines(line, ctx, opts);
+            let text = rendered.trim_start_matches(' ');
+            out.push_str(&"&nbsp;".repeat(rendered.len() - text.len()));
+            out.push_str(text.trim_end());
         }
+        stanzas.push(out);
+    }
+    let out = stanzas.join("\n\n");
+    match opts.poem_style {
+        PoemStyle::Plain => out,
+        PoemStyle::BlockQuote => prefix_lines(&out, "> "),
     }
 }
 
-fn render_html_block(node: &HtmlBlock, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+/// The blocks of a section shown on the page (`<noinclude>`, `<onlyinclude>`);
+/// nothing for `<includeonly>`.
+pub fn render_inclusion(
+    mode: InclusionMode,
+    blocks: &[BlockNode],
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    if !mode.shown_on_page() {
+        return String::new();
+    }
     let mut out = String::new();
-    out.push('<');
-    out.push_str(&node.name);
-    for a in &node.attrs {
-        out.push(' ');
-        out.push_str(&a.name);
-        if let Some(v) = &a.value {
-            out.push_str("=\"");
-            out.push_str(v);
-            out.push('"');
+    for b in blocks {
+        let rendered = render_block(b, ctx, opts);
+        if !rendered.is_empty() {
+            if !out.is_empty() {
+                out.push_str("\n\n");
+            }
+            out.push_str(&rendered);
         }
     }
+    out
+}
+
+pub fn render_html_block(
+    node: &HtmlBlock,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let attrs = match opts.html_policy.action(&node.name, &node.attrs) {
+        TagAction::Keep(attrs) => attrs,
+        TagAction::Unwrap => {
+            let mut out = String::new();
+            for b in &node.children {
+                let rendered = render_block(b, ctx, opts);
+                if !rendered.is_empty() {
+                    if !out.is_empty() {
+                        out.push_str("\n\n");
+                    }
+                    out.push_str(&rendered);
+                }
+            }
+            return out;
+        }
+        TagAction::Drop => return String::new(),
+    };
+
+    let mut out = String::new();
+    out.push('<');
+    out.push_str(&node.name);
+    out.push_str(&render_html_attrs(attrs));
 
     if node.self_closing {
         out.push_str(" />");
@@ -537,11 +1695,18 @@ fn render_html_block(node: &HtmlBlock, ctx: &mut RenderContext, opts: &RenderOpt
     out
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum TableColumnAlign {
-    Left,
-    Center,
-    Right,
+fn render_html_attrs(attrs: Vec<&HtmlAttr>) -> String {
+    let mut out = String::new();
+    for a in attrs {
+        out.push(' ');
+        out.push_str(&a.name);
+        if let Some(v) = &a.value {
+            out.push_str("=\"");
+            out.push_str(v);
+            out.push('"');
+        }
+    }
+    out
 }
 
 fn parse_text_align_from_attrs(attrs: &[HtmlAttr]) -> Option<TableColumnAlign> {
@@ -599,7 +1764,7 @@ fn parse_text_align_from_attrs(attrs: &[HtmlAttr]) -> Option<TableColumnAlign> {
 fn compute_table_column_alignments(
     table: &Table,
     col_count: usize,
-    header_row_idx: usize,
+    header_rows: Range<usize>,
 ) -> Vec<TableColumnAlign> {
     let mut out = vec![TableColumnAlign::Left; col_count];
 
@@ -623,7 +1788,7 @@ fn compute_table_column_alignments(
                 all_headers = false;
             }
 
-            if ri != header_row_idx && cell.kind == TableCellKind::Data {
+            if !header_rows.contains(&ri) && cell.kind == TableCellKind::Data {
                 any_data = true;
                 if parse_text_align_from_attrs(&cell.attrs) != Some(TableColumnAlign::Right) {
                     all_data_right = false;
@@ -641,12 +1806,22 @@ fn compute_table_column_alignments(
     out
 }
 
-fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+pub fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
     // basic Markdown table rendering.
     // - flatten cell blocks into a single line of text.
     // - supports a limited amount of alignment inference from cell attributes.
-    // - render `|+` captions as a plain line above the table.
+    // - render `|+` captions above or below the table (see `table_caption_position`).
+    // - optionally, put an anchor before it (see `table_anchors`).
     let mut out = String::new();
+    let anchor = ctx
+        .next_table_id()
+        .filter(|_| opts.table_anchors && anchors_enabled(opts) && !ctx.in_table_cell())
+        .map(|id| render_anchor(&id, "", opts))
+        .filter(|a| !a.is_empty());
+    if let Some(anchor) = anchor {
+        out.push_str(&anchor);
+        out.push_str("\n\n");
+    }
 
     // caption (|+ ...)
     let caption_text = table
@@ -654,7 +1829,12 @@ fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) ->
         .as_ref()
         .map(|c| render_inlines(&c.content, ctx, opts))
         .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty());
+        .filter(|s| !s.is_empty())
+        .map(|s| match opts.table_caption_style {
+            TableCaptionStyle::Plain => s,
+            TableCaptionStyle::Italic => format!("*{}*", s),
+            TableCaptionStyle::Bold => format!("**{}**", s),
+        });
 
     let mut rows: Vec<Vec<String>> = Vec::new();
     for row in &table.rows {
@@ -679,22 +1859,48 @@ fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) ->
         }
     }
 
-    // choose a header row. prefer the first row that contains at least one header cell.
-    let header_row_idx = table
+    // choose the header row. by default, the first row that contains at least one
+    // header cell.
+    let first_header_row = table
         .rows
         .iter()
         .position(|r| r.cells.iter().any(|c| c.kind == TableCellKind::Header))
         .unwrap_or(0);
+    let header_rows = match opts.table_header_rows {
+        TableHeaderRows::FirstHeaderRow | TableHeaderRows::DemoteExtra => {
+            first_header_row..first_header_row + 1
+        }
+        TableHeaderRows::Empty => 0..0,
+        TableHeaderRows::Join(n) => 0..n.clamp(1, rows.len()),
+    };
+    let header: Vec<String> = (0..col_count)
+        .map(|col| {
+            let parts: Vec<&str> = rows[header_rows.clone()]
+                .iter()
+                .map(|r| r[col].trim())
+                .filter(|c| !c.is_empty())
+                .collect();
+            let mut joined: Vec<&str> = Vec::new();
+            for part in parts {
+                // a cell spanning the rows below it shouldn't repeat.
+                if joined.last() != Some(&part) {
+                    joined.push(part);
+                }
+            }
+            joined.join(" ")
+        })
+        .collect();
 
-    let aligns = compute_table_column_alignments(table, col_count, header_row_idx);
+    let mut aligns = compute_table_column_alignments(table, col_count, header_rows.clone());
+    opts.table_alignment
+        .apply(table, header_rows.clone(), &mut aligns);
 
     // build the Markdown table into its own buffer so we can optionally
     // wrap it in centering HTML.
     let mut table_out = String::new();
 
-    let header = rows.get(header_row_idx).unwrap_or(&rows[0]);
     table_out.push('|');
-    for cell in header {
+    for cell in &header {
         table_out.push(' ');
         table_out.push_str(&escape_table_cell(cell));
         table_out.push(' ');
@@ -715,13 +1921,24 @@ fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) ->
     table_out.push('\n');
 
     for (ri, row) in rows.iter().enumerate() {
-        if ri == header_row_idx {
+        if header_rows.contains(&ri) {
             continue;
         }
+        let bold = opts.table_header_rows == TableHeaderRows::DemoteExtra
+            && ri > first_header_row
+            && table.rows[ri]
+                .cells
+                .iter()
+                .all(|c| c.kind == TableCellKind::Header);
         table_out.push('|');
         for cell in row {
             table_out.push(' ');
-            table_out.push_str(&escape_table_cell(cell));
+            match cell.trim() {
+                c if bold && !c.is_empty() => {
+                    table_out.push_str(&format!("**{}**", escape_table_cell(c)))
+                }
+                _ => table_out.push_str(&escape_table_cell(cell)),
+            }
             table_out.push(' ');
             table_out.push('|');
         }
@@ -730,32 +1947,51 @@ fn render_table(table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) ->
 
     let table_md = table_out.trim_end_matches('\n');
 
+    let mut body = String::new();
+    match (caption_text, opts.table_caption_position) {
+        (Some(cap), TableCaptionPosition::Above) => {
+            body.push_str(&cap);
+            body.push_str("\n\n");
+            body.push_str(table_md);
+        }
+        (Some(cap), TableCaptionPosition::Below) => {
+            body.push_str(table_md);
+            body.push_str("\n\n");
+            body.push_str(&cap);
+        }
+        (None, _) => body.push_str(table_md),
+    }
+
     // optionally, center the caption + table using HTML.
+    let table_start = out.len();
     if opts.center_tables_and_captions {
         out.push_str(
             "<div style=\"display:flex; flex-direction:column; align-items:center;\">\n\n",
         );
-
-        if let Some(cap) = caption_text {
-            out.push_str(&cap);
-            out.push_str("\n\n");
-        }
-
-        out.push_str(table_md);
+        out.push_str(&body);
         out.push_str("\n\n</div>");
-        return out.trim_end_matches('\n').to_string();
+    } else {
+        out.push_str(&body);
     }
 
-    if let Some(cap) = caption_text {
-        out.push_str(&cap);
-        out.push_str("\n\n");
+    // optionally, let wide tables scroll sideways instead of stretching the page.
+    if opts
+        .scroll_wide_tables
+        .is_some_and(|wide| wide.matches(&rows, col_count))
+    {
+        let (anchor, table) = out.split_at(table_start);
+        out = format!(
+            "{}<div style=\"overflow-x:auto\">\n\n{}\n\n</div>",
+            anchor,
+            table.trim_end_matches('\n')
+        );
     }
-    out.push_str(table_md);
 
     out.trim_end_matches('\n').to_string()
 }
 
 fn render_table_cell(cell: &TableCell, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+    let was_in_cell = std::mem::replace(&mut ctx.in_table_cell, true);
     let mut parts: Vec<String> = Vec::new();
     for b in &cell.blocks {
         let s = render_block(b, ctx, opts);
@@ -765,10 +2001,15 @@ fn render_table_cell(cell: &TableCell, ctx: &mut RenderContext, opts: &RenderOpt
             parts.push(s);
         }
     }
+    ctx.in_table_cell = was_in_cell;
     parts.join(" ")
 }
 
-fn render_references(ctx: &mut RenderContext, opts: &RenderOptions, emit_heading: bool) -> String {
+pub fn render_references(
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+    emit_heading: bool,
+) -> String {
     if ctx.refs.is_empty() {
         return String::new();
     }
@@ -781,7 +2022,14 @@ fn render_references(ctx: &mut RenderContext, opts: &RenderOptions, emit_heading
         // the article title is rendered as H1, so references should be H2.
         out.push_str("## References\n\n");
     }
-    for (i, r) in ctx.refs.iter().enumerate() {
+    out.push_str(&footnote_definitions(&ctx.refs, 0));
+    out
+}
+
+/// `[^n]: body` lines for `refs[start..]`.
+fn footnote_definitions(refs: &[String], start: usize) -> String {
+    let mut out = String::new();
+    for (i, r) in refs.iter().enumerate().skip(start) {
         let n = i + 1;
         let body = r.trim();
         if body.is_empty() {
@@ -793,15 +2041,11 @@ fn render_references(ctx: &mut RenderContext, opts: &RenderOptions, emit_heading
     out.trim_end_matches('\n').to_string()
 }
 
-fn render_inlines(inlines: &[InlineNode], ctx: &mut RenderContext, opts: &RenderOptions) -> String {
-    // Obsidian misinterprets multiple literal asterisks in normal text as emphasis
-    // markers, even when surrounded by spaces.
-    //
-    // when enabled, the code replaces `*` in plain text/Raw nodes with a safer token
-    // (default: `&middot;`). the code does not touch the `*` characters if they're
-    // emphasis or part of a list.
-    let apply_star_workaround = opts.obsidian_text_asterisk_workaround;
-
+pub fn render_inlines(
+    inlines: &[InlineNode],
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
     let mut out = String::new();
     for node in inlines {
         // footnote markers should attach to the preceding token (no extra space).
@@ -811,16 +2055,24 @@ fn render_inlines(inlines: &[InlineNode], ctx: &mut RenderContext, opts: &Render
             }
         }
 
+        let protects = protects_text(node);
+        ctx.protected_text += protects as usize;
         let mut rendered = render_inline(node, ctx, opts);
+        ctx.protected_text -= protects as usize;
 
-        if apply_star_workaround {
-            match node.kind {
-                InlineKind::Text { .. } | InlineKind::Raw { .. } => {
-                    rendered = rendered.replace('*', &opts.obsidian_text_asterisk_replacement);
-                }
-                _ => {}
+        // escape literal text; markup emitted by other nodes is left alone.
+        let escape = |text: &str| {
+            opts.text_escapes
+                .escape(&opts.html_policy.sanitize_text(text))
+        };
+        match node.kind {
+            InlineKind::Text { .. } if !opts.text_rules.is_empty() && ctx.protected_text == 0 => {
+                rendered = opts.text_rules.apply(&rendered, escape);
             }
+            InlineKind::Text { .. } | InlineKind::Raw { .. } => rendered = escape(&rendered),
+            _ => {}
         }
+        rendered = opts.hooks.inline(node, rendered);
 
         // if the previous inline emitted an explicit newline (e.g. <br/>\n),
         // strip leading spaces on the next fragment for cleaner output.
@@ -836,39 +2088,25 @@ fn render_inlines(inlines: &[InlineNode], ctx: &mut RenderContext, opts: &Render
     out
 }
 
-fn render_inline(node: &InlineNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+/// Links and code, whose text [`TextRules`] leave alone.
+fn protects_text(node: &InlineNode) -> bool {
     match &node.kind {
-        InlineKind::Text { value } => {
-            // normalize raw newlines into spaces for Markdown paragraphs.
-            value.replace(['\r', '\n'], " ")
-        }
-        InlineKind::Bold { content } => format!("**{}**", render_inlines(content, ctx, opts)),
-        InlineKind::Italic { content } => format!("*{}*", render_inlines(content, ctx, opts)),
-        InlineKind::BoldItalic { content } => {
-            format!("***{}***", render_inlines(content, ctx, opts))
-        }
-        // emit a real newline after the HTML break so that Markdown renderers (e.g., Obsidian)
-        // don't treat the following text as part of the same visual line.
-        InlineKind::LineBreak => "<br/>\n".to_string(),
-        InlineKind::InternalLink { link } => render_internal_link(link, ctx, opts),
-        InlineKind::ExternalLink { link } => render_external_link(link, ctx, opts),
-        InlineKind::FileLink { link } => render_file_link(link, ctx, opts),
-        InlineKind::Template { node } => render_template(node, ctx, opts),
-        InlineKind::Ref { node } => {
-            let content = node
-                .content
-                .as_ref()
-                .map(|c| render_inlines(c, ctx, opts))
-                .unwrap_or_default();
-            ctx.refs.push(content);
-            format!("[^{}]", ctx.refs.len())
-        }
-        InlineKind::HtmlTag { node } => render_html_tag(node, ctx, opts),
-        InlineKind::Raw { text } => text.clone(),
-    }
-}
-
-fn render_internal_link(
+        InlineKind::InternalLink { .. }
+        | InlineKind::ExternalLink { .. }
+        | InlineKind::FileLink { .. } => true,
+        InlineKind::HtmlTag { node } => ["code", "tt", "kbd", "samp", "pre", "math"]
+            .iter()
+            .any(|tag| node.name.eq_ignore_ascii_case(tag)),
+        _ => false,
+    }
+}
+
+/// Render an inline node through the configured backend.
+pub fn render_inline(node: &InlineNode, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+    opts.backend.inline(node, ctx, opts)
+}
+
+pub fn render_internal_link(
     link: &InternalLink,
     ctx: &mut RenderContext,
     opts: &RenderOptions,
@@ -888,6 +2126,15 @@ fn render_internal_link(
             .map(str::trim)
             .filter(|s| !s.is_empty())
         {
+            // wikilinks only reach headings; `<a name>` targets need a URL fragment.
+            if ctx.is_span_anchor(anchor) || (opts.table_anchors && ctx.is_table_anchor(anchor)) {
+                let label = if label_trim.is_empty() {
+                    anchor
+                } else {
+                    label_trim
+                };
+                return format!("[{}](#{})", label, anchor.replace(' ', "%20"));
+            }
             if label_trim.is_empty() || label_trim.eq_ignore_ascii_case(anchor) {
                 return format!("[[#{}]]", anchor);
             }
@@ -919,25 +2166,60 @@ fn render_internal_link(
     format!("[[{}|{}]]", target_title, label_trim)
 }
 
-fn render_external_link(
+pub fn render_external_link(
     link: &ExternalLink,
     ctx: &mut RenderContext,
     opts: &RenderOptions,
 ) -> String {
+    let url = opts.url_rewrites.apply(&link.url);
     match &link.text {
         Some(nodes) => {
             let label = render_inlines(nodes, ctx, opts);
-            format!("[{}]({})", label.trim(), link.url)
+            format!("[{}]({})", label.trim(), url)
         }
-        None => format!("<{}>", link.url),
+        None => match opts.external_link_numbering {
+            ExternalLinkNumbering::Inline => {
+                format!("[\\[{}\\]]({})", ctx.next_external_link(), url)
+            }
+            ExternalLinkNumbering::Footnote
+                if opts.footnote_placement == FootnotePlacement::Inline =>
+            {
+                format!("^[<{}>]", url)
+            }
+            ExternalLinkNumbering::Footnote => format!("[^{}]", ctx.push_ref(format!("<{}>", url))),
+            ExternalLinkNumbering::Autolink => format!("<{}>", url),
+        },
     }
 }
 
-fn render_file_link(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
-    // best-effort: link to the "File:" page on the configured MediaWiki base.
+pub fn render_magic_link(
+    link: &MagicLink,
+    _ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let url = opts.magic_link_urls.url(link);
+    format!(
+        "[{}]({})",
+        link.text.replace('\u{a0}', " "),
+        opts.url_rewrites.apply(&url)
+    )
+}
+
+pub fn render_file_link(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+    if opts.chess_symbols
+        && let Some(symbol) = chess::piece_image_symbol(&link.target)
+    {
+        return symbol.to_string();
+    }
+    // best-effort: link to the "File:" page on the configured MediaWiki base, or,
+    // for `[[Media:...]]`, straight to the uploaded file.
     let base = opts.mediawiki_base_url.trim_end_matches('/');
-    let file_target = link.target.replace(' ', "_");
-    let file_page = format!("{}/File:{}", base, file_target);
+    let file_page = match link.namespace {
+        FileNamespace::Media => mediawiki_file_url(&opts.image_base(), &link.target),
+        FileNamespace::File | FileNamespace::Image => {
+            format!("{}/File:{}", base, link.target.replace(' ', "_"))
+        }
+    };
 
     // caption: pick the last param that isn't an option-like token;
     // fall back to the file name.
@@ -953,48 +2235,53 @@ fn render_file_link(link: &FileLink, ctx: &mut RenderContext, opts: &RenderOptio
     format!("[{}]({})", caption.trim(), file_page)
 }
 
-fn render_template(
+pub fn render_template(
     inv: &TemplateInvocation,
     ctx: &mut RenderContext,
     opts: &RenderOptions,
 ) -> String {
-    match inv.name.kind {
-        TemplateNameKind::ParserFunction if inv.name.raw.eq_ignore_ascii_case("#evu") => {
-            // {{#evu:URL|...}} => just emit the URL as a link.
-            let url = inv
-                .params
-                .first()
-                .map(|p| render_inlines(&p.value, ctx, opts))
-                .unwrap_or_default();
-            if url.trim().is_empty() {
-                "".to_string()
-            } else {
-                format!("[Video]({})", url.trim())
-            }
-        }
-        _ => {
-            // preserve unknown templates in a non-destructive way.
-            let mut s = String::new();
-            s.push_str("{{");
-            s.push_str(&inv.name.raw);
-            for p in &inv.params {
-                s.push('|');
-                if let Some(n) = &p.name {
-                    s.push_str(n);
-                    s.push('=');
-                }
-                s.push_str(&render_inlines(&p.value, ctx, opts));
-            }
-            s.push_str("}}");
-            s
+    if opts
+        .infobox
+        .as_ref()
+        .is_some_and(|infobox| infobox.matches(inv))
+        || (opts.drop_navboxes && templates::is_navbox_name(&inv.name.raw))
+        || templates::is_display_title(inv)
+    {
+        return String::new();
+    }
+    if let Some(handler) = opts.template_handlers.get(&inv.name.raw)
+        && let Some(out) = handler(inv, ctx, opts)
+    {
+        return out;
+    }
+    render_template_passthrough(inv, ctx, opts)
+}
+
+/// Preserve a template in a non-destructive way: `{{name|key=value|...}}`.
+pub fn render_template_passthrough(
+    inv: &TemplateInvocation,
+    ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let mut s = String::new();
+    s.push_str("{{");
+    s.push_str(&inv.name.raw);
+    for p in &inv.params {
+        s.push('|');
+        if let Some(n) = &p.name {
+            s.push_str(n);
+            s.push('=');
         }
+        s.push_str(&render_inlines(&p.value, ctx, opts));
     }
+    s.push_str("}}");
+    s
 }
 
-fn render_html_tag(tag: &HtmlTag, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
-    // conservative pass-through for most tags.
+pub fn render_html_tag(tag: &HtmlTag, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
     // special-case <span id="...">...</span> => <a name="...">...</a> for stable anchors.
     if tag.name.eq_ignore_ascii_case("span")
+        && anchors_enabled(opts)
         && let Some(id) = tag
             .attrs
             .iter()
@@ -1002,23 +2289,24 @@ fn render_html_tag(tag: &HtmlTag, ctx: &mut RenderContext, opts: &RenderOptions)
             .and_then(|a| a.value.as_ref())
     {
         let inner = render_inlines(&tag.children, ctx, opts);
-        if inner.trim().is_empty() {
-            return format!("<a name=\"{}\"></a>", id);
-        }
-        return format!("<a name=\"{}\">{}</a>", id, inner);
+        let inner = if inner.trim().is_empty() {
+            ""
+        } else {
+            inner.as_str()
+        };
+        return render_anchor(id, inner, opts);
     }
+
+    // conservative pass-through for most tags.
+    let attrs = match opts.html_policy.action(&tag.name, &tag.attrs) {
+        TagAction::Keep(attrs) => attrs,
+        TagAction::Unwrap => return render_inlines(&tag.children, ctx, opts),
+        TagAction::Drop => return String::new(),
+    };
     let mut out = String::new();
     out.push('<');
     out.push_str(&tag.name);
-    for a in &tag.attrs {
-        out.push(' ');
-        out.push_str(&a.name);
-        if let Some(v) = &a.value {
-            out.push_str("=\"");
-            out.push_str(v);
-            out.push('"');
-        }
-    }
+    out.push_str(&render_html_attrs(attrs));
     if tag.self_closing {
         out.push_str(" />");
         return out;
@@ -1201,7 +2489,7 @@ By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:
                                 InlineNode {
                                     span: Span::new(0, 4),
                                     kind: InlineKind::Text {
-                                        value: "Text".to_string(),
+                                        value: "Text".into(),
                                     },
                                 },
                                 InlineNode {
@@ -1212,7 +2500,7 @@ By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:
                                             content: Some(vec![InlineNode {
                                                 span: Span::new(0, 8),
                                                 kind: InlineKind::Text {
-                                                    value: "Ref body".to_string(),
+                                                    value: "Ref body".into(),
                                                 },
                                             }]),
                                             self_closing: false,
@@ -1225,12 +2513,18 @@ By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:
                     BlockNode {
                         span: Span::new(0, 0),
                         kind: BlockKind::References {
-                            node: ReferencesNode { attrs: vec![] },
+                            node: ReferencesNode {
+                                attrs: vec![],
+                                refs: vec![],
+                            },
                         },
                     },
                 ],
                 categories: vec![],
                 redirect: None,
+                breadcrumb: None,
+                trivia: vec![],
+                parts: vec![],
             },
         };
 
@@ -1238,4 +2532,973 @@ By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:
         assert!(md.contains("Text[^1]"));
         assert!(md.contains("[^1]: Ref body"));
     }
+
+    #[derive(Debug)]
+    struct CsvTables;
+
+    impl MarkupBackend for CsvTables {
+        fn table(&self, table: &Table, ctx: &mut RenderContext, opts: &RenderOptions) -> String {
+            table
+                .rows
+                .iter()
+                .map(|row| {
+                    row.cells
+                        .iter()
+                        .map(|c| render_table_cell(c, ctx, opts))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+
+    #[test]
+    fn custom_backend_overrides_a_single_construct() {
+        let src = "==Data==\n{|\n! a !! b\n|-\n| '''1''' || 2\n|}\n";
+        let parsed = parse_wiki(src);
+        let opts = RenderOptions {
+            backend: std::sync::Arc::new(CsvTables),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+
+        // headings and inline markup still come from the default emitters.
+        assert_eq!(md, "### Data\n\na,b\n**1**,2");
+    }
+
+    #[test]
+    fn hard_break_style_controls_line_breaks_outside_tables() {
+        let src = "one<br/>two\n{|\n| a<br/>b\n|}\nlast<br/> \n\n* item<br>\n";
+        let parsed = parse_wiki(src);
+
+        let render = |style| {
+            let opts = RenderOptions {
+                hard_break_style: style,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        let html = render(HardBreakStyle::Html);
+        assert!(html.starts_with("one<br/>\ntwo"), "{html}");
+
+        let backslash = render(HardBreakStyle::Backslash);
+        assert!(backslash.starts_with("one\\\ntwo"), "{backslash}");
+        assert!(backslash.contains("| a<br/> b |"), "{backslash}");
+        // nothing follows the last break of a block, so it is dropped.
+        assert!(backslash.ends_with("\nlast\n\n- item"), "{backslash}");
+
+        let spaces = render(HardBreakStyle::TrailingSpaces);
+        assert!(spaces.starts_with("one  \ntwo"), "{spaces}");
+        assert!(spaces.contains("| a<br/> b |"), "{spaces}");
+        assert!(spaces.ends_with("\nlast\n\n- item"), "{spaces}");
+    }
+
+    #[test]
+    fn unlabeled_external_links_are_numbered() {
+        let src = "A [https://a.org] B [https://b.org label] C [https://c.org]<ref>Note</ref>\n<references />\n";
+        let parsed = parse_wiki(src);
+
+        let render = |numbering| {
+            let opts = RenderOptions {
+                external_link_numbering: numbering,
+                emit_br_before_references: false,
+                emit_references_heading: false,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        assert_eq!(
+            render(ExternalLinkNumbering::Inline),
+            "A [\\[1\\]](https://a.org) B [label](https://b.org) C [\\[2\\]](https://c.org)[^1]\n\n[^1]: Note"
+        );
+        assert_eq!(
+            render(ExternalLinkNumbering::Footnote),
+            "A [^1] B [label](https://b.org) C [^2][^3]\n\n[^1]: <https://a.org>\n[^2]: <https://c.org>\n[^3]: Note"
+        );
+        assert!(render(ExternalLinkNumbering::Autolink).starts_with("A <https://a.org> B"));
+    }
+
+    #[test]
+    fn magic_links_use_configured_url_templates() {
+        let src = "Knuth, ISBN 978-0-201-89683-1, [https://x.org ISBN 0-201-89683-4], RFC 2616, PMID 123, PMIDS 4.\n";
+        let parsed = parse_wiki(src);
+        let opts = RenderOptions {
+            magic_link_urls: MagicLinkUrls {
+                isbn: "https://openlibrary.org/isbn/{id}".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&parsed.document, &opts),
+            "Knuth, [ISBN 978-0-201-89683-1](https://openlibrary.org/isbn/9780201896831), \
+             [ISBN 0-201-89683-4](https://x.org), [RFC 2616](https://datatracker.ietf.org/doc/html/rfc2616), \
+             [PMID 123](https://pubmed.ncbi.nlm.nih.gov/123/), PMIDS 4."
+        );
+    }
+
+    #[test]
+    fn wrap_width_reflows_paragraphs_only() {
+        let src = "==A heading that is longer than the wrap width==\nThe [[Alpha-Beta|alpha beta]] algorithm prunes branches.\n";
+        let parsed = parse_wiki(src);
+        let opts = RenderOptions {
+            wrap_width: Some(20),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(
+            md,
+            "### A heading that is longer than the wrap width\n\nThe\n[[Alpha-Beta|alpha beta]]\nalgorithm prunes\nbranches."
+        );
+    }
+
+    #[test]
+    fn html_policy_filters_raw_html() {
+        let src = "a <span style=\"color:red\" onclick=\"x()\">red</span> <font color=\"blue\">b</font> <script>evil()</script>\n";
+        let parsed = parse_wiki(src);
+        let render = |policy| {
+            let opts = RenderOptions {
+                html_policy: policy,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        let all = render(HtmlPolicy::AllowAll);
+        assert!(
+            all.contains("<span style=\"color:red\" onclick=\"x()\">red</span>"),
+            "{all}"
+        );
+
+        let safe = render(HtmlPolicy::safe_default());
+        assert!(safe.contains("<span>red</span>"), "{safe}");
+        assert!(safe.contains("<font color=\"blue\">b</font>"), "{safe}");
+        assert!(!safe.contains("evil"), "{safe}");
+
+        let custom = render(HtmlPolicy::Allowlist(
+            HtmlAllowlist::new().allow("font", &[]),
+        ));
+        assert!(custom.contains("a red <font>b</font>"), "{custom}");
+
+        let text = render(HtmlPolicy::StripToText);
+        assert_eq!(text, "a red b");
+
+        let parsed = parse_wiki("x < y <sup onmouseover=\"z\">2</sup><!-- note -->\n");
+        let opts = RenderOptions {
+            html_policy: HtmlPolicy::safe_default(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&parsed.document, &opts),
+            "x &lt; y <sup>2</sup>"
+        );
+    }
+
+    #[test]
+    fn template_handlers_replace_or_fall_back_to_passthrough() {
+        let src = "{{#evu:https://youtu.be/a_b|alignment=inline}} {{Wp|Alpha_beta}} {{Other|x}}\n";
+        let parsed = parse_wiki(src);
+
+        let md = render_doc(&parsed.document);
+        assert_eq!(
+            md,
+            "[Video](https://youtu.be/a_b) {{Wp|Alpha_beta}} {{Other|x}}"
+        );
+
+        let mut handlers = TemplateHandlers::builtin();
+        handlers.register("wp", |inv, ctx, opts| {
+            let page = render_inlines(&inv.params.first()?.value, ctx, opts);
+            Some(format!(
+                "[{0}](https://en.wikipedia.org/wiki/{0})",
+                page.trim()
+            ))
+        });
+        let opts = RenderOptions {
+            template_handlers: handlers,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(
+            md,
+            "[Video](https://youtu.be/a_b) [Alpha_beta](https://en.wikipedia.org/wiki/Alpha_beta) {{Other|x}}"
+        );
+    }
+
+    #[test]
+    fn breadcrumb_is_metadata_with_render_styles() {
+        let src = "'''[[Main Page|Home]] * [[People]] * Barend Swets'''\n\nBody.\n";
+        let parsed = parse_wiki(src);
+        let breadcrumb = parsed.document.breadcrumb.as_ref().unwrap();
+        let targets: Vec<&str> = breadcrumb.links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(targets, ["Main Page", "People"]);
+        assert_eq!(breadcrumb.current.as_deref(), Some("Barend Swets"));
+
+        let render = |style| {
+            let opts = RenderOptions {
+                breadcrumb_style: style,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+        assert_eq!(
+            render(BreadcrumbStyle::Paragraph),
+            "**[[Main Page|Home]] &middot; [[People]] &middot; Barend Swets**\n\nBody."
+        );
+        assert_eq!(render(BreadcrumbStyle::Omit), "Body.");
+        assert_eq!(
+            render(BreadcrumbStyle::Heading),
+            "###### [[Main Page|Home]] › [[People]] › Barend Swets\n\nBody."
+        );
+        assert_eq!(
+            render(BreadcrumbStyle::Line),
+            "[[Main Page|Home]] › [[People]] › Barend Swets\n\nBody."
+        );
+    }
+
+    #[test]
+    fn navigation_templates_can_be_dropped() {
+        let src = "{{Stub}} Intro {{Navbox mid}}.\n\n{{Engines}}\n\nMore text.\n\n{{Chess engines}}\n{{Up}}\n\n<references />\n\n{{Programming footer}}\n";
+        let parsed = parse_wiki(src);
+
+        let mut handlers = TemplateHandlers::builtin();
+        handlers.drop_template("stub");
+        let opts = RenderOptions {
+            template_handlers: handlers,
+            drop_navboxes: true,
+            emit_references_heading: false,
+            emit_br_before_references: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&parsed.document, &opts),
+            "Intro .\n\n{{Engines}}\n\nMore text."
+        );
+    }
+
+    #[test]
+    fn chess_diagram_templates_render_as_boards() {
+        let src = "{{FEN|4k3/8/8/8/8/8/4P3/4K3 w - - 0 1|caption=King and pawn}}\n";
+        let parsed = parse_wiki(src);
+
+        let md = render_doc(&parsed.document);
+        assert!(
+            md.starts_with("```text\n  +-----------------+\n8 | . . . . k . . . |\n"),
+            "{md}"
+        );
+        assert!(md.contains("\n2 | . . . . P . . . |\n"), "{md}");
+        assert!(md.ends_with("```\n*King and pawn*"), "{md}");
+
+        let opts = RenderOptions {
+            chess_diagram_style: ChessDiagramStyle::Unicode,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert!(md.contains("8 | · · · · ♚ · · · |"), "{md}");
+
+        let opts = RenderOptions {
+            chess_diagram_style: ChessDiagramStyle::Image,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(
+            md,
+            "![King and pawn](https://lichess1.org/export/fen.gif?fen=4k3/8/8/8/8/8/4P3/4K3%20w%20-%20-%200%201)"
+        );
+
+        let mut squares = vec![""; 64];
+        squares[4] = "kd";
+        squares[60] = "kl";
+        let src = format!(
+            "{{{{Chess diagram|tright||{}|Bare kings}}}}\n",
+            squares.join("|")
+        );
+        let parsed = parse_wiki(&src);
+        let opts = RenderOptions {
+            chess_diagram_style: ChessDiagramStyle::Image,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert!(
+            md.starts_with(
+                "![Bare kings](https://lichess1.org/export/fen.gif?fen=4k3/8/8/8/8/8/8/4K3%20w"
+            ),
+            "{md}"
+        );
+    }
+
+    #[test]
+    fn code_block_languages_are_aliased_or_inferred() {
+        let src = "<syntaxhighlight lang=\"C++\">int x;</syntaxhighlight>\n\n<pre>U64 b = 0;\nif (b) return;\n</pre>\n";
+        let parsed = parse_wiki(src);
+        let md = render_doc(&parsed.document);
+        assert!(md.starts_with("```cpp\nint x;\n```"), "{md}");
+        assert!(md.contains("```c\nU64 b = 0;"), "{md}");
+
+        let opts = RenderOptions {
+            infer_code_lang: false,
+            code_lang_aliases: HashMap::new(),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert!(md.starts_with("```C++\n"), "{md}");
+        assert!(md.contains("```\nU64 b = 0;"), "{md}");
+    }
+
+    #[test]
+    fn code_normalization_dedents_fenced_blocks() {
+        let src = "<pre>\n    a = 1;\t\n      b = 2;\n</pre>\n";
+        let parsed = parse_wiki(src);
+        let opts = RenderOptions {
+            code_normalization: CodeNormalization::all(),
+            infer_code_lang: false,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(md, "```\n\na = 1;\n  b = 2;\n```");
+    }
+
+    #[test]
+    fn leading_space_override_forces_quote_or_code() {
+        let src = " Problem is, no one else has stepped forward.\nCode:\n U64 b = 0;\n b |= 1;\n";
+        let parsed = parse_wiki(src);
+
+        let auto = render_doc_with_options(&parsed.document, &RenderOptions::default());
+        assert_eq!(
+            auto,
+            "> Problem is, no one else has stepped forward.\n\nCode:\n\n```\nU64 b = 0;\nb |= 1;\n```"
+        );
+
+        let quote = RenderOptions {
+            leading_space_as_blockquote: Some(true),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &quote);
+        assert_eq!(
+            md,
+            "> Problem is, no one else has stepped forward.\n\nCode:\n\n> U64 b = 0;\n> b |= 1;"
+        );
+
+        let code = RenderOptions {
+            leading_space_as_blockquote: Some(false),
+            infer_code_lang: false,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &code);
+        assert_eq!(
+            md,
+            "```\nProblem is, no one else has stepped forward.\n```\n\nCode:\n\n```\nU64 b = 0;\nb |= 1;\n```"
+        );
+    }
+
+    #[test]
+    fn quote_attribution_styles() {
+        let src = "By [[Robert Hyatt]], 1997 <ref>Quote ref</ref>:\n Problem is, no one else has stepped forward.\n";
+        let parsed = parse_wiki(src);
+        let render = |style| {
+            let opts = RenderOptions {
+                quote_attribution: style,
+                emit_references_heading: false,
+                emit_br_before_references: false,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        let md = render(QuoteAttributionStyle::Intro);
+        assert!(
+            md.starts_with("By [[Robert Hyatt]], 1997[^1]:\n\n> Problem is"),
+            "{md}"
+        );
+        let md = render(QuoteAttributionStyle::Trailer);
+        assert!(
+            md.starts_with(
+                "> Problem is, no one else has stepped forward.\n> \n> — [[Robert Hyatt]], 1997[^1]"
+            ),
+            "{md}"
+        );
+        let md = render(QuoteAttributionStyle::CalloutTitle);
+        assert!(
+            md.starts_with("> [!quote] [[Robert Hyatt]], 1997[^1]\n> Problem is"),
+            "{md}"
+        );
+    }
+
+    #[test]
+    fn url_rewrites_apply_to_external_links() {
+        let parsed = parse_wiki("See [http://www.example.com/a?utm_source=wiki the paper].\n");
+        let opts = RenderOptions {
+            url_rewrites: UrlRewrites::new()
+                .push(UrlRewriteRule::new("^http://", "https://").unwrap())
+                .push(UrlRewriteRule::new(r"\?utm_source=[^&]*$", "").unwrap()),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert!(
+            md.contains("[the paper](https://www.example.com/a)"),
+            "{md}"
+        );
+    }
+
+    #[test]
+    fn hooks_replace_rendered_blocks_inlines_and_document() {
+        let parsed = parse_wiki("Intro with [[Perft]].\n\n<pre>e2e4</pre>\n\n* item [[Perft]]\n");
+        let opts = RenderOptions {
+            hooks: RenderHooks::new()
+                .on_block(|block, md| match block.kind {
+                    BlockKind::CodeBlock { .. } => {
+                        Some(format!("<div class=\"code\">\n\n{}\n\n</div>", md))
+                    }
+                    _ => None,
+                })
+                .on_inline(|node, _| match &node.kind {
+                    InlineKind::InternalLink { link } if link.target == "Perft" => {
+                        Some("[[Perft Results]]".to_string())
+                    }
+                    _ => None,
+                })
+                .post_document(|_, md| Some(format!("{}\n\n(end)", md))),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(
+            md,
+            "Intro with [[Perft Results]].\n\n<div class=\"code\">\n\n```\ne2e4\n```\n\n</div>\n\n- item [[Perft Results]]\n\n(end)"
+        );
+    }
+
+    #[test]
+    fn text_rules_skip_links_and_code() {
+        let parsed = parse_wiki(
+            "After e2e4 and perft(5), see [[e2e4]] or <code>perft(5)</code>.\n== Opening e2e4 ==\n",
+        );
+        let opts = RenderOptions {
+            text_rules: TextRules::new()
+                .push(TextRule::new(r"\bperft\(\d+\)", "`$0`").unwrap())
+                .push(TextRule::new(r"\b[a-h][1-8][a-h][1-8]\b", "`$0`").unwrap()),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert!(
+            md.starts_with("After `e2e4` and `perft(5)`, see [[e2e4]] or <code>perft(5)</code>."),
+            "{md}"
+        );
+        assert!(md.contains("## Opening `e2e4`"), "{md}");
+    }
+
+    #[test]
+    fn long_code_blocks_collapse() {
+        let parsed = parse_wiki(
+            "<pre>a\nb\nc</pre>\n\n<syntaxhighlight lang=\"c\">int a;\nint b;</syntaxhighlight>\n",
+        );
+        let opts = RenderOptions {
+            collapse_code_lines: Some(2),
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parsed.document, &opts);
+        assert_eq!(
+            md,
+            "<details>\n<summary>3 lines</summary>\n\n```\na\nb\nc\n```\n\n</details>\n\n```c\nint a;\nint b;\n```"
+        );
+    }
+
+    #[test]
+    fn wide_tables_scroll() {
+        let parsed = parse_wiki("{|\n! A !! B !! C\n|-\n| 1 || 2 || 3\n|}\n");
+        let render = |wide: WideTables, center: bool| {
+            let opts = RenderOptions {
+                scroll_wide_tables: Some(wide),
+                center_tables_and_captions: center,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        let narrow = render(WideTables::default(), false);
+        assert!(narrow.starts_with("| A |"), "{narrow}");
+        let by_columns = render(
+            WideTables {
+                max_columns: 2,
+                ..WideTables::default()
+            },
+            true,
+        );
+        assert!(
+            by_columns.starts_with("<div style=\"overflow-x:auto\">\n\n<div style=\"display:flex;"),
+            "{by_columns}"
+        );
+        assert!(by_columns.ends_with("</div>\n\n</div>"), "{by_columns}");
+        let by_width = render(
+            WideTables {
+                max_width: 12,
+                ..WideTables::default()
+            },
+            false,
+        );
+        assert!(
+            by_width.starts_with("<div style=\"overflow-x:auto\">\n\n| A |"),
+            "{by_width}"
+        );
+    }
+
+    #[test]
+    fn table_header_row_choices() {
+        let parsed = parse_wiki(
+            "{| class=\"wikitable\"\n! Depth !! Perft !! Perft\n|-\n! !! Nodes !! Captures\n|-\n| 1 || 20 || 0\n|-\n| 2 || 400 || 0\n|}\n",
+        );
+        let render = |table_header_rows: TableHeaderRows| {
+            let opts = RenderOptions {
+                table_header_rows,
+                ..Default::default()
+            };
+            render_doc_with_options(&parsed.document, &opts)
+        };
+
+        let md = render(TableHeaderRows::default());
+        assert!(md.starts_with("| Depth | Perft | Perft |\n"), "{md}");
+        assert!(md.contains("|  | Nodes | Captures |\n| 1 |"), "{md}");
+
+        let md = render(TableHeaderRows::Empty);
+        assert!(md.starts_with("|  |  |  |\n"), "{md}");
+        assert!(
+            md.contains("| Depth | Perft | Perft |\n|  | Nodes | Captures |\n| 1 | 20 | 0 |"),
+            "{md}"
+        );
+
+        let md = render(TableHeaderRows::Join(2));
+        assert!(
+            md.starts_with("| Depth | Perft Nodes | Perft Captures |\n"),
+            "{md}"
+        );
+        assert!(!md.contains("|  | Nodes |"), "{md}");
+
+        let md = render(TableHeaderRows::DemoteExtra);
+        assert!(md.starts_with("| Depth | Perft | Perft |\n"), "{md}");
+        assert!(
+            md.contains("|  | **Nodes** | **Captures** |\n| 1 |"),
+            "{md}"
+        );
+    }
+
+    #[test]
+    fn table_alignment_overrides() {
+        let parsed = parse_wiki(
+            "{| class=\"wikitable sortable\"\n|+ Results\n! Engine !! Elo !! Year\n|-\n! Stockfish\n| 3,500 || 2008\n|-\n! Crafty\n| 2,800 || n/a\n|-\n! Fruit\n| 2,900 || 2004\n|}\n",
+        );
+        let render = |table_alignment: TableAlignment| {
+            let opts = RenderOptions {
+                table_alignment,
+                ..Default::default()
+            };
+            let md = render_doc_with_options(&parsed.document, &opts);
+            md.lines()
+                .find(|l| l.starts_with("|-") || l.starts_with("|:"))
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(render(TableAlignment::default()), "|:---:|---|---|");
+        assert_eq!(
+            render(TableAlignment {
+                numeric_right: true,
+                first_column_left: true,
+                ..Default::default()
+            }),
+            "|---|----:|----:|"
+        );
+        let overrides = vec![
+            TableAlignOverride::parse("caption=Other=>r").unwrap(),
+            TableAlignOverride::parse("class=sortable=>c,c").unwrap(),
+        ];
+        assert_eq!(
+            render(TableAlignment {
+                numeric_right: true,
+                overrides,
+                ..Default::default()
+            }),
+            "|:---:|:---:|----:|"
+        );
+
+        // `n/a` is a placeholder, not text: the year column is still all numbers.
+        assert_eq!(
+            render(TableAlignment {
+                numeric_right: true,
+                numeric_min_percent: 100,
+                ..Default::default()
+            }),
+            "|:---:|----:|----:|"
+        );
+    }
+
+    #[test]
+    fn numeric_min_percent_counts_text_cells() {
+        // four years and one `unknown`: 80% of the counted cells are numbers.
+        let parsed = parse_wiki(
+            "{|\n! Engine !! Year\n|-\n| Belle || 1978\n|-\n| Crafty || 1994\n|-\n| Fruit || 2004\n|-\n| Rybka || 2005\n|-\n| Unnamed || unknown\n|}\n",
+        );
+        let render = |numeric_min_percent| {
+            let opts = RenderOptions {
+                table_alignment: TableAlignment {
+                    numeric_right: true,
+                    numeric_min_percent,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let md = render_doc_with_options(&parsed.document, &opts);
+            md.lines().nth(1).unwrap().to_string()
+        };
+
+        assert_eq!(render(100), "|---|---|");
+        assert_eq!(render(80), "|---|----:|");
+    }
+
+    #[test]
+    fn timelines_render_as_mermaid() {
+        let src = "<timeline>\nDateFormat = yyyy\nPeriod = from:1990 till:2010\n\nBarData =\n  bar:SF text:\"[[Stockfish]]\"\n  bar:Fruit\n\nPlotData =\n  width:15\n  bar:SF from:2008 till:end text:Stockfish\n  bar:Fruit from:1995 till:2005 text:\"Fruit~1.0\"\n  at:1997 text:\"Deep Blue: Kasparov\"\n</timeline>\n";
+        let parsed = parse_wiki(src);
+        let BlockKind::Timeline { timeline } = &parsed.document.blocks[0].kind else {
+            panic!("{:?}", parsed.document.blocks);
+        };
+        assert_eq!(timeline.bars[0].text.as_deref(), Some("Stockfish"));
+        assert_eq!(timeline.entries.len(), 3);
+        assert_eq!(timeline.entries[1].text.as_deref(), Some("Fruit 1.0"));
+
+        let render = |timeline_style: TimelineStyle, src: &str| {
+            let opts = RenderOptions {
+                timeline_style,
+                ..Default::default()
+            };
+            render_doc_with_options(&parse_wiki(src).document, &opts)
+        };
+        assert!(
+            render(TimelineStyle::Fenced, src).starts_with("```easytimeline\nDateFormat = yyyy\n")
+        );
+        assert_eq!(
+            render(TimelineStyle::MermaidGantt, src),
+            "```mermaid\ngantt\n    dateFormat YYYY\n    axisFormat %Y\n    Deep Blue#58; Kasparov :milestone, 1997, 0d\n    section Stockfish\n    Stockfish :2008, 2010\n    section Fruit\n    Fruit 1.0 :1995, 2005\n```"
+        );
+        assert_eq!(
+            render(TimelineStyle::MermaidTimeline, src),
+            "```mermaid\ntimeline\n    1997 : Deep Blue#58; Kasparov\n    section Stockfish\n    2008 – 2010 : Stockfish\n    section Fruit\n    1995 – 2005 : Fruit 1.0\n```"
+        );
+
+        // numeric dates have no gantt equivalent.
+        let numeric = src.replace("yyyy", "x.y");
+        assert!(render(TimelineStyle::MermaidGantt, &numeric).starts_with("```easytimeline\n"));
+    }
+
+    #[test]
+    fn poems_keep_line_breaks() {
+        let parsed = parse_wiki(
+            "Intro.\n<poem>\nThe ''knight'' moves\n  in an [[L-shape|L]]\n\nSecond stanza\n</poem>\n",
+        );
+        let BlockKind::Poem { lines } = &parsed.document.blocks[1].kind else {
+            panic!("{:?}", parsed.document.blocks);
+        };
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].is_empty());
+
+        let md = render_doc_with_options(&parsed.document, &RenderOptions::default());
+        assert_eq!(
+            md,
+            "Intro.\n\nThe *knight* moves<br/>\n&nbsp;&nbsp;in an [[L-shape|L]]\n\nSecond stanza"
+        );
+        let opts = RenderOptions {
+            poem_style: PoemStyle::BlockQuote,
+            hard_break_style: HardBreakStyle::Backslash,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&parsed.document, &opts),
+            "Intro.\n\n> The *knight* moves\\\n> &nbsp;&nbsp;in an [[L-shape|L]]\n> \n> Second stanza"
+        );
+    }
+
+    #[test]
+    fn block_ids_follow_quotes_and_tables() {
+        let src = "Intro.\n\n{|\n| a\n|}\n\n More.\n\n{|\n| a\n|}\n";
+        let opts = RenderOptions {
+            block_ids: true,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&parse_wiki(src).document, &opts);
+        assert_eq!(
+            md,
+            "Intro.\n\n| a |\n|---|\n\n^fbf36c\n\n> More.\n\n^6314ed\n\n| a |\n|---|\n\n^fbf36c-2"
+        );
+
+        // editing other blocks keeps the ids.
+        let edited_src = src.replace("Intro.", "A longer introduction.");
+        let edited = parse_wiki(&edited_src);
+        assert!(
+            render_doc_with_options(&edited.document, &opts)
+                .ends_with(md.strip_prefix("Intro.").unwrap())
+        );
+    }
+
+    #[test]
+    fn drop_see_also_skips_the_section() {
+        let src = "Intro.\n\n=See also=\n* [[Divide]]\n==Software==\n* [[Perft]]\n\n=Forum Posts=\nPosts.\n";
+        let doc = parse_wiki(src).document;
+        let opts = RenderOptions {
+            drop_see_also: true,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&doc, &opts);
+        assert!(
+            !md.contains("See also") && !md.contains("Software") && !md.contains("Divide"),
+            "{md}"
+        );
+        assert!(
+            md.contains("Intro.") && md.contains("# Forum Posts\n\nPosts."),
+            "{md}"
+        );
+        assert!(render_doc(&doc).contains("# See also"));
+    }
+
+    #[test]
+    fn standalone_span_anchors_become_blocks() {
+        let src = "Intro.\n<span id=\"Start\"></span>\n\n<span id=\"Move Gen\"></span>\nText [[#Move Gen|here]] and [[#Intro]].\n<span id=\"End\"></span>\n\nA <span id=\"Mid\"></span> B.\n";
+        let doc = parse_wiki(src).document;
+        let md = render_doc(&doc);
+        assert!(md.contains("Intro.\n\n<a name=\"Start\"></a>\n\n"), "{md}");
+        assert!(md.contains("<a name=\"Move Gen\"></a>\n\nText [here](#Move%20Gen) and [[#Intro]].\n\n<a name=\"End\"></a>"), "{md}");
+        assert!(md.contains("A <a name=\"Mid\"></a> B."), "{md}");
+    }
+
+    #[test]
+    fn heading_anchor_styles() {
+        let src = "== <span id=\"Ply\"></span>Plies ==\n<span id=\"Start\"></span>\nText with <span id=\"k\">kings</span>.\n";
+        let doc = parse_wiki(src).document;
+        let render = |heading_anchor_style| {
+            render_doc_with_options(
+                &doc,
+                &RenderOptions {
+                    heading_anchor_style,
+                    ..Default::default()
+                },
+            )
+        };
+        let md = render(HeadingAnchorStyle::Attribute);
+        assert!(
+            md.contains("### Plies {#Ply}\n\n[]{#Start}\n\nText with [kings]{#k}."),
+            "{md}"
+        );
+        let md = render(HeadingAnchorStyle::None);
+        assert!(md.contains("### Plies\n\nText with kings."), "{md}");
+        let md = render(HeadingAnchorStyle::Html);
+        assert!(md.contains("<a name=\"Ply\"></a>\n### Plies"), "{md}");
+    }
+
+    #[test]
+    fn media_links_point_at_the_file() {
+        let doc = parse_wiki("See [[Media:perft results.pdf|the results]], [[Media:Perft_results.pdf]] and [[File:Perft results.pdf|its page]].\n").document;
+        let md = render_doc(&doc);
+        assert!(
+            md.contains(
+                "[the results](https://www.chessprogramming.org/images/5/5a/Perft_results.pdf)"
+            ),
+            "{md}"
+        );
+        assert!(md.contains("[Perft_results.pdf](https://www.chessprogramming.org/images/5/5a/Perft_results.pdf)"), "{md}");
+        assert!(
+            md.contains("[its page](https://www.chessprogramming.org/File:Perft_results.pdf)"),
+            "{md}"
+        );
+
+        let opts = RenderOptions {
+            image_base_url: Some("http://localhost:8000/files/".to_string()),
+            ..Default::default()
+        };
+        let doc = parse_wiki(
+            "[[File:BarendSwets.jpg|Barend Swets]]\n\nSee [[Media:Perft results.pdf]].\n",
+        )
+        .document;
+        let md = render_doc_with_options(&doc, &opts);
+        assert!(
+            md.contains(
+                "](http://localhost:8000/files/thumb/a/a9/BarendSwets.jpg/300px-BarendSwets.jpg)"
+            ),
+            "{md}"
+        );
+        assert!(
+            md.contains("(http://localhost:8000/files/5/5a/Perft_results.pdf)"),
+            "{md}"
+        );
+    }
+
+    #[test]
+    fn table_anchors_precede_tables() {
+        let src = "See [[#table-perft-results|the table below]].\n{|\n|+ Perft Results\n! Depth !! Nodes\n|-\n| 1 || 20\n|}\n{|\n| x\n|}\n";
+        let doc = parse_wiki(src).document;
+        let opts = RenderOptions {
+            table_anchors: true,
+            ..Default::default()
+        };
+        let md = render_doc_with_options(&doc, &opts);
+        assert!(
+            md.contains("[the table below](#table-perft-results)"),
+            "{md}"
+        );
+        assert!(
+            md.contains("<a name=\"table-perft-results\"></a>\n\nPerft Results\n\n| Depth |"),
+            "{md}"
+        );
+        assert!(md.contains("<a name=\"table-2\"></a>\n\n| x |"), "{md}");
+
+        let md = render_doc(&doc);
+        assert!(
+            md.contains("[[#table-perft-results|the table below]]"),
+            "{md}"
+        );
+        assert!(!md.contains("<a name"), "{md}");
+    }
+
+    #[test]
+    fn html_blockquote_cites_trail_the_quote() {
+        let src = "<blockquote>\nUnix is simple.\n<cite>[[Dennis Ritchie]]</cite>\n</blockquote>\n";
+        let doc = parse_wiki(src).document;
+        assert_eq!(
+            render_doc(&doc).trim_end(),
+            "> Unix is simple.\n> \n> — [[Dennis Ritchie]]"
+        );
+        let opts = RenderOptions {
+            quote_attribution: QuoteAttributionStyle::CalloutTitle,
+            leading_space_as_blockquote: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&doc, &opts).trim_end(),
+            "> [!quote] [[Dennis Ritchie]]\n> Unix is simple."
+        );
+        assert_eq!(
+            wikitext::serialize_block(&doc.blocks[0]),
+            "<blockquote>\nUnix is simple.\n<cite>[[Dennis Ritchie]]</cite>\n</blockquote>"
+        );
+    }
+
+    #[test]
+    fn footnote_placement() {
+        let src =
+            "Intro.<ref>A</ref>\n\n=One=\nText.<ref>B</ref>\n\n=References=\n<references />\n";
+        let doc = parse_wiki(src).document;
+        let render = |footnote_placement| {
+            let opts = RenderOptions {
+                footnote_placement,
+                emit_br_before_references: false,
+                ..Default::default()
+            };
+            render_doc_with_options(&doc, &opts)
+        };
+        assert_eq!(
+            render(FootnotePlacement::References),
+            "Intro.[^1]\n\n## One\n\nText.[^2]\n\n## References\n\n[^1]: A\n[^2]: B"
+        );
+        assert_eq!(
+            render(FootnotePlacement::Section),
+            "Intro.[^1]\n\n[^1]: A\n\n## One\n\nText.[^2]\n\n[^2]: B"
+        );
+        assert_eq!(
+            render(FootnotePlacement::End),
+            "Intro.[^1]\n\n## One\n\nText.[^2]\n\n## References\n\n[^1]: A\n[^2]: B"
+        );
+        assert_eq!(
+            render(FootnotePlacement::Inline),
+            "Intro.^[A]\n\n## One\n\nText.^[B]"
+        );
+
+        // without `<references />`, the definitions still appear.
+        let doc = parse_wiki("Intro.<ref>A</ref>\n").document;
+        let opts = RenderOptions {
+            footnote_placement: FootnotePlacement::End,
+            emit_br_before_references: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&doc, &opts),
+            "Intro.[^1]\n\n## References\n\n[^1]: A"
+        );
+    }
+
+    #[test]
+    fn dedupe_refs_shares_identical_footnotes() {
+        let src = "A.<ref>Knuth 1975</ref> B.<ref>Other</ref> C.<ref> Knuth 1975 </ref> D.<ref name=\"k\">Knuth 1975</ref>\n\n<references />\n";
+        let doc = parse_wiki(src).document;
+        let opts = RenderOptions {
+            dedupe_refs: true,
+            emit_br_before_references: false,
+            emit_references_heading: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_doc_with_options(&doc, &opts),
+            "A.[^1] B.[^2] C.[^1] D.[^1]\n\n[^1]: Knuth 1975\n[^2]: Other"
+        );
+        assert!(render_doc(&doc).contains("[^4]: Knuth 1975"));
+    }
+
+    #[test]
+    fn pooled_renders_start_fresh() {
+        let opts = RenderOptions {
+            block_ids: true,
+            ..Default::default()
+        };
+        // the same ref names and table in both documents, so leftover numbering,
+        // named refs, or block ids from the first would change the second.
+        let first = "A<ref name=\"x\">One.</ref> B<ref name=\"y\">Two.</ref> C<ref name=\"x\"/>\n\n{|\n| a\n|}\n\n<references/>\n";
+        let second = "D<ref name=\"y\">Three.</ref> E<ref name=\"x\">Four.</ref>\n\n{|\n| a\n|}\n\n<references/>\n";
+        let mut pool = RenderPool::new();
+        for src in [first, second, first] {
+            let doc = parse_wiki(src).document;
+            let pooled = render_doc_pooled(&doc, &opts, &mut pool);
+            assert_eq!(pooled, render_doc_with_options(&doc, &opts));
+            assert!(
+                pooled.contains("^fbf36c\n") && !pooled.contains("^fbf36c-2"),
+                "{pooled}"
+            );
+        }
+        let second = render_doc_pooled(&parse_wiki(second).document, &opts, &mut pool);
+        assert!(second.starts_with("D[^1] E[^2]"), "{second}");
+        assert!(second.contains("[^1]: Three.\n[^2]: Four."), "{second}");
+    }
+
+    #[test]
+    fn raw_block_policy() {
+        let mut doc = parse_wiki("Intro.\n\nOutro.\n").document;
+        doc.blocks.insert(
+            1,
+            BlockNode {
+                span: Span::new(7, 7),
+                kind: BlockKind::Raw {
+                    text: "{| broken -->\n".to_string(),
+                },
+            },
+        );
+        assert_eq!(crate::ast::raw_block_count(&doc), 1);
+        let render = |raw_blocks| {
+            render_doc_with_options(
+                &doc,
+                &RenderOptions {
+                    raw_blocks,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(RawBlockPolicy::Fenced),
+            "Intro.\n\n```text\n{| broken -->\n```\n\nOutro."
+        );
+        assert_eq!(
+            render(RawBlockPolicy::Comment),
+            "Intro.\n\n<!-- {| broken --&gt; -->\n\nOutro."
+        );
+        assert_eq!(
+            render(RawBlockPolicy::Passthrough),
+            "Intro.\n\n{| broken -->\n\nOutro."
+        );
+        assert_eq!(render(RawBlockPolicy::Drop), "Intro.\n\nOutro.");
+    }
 }
@@ -0,0 +1,260 @@
+//! Overrides for the column alignment of Markdown tables.
+//!
+//! By default a column is right-aligned only when every data cell says so
+//! (`style="text-align:right"`), and centered when it holds only headers.
+//! [`TableAlignment`] can additionally right-align numeric columns, keep the
+//! first column left-aligned, and set the columns of specific tables outright.
+
+use crate::ast::{self, HtmlAttr, Table, TableCellKind};
+use std::ops::Range;
+
+/// Alignment of one Markdown table column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TableColumnAlign {
+    /// `l`/`left`, `c`/`center`, or `r`/`right`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "l" | "left" => Some(TableColumnAlign::Left),
+            "c" | "center" => Some(TableColumnAlign::Center),
+            "r" | "right" => Some(TableColumnAlign::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Which tables a [`TableAlignOverride`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableMatch {
+    /// Tables whose caption text is this (case-insensitively).
+    Caption(String),
+    /// Tables with this attribute, e.g. `class` `sortable`. The value matches the
+    /// whole attribute value or one of its space-separated words.
+    Attr { name: String, value: String },
+}
+
+impl TableMatch {
+    pub fn matches(&self, table: &Table) -> bool {
+        match self {
+            TableMatch::Caption(caption) => table.caption.as_ref().is_some_and(|c| {
+                let mut text = String::new();
+                ast::plain_text(&c.content, &mut text);
+                text.trim().eq_ignore_ascii_case(caption.trim())
+            }),
+            TableMatch::Attr { name, value } => {
+                table.attrs.iter().any(|a| attr_matches(a, name, value))
+            }
+        }
+    }
+}
+
+fn attr_matches(attr: &HtmlAttr, name: &str, value: &str) -> bool {
+    let Some(v) = attr
+        .value
+        .as_deref()
+        .filter(|_| attr.name.eq_ignore_ascii_case(name))
+    else {
+        return false;
+    };
+    v.trim() == value.trim() || v.split_whitespace().any(|word| word == value.trim())
+}
+
+/// Column alignments for the tables matching `table`. Columns beyond the list
+/// keep their computed alignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableAlignOverride {
+    pub table: TableMatch,
+    pub columns: Vec<TableColumnAlign>,
+}
+
+impl TableAlignOverride {
+    /// Parse `caption=Perft Results=>l,r,r` or `class=sortable=>left,right`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (key, columns) = spec
+            .split_once("=>")
+            .ok_or_else(|| format!("expected KEY=VALUE=>ALIGN,...: {}", spec))?;
+        let (name, value) = key
+            .split_once('=')
+            .ok_or_else(|| format!("expected caption=TEXT or ATTR=VALUE: {}", key))?;
+        let table = match name.trim() {
+            "caption" => TableMatch::Caption(value.trim().to_string()),
+            name => TableMatch::Attr {
+                name: name.to_string(),
+                value: value.trim().to_string(),
+            },
+        };
+        let columns = columns
+            .split(',')
+            .map(|c| {
+                TableColumnAlign::parse(c)
+                    .ok_or_else(|| format!("unknown alignment {:?}", c.trim()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TableAlignOverride { table, columns })
+    }
+}
+
+/// Adjustments applied on top of the attribute-based column alignment. All off
+/// by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableAlignment {
+    /// Right-align numeric columns (node counts, Elo, percentages), whatever their
+    /// attributes say. See `numeric_min_percent`.
+    pub numeric_right: bool,
+
+    /// How many of a column's data cells must be numbers for it to count as
+    /// numeric, in percent. Empty cells and placeholders (`-`, `?`, `n/a`) don't
+    /// count either way.
+    pub numeric_min_percent: u8,
+
+    /// Always left-align the first column (usually row labels).
+    pub first_column_left: bool,
+
+    /// Per-table alignments. The first matching override wins, over everything else.
+    pub overrides: Vec<TableAlignOverride>,
+}
+
+impl Default for TableAlignment {
+    fn default() -> Self {
+        TableAlignment {
+            numeric_right: false,
+            numeric_min_percent: 80,
+            first_column_left: false,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl TableAlignment {
+    pub(crate) fn apply(
+        &self,
+        table: &Table,
+        header_rows: Range<usize>,
+        aligns: &mut [TableColumnAlign],
+    ) {
+        if self.numeric_right {
+            for (col, align) in aligns.iter_mut().enumerate() {
+                if is_numeric_column(table, header_rows.clone(), col, self.numeric_min_percent) {
+                    *align = TableColumnAlign::Right;
+                }
+            }
+        }
+        if self.first_column_left
+            && let Some(first) = aligns.first_mut()
+        {
+            *first = TableColumnAlign::Left;
+        }
+        if let Some(over) = self.overrides.iter().find(|o| o.table.matches(table)) {
+            for (align, forced) in aligns.iter_mut().zip(&over.columns) {
+                *align = *forced;
+            }
+        }
+    }
+}
+
+/// True if at least `min_percent` of the data cells of column `col` (outside the
+/// header rows, ignoring empty cells and placeholders) are numbers, and there is
+/// at least one.
+fn is_numeric_column(
+    table: &Table,
+    header_rows: Range<usize>,
+    col: usize,
+    min_percent: u8,
+) -> bool {
+    let (mut numbers, mut counted) = (0usize, 0usize);
+    for (ri, row) in table.rows.iter().enumerate() {
+        let Some(cell) = row.cells.get(col) else {
+            continue;
+        };
+        if header_rows.contains(&ri) || cell.kind != TableCellKind::Data {
+            continue;
+        }
+        let text = crate::tables::cell_text(&cell.blocks);
+        if is_placeholder(&text) {
+            continue;
+        }
+        counted += 1;
+        if is_number(&text) {
+            numbers += 1;
+        }
+    }
+    numbers > 0 && numbers * 100 >= counted * usize::from(min_percent)
+}
+
+/// Cells that stand for a missing value.
+fn is_placeholder(text: &str) -> bool {
+    matches!(
+        text.trim().to_ascii_lowercase().as_str(),
+        "" | "-" | "–" | "—" | "?" | "n/a" | "na" | "tbd"
+    )
+}
+
+/// `42`, `-3.5`, `+35`, `±12`, `1,234,567`, `1 234`, `45%`, `12.5 %`.
+fn is_number(text: &str) -> bool {
+    let text = text.trim();
+    let text = text.strip_suffix('%').unwrap_or(text).trim_end();
+    let digits: String = text
+        .trim_start_matches(['+', '-', '−', '±'])
+        .chars()
+        .filter(|c| !matches!(c, ',' | ' ' | '\u{a0}' | '\u{202f}' | '\u{2009}'))
+        .collect();
+    let mut parts = digits.split('.');
+    let int = parts.next().unwrap_or("");
+    let frac = parts.next();
+    parts.next().is_none()
+        && (!int.is_empty() || frac.is_some_and(|f| !f.is_empty()))
+        && int.chars().all(|c| c.is_ascii_digit())
+        && frac.is_none_or(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_overrides_and_numbers() {
+        let over = TableAlignOverride::parse("caption=Perft Results=>l,r,c").unwrap();
+        assert_eq!(over.table, TableMatch::Caption("Perft Results".to_string()));
+        assert_eq!(
+            over.columns,
+            [
+                TableColumnAlign::Left,
+                TableColumnAlign::Right,
+                TableColumnAlign::Center
+            ]
+        );
+        let over = TableAlignOverride::parse("class=sortable=>right").unwrap();
+        assert_eq!(
+            over.table,
+            TableMatch::Attr {
+                name: "class".to_string(),
+                value: "sortable".to_string()
+            }
+        );
+        assert!(TableAlignOverride::parse("caption=X=>l,up").is_err());
+        assert!(TableAlignOverride::parse("caption=X").is_err());
+
+        for n in [
+            "42",
+            "-3.5",
+            "+35",
+            "±12",
+            "1,234,567",
+            "1 234",
+            ".5",
+            "45%",
+            "12.5 %",
+        ] {
+            assert!(is_number(n), "{n}");
+        }
+        for n in ["", "-", "%", "1.2.3", "3.", "1e6", "Elo", "1-0"] {
+            assert!(!is_number(n), "{n}");
+        }
+        assert!(is_placeholder(" N/A ") && is_placeholder("—") && !is_placeholder("0"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Template handler registry.
+//!
+//! Templates are rendered by looking up a handler for the template name. A
+//! handler returns `Some(markdown)` to replace the invocation, or `None` to fall
+//! back to the non-destructive `{{name|...}}` passthrough.
+
+use super::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Renders one template invocation. Return `None` to use the passthrough.
+pub type TemplateHandler = Arc<
+    dyn Fn(&TemplateInvocation, &mut RenderContext, &RenderOptions) -> Option<String> + Send + Sync,
+>;
+
+/// Template handlers keyed by template name.
+///
+/// Names are matched the way MediaWiki resolves them: case-insensitively, with
+/// `_` and spaces treated alike (`{{Chess Diagram}}` == `{{chess_diagram}}`).
+#[derive(Clone, Default)]
+pub struct TemplateHandlers {
+    handlers: HashMap<String, TemplateHandler>,
+}
+
+impl fmt::Debug for TemplateHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.handlers.keys().collect();
+        names.sort();
+        f.debug_struct("TemplateHandlers")
+            .field("names", &names)
+            .finish()
+    }
+}
+
+impl TemplateHandlers {
+    /// An empty registry: every template uses the passthrough.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The handlers `RenderOptions` uses by default.
+    pub fn builtin() -> Self {
+        Self::new()
+            .with("#evu", evu)
+            .with("FEN", chess::fen_template)
+            .with("ChessBoard", chess::fen_template)
+            .with("Chess diagram", chess::square_list_template)
+            .with("ChessDiagram", chess::square_list_template)
+            .with("Chess diagram small", chess::square_list_template)
+    }
+
+    /// Register (or replace) the handler for `name`.
+    pub fn register<F>(&mut self, name: &str, handler: F)
+    where
+        F: Fn(&TemplateInvocation, &mut RenderContext, &RenderOptions) -> Option<String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.handlers
+            .insert(normalize_name(name), Arc::new(handler));
+    }
+
+    /// Builder-style [`TemplateHandlers::register`].
+    pub fn with<F>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(&TemplateInvocation, &mut RenderContext, &RenderOptions) -> Option<String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.register(name, handler);
+        self
+    }
+
+    /// Register `name` as a template that renders to nothing, e.g. a navigation
+    /// box or a maintenance banner.
+    pub fn drop_template(&mut self, name: &str) {
+        self.register(name, |_, _, _| Some(String::new()));
+    }
+
+    /// Remove the handler for `name`. Returns true if one was registered.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.handlers.remove(&normalize_name(name)).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TemplateHandler> {
+        if self.handlers.is_empty() {
+            return None;
+        }
+        self.handlers.get(&normalize_name(name))
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().replace('_', " ").to_lowercase()
+}
+
+/// True for templates named like navigation boxes (`{{Navbox engines}}`,
+/// `{{Search navbox}}`).
+pub(crate) fn is_navbox_name(name: &str) -> bool {
+    normalize_name(name).contains("navbox")
+}
+
+/// `{{DISPLAYTITLE:...}}`, which only sets the title (see
+/// [`crate::frontmatter::display_title`]) and shows nothing.
+pub(crate) fn is_display_title(inv: &TemplateInvocation) -> bool {
+    inv.name
+        .raw
+        .split_once(':')
+        .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("DISPLAYTITLE"))
+}
+
+/// `{{#evu:URL|...}}` => just emit the URL as a link.
+fn evu(inv: &TemplateInvocation, ctx: &mut RenderContext, opts: &RenderOptions) -> Option<String> {
+    // URLs are not Markdown text, so keep them unescaped when they're plain.
+    let url = inv
+        .params
+        .first()
+        .map(|p| {
+            inlines_plain_text(&p.value).unwrap_or_else(|| render_inlines(&p.value, ctx, opts))
+        })
+        .unwrap_or_default();
+    if url.trim().is_empty() {
+        Some(String::new())
+    } else {
+        Some(format!("[Video]({})", url.trim()))
+    }
+}
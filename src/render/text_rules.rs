@@ -0,0 +1,174 @@
+//! User-defined rules for running text.
+//!
+//! Each rule is a regex and a replacement (`$1`, `${name}` refer to capture
+//! groups), applied in order to the text of every Text node, e.g. to wrap move
+//! notation or `perft(d)` calls in backticks:
+//!
+//! ```text
+//! \bperft\(\d+\)=>`$0`
+//! \b[a-h][1-8][a-h][1-8][qrbn]?\b=>`$0`
+//! ```
+//!
+//! Replacements are emitted as Markdown and are not escaped; the text around the
+//! matches is escaped as usual. Text inside links and code (`<code>`, `<tt>`,
+//! `<kbd>`, `<pre>`, ...) is never touched, and neither is text a rule already
+//! replaced.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// One `pattern => replacement` rule.
+#[derive(Debug, Clone)]
+pub struct TextRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl TextRule {
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.into(),
+        })
+    }
+
+    /// Parse the `PATTERN=>REPLACEMENT` form used on the command line and in rule
+    /// files.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (pattern, replacement) = rule
+            .split_once("=>")
+            .ok_or_else(|| format!("expected PATTERN=>REPLACEMENT: {}", rule))?;
+        Self::new(pattern.trim(), replacement.trim()).map_err(|e| e.to_string())
+    }
+}
+
+/// An ordered list of [`TextRule`]s. Empty by default.
+#[derive(Debug, Clone, Default)]
+pub struct TextRules {
+    rules: Vec<TextRule>,
+}
+
+impl TextRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule, applied after the existing ones.
+    pub fn push(mut self, rule: TextRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Parse a rule file: one `PATTERN=>REPLACEMENT` rule per line; blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn parse_rules(text: &str) -> Result<Self, String> {
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .try_fold(Self::new(), |rules, (i, line)| {
+                let rule = TextRule::parse(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+                Ok(rules.push(rule))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `text` with every rule applied in order. Text outside the matches goes
+    /// through `escape`; replacements are kept as they are.
+    pub fn apply(&self, text: &str, escape: impl Fn(&str) -> String) -> String {
+        // later rules only see text that is neither protected nor replaced yet.
+        let mut pieces = protect_code_tags(text);
+        for rule in &self.rules {
+            let mut next = Vec::with_capacity(pieces.len());
+            for (piece, kind) in pieces {
+                if kind != Piece::Text {
+                    next.push((piece, kind));
+                    continue;
+                }
+                let mut last = 0;
+                for caps in rule.pattern.captures_iter(&piece) {
+                    let m = caps.get(0).expect("group 0 always matches");
+                    if m.as_str().is_empty() {
+                        continue;
+                    }
+                    next.push((piece[last..m.start()].to_string(), Piece::Text));
+                    let mut replacement = String::new();
+                    caps.expand(&rule.replacement, &mut replacement);
+                    next.push((replacement, Piece::Replaced));
+                    last = m.end();
+                }
+                next.push((piece[last..].to_string(), Piece::Text));
+            }
+            pieces = next;
+        }
+        pieces
+            .into_iter()
+            .filter(|(piece, _)| !piece.is_empty())
+            .map(|(piece, kind)| match kind {
+                Piece::Replaced => piece,
+                Piece::Text | Piece::Protected => escape(&piece),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    Text,
+    /// Code tags (and any other tag markup) left in the text by the parser.
+    Protected,
+    Replaced,
+}
+
+/// Split `text` at literal `<code>...</code>` (and `tt`, `kbd`, `samp`, `pre`,
+/// `math`) elements and other HTML tags, which the parser leaves in Text nodes.
+fn protect_code_tags(text: &str) -> Vec<(String, Piece)> {
+    static CODE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+        let elements: Vec<String> = ["code", "tt", "kbd", "samp", "pre", "math"]
+            .iter()
+            .map(|tag| format!(r"<{tag}\b[^>]*>.*?</{tag}\s*>"))
+            .collect();
+        Regex::new(&format!(r"(?is){}|</?[a-z][^<>]*>", elements.join("|"))).expect("valid regex")
+    });
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for m in CODE_TAG.find_iter(text) {
+        pieces.push((text[last..m.start()].to_string(), Piece::Text));
+        pieces.push((m.as_str().to_string(), Piece::Protected));
+        last = m.end();
+    }
+    pieces.push((text[last..].to_string(), Piece::Text));
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacements_are_not_escaped_or_rewritten() {
+        let rules = TextRules::parse_rules(
+            "# backtick perft calls and moves\n\
+             \\bperft\\(\\d+\\)=>`$0`\n\
+             \n\
+             \\b[a-h][1-8][a-h][1-8]\\b=>`$0`\n\
+             `=>'\n",
+        )
+        .unwrap();
+        let escape = |s: &str| s.replace('_', "\\_");
+        assert_eq!(
+            rules.apply("perft(5) after e2e4 in my_engine", escape),
+            "`perft(5)` after `e2e4` in my\\_engine"
+        );
+        assert_eq!(
+            rules.apply("<code>perft(1)</code> <b title=\"e2e4\">e2e4</b>", escape),
+            "<code>perft(1)</code> <b title=\"e2e4\">`e2e4`</b>"
+        );
+
+        let err = TextRules::parse_rules("ok=>fine\nno arrow\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+    }
+}
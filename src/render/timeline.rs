@@ -0,0 +1,188 @@
+//! EasyTimeline charts.
+//!
+//! A `<timeline>` can't be drawn in Markdown, so by default its script is kept
+//! in a fenced block. [`TimelineStyle`] can instead turn its bars and events into
+//! a Mermaid `gantt` chart or `timeline` diagram; scripts with nothing to plot,
+//! or (for `gantt`) a date format Mermaid can't read, still fall back to the
+//! fenced script.
+
+use super::*;
+
+/// How `<timeline>` blocks are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimelineStyle {
+    /// The EasyTimeline script in an `easytimeline` code fence.
+    #[default]
+    Fenced,
+    /// A Mermaid `gantt` chart: one section per bar, events as milestones.
+    MermaidGantt,
+    /// A Mermaid `timeline` diagram: one section per bar, one row per entry.
+    MermaidTimeline,
+}
+
+pub fn render_timeline(
+    timeline: &Timeline,
+    _ctx: &mut RenderContext,
+    opts: &RenderOptions,
+) -> String {
+    let mermaid = match opts.timeline_style {
+        TimelineStyle::Fenced => None,
+        TimelineStyle::MermaidGantt => gantt(timeline),
+        TimelineStyle::MermaidTimeline => mermaid_timeline(timeline),
+    };
+    match mermaid {
+        Some(chart) => format!("```mermaid\n{}\n```", chart),
+        None => format!(
+            "```easytimeline\n{}\n```",
+            timeline.source.trim_matches('\n')
+        ),
+    }
+}
+
+fn gantt(timeline: &Timeline) -> Option<String> {
+    let (date_format, axis_format) = gantt_date_format(timeline.date_format.as_deref())?;
+    let mut tasks = Vec::new();
+    for (section, entries) in sections(timeline) {
+        let mut lines: Vec<String> = Vec::new();
+        for entry in entries {
+            let label = label(timeline, entry);
+            if let Some(at) = date(timeline, entry.at.as_deref()) {
+                lines.push(format!("    {} :milestone, {}, 0d", label, at));
+            } else if let (Some(from), Some(till)) = (
+                date(timeline, entry.from.as_deref().or(Some("start"))),
+                date(timeline, entry.till.as_deref().or(Some("end"))),
+            ) {
+                lines.push(format!("    {} :{}, {}", label, from, till));
+            }
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        if let Some(section) = section {
+            tasks.push(format!("    section {}", section));
+        }
+        tasks.extend(lines);
+    }
+    if tasks.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "gantt\n    dateFormat {}\n    axisFormat {}\n{}",
+        date_format,
+        axis_format,
+        tasks.join("\n")
+    ))
+}
+
+fn mermaid_timeline(timeline: &Timeline) -> Option<String> {
+    let mut rows = Vec::new();
+    for (section, entries) in sections(timeline) {
+        let mut lines: Vec<String> = Vec::new();
+        for entry in entries {
+            let when = match (&entry.at, &entry.from, &entry.till) {
+                (Some(at), _, _) => date(timeline, Some(at.as_str())),
+                (None, None, None) => None,
+                (None, from, till) => {
+                    let from = date(timeline, from.as_deref().or(Some("start")));
+                    let till = date(timeline, till.as_deref().or(Some("end")));
+                    match (from, till) {
+                        (Some(from), Some(till)) if from == till => Some(from),
+                        (Some(from), Some(till)) => Some(format!("{} – {}", from, till)),
+                        (from, till) => from.or(till),
+                    }
+                }
+            };
+            if let Some(when) = when {
+                lines.push(format!(
+                    "    {} : {}",
+                    mermaid_text(&when),
+                    label(timeline, entry)
+                ));
+            }
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        if let Some(section) = section {
+            rows.push(format!("    section {}", section));
+        }
+        rows.extend(lines);
+    }
+    (!rows.is_empty()).then(|| format!("timeline\n{}", rows.join("\n")))
+}
+
+/// Entries grouped by bar: bar-less entries first, then the `BarData` bars in
+/// order, then any other bars in the order they first appear.
+fn sections(timeline: &Timeline) -> Vec<(Option<String>, Vec<&TimelineEntry>)> {
+    let mut order: Vec<Option<&str>> = vec![None];
+    order.extend(timeline.bars.iter().map(|b| Some(b.id.as_str())));
+    for entry in &timeline.entries {
+        if !order.contains(&entry.bar.as_deref()) {
+            order.push(entry.bar.as_deref());
+        }
+    }
+    order
+        .into_iter()
+        .map(|bar| {
+            let entries = timeline
+                .entries
+                .iter()
+                .filter(|e| e.bar.as_deref() == bar)
+                .collect();
+            (
+                bar.map(|id| mermaid_text(&bar_label(timeline, id))),
+                entries,
+            )
+        })
+        .collect()
+}
+
+fn bar_label(timeline: &Timeline, id: &str) -> String {
+    timeline
+        .bars
+        .iter()
+        .find(|b| b.id == id)
+        .and_then(|b| b.text.clone())
+        .unwrap_or_else(|| id.to_string())
+}
+
+fn label(timeline: &Timeline, entry: &TimelineEntry) -> String {
+    let text = match (&entry.text, &entry.bar) {
+        (Some(text), _) => text.clone(),
+        (None, Some(bar)) => bar_label(timeline, bar),
+        (None, None) => "Event".to_string(),
+    };
+    mermaid_text(&text)
+}
+
+/// A date as written, with `start`/`end` taken from the period.
+fn date(timeline: &Timeline, value: Option<&str>) -> Option<String> {
+    let period = timeline.period.as_ref();
+    match value?.trim() {
+        "start" => period.map(|p| p.from.clone()),
+        "end" => period.map(|p| p.till.clone()),
+        "" => None,
+        v => Some(v.to_string()),
+    }
+}
+
+/// EasyTimeline's date format (`dd/mm/yyyy` when unset) as Mermaid's `dateFormat`
+/// and `axisFormat`. `x.y` (plain numbers) has no Mermaid equivalent.
+fn gantt_date_format(format: Option<&str>) -> Option<(&'static str, &'static str)> {
+    match format
+        .unwrap_or("dd/mm/yyyy")
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "yyyy" => Some(("YYYY", "%Y")),
+        "dd/mm/yyyy" => Some(("DD/MM/YYYY", "%d/%m/%Y")),
+        "mm/dd/yyyy" => Some(("MM/DD/YYYY", "%m/%d/%Y")),
+        _ => None,
+    }
+}
+
+/// `:` and `;` separate fields and statements in Mermaid; use entity codes.
+fn mermaid_text(text: &str) -> String {
+    text.replace(';', "#59;").replace(':', "#58;")
+}
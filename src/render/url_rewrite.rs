@@ -0,0 +1,109 @@
+//! Rewrite rules for external link URLs.
+//!
+//! Each rule is a regex and a replacement (`$1`, `${name}` refer to capture
+//! groups), applied to every `[https://... label]` URL in order, e.g. to force
+//! https, strip tracking parameters, or point at a mirror.
+
+use regex::Regex;
+use std::borrow::Cow;
+
+/// One `pattern => replacement` rule.
+#[derive(Debug, Clone)]
+pub struct UrlRewriteRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl UrlRewriteRule {
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.into(),
+        })
+    }
+
+    /// Parse the `PATTERN=>REPLACEMENT` form used on the command line and in rule
+    /// files.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (pattern, replacement) = rule
+            .split_once("=>")
+            .ok_or_else(|| format!("expected PATTERN=>REPLACEMENT: {}", rule))?;
+        Self::new(pattern.trim(), replacement.trim()).map_err(|e| e.to_string())
+    }
+}
+
+/// An ordered list of [`UrlRewriteRule`]s. Empty by default.
+#[derive(Debug, Clone, Default)]
+pub struct UrlRewrites {
+    rules: Vec<UrlRewriteRule>,
+}
+
+impl UrlRewrites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule, applied after the existing ones.
+    pub fn push(mut self, rule: UrlRewriteRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Parse a rule file: one `PATTERN=>REPLACEMENT` rule per line; blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn parse_rules(text: &str) -> Result<Self, String> {
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .try_fold(Self::new(), |rules, (i, line)| {
+                let rule =
+                    UrlRewriteRule::parse(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+                Ok(rules.push(rule))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `url` with every rule applied in order.
+    pub fn apply<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        let mut out = Cow::Borrowed(url);
+        for rule in &self.rules {
+            if let Cow::Owned(rewritten) = rule.pattern.replace_all(&out, rule.replacement.as_str())
+            {
+                out = Cow::Owned(rewritten);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_rules_in_order() {
+        let rules = UrlRewrites::parse_rules(
+            "# force https, then drop tracking parameters\n\
+             ^http://=>https://\n\
+             \n\
+             [?&]utm_[a-z]+=[^&]*=>\n\
+             ^https://old\\.example\\.com/(.*)=>https://mirror.example.org/$1\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rules.apply("http://old.example.com/paper.pdf?utm_source=x&utm_medium=y"),
+            "https://mirror.example.org/paper.pdf"
+        );
+        assert!(matches!(
+            rules.apply("https://other.org/"),
+            Cow::Borrowed(_)
+        ));
+
+        let err = UrlRewrites::parse_rules("ok=>fine\nno arrow\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+    }
+}
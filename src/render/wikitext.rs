@@ -0,0 +1,426 @@
+//! AST -> Wikitext serializer.
+//!
+//! Every node has a *canonical* wikitext form (e.g. `==Title==`, `'''bold'''`).
+//! Documents produced by the lossless parse mode additionally carry
+//! [`Trivia`] records; for those, unedited top-level nodes are reproduced from
+//! their original source text so that parse -> serialize is byte-for-byte.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+/// Serialize a document back into wikitext.
+///
+/// If the document carries trivia (see `parse::parse_wiki_lossless`), unedited
+/// nodes and the whitespace between them are reproduced exactly. Nodes whose
+/// canonical form changed since parsing are emitted canonically.
+pub fn serialize_doc(doc: &Document) -> String {
+    let trivia: HashMap<(TriviaKind, Span), &Trivia> =
+        doc.trivia.iter().map(|t| ((t.kind, t.span), t)).collect();
+
+    // top-level items in source order.
+    let mut items: Vec<(TriviaKind, Span, String)> = Vec::new();
+    if let Some(r) = &doc.redirect {
+        items.push((TriviaKind::Redirect, r.span, serialize_redirect(r)));
+    }
+    for b in &doc.blocks {
+        items.push((TriviaKind::Block, b.span, serialize_block(b)));
+    }
+    for c in &doc.categories {
+        items.push((TriviaKind::Category, c.span, serialize_category(c)));
+    }
+    items.sort_by_key(|(kind, span, _)| (span.start, *kind == TriviaKind::Category));
+
+    let mut out = String::new();
+    for (kind, span, canonical) in items {
+        match trivia.get(&(kind, span)) {
+            Some(t) => {
+                out.push_str(&t.leading);
+                match &t.original {
+                    Some(orig) if fingerprint(&canonical) == t.fingerprint => out.push_str(orig),
+                    _ => out.push_str(&canonical),
+                }
+            }
+            None => {
+                if !out.is_empty() {
+                    if !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    if kind == TriviaKind::Block {
+                        out.push('\n');
+                    }
+                }
+                out.push_str(&canonical);
+            }
+        }
+    }
+
+    match doc.trivia.iter().find(|t| t.kind == TriviaKind::Eof) {
+        Some(t) => out.push_str(&t.leading),
+        None if !out.is_empty() => out.push('\n'),
+        None => {}
+    }
+    out
+}
+
+/// Stable fingerprint of a canonical serialization, stored in [`Trivia::fingerprint`].
+pub fn fingerprint(canonical: &str) -> String {
+    format!("{:x}", md5::compute(canonical.as_bytes()))
+}
+
+pub fn serialize_redirect(r: &Redirect) -> String {
+    match &r.anchor {
+        Some(a) => format!("#REDIRECT [[{}#{}]]", r.target, a),
+        None => format!("#REDIRECT [[{}]]", r.target),
+    }
+}
+
+pub fn serialize_category(c: &CategoryTag) -> String {
+    match &c.sort_key {
+        Some(k) => format!("[[Category:{}|{}]]", c.name, k),
+        None => format!("[[Category:{}]]", c.name),
+    }
+}
+
+pub fn serialize_block(block: &BlockNode) -> String {
+    match &block.kind {
+        BlockKind::Heading { level, content } => {
+            let eq = "=".repeat(*level as usize);
+            format!("{}{}{}", eq, serialize_inlines(content), eq)
+        }
+        BlockKind::Paragraph { content } => serialize_inlines(content),
+        BlockKind::List { items } => serialize_list(items, ""),
+        BlockKind::Table { table } => serialize_table(table),
+        BlockKind::CodeBlock { block } => serialize_code_block(block),
+        BlockKind::Poem { lines } => {
+            let lines: Vec<String> = lines.iter().map(|l| serialize_inlines(l)).collect();
+            format!("<poem>\n{}\n</poem>", lines.join("\n"))
+        }
+        BlockKind::Timeline { timeline } => format!("<timeline>{}</timeline>", timeline.source),
+        BlockKind::References { node } if node.refs.is_empty() => {
+            format!("<references{} />", serialize_attrs(&node.attrs))
+        }
+        BlockKind::References { node } => {
+            let refs: Vec<String> = node
+                .refs
+                .iter()
+                .map(|r| serialize_inlines(std::slice::from_ref(r)))
+                .collect();
+            format!(
+                "<references{}>\n{}\n</references>",
+                serialize_attrs(&node.attrs),
+                refs.join("\n")
+            )
+        }
+        BlockKind::HtmlBlock { node } => {
+            let open = format!("<{}{}", node.name, serialize_attrs(&node.attrs));
+            if node.self_closing {
+                return format!("{} />", open);
+            }
+            let inner = serialize_blocks(&node.children, "\n\n");
+            format!("{}>{}</{}>", open, inner, node.name)
+        }
+        BlockKind::MagicWord { name } => name.clone(),
+        BlockKind::HorizontalRule => "----".to_string(),
+        BlockKind::BlockQuote {
+            blocks,
+            attribution,
+            html: true,
+        } => {
+            let inner = serialize_blocks(blocks, "\n\n");
+            let intro = attribution
+                .as_ref()
+                .filter(|a| !a.trailing)
+                .map(|a| serialize_inlines(&a.content) + "\n");
+            let cite = attribution
+                .as_ref()
+                .filter(|a| a.trailing)
+                .map(|a| format!("\n<cite>{}</cite>", serialize_inlines(&a.content)));
+            format!(
+                "{}<blockquote>\n{}{}\n</blockquote>",
+                intro.unwrap_or_default(),
+                inner,
+                cite.unwrap_or_default()
+            )
+        }
+        BlockKind::BlockQuote {
+            blocks,
+            attribution,
+            html: false,
+        } => {
+            // leading-space quotes: one space per line, paragraphs separated by a blank line.
+            let inner = serialize_blocks(blocks, "\n\n");
+            let quote = inner
+                .split('\n')
+                .map(|l| {
+                    if l.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", l)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            match attribution {
+                Some(a) => format!("{}\n{}", serialize_inlines(&a.content), quote),
+                None => quote,
+            }
+        }
+        BlockKind::Inclusion { mode, blocks } => {
+            format!(
+                "<{tag}>\n{}\n</{tag}>",
+                serialize_blocks(blocks, "\n\n"),
+                tag = mode.tag()
+            )
+        }
+        BlockKind::Raw { text } => text.clone(),
+    }
+}
+
+fn serialize_blocks(blocks: &[BlockNode], sep: &str) -> String {
+    blocks
+        .iter()
+        .map(serialize_block)
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn serialize_list(items: &[ListItem], prefix: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for item in items {
+        let marker = match item.marker {
+            ListMarker::Unordered => '*',
+            ListMarker::Ordered => '#',
+            ListMarker::Term => ';',
+            ListMarker::Definition => ':',
+        };
+        let item_prefix = format!("{}{}", prefix, marker);
+        let mut line = item_prefix.clone();
+        let mut rest = item.blocks.as_slice();
+        if let Some(BlockNode {
+            kind: BlockKind::Paragraph { content },
+            ..
+        }) = rest.first()
+        {
+            line.push(' ');
+            line.push_str(&serialize_inlines(content));
+            rest = &rest[1..];
+        }
+        lines.push(line);
+        for b in rest {
+            match &b.kind {
+                BlockKind::List { items } => lines.push(serialize_list(items, &item_prefix)),
+                _ => lines.push(format!("{} {}", item_prefix, serialize_block(b))),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn serialize_table(table: &Table) -> String {
+    let mut out = format!("{{|{}", serialize_attrs(&table.attrs));
+    if let Some(cap) = &table.caption {
+        out.push_str("\n|+");
+        if !cap.attrs.is_empty() {
+            out.push_str(&serialize_attrs(&cap.attrs));
+            out.push_str(" |");
+        }
+        out.push(' ');
+        out.push_str(&serialize_inlines(&cap.content));
+    }
+    for row in &table.rows {
+        out.push_str("\n|-");
+        out.push_str(&serialize_attrs(&row.attrs));
+        for cell in &row.cells {
+            out.push('\n');
+            out.push(match cell.kind {
+                TableCellKind::Header => '!',
+                TableCellKind::Data => '|',
+            });
+            if !cell.attrs.is_empty() {
+                out.push_str(&serialize_attrs(&cell.attrs));
+                out.push_str(" |");
+            }
+            let content = serialize_blocks(&cell.blocks, "\n");
+            if !content.is_empty() {
+                out.push(' ');
+                out.push_str(&content);
+            }
+        }
+    }
+    out.push_str("\n|}");
+    out
+}
+
+fn serialize_code_block(block: &CodeBlock) -> String {
+    let lang_attr = block
+        .lang
+        .as_ref()
+        .map(|l| format!(" lang=\"{}\"", l))
+        .unwrap_or_default();
+    match block.kind {
+        CodeBlockKind::SyntaxHighlight => {
+            format!(
+                "<syntaxhighlight{}>{}</syntaxhighlight>",
+                lang_attr, block.text
+            )
+        }
+        CodeBlockKind::PreTag => format!("<pre{}>{}</pre>", lang_attr, block.text),
+        CodeBlockKind::LeadingSpace => block
+            .text
+            .split('\n')
+            .map(|l| format!(" {}", l))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn serialize_attrs(attrs: &[HtmlAttr]) -> String {
+    let mut out = String::new();
+    for a in attrs {
+        out.push(' ');
+        out.push_str(&a.name);
+        if let Some(v) = &a.value {
+            out.push_str("=\"");
+            out.push_str(v);
+            out.push('"');
+        }
+    }
+    out
+}
+
+pub fn serialize_inlines(nodes: &[InlineNode]) -> String {
+    nodes.iter().map(serialize_inline).collect()
+}
+
+fn serialize_inline(node: &InlineNode) -> String {
+    match &node.kind {
+        InlineKind::Text { value } => value.to_string(),
+        InlineKind::Bold { content } => format!("'''{}'''", serialize_inlines(content)),
+        InlineKind::Italic { content } => format!("''{}''", serialize_inlines(content)),
+        InlineKind::BoldItalic { content } => format!("'''''{}'''''", serialize_inlines(content)),
+        InlineKind::InternalLink { link } => {
+            let mut s = format!("[[{}", link.target);
+            if let Some(a) = &link.anchor {
+                s.push('#');
+                s.push_str(a);
+            }
+            if let Some(t) = &link.text {
+                s.push('|');
+                s.push_str(&serialize_inlines(t));
+            }
+            s.push_str("]]");
+            s
+        }
+        InlineKind::ExternalLink { link } => match &link.text {
+            Some(t) => format!("[{} {}]", link.url, serialize_inlines(t)),
+            None => format!("[{}]", link.url),
+        },
+        InlineKind::FileLink { link } => {
+            let ns = match link.namespace {
+                FileNamespace::File => "File",
+                FileNamespace::Image => "Image",
+                FileNamespace::Media => "Media",
+            };
+            let mut s = format!("[[{}:{}", ns, link.target);
+            for p in &link.params {
+                s.push('|');
+                s.push_str(&serialize_inlines(&p.content));
+            }
+            s.push_str("]]");
+            s
+        }
+        InlineKind::MagicLink { link } => link.text.clone(),
+        InlineKind::LineBreak => "<br/>".to_string(),
+        InlineKind::Ref { node } => {
+            let open = format!("<ref{}", serialize_attrs(&node.attrs));
+            match (&node.content, node.self_closing) {
+                (_, true) => format!("{} />", open),
+                (Some(c), false) => format!("{}>{}</ref>", open, serialize_inlines(c)),
+                (None, false) => format!("{}></ref>", open),
+            }
+        }
+        InlineKind::HtmlTag { node } => {
+            let open = format!("<{}{}", node.name, serialize_attrs(&node.attrs));
+            if node.self_closing {
+                format!("{} />", open)
+            } else {
+                format!(
+                    "{}>{}</{}>",
+                    open,
+                    serialize_inlines(&node.children),
+                    node.name
+                )
+            }
+        }
+        InlineKind::Template { node } => {
+            let mut s = format!("{{{{{}", node.name.raw);
+            let mut params = node.params.iter();
+            if node.name.kind == TemplateNameKind::ParserFunction
+                && let Some(first) = params.next()
+            {
+                s.push(':');
+                s.push_str(&serialize_inlines(&first.value));
+            }
+            for p in params {
+                s.push('|');
+                if let Some(n) = &p.name {
+                    s.push_str(n);
+                    s.push('=');
+                }
+                s.push_str(&serialize_inlines(&p.value));
+            }
+            s.push_str("}}");
+            s
+        }
+        InlineKind::TemplateArg { name, default } => match default {
+            Some(default) => format!("{{{{{{{}|{}}}}}}}", name, serialize_inlines(default)),
+            None => format!("{{{{{{{}}}}}}}", name),
+        },
+        InlineKind::Inclusion { mode, content } => {
+            format!(
+                "<{tag}>{}</{tag}>",
+                serialize_inlines(content),
+                tag = mode.tag()
+            )
+        }
+        InlineKind::Raw { text } => text.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_wiki, parse_wiki_lossless};
+
+    #[test]
+    fn lossless_round_trip_is_byte_exact() {
+        let src = "#REDIRECT [[Target#Sec]] extra\n\n\n== Title ==\r\nSome '''bold''' text\n\n* a\n** b\n\n{|\n|-\n| x || y\n|}\n\n  [[Category: Chess ]]\n\n";
+        let out = parse_wiki_lossless(src);
+        assert_eq!(serialize_doc(&out.document), src);
+    }
+
+    #[test]
+    fn edited_block_is_serialized_canonically_and_neighbors_are_untouched() {
+        let src = "== Title ==\n\nFirst  paragraph.\n\n\nSecond paragraph.\n";
+        let mut out = parse_wiki_lossless(src);
+        let BlockKind::Paragraph { content } = &mut out.document.blocks[1].kind else {
+            panic!("expected paragraph");
+        };
+        content[0].kind = InlineKind::Text {
+            value: "Edited.".into(),
+        };
+        assert_eq!(
+            serialize_doc(&out.document),
+            "== Title ==\n\nEdited.\n\n\nSecond paragraph.\n"
+        );
+    }
+
+    #[test]
+    fn documents_without_trivia_serialize_canonically() {
+        let out = parse_wiki("= Title =\nBody with [[Link|label]].\n[[Category:X]]\n");
+        assert_eq!(
+            serialize_doc(&out.document),
+            "=Title=\n\nBody with [[Link|label]].\n[[Category:X]]\n"
+        );
+    }
+}
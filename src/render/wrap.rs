@@ -0,0 +1,207 @@
+//! Paragraph reflow for rendered Markdown.
+//!
+//! Wrapping happens on the rendered text, so it has to recognize Markdown
+//! constructs that must stay on one line: wiki links (`[[a b|c d]]`), Markdown
+//! links and images (`[a b](url)`), code spans, and inline HTML tags. Spaces
+//! inside those are never used as break points.
+
+/// Reflow `text` so lines are at most `width` characters where possible.
+///
+/// Existing line breaks (e.g. hard breaks) are kept. Words longer than `width`
+/// are left intact on their own line.
+pub(crate) fn wrap_markdown(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| wrap_line(line, width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+
+    // keep leading indentation and trailing whitespace (two-space hard breaks).
+    let body = line.trim_start_matches(' ');
+    let lead = &line[..line.len() - body.len()];
+    let trimmed = body.trim_end_matches(' ');
+    let trail = &body[trimmed.len()..];
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut cur = lead.to_string();
+    let mut cur_len = lead.chars().count();
+    let mut cur_has_word = false;
+    for word in unbreakable_words(trimmed) {
+        let wlen = word.chars().count();
+        if cur_has_word && cur_len + 1 + wlen > width && can_start_line(word) {
+            lines.push(std::mem::take(&mut cur));
+            cur_len = 0;
+            cur_has_word = false;
+        }
+        if cur_has_word {
+            cur.push(' ');
+            cur_len += 1;
+        }
+        cur.push_str(word);
+        cur_len += wlen;
+        cur_has_word = true;
+    }
+    cur.push_str(trail);
+    lines.push(cur);
+    lines.join("\n")
+}
+
+/// Split on spaces that are outside links, code spans, and HTML tags.
+fn unbreakable_words(line: &str) -> Vec<&str> {
+    let bytes = line.as_bytes();
+    let mut words = Vec::new();
+    let mut start = 0usize;
+    let mut i = 0usize;
+
+    // nesting state.
+    let mut wiki_link = 0usize;
+    let mut bracket = 0usize;
+    let mut paren = 0usize;
+    let mut code_ticks = 0usize;
+    let mut in_tag = false;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if code_ticks > 0 {
+            if b == b'`' {
+                let run = bytes[i..].iter().take_while(|c| **c == b'`').count();
+                if run == code_ticks {
+                    code_ticks = 0;
+                }
+                i += run;
+                continue;
+            }
+            i += 1;
+            continue;
+        }
+        if in_tag {
+            if b == b'>' {
+                in_tag = false;
+            }
+            i += 1;
+            continue;
+        }
+        match b {
+            // backslash escapes (`\[`, `\_`) are literal text.
+            b'\\' => {
+                i += 2;
+                continue;
+            }
+            b'`' => {
+                let run = bytes[i..].iter().take_while(|c| **c == b'`').count();
+                // only treat as a code span if it actually closes on this line.
+                let closer = "`".repeat(run);
+                if line[i + run..].contains(&closer) {
+                    code_ticks = run;
+                }
+                i += run;
+                continue;
+            }
+            b'<' if bytes
+                .get(i + 1)
+                .is_some_and(|n| n.is_ascii_alphabetic() || *n == b'/' || *n == b'!')
+                && line[i..].contains('>') =>
+            {
+                in_tag = true;
+            }
+            b'[' if bytes.get(i + 1) == Some(&b'[') => {
+                wiki_link += 1;
+                i += 2;
+                continue;
+            }
+            b']' if wiki_link > 0 && bytes.get(i + 1) == Some(&b']') => {
+                wiki_link -= 1;
+                i += 2;
+                continue;
+            }
+            b'[' => bracket += 1,
+            b']' if bracket > 0 => {
+                bracket -= 1;
+                if bracket == 0 && bytes.get(i + 1) == Some(&b'(') {
+                    paren = 1;
+                    i += 2;
+                    continue;
+                }
+            }
+            b'(' if paren > 0 => paren += 1,
+            b')' if paren > 0 => paren -= 1,
+            b' ' if wiki_link == 0 && bracket == 0 && paren == 0 => {
+                if i > start {
+                    words.push(&line[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+/// A word that would turn into block syntax at the start of a line (list
+/// marker, heading, quote, fence, setext underline) must stay on the previous line.
+fn can_start_line(word: &str) -> bool {
+    if matches!(word, "-" | "+" | "*" | ">" | "|") {
+        return false;
+    }
+    if word.starts_with('#') || word.starts_with('>') || word.starts_with("```") {
+        return false;
+    }
+    if word.len() >= 2 && (word.bytes().all(|b| b == b'-') || word.bytes().all(|b| b == b'=')) {
+        return false;
+    }
+    // ordered list markers: `1.` / `12)`
+    let digits = word.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && digits + 1 == word.len() && matches!(word.as_bytes()[digits], b'.' | b')') {
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_at_width_without_splitting_links() {
+        let text = "See [[Move Generation|move gen]] and [the wiki page](https://example.com/a b) for `x = y + z` details.";
+        let wrapped = wrap_markdown(text, 20);
+        assert_eq!(
+            wrapped,
+            "See\n[[Move Generation|move gen]]\nand\n[the wiki page](https://example.com/a b)\nfor `x = y + z`\ndetails."
+        );
+    }
+
+    #[test]
+    fn keeps_html_tags_and_footnotes_intact() {
+        let text = "alpha <a name=\"Some Anchor\"></a> beta gamma[^12] delta";
+        let wrapped = wrap_markdown(text, 12);
+        assert_eq!(
+            wrapped,
+            "alpha\n<a name=\"Some Anchor\"></a>\nbeta\ngamma[^12]\ndelta"
+        );
+    }
+
+    #[test]
+    fn never_starts_a_line_with_block_syntax() {
+        let wrapped = wrap_markdown("aaaa bbbb - cccc # dddd 1. eeee", 9);
+        assert_eq!(wrapped, "aaaa bbbb -\ncccc #\ndddd 1.\neeee");
+    }
+
+    #[test]
+    fn existing_breaks_and_trailing_spaces_survive() {
+        let wrapped = wrap_markdown("one two three  \nfour five", 8);
+        assert_eq!(wrapped, "one two\nthree  \nfour\nfive");
+    }
+}
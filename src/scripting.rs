@@ -0,0 +1,207 @@
+//! [rhai](https://rhai.rs) scripts for site-specific templates and text, so the
+//! long tail of templates doesn't need a recompile (the `scripting` feature).
+//!
+//! A script registers handlers when it runs:
+//!
+//! ```rhai
+//! // `params` maps "1", "2", ... (unnamed) and named keys to rendered Markdown.
+//! template("Quote", |name, params| `> ${params["1"]}`);
+//!
+//! fn cpw(name, params) {
+//!     if params.contains("url") { `[${params["1"]}](${params["url"]})` }
+//! }
+//! template("CPW", Fn("cpw"));
+//!
+//! // called with the Markdown of every run of plain text.
+//! text(|md| { md.replace("e.p.", "en passant"); md });
+//! ```
+//!
+//! A handler returns the Markdown to emit, or `()` to leave the template (or
+//! text) as it would otherwise be rendered. Runtime errors are logged and treated
+//! like `()`. `print` writes to stderr.
+
+use crate::ast::{InlineKind, TemplateInvocation};
+use crate::error::{Error, Result};
+use crate::render::{RenderContext, RenderOptions, render_inlines};
+use rhai::{AST, Dynamic, Engine, FnPtr, Map};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A compiled script and the handlers it registered.
+#[derive(Clone)]
+pub struct Script {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    engine: Engine,
+    ast: AST,
+    templates: Vec<(String, FnPtr)>,
+    text: Vec<FnPtr>,
+}
+
+#[derive(Default)]
+struct Registered {
+    templates: Vec<(String, FnPtr)>,
+    text: Vec<FnPtr>,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&String> = self.inner.templates.iter().map(|(name, _)| name).collect();
+        f.debug_struct("Script")
+            .field("templates", &names)
+            .field("text", &self.inner.text.len())
+            .finish()
+    }
+}
+
+impl Script {
+    /// Compile and run `source`, collecting the handlers it registers.
+    pub fn new(source: &str) -> Result<Self> {
+        let registered = Arc::new(Mutex::new(Registered::default()));
+        let mut engine = Engine::new();
+        engine.on_print(|s| eprintln!("{}", s));
+        let r = Arc::clone(&registered);
+        engine.register_fn("template", move |name: &str, handler: FnPtr| {
+            r.lock()
+                .unwrap()
+                .templates
+                .push((name.to_string(), handler));
+        });
+        let r = Arc::clone(&registered);
+        engine.register_fn("text", move |handler: FnPtr| {
+            r.lock().unwrap().text.push(handler);
+        });
+
+        let script_error = |e: &dyn fmt::Display| Error::Config(format!("script: {}", e));
+        let ast = engine.compile(source).map_err(|e| script_error(&e))?;
+        engine.run_ast(&ast).map_err(|e| script_error(&e))?;
+        let registered = std::mem::take(&mut *registered.lock().unwrap());
+        Ok(Script {
+            inner: Arc::new(Inner {
+                engine,
+                ast,
+                templates: registered.templates,
+                text: registered.text,
+            }),
+        })
+    }
+
+    /// Compile and run the script file at `path`.
+    #[cfg(feature = "native")]
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).map_err(Error::io(path))?;
+        Self::new(&source).map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Register the script's template handlers (replacing built-in ones of the
+    /// same name) and its text transforms, as the `on_inline` hook for text.
+    pub fn apply(&self, opts: &mut RenderOptions) {
+        for (name, handler) in &self.inner.templates {
+            let script = self.clone();
+            let handler = handler.clone();
+            opts.template_handlers
+                .register(name, move |inv, ctx, opts| {
+                    script.call_template(&handler, inv, ctx, opts)
+                });
+        }
+        if !self.inner.text.is_empty() {
+            let script = self.clone();
+            let previous = opts.hooks.on_inline.take();
+            opts.hooks.on_inline = Some(Arc::new(move |node, md| {
+                let replaced = previous.as_ref().and_then(|hook| hook(node, md));
+                match &node.kind {
+                    InlineKind::Text { .. } => script
+                        .call_text(replaced.as_deref().unwrap_or(md))
+                        .or(replaced),
+                    _ => replaced,
+                }
+            }));
+        }
+    }
+
+    fn call_template(
+        &self,
+        handler: &FnPtr,
+        inv: &TemplateInvocation,
+        ctx: &mut RenderContext,
+        opts: &RenderOptions,
+    ) -> Option<String> {
+        let mut params = Map::new();
+        let mut position = 0;
+        for p in &inv.params {
+            let key = match &p.name {
+                Some(name) => name.trim().to_string(),
+                None => {
+                    position += 1;
+                    position.to_string()
+                }
+            };
+            params.insert(
+                key.into(),
+                render_inlines(&p.value, ctx, opts)
+                    .trim()
+                    .to_string()
+                    .into(),
+            );
+        }
+        self.call(handler, (inv.name.raw.trim().to_string(), params))
+    }
+
+    fn call_text(&self, md: &str) -> Option<String> {
+        let mut out: Option<String> = None;
+        for handler in &self.inner.text {
+            let current = out.as_deref().unwrap_or(md).to_string();
+            if let Some(md) = self.call(handler, (current,)) {
+                out = Some(md);
+            }
+        }
+        out
+    }
+
+    /// `Some` for a returned value, `None` for `()` and errors.
+    fn call(&self, handler: &FnPtr, args: impl rhai::FuncArgs) -> Option<String> {
+        match handler.call::<Dynamic>(&self.inner.engine, &self.inner.ast, args) {
+            Ok(value) if value.is_unit() => None,
+            Ok(value) => Some(value.to_string()),
+            Err(e) => {
+                tracing::warn!("script handler {} failed: {}", handler.fn_name(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_render_templates_and_text() {
+        let script = Script::new(
+            r#"
+            template("Quote", |name, params| `> ${params["1"]} (${name})`);
+            fn cpw(name, params) {
+                if params.contains("url") { `[${params["1"]}](${params["url"]})` }
+            }
+            template("CPW", Fn("cpw"));
+            text(|md| { md.replace("e.p.", "en passant"); md });
+            "#,
+        )
+        .unwrap();
+        let mut opts = RenderOptions::default();
+        script.apply(&mut opts);
+
+        let md = crate::convert_wikitext(
+            "{{Quote|Hi [[Bob]]}}\n\nCapture e.p. {{CPW|Wiki|url=https://example.org}} {{CPW|x}}\n",
+            &opts,
+        );
+        assert_eq!(
+            md,
+            "> Hi [[Bob]] (Quote)\n\nCapture en passant [Wiki](https://example.org) {{CPW|x}}"
+        );
+
+        assert!(Script::new("template(").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Client-side search index for a static site built from `docs/md`.
+//!
+//! The index holds one plain-text document per article, in the shape lunr's
+//! `add` and elasticlunr's `addDoc` take, so the browser only has to index it:
+//!
+//! ```js
+//! const data = await (await fetch("search-index.json")).json();
+//! const idx = lunr(function () {
+//!   this.ref(data.ref);
+//!   data.fields.forEach((f) => this.field(f));
+//!   data.documents.forEach((d) => this.add(d));
+//! });
+//! ```
+
+use crate::ast::{self, BlockKind, Document};
+use serde::Serialize;
+
+/// The fields every [`SearchDocument`] is indexed by.
+pub const SEARCH_FIELDS: &[&str] = &["title", "headings", "body", "tags"];
+
+/// One article's searchable text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchDocument {
+    /// The article id (`Ken_Thompson`), used as the lunr `ref`.
+    pub id: String,
+    /// The generated `.md` file, relative to the Markdown root, with `/` separators.
+    pub path: String,
+    pub title: String,
+    pub headings: Vec<String>,
+    /// Paragraph, list, table, and quote text without markup.
+    pub body: String,
+    pub tags: Vec<String>,
+}
+
+impl SearchDocument {
+    pub fn new(article_id: &str, path: String, doc: &Document, tags: Vec<String>) -> Self {
+        let mut headings = Vec::new();
+        let mut body = String::new();
+        ast::walk_blocks(&doc.blocks, &mut |block| {
+            let mut text = String::new();
+            match &block.kind {
+                BlockKind::Heading { content, .. } => {
+                    ast::plain_text(content, &mut text);
+                    headings.push(squash_whitespace(&text));
+                    return;
+                }
+                BlockKind::Paragraph { content } => ast::plain_text(content, &mut text),
+                BlockKind::Table { table } => {
+                    if let Some(caption) = &table.caption {
+                        ast::plain_text(&caption.content, &mut text);
+                    }
+                }
+                _ => return,
+            }
+            let text = squash_whitespace(&text);
+            if !text.is_empty() {
+                if !body.is_empty() {
+                    body.push('\n');
+                }
+                body.push_str(&text);
+            }
+        });
+        SearchDocument {
+            id: article_id.to_string(),
+            path,
+            title: article_id.replace('_', " "),
+            headings,
+            body,
+            tags,
+        }
+    }
+}
+
+/// All documents plus the lunr `ref`/`field` names.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchIndex {
+    pub version: u32,
+    #[serde(rename = "ref")]
+    pub ref_field: &'static str,
+    pub fields: &'static [&'static str],
+    pub documents: Vec<SearchDocument>,
+}
+
+impl SearchIndex {
+    pub fn new(mut documents: Vec<SearchDocument>) -> Self {
+        documents.sort_by(|a, b| a.id.cmp(&b.id));
+        SearchIndex {
+            version: 1,
+            ref_field: "id",
+            fields: SEARCH_FIELDS,
+            documents,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+fn squash_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn extracts_plain_text_and_headings() {
+        let doc = parse_wiki(
+            "'''Ken Thompson''' wrote [[Belle|a chess machine]].<ref>note</ref>\n\
+             == Chess ==\n* Co-author of  [[Unix]]\n<pre>code</pre>\n",
+        )
+        .document;
+        let d = SearchDocument::new(
+            "Ken_Thompson",
+            "k/Ken Thompson.md".into(),
+            &doc,
+            vec!["people".into()],
+        );
+        assert_eq!(d.title, "Ken Thompson");
+        assert_eq!(d.headings, ["Chess"]);
+        assert_eq!(
+            d.body,
+            "Ken Thompson wrote a chess machine.\nCo-author of Unix"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&SearchIndex::new(vec![d]).to_json()).unwrap();
+        assert_eq!(json["ref"], "id");
+        assert_eq!(json["documents"][0]["tags"][0], "people");
+    }
+}
@@ -0,0 +1,286 @@
+//! Local preview server (`wiki2md serve`).
+//!
+//! Renders articles from the `.wiki` cache on every request, so a page always
+//! reflects the current render options and source. `/` lists the articles and
+//! `/wiki/<article_id>` shows one, with internal links pointing at other
+//! `/wiki/...` routes.
+
+use crate::config::Config;
+use crate::driver::{article_id_from_path, parse_file, wiki_path_for, wiki_source_files};
+use crate::error::{Error, Result};
+use crate::render::html::{self, HtmlOptions};
+use crate::{render_body, sanitize_article_id};
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+/// Route prefix for article pages.
+const ARTICLE_ROUTE: &str = "/wiki/";
+
+/// What article pages show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ServeFormat {
+    /// The HTML renderer's output.
+    #[default]
+    Html,
+    /// The converted Markdown, rendered to HTML as a Markdown viewer would.
+    Markdown,
+}
+
+/// Where and what to serve.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Address to listen on, e.g. `127.0.0.1:8080`.
+    pub addr: String,
+    pub format: ServeFormat,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        ServeOptions {
+            addr: "127.0.0.1:8080".to_string(),
+            format: ServeFormat::Html,
+        }
+    }
+}
+
+/// Serve the articles in the config's wiki directory until the process is stopped.
+pub fn serve(config: &Config, opts: &ServeOptions) -> Result<()> {
+    if !config.wiki_dir.exists() {
+        return Err(Error::Io {
+            path: config.wiki_dir.clone(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "wiki source directory not found",
+            ),
+        });
+    }
+    let server = tiny_http::Server::http(&opts.addr)
+        .map_err(|e| Error::Config(format!("cannot listen on {}: {}", opts.addr, e)))?;
+    eprintln!(
+        "Serving {} at http://{}/ (Ctrl+C to stop).",
+        config.wiki_dir.display(),
+        server.server_addr()
+    );
+
+    for request in server.incoming_requests() {
+        let page = handle(config, opts.format, request.url());
+        tracing::info!(url = request.url(), status = page.status, "served");
+        let content_type =
+            tiny_http::Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+                .expect("static header is valid");
+        let response = tiny_http::Response::from_string(page.body)
+            .with_status_code(page.status)
+            .with_header(content_type);
+        if let Err(e) = request.respond(response) {
+            tracing::warn!(error = %e, "failed to send response");
+        }
+    }
+    Ok(())
+}
+
+/// A rendered response.
+#[derive(Debug)]
+struct Page {
+    status: u16,
+    body: String,
+}
+
+fn handle(config: &Config, format: ServeFormat, url: &str) -> Page {
+    let path = url.split(['?', '#']).next().unwrap_or("/");
+    if path == "/" {
+        return index_page(config);
+    }
+    match path.strip_prefix(ARTICLE_ROUTE) {
+        Some(id) if !id.is_empty() => article_page(
+            config,
+            format,
+            &String::from_utf8_lossy(&crate::percent_decode(id, |_| true)),
+        ),
+        _ => error_page(404, "Not found", path),
+    }
+}
+
+fn index_page(config: &Config) -> Page {
+    let mut body = String::from("<h1>Articles</h1>\n<ul>\n");
+    for entry in wiki_source_files(&config.wiki_dir) {
+        let id = article_id_from_path(entry.path());
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            encode_double_quoted_attribute(&article_href(&id)),
+            encode_text(&id.replace('_', " "))
+        ));
+    }
+    body.push_str("</ul>\n");
+    Page {
+        status: 200,
+        body: html::standalone_page(
+            Some("Articles"),
+            HtmlOptions::default().css.as_deref(),
+            &body,
+        ),
+    }
+}
+
+fn article_page(config: &Config, format: ServeFormat, raw_id: &str) -> Page {
+    let article_id = sanitize_article_id(raw_id);
+    let wiki_path = wiki_path_for(&config.wiki_dir, &article_id);
+    if !wiki_path.exists() {
+        return error_page(404, "Article not found", &wiki_path.display().to_string());
+    }
+    let parsed = match parse_file(&wiki_path, &config.parse) {
+        Ok(parsed) => parsed,
+        Err(e) => return error_page(500, "Failed to read article", &e.to_string()),
+    };
+    let title = article_id.replace('_', " ");
+    let render = &config.render;
+    let css = HtmlOptions::default().css;
+    let body = match format {
+        ServeFormat::Html => {
+            let opts = HtmlOptions {
+                title: Some(title),
+                mediawiki_base_url: render.mediawiki_base_url.clone(),
+                image_base_url: render.image_base_url.clone(),
+                internal_link_prefix: ARTICLE_ROUTE.to_string(),
+                default_image_width_px: render.default_image_width_px,
+                respect_wikitext_image_width: render.respect_wikitext_image_width,
+                url_rewrites: render.url_rewrites.clone(),
+                magic_link_urls: render.magic_link_urls.clone(),
+                ..Default::default()
+            };
+            html::render_doc(&parsed.document, &opts)
+        }
+        ServeFormat::Markdown => {
+            let md = format!("# {}\n\n{}", title, render_body(&parsed.document, render));
+            html::standalone_page(Some(&title), css.as_deref(), &markdown_to_html(&md))
+        }
+    };
+    Page { status: 200, body }
+}
+
+fn error_page(status: u16, title: &str, detail: &str) -> Page {
+    let body = format!(
+        "<h1>{}</h1>\n<p>{}</p>\n<p><a href=\"/\">All articles</a></p>\n",
+        encode_text(title),
+        encode_text(detail)
+    );
+    Page {
+        status,
+        body: html::standalone_page(Some(title), HtmlOptions::default().css.as_deref(), &body),
+    }
+}
+
+/// Render the Markdown the way a Markdown viewer would, with `[[wikilinks]]` as
+/// links to article routes.
+fn markdown_to_html(md: &str) -> String {
+    use pulldown_cmark::{Options, Parser};
+
+    static WIKILINK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]").expect("valid regex")
+    });
+    let linked = WIKILINK.replace_all(md, |c: &Captures| {
+        let target = c.get(1).map_or("", |m| m.as_str()).trim();
+        let anchor = c.get(2).map(|m| m.as_str().trim());
+        let mut href = if target.is_empty() {
+            String::new()
+        } else {
+            article_href(target.trim_start_matches(':'))
+        };
+        if let Some(anchor) = anchor {
+            href.push('#');
+            href.push_str(&percent_encode(&anchor.replace(' ', "_")));
+        }
+        let label = match (c.get(3), anchor) {
+            (Some(label), _) => label.as_str().trim().to_string(),
+            (None, Some(anchor)) if target.is_empty() => anchor.to_string(),
+            _ => target.trim_start_matches(':').to_string(),
+        };
+        format!("[{}](<{}>)", label, href)
+    });
+
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH;
+    let mut out = String::new();
+    pulldown_cmark::html::push_html(&mut out, Parser::new_ext(&linked, options));
+    out
+}
+
+fn article_href(target: &str) -> String {
+    format!(
+        "{}{}",
+        ARTICLE_ROUTE,
+        percent_encode(&target.trim().replace(' ', "_"))
+    )
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~()!,:;'*@+".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn serves_the_index_and_articles_in_both_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let wiki_dir = dir.path().join("wiki");
+        fs::create_dir_all(wiki_dir.join("k")).unwrap();
+        fs::write(
+            wiki_dir.join("k").join("Ken_Thompson.wiki"),
+            "'''Ken''' wrote [[Belle (Chess Machine)|Belle]] and [[Unix#History]].\n",
+        )
+        .unwrap();
+        let config = Config::new().wiki_dir(&wiki_dir);
+
+        let index = handle(&config, ServeFormat::Html, "/");
+        assert_eq!(index.status, 200);
+        assert!(
+            index
+                .body
+                .contains("<a href=\"/wiki/Ken_Thompson\">Ken Thompson</a>"),
+            "{}",
+            index.body
+        );
+
+        let page = handle(&config, ServeFormat::Html, "/wiki/Ken%20Thompson?x=1");
+        assert_eq!(page.status, 200);
+        assert!(
+            page.body
+                .contains("<a href=\"/wiki/Belle_(Chess_Machine)\">Belle</a>"),
+            "{}",
+            page.body
+        );
+
+        let page = handle(&config, ServeFormat::Markdown, "/wiki/Ken_Thompson");
+        assert!(page.body.contains("<h1>Ken Thompson</h1>"), "{}", page.body);
+        assert!(page.body.contains("<strong>Ken</strong>"), "{}", page.body);
+        assert!(
+            page.body
+                .contains("<a href=\"/wiki/Belle_(Chess_Machine)\">Belle</a>"),
+            "{}",
+            page.body
+        );
+        assert!(
+            page.body
+                .contains("<a href=\"/wiki/Unix#History\">Unix</a>"),
+            "{}",
+            page.body
+        );
+
+        assert_eq!(handle(&config, ServeFormat::Html, "/wiki/Nope").status, 404);
+        assert_eq!(
+            handle(&config, ServeFormat::Html, "/favicon.ico").status,
+            404
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Corpus statistics (`wiki2md stats`): which node kinds, diagnostics, and
+//! templates occur how often, how long parsing takes, and which articles are the
+//! largest. Useful for deciding what the parser should handle next and for
+//! spotting regressions between runs.
+
+use crate::ast::{self, Diagnostic, Document, InlineKind, TemplateNameKind};
+use crate::graph::link_title;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+/// Counts accumulated over a set of documents.
+#[derive(Debug, Clone, Default)]
+pub struct CorpusStats {
+    pub documents: usize,
+    pub total_bytes: u64,
+    /// Block nodes per kind (`"paragraph"`), nested blocks included.
+    pub blocks: BTreeMap<&'static str, usize>,
+    /// Inline nodes per kind (`"internal_link"`), nested nodes included.
+    pub inlines: BTreeMap<&'static str, usize>,
+    /// Diagnostics per code; diagnostics without a code count as `"(none)"`.
+    pub diagnostics: BTreeMap<String, usize>,
+    /// Template invocations per template name (parser functions as `#if` etc.).
+    pub templates: BTreeMap<String, usize>,
+    /// Documents containing at least one table / image / template.
+    pub documents_with_tables: usize,
+    pub documents_with_images: usize,
+    pub documents_with_templates: usize,
+    /// Source size of each document, by article id.
+    pub sizes: Vec<(String, u64)>,
+    /// Raw block count of each document that has any, by article id.
+    pub raw_blocks: Vec<(String, usize)>,
+    /// Parse time of each document, when it was parsed (not read from JSON).
+    pub parse_times: Vec<Duration>,
+}
+
+impl CorpusStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one document.
+    pub fn add(
+        &mut self,
+        article_id: &str,
+        doc: &Document,
+        diagnostics: &[Diagnostic],
+        byte_len: u64,
+        parse_time: Option<Duration>,
+    ) {
+        self.documents += 1;
+        self.total_bytes += byte_len;
+        self.sizes.push((article_id.to_string(), byte_len));
+        self.parse_times.extend(parse_time);
+        match ast::raw_block_count(doc) {
+            0 => {}
+            n => self.raw_blocks.push((article_id.to_string(), n)),
+        }
+
+        let mut kinds = BTreeSet::new();
+        ast::walk_blocks(&doc.blocks, &mut |block| {
+            *self.blocks.entry(block.kind.name()).or_default() += 1;
+            kinds.insert(block.kind.name());
+        });
+        ast::walk_inlines(&doc.blocks, &mut |node| {
+            *self.inlines.entry(node.kind.name()).or_default() += 1;
+            kinds.insert(node.kind.name());
+            if let InlineKind::Template { node } = &node.kind {
+                *self
+                    .templates
+                    .entry(template_name(&node.name.raw, node.name.kind))
+                    .or_default() += 1;
+            }
+        });
+        if kinds.contains("table") {
+            self.documents_with_tables += 1;
+        }
+        if kinds.contains("file_link") {
+            self.documents_with_images += 1;
+        }
+        if kinds.contains("template") {
+            self.documents_with_templates += 1;
+        }
+
+        for d in diagnostics {
+            let code = d.code.as_deref().unwrap_or("(none)");
+            *self.diagnostics.entry(code.to_string()).or_default() += d.occurrences as usize;
+        }
+    }
+
+    /// The parse time below which `p` percent of documents parsed (nearest rank).
+    pub fn parse_time_percentile(&self, p: f64) -> Option<Duration> {
+        let mut times = self.parse_times.clone();
+        times.sort();
+        let rank = ((p / 100.0) * times.len() as f64).ceil() as usize;
+        times.get(rank.clamp(1, times.len().max(1)) - 1).copied()
+    }
+
+    /// The `n` largest documents by source size, largest first.
+    pub fn largest(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut sizes: Vec<(&str, u64)> = self
+            .sizes
+            .iter()
+            .map(|(id, len)| (id.as_str(), *len))
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        sizes.truncate(n);
+        sizes
+    }
+
+    /// The `n` documents with the most raw blocks, most first.
+    pub fn most_raw_blocks(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self
+            .raw_blocks
+            .iter()
+            .map(|(id, n)| (id.as_str(), *n))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// A human-readable report; `top` limits the template and largest-document lists.
+    pub fn to_text(&self, top: usize) -> String {
+        let mut out = format!(
+            "Documents: {} ({} bytes)\n",
+            self.documents, self.total_bytes
+        );
+        match self.parse_time_summary() {
+            Some([p50, p90, p99, max]) => out.push_str(&format!(
+                "Parse time: p50 {:.3}ms, p90 {:.3}ms, p99 {:.3}ms, max {:.3}ms\n",
+                ms(p50),
+                ms(p90),
+                ms(p99),
+                ms(max)
+            )),
+            None => out.push_str("Parse time: n/a (read from JSON)\n"),
+        }
+        out.push_str(&format!(
+            "Documents with tables: {}, images: {}, templates: {}\n",
+            self.documents_with_tables, self.documents_with_images, self.documents_with_templates
+        ));
+
+        push_counts(
+            &mut out,
+            "Block kinds",
+            self.blocks.iter().map(|(k, v)| (*k, *v)),
+            usize::MAX,
+        );
+        push_counts(
+            &mut out,
+            "Inline kinds",
+            self.inlines.iter().map(|(k, v)| (*k, *v)),
+            usize::MAX,
+        );
+        push_counts(
+            &mut out,
+            "Diagnostics",
+            self.diagnostics.iter().map(|(k, v)| (k.as_str(), *v)),
+            usize::MAX,
+        );
+        push_counts(
+            &mut out,
+            "Templates",
+            self.templates.iter().map(|(k, v)| (k.as_str(), *v)),
+            top,
+        );
+
+        out.push_str("\nLargest documents:\n");
+        for (id, len) in self.largest(top) {
+            out.push_str(&format!("  {:>10}  {}\n", len, id));
+        }
+        push_counts(
+            &mut out,
+            "Raw blocks",
+            self.most_raw_blocks(top).into_iter(),
+            usize::MAX,
+        );
+        out
+    }
+
+    pub fn to_json(&self, top: usize) -> String {
+        let parse_time_ms = self.parse_time_summary().map(|[p50, p90, p99, max]| {
+            json!({ "p50": ms(p50), "p90": ms(p90), "p99": ms(p99), "max": ms(max) })
+        });
+        let largest: Vec<_> = self
+            .largest(top)
+            .into_iter()
+            .map(|(id, bytes)| json!({ "article_id": id, "bytes": bytes }))
+            .collect();
+        let raw_blocks: Vec<_> = self
+            .most_raw_blocks(top)
+            .into_iter()
+            .map(|(id, count)| json!({ "article_id": id, "count": count }))
+            .collect();
+        let value = json!({
+            "documents": self.documents,
+            "total_bytes": self.total_bytes,
+            "parse_time_ms": parse_time_ms,
+            "documents_with_tables": self.documents_with_tables,
+            "documents_with_images": self.documents_with_images,
+            "documents_with_templates": self.documents_with_templates,
+            "blocks": self.blocks,
+            "inlines": self.inlines,
+            "diagnostics": self.diagnostics,
+            "templates": self.templates,
+            "largest": largest,
+            "raw_blocks": raw_blocks,
+        });
+        let mut json = serde_json::to_string_pretty(&value).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    fn parse_time_summary(&self) -> Option<[Duration; 4]> {
+        Some([
+            self.parse_time_percentile(50.0)?,
+            self.parse_time_percentile(90.0)?,
+            self.parse_time_percentile(99.0)?,
+            self.parse_time_percentile(100.0)?,
+        ])
+    }
+}
+
+/// `Infobox person` for `{{infobox_person|...}}`, `#if` for `{{#if:...}}`.
+fn template_name(raw: &str, kind: TemplateNameKind) -> String {
+    match kind {
+        TemplateNameKind::ParserFunction => {
+            raw.split(':').next().unwrap_or(raw).trim().to_lowercase()
+        }
+        TemplateNameKind::Template => link_title(raw),
+    }
+}
+
+/// A `title:` section with one `  name  count` line per entry, most frequent first.
+fn push_counts<'a>(
+    out: &mut String,
+    title: &str,
+    counts: impl Iterator<Item = (&'a str, usize)>,
+    top: usize,
+) {
+    let mut counts: Vec<(&str, usize)> = counts.collect();
+    if counts.is_empty() {
+        return;
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    out.push_str(&format!("\n{}:\n", title));
+    for (name, count) in counts.into_iter().take(top) {
+        out.push_str(&format!("  {:>10}  {}\n", count, name));
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn counts_kinds_templates_and_sizes() {
+        let mut stats = CorpusStats::new();
+        let pages = [
+            (
+                "Ken_Thompson",
+                "== A ==\n{{Infobox_person|name=[[Ken]]}} text {{#if: x|y}}\n{|\n| cell\n|}\n",
+                3,
+            ),
+            ("Short", "Plain [[Link]].\n", 1),
+        ];
+        for (id, src, ms) in pages {
+            let parsed = parse_wiki(src);
+            let time = Some(Duration::from_millis(ms));
+            stats.add(
+                id,
+                &parsed.document,
+                &parsed.diagnostics,
+                src.len() as u64,
+                time,
+            );
+        }
+
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.blocks["heading"], 1);
+        assert_eq!(stats.blocks["table"], 1);
+        assert_eq!(stats.inlines["internal_link"], 2);
+        assert_eq!(stats.templates["Infobox person"], 1);
+        assert_eq!(stats.templates["#if"], 1);
+        assert_eq!(stats.documents_with_tables, 1);
+        assert_eq!(stats.documents_with_templates, 1);
+        assert_eq!(
+            stats.largest(1),
+            [("Ken_Thompson", pages[0].1.len() as u64)]
+        );
+        assert_eq!(
+            stats.parse_time_percentile(50.0),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            stats.parse_time_percentile(100.0),
+            Some(Duration::from_millis(3))
+        );
+
+        let mut raw = parse_wiki("x\n").document;
+        raw.blocks[0].kind = crate::ast::BlockKind::Raw {
+            text: "x".to_string(),
+        };
+        stats.add("Raw", &raw, &[], 2, None);
+        assert_eq!(stats.most_raw_blocks(5), [("Raw", 1)]);
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json(5)).unwrap();
+        assert_eq!(json["templates"]["#if"], 1);
+        assert_eq!(json["raw_blocks"][0]["count"], 1);
+        assert!(stats.to_text(5).contains("Largest documents:\n"));
+    }
+}
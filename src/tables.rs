@@ -0,0 +1,203 @@
+//! Table export (`wiki2md tables`): the tables of an article as CSV or TSV, with
+//! cell contents flattened to plain text.
+
+use crate::ast::{self, BlockKind, BlockNode, Document, Table};
+
+/// Field-separated output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    /// Comma-separated, quoted as in RFC 4180.
+    #[default]
+    Csv,
+    /// Tab-separated; tabs and line breaks inside cells become spaces.
+    Tsv,
+}
+
+impl TableFormat {
+    /// The file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Tsv => "tsv",
+        }
+    }
+}
+
+/// One table as a grid of plain-text cells.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataTable {
+    pub caption: Option<String>,
+    /// Rows of cells. A cell spanning several columns or rows fills its first
+    /// position; the positions it covers are empty, so every row of a table has
+    /// the same number of fields.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl DataTable {
+    /// The table flattened into a grid, with row and column spans expanded.
+    pub fn from_table(table: &Table) -> Self {
+        let caption = table.caption.as_ref().map(|c| {
+            let mut text = String::new();
+            ast::plain_text(&c.content, &mut text);
+            collapse_whitespace(&text)
+        });
+
+        let mut rows: Vec<Vec<Option<String>>> = Vec::new();
+        for (r, row) in table.rows.iter().enumerate() {
+            if rows.len() <= r {
+                rows.resize_with(r + 1, Vec::new);
+            }
+            let mut col = 0;
+            for cell in &row.cells {
+                while rows[r].get(col).is_some_and(Option::is_some) {
+                    col += 1;
+                }
+                let rowspan = cell.rowspan.unwrap_or(1).max(1) as usize;
+                let colspan = cell.colspan.unwrap_or(1).max(1) as usize;
+                if rows.len() < r + rowspan {
+                    rows.resize_with(r + rowspan, Vec::new);
+                }
+                let text = cell_text(&cell.blocks);
+                for (dr, spanned) in rows[r..r + rowspan].iter_mut().enumerate() {
+                    if spanned.len() < col + colspan {
+                        spanned.resize(col + colspan, None);
+                    }
+                    for (dc, slot) in spanned[col..col + colspan].iter_mut().enumerate() {
+                        *slot = Some(if dr == 0 && dc == 0 {
+                            text.clone()
+                        } else {
+                            String::new()
+                        });
+                    }
+                }
+                col += colspan;
+            }
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                let mut row: Vec<String> = row.into_iter().map(Option::unwrap_or_default).collect();
+                row.resize(width, String::new());
+                row
+            })
+            .collect();
+        DataTable { caption, rows }
+    }
+
+    /// The rows as CSV or TSV, one line per row, each line ending in `\n`.
+    pub fn to_delimited(&self, format: TableFormat) -> String {
+        let mut out = String::new();
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|cell| escape_field(cell, format)).collect();
+            let sep = match format {
+                TableFormat::Csv => ",",
+                TableFormat::Tsv => "\t",
+            };
+            out.push_str(&fields.join(sep));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Every table in `doc`, in document order, including tables nested in cells.
+pub fn extract_tables(doc: &Document) -> Vec<DataTable> {
+    let mut tables = Vec::new();
+    ast::walk_blocks(&doc.blocks, &mut |block| {
+        if let BlockKind::Table { table } = &block.kind {
+            tables.push(DataTable::from_table(table));
+        }
+    });
+    tables
+}
+
+/// The readable text of a cell's blocks, on one line. Nested tables are left out
+/// (they are exported on their own).
+pub(crate) fn cell_text(blocks: &[BlockNode]) -> String {
+    let mut text = String::new();
+    collect_text(blocks, &mut text);
+    collapse_whitespace(&text)
+}
+
+fn collect_text(blocks: &[BlockNode], out: &mut String) {
+    for block in blocks {
+        match &block.kind {
+            BlockKind::Heading { content, .. } | BlockKind::Paragraph { content } => {
+                ast::plain_text(content, out);
+            }
+            BlockKind::List { items } => {
+                for item in items {
+                    collect_text(&item.blocks, out);
+                    out.push(' ');
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. } => collect_text(blocks, out),
+            BlockKind::HtmlBlock { node } => collect_text(&node.children, out),
+            BlockKind::Inclusion { mode, blocks } => {
+                if mode.shown_on_page() {
+                    collect_text(blocks, out);
+                }
+            }
+            BlockKind::CodeBlock { block } => out.push_str(&block.text),
+            BlockKind::Poem { lines } => {
+                for line in lines {
+                    ast::plain_text(line, out);
+                    out.push(' ');
+                }
+            }
+            BlockKind::Table { .. }
+            | BlockKind::Timeline { .. }
+            | BlockKind::References { .. }
+            | BlockKind::MagicWord { .. }
+            | BlockKind::HorizontalRule
+            | BlockKind::Raw { .. } => {}
+        }
+        out.push(' ');
+    }
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_field(field: &str, format: TableFormat) -> String {
+    match format {
+        TableFormat::Csv => {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        }
+        TableFormat::Tsv => field.replace(['\t', '\n', '\r'], " "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_wiki;
+
+    #[test]
+    fn tables_flatten_to_csv_and_tsv() {
+        let src = "{| class=\"wikitable\"\n|+ Perft results\n! Depth !! Nodes\n|-\n| 1 || 20\n|-\n| 2 || [[Nodes|400]], \"exact\"\n|-\n| colspan=\"2\" | '''total'''\n|}\n";
+        let doc = parse_wiki(src).document;
+        let tables = extract_tables(&doc);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].caption.as_deref(), Some("Perft results"));
+        assert_eq!(
+            tables[0].to_delimited(TableFormat::Csv),
+            "Depth,Nodes\n1,20\n2,\"400, \"\"exact\"\"\"\ntotal,\n"
+        );
+        assert_eq!(
+            tables[0].to_delimited(TableFormat::Tsv),
+            "Depth\tNodes\n1\t20\n2\t400, \"exact\"\ntotal\t\n"
+        );
+
+        let src = "{|\n| rowspan=\"2\" | A || B\n|-\n| C\n|}\n";
+        let tables = extract_tables(&parse_wiki(src).document);
+        assert_eq!(tables[0].rows, [["A", "B"], ["", "C"]]);
+    }
+}
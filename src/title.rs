@@ -0,0 +1,160 @@
+//! MediaWiki page titles: turning what a user typed or a link points at
+//! (`category:perft_Results`, `Perft%20Results#Start`, `:File:Board.png`) into the
+//! one title MediaWiki would use (`Category:Perft Results`).
+//!
+//! Fetching, cache paths, link targets, and frontmatter all go through
+//! [`Title::parse`], so the same page always ends up under the same id.
+
+use std::fmt;
+
+/// The namespaces of a stock MediaWiki install, with their canonical names.
+/// `Image` is the old name of `File`, `Project` the wiki's own namespace.
+const NAMESPACES: &[(&str, &str)] = &[
+    ("media", "Media"),
+    ("special", "Special"),
+    ("talk", "Talk"),
+    ("user", "User"),
+    ("user talk", "User talk"),
+    ("project", "Project"),
+    ("project talk", "Project talk"),
+    ("file", "File"),
+    ("image", "File"),
+    ("file talk", "File talk"),
+    ("image talk", "File talk"),
+    ("mediawiki", "MediaWiki"),
+    ("mediawiki talk", "MediaWiki talk"),
+    ("template", "Template"),
+    ("template talk", "Template talk"),
+    ("help", "Help"),
+    ("help talk", "Help talk"),
+    ("category", "Category"),
+    ("category talk", "Category talk"),
+];
+
+/// A normalized page title.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Title {
+    /// The canonical namespace (`Category`), or `None` for articles.
+    pub namespace: Option<&'static str>,
+    /// The title within the namespace, with spaces and its first letter
+    /// capitalized (`Perft Results`).
+    pub name: String,
+}
+
+impl Title {
+    /// Normalize `raw` the way MediaWiki does: percent-escapes and HTML entities
+    /// decoded, `_` and other whitespace as single spaces, a leading `:` and any
+    /// `#fragment` dropped, a known namespace prefix recognized (in any case, `Image`
+    /// as `File`), and the first letter of the name capitalized.
+    pub fn parse(raw: &str) -> Self {
+        let decoded = percent_decode(raw);
+        let decoded = html_escape::decode_html_entities(&decoded);
+        let without_fragment = decoded.split('#').next().unwrap_or("");
+        let text = clean_whitespace(without_fragment);
+        let text = text.trim_start_matches(':').trim_start();
+
+        if let Some((prefix, rest)) = text.split_once(':') {
+            let prefix = prefix.trim().to_lowercase();
+            if let Some((_, canonical)) = NAMESPACES.iter().find(|(name, _)| *name == prefix) {
+                return Title {
+                    namespace: Some(canonical),
+                    name: capitalize_first(rest.trim()),
+                };
+            }
+        }
+        Title {
+            namespace: None,
+            name: capitalize_first(text),
+        }
+    }
+
+    /// The title with underscores, as in URLs and article ids (`Category:Perft_Results`).
+    pub fn db_key(&self) -> String {
+        self.to_string().replace(' ', "_")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.namespace.is_none() && self.name.is_empty()
+    }
+}
+
+impl fmt::Display for Title {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.namespace {
+            Some(ns) => write!(f, "{}:{}", ns, self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+/// `%XX` escapes decoded, as long as the result is UTF-8; otherwise `raw` as is.
+fn percent_decode(raw: &str) -> String {
+    if !raw.contains('%') {
+        return raw.to_string();
+    }
+    String::from_utf8(crate::percent_decode(raw, |_| true)).unwrap_or_else(|_| raw.to_string())
+}
+
+/// Underscores and Unicode spaces as single spaces, trimmed; direction marks and
+/// zero-width characters removed.
+fn clean_whitespace(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\u{200B}' | '\u{200E}' | '\u{200F}' | '\u{2060}' | '\u{FEFF}' => {}
+            c if c == '_' || c.is_whitespace() => {
+                if !out.is_empty() && !out.ends_with(' ') {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn capitalize_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_like_mediawiki() {
+        for (raw, title, db_key) in [
+            ("perft_Results", "Perft Results", "Perft_Results"),
+            ("  Ken   Thompson ", "Ken Thompson", "Ken_Thompson"),
+            ("Perft%20Results#Start", "Perft Results", "Perft_Results"),
+            (
+                "category:chess_programmers",
+                "Category:Chess programmers",
+                "Category:Chess_programmers",
+            ),
+            (":Image:board.png", "File:Board.png", "File:Board.png"),
+            ("user talk:foo", "User talk:Foo", "User_talk:Foo"),
+            ("Re: Perft", "Re: Perft", "Re:_Perft"),
+            ("AT&amp;T", "AT&T", "AT&T"),
+            (
+                "Ken\u{00A0}Thompson\u{200E}",
+                "Ken Thompson",
+                "Ken_Thompson",
+            ),
+            ("100%_Done", "100% Done", "100%_Done"),
+            ("Perft%+1", "Perft%+1", "Perft%+1"),
+            ("%C3%A9cole", "École", "École"),
+        ] {
+            let t = Title::parse(raw);
+            assert_eq!(t.to_string(), title, "{raw}");
+            assert_eq!(t.db_key(), db_key, "{raw}");
+        }
+        assert_eq!(Title::parse("category:X").namespace, Some("Category"));
+        assert!(Title::parse("#Section").is_empty());
+    }
+}
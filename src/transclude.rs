@@ -0,0 +1,574 @@
+//! Transclusion: replace `{{:Other Page}}` with the content of that page.
+//!
+//! Pages are looked up through a caller-provided function (the driver reads the
+//! local `.wiki` cache), so nothing is fetched. A transcluded page is reduced to
+//! what MediaWiki would include (see [`included_blocks`]). Transclusions inside
+//! transcluded pages are expanded too, up to [`TranscludeOptions::max_depth`],
+//! and cycles are reported instead of followed.
+//!
+//! `{{{1}}}` / `{{{name|default}}}` placeholders in a transcluded page are
+//! replaced with the transclusion's arguments (`{{:Page|value|name=value}}`).
+//!
+//! Spans of transcluded nodes refer to the transcluded page's source.
+
+use crate::ast::{
+    self, BlockKind, BlockNode, Diagnostic, DiagnosticPhase, Document, InclusionMode, InlineKind,
+    InlineNode, Severity, Span, TemplateNameKind,
+};
+use crate::parse;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscludeOptions {
+    /// How many levels of transclusion inside transcluded pages are expanded.
+    pub max_depth: usize,
+}
+
+impl Default for TranscludeOptions {
+    fn default() -> Self {
+        TranscludeOptions { max_depth: 5 }
+    }
+}
+
+/// Expand every `{{:Page}}` in `doc`, the page with id `article_id`. `load` returns
+/// the wikitext of a page by title (as written, without the leading `:`), or `None`
+/// if it isn't available. Returns diagnostics for transclusions left in place.
+///
+/// A transclusion that is a paragraph of its own is replaced by the page's blocks;
+/// one inside other text is replaced by the page's text if the page is a single
+/// paragraph.
+pub fn transclude(
+    doc: &mut Document,
+    article_id: &str,
+    load: &mut impl FnMut(&str) -> Option<String>,
+    opts: &TranscludeOptions,
+) -> Vec<Diagnostic> {
+    let mut cx = Transcluder {
+        load,
+        opts,
+        stack: vec![page_key(article_id)],
+        diagnostics: Vec::new(),
+    };
+    cx.blocks(&mut doc.blocks);
+    cx.diagnostics
+}
+
+/// The part of a page that is included where it is transcluded: only its
+/// `<onlyinclude>` sections if it has any, otherwise everything except its
+/// `<noinclude>` sections. `<includeonly>` content is kept either way.
+pub fn included_blocks<'a>(blocks: Vec<BlockNode<'a>>) -> Vec<BlockNode<'a>> {
+    let mut has_onlyinclude = false;
+    ast::walk_blocks(&blocks, &mut |block| {
+        has_onlyinclude |= matches!(
+            block.kind,
+            BlockKind::Inclusion {
+                mode: InclusionMode::OnlyInclude,
+                ..
+            }
+        );
+    });
+    ast::walk_inlines(&blocks, &mut |node| {
+        has_onlyinclude |= matches!(
+            node.kind,
+            InlineKind::Inclusion {
+                mode: InclusionMode::OnlyInclude,
+                ..
+            }
+        );
+    });
+    match has_onlyinclude {
+        true => only_included(blocks),
+        false => without_noinclude(blocks),
+    }
+}
+
+/// The `<onlyinclude>` sections of `blocks`. Those inside a paragraph or heading
+/// are joined into one paragraph.
+fn only_included<'a>(blocks: Vec<BlockNode<'a>>) -> Vec<BlockNode<'a>> {
+    let mut out = Vec::new();
+    for block in blocks {
+        match block.kind {
+            BlockKind::Inclusion {
+                mode: InclusionMode::OnlyInclude,
+                blocks,
+            } => out.extend(without_noinclude(blocks)),
+            BlockKind::Inclusion { blocks, .. } => out.extend(only_included(blocks)),
+            BlockKind::Paragraph { content } | BlockKind::Heading { content, .. } => {
+                let mut parts = Vec::new();
+                collect_onlyinclude(content, &mut parts);
+                push_paragraph(&mut out, without_noinclude_inlines(parts), block.span);
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn collect_onlyinclude<'a>(nodes: Vec<InlineNode<'a>>, out: &mut Vec<InlineNode<'a>>) {
+    for node in nodes {
+        match node.kind {
+            InlineKind::Inclusion {
+                mode: InclusionMode::OnlyInclude,
+                content,
+            } => out.extend(content),
+            InlineKind::Inclusion { content, .. } => collect_onlyinclude(content, out),
+            _ => {}
+        }
+    }
+}
+
+/// `blocks` without `<noinclude>` sections, and with the content of other
+/// inclusion sections in their place.
+fn without_noinclude<'a>(blocks: Vec<BlockNode<'a>>) -> Vec<BlockNode<'a>> {
+    let mut out = Vec::with_capacity(blocks.len());
+    for mut block in blocks {
+        match &mut block.kind {
+            BlockKind::Inclusion {
+                mode: InclusionMode::NoInclude,
+                ..
+            } => continue,
+            BlockKind::Inclusion { blocks, .. } => {
+                out.extend(without_noinclude(std::mem::take(blocks)));
+                continue;
+            }
+            BlockKind::Paragraph { content } => {
+                let content = without_noinclude_inlines(std::mem::take(content));
+                push_paragraph(&mut out, content, block.span);
+                continue;
+            }
+            BlockKind::Heading { content, .. } => {
+                *content = without_noinclude_inlines(std::mem::take(content))
+            }
+            BlockKind::List { items } => {
+                for item in items {
+                    item.blocks = without_noinclude(std::mem::take(&mut item.blocks));
+                }
+            }
+            BlockKind::Table { table } => {
+                for cell in table.rows.iter_mut().flat_map(|row| &mut row.cells) {
+                    cell.blocks = without_noinclude(std::mem::take(&mut cell.blocks));
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. } => {
+                *blocks = without_noinclude(std::mem::take(blocks))
+            }
+            BlockKind::HtmlBlock { node } => {
+                node.children = without_noinclude(std::mem::take(&mut node.children))
+            }
+            BlockKind::CodeBlock { .. }
+            | BlockKind::Poem { .. }
+            | BlockKind::Timeline { .. }
+            | BlockKind::References { .. }
+            | BlockKind::MagicWord { .. }
+            | BlockKind::HorizontalRule
+            | BlockKind::Raw { .. } => {}
+        }
+        out.push(block);
+    }
+    out
+}
+
+fn without_noinclude_inlines<'a>(nodes: Vec<InlineNode<'a>>) -> Vec<InlineNode<'a>> {
+    let mut out = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match node.kind {
+            InlineKind::Inclusion {
+                mode: InclusionMode::NoInclude,
+                ..
+            } => {}
+            InlineKind::Inclusion { content, .. } => out.extend(without_noinclude_inlines(content)),
+            _ => out.push(node),
+        }
+    }
+    out
+}
+
+/// `Other_page` for `Other page`, ` other_page `, ...: MediaWiki treats these as
+/// the same page.
+fn page_key(title: &str) -> String {
+    let title = title.trim().replace(' ', "_");
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => title,
+    }
+}
+
+/// The page title of a `{{:Page}}` transclusion.
+fn transcluded_title<'a>(node: &'a InlineNode) -> Option<&'a str> {
+    match &node.kind {
+        InlineKind::Template { node } if node.name.kind == TemplateNameKind::Template => {
+            let title = node.name.raw.trim().strip_prefix(':')?.trim();
+            (!title.is_empty()).then_some(title)
+        }
+        _ => None,
+    }
+}
+
+/// The arguments of a transclusion by name; unnamed ones are `1`, `2`, ...
+type TemplateArgs = HashMap<String, Vec<InlineNode<'static>>>;
+
+fn template_args(node: &InlineNode) -> TemplateArgs {
+    let mut args = TemplateArgs::new();
+    if let InlineKind::Template { node } = &node.kind {
+        let mut position = 0;
+        for param in &node.params {
+            let name = match &param.name {
+                Some(name) => name.trim().to_string(),
+                None => {
+                    position += 1;
+                    position.to_string()
+                }
+            };
+            args.insert(
+                name,
+                param
+                    .value
+                    .iter()
+                    .cloned()
+                    .map(InlineNode::into_owned)
+                    .collect(),
+            );
+        }
+    }
+    args
+}
+
+/// Replace the `{{{...}}}` placeholders of a transcluded page with `args`, or
+/// their defaults. Placeholders with neither are left as written, as MediaWiki
+/// does. Substituted nodes keep their spans in the transcluding page.
+fn substitute_args(blocks: &mut [BlockNode<'static>], args: &TemplateArgs) {
+    ast::walk_inline_lists_mut(blocks, &mut |nodes| {
+        if !nodes
+            .iter()
+            .any(|n| matches!(n.kind, InlineKind::TemplateArg { .. }))
+        {
+            return;
+        }
+        let mut out = Vec::with_capacity(nodes.len());
+        for node in nodes.drain(..) {
+            match node.kind {
+                InlineKind::TemplateArg { name, default } => match (args.get(&name), default) {
+                    (Some(value), _) => out.extend(value.iter().cloned()),
+                    (None, Some(default)) => out.extend(default),
+                    (None, None) => out.push(InlineNode {
+                        span: node.span,
+                        kind: InlineKind::TemplateArg {
+                            name,
+                            default: None,
+                        },
+                    }),
+                },
+                kind => out.push(InlineNode {
+                    span: node.span,
+                    kind,
+                }),
+            }
+        }
+        *nodes = out;
+    });
+}
+
+struct Transcluder<'a, F> {
+    load: &'a mut F,
+    opts: &'a TranscludeOptions,
+    /// Keys of the pages being expanded, outermost first.
+    stack: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<F: FnMut(&str) -> Option<String>> Transcluder<'_, F> {
+    fn blocks<'a>(&mut self, blocks: &mut Vec<BlockNode<'a>>) {
+        let mut out = Vec::with_capacity(blocks.len());
+        for mut block in blocks.drain(..) {
+            match &mut block.kind {
+                BlockKind::Paragraph { content }
+                    if content.iter().any(|n| transcluded_title(n).is_some()) =>
+                {
+                    self.paragraph(std::mem::take(content), block.span, &mut out);
+                    continue;
+                }
+                BlockKind::Paragraph { .. } => {}
+                BlockKind::Heading { content, .. } => self.inlines(content),
+                BlockKind::List { items } => {
+                    for item in items {
+                        self.blocks(&mut item.blocks);
+                    }
+                }
+                BlockKind::Table { table } => {
+                    for row in &mut table.rows {
+                        for cell in &mut row.cells {
+                            self.blocks(&mut cell.blocks);
+                        }
+                    }
+                }
+                BlockKind::BlockQuote { blocks, .. } => self.blocks(blocks),
+                BlockKind::HtmlBlock { node } => self.blocks(&mut node.children),
+                BlockKind::Inclusion { blocks, .. } => self.blocks(blocks),
+                BlockKind::CodeBlock { .. }
+                | BlockKind::Poem { .. }
+                | BlockKind::Timeline { .. }
+                | BlockKind::References { .. }
+                | BlockKind::MagicWord { .. }
+                | BlockKind::HorizontalRule
+                | BlockKind::Raw { .. } => {}
+            }
+            out.push(block);
+        }
+        *blocks = out;
+    }
+
+    /// Expand the transclusions of a paragraph into `out`. A page that is a single
+    /// paragraph continues the surrounding text; any other page splits the
+    /// paragraph around its blocks.
+    fn paragraph<'a>(
+        &mut self,
+        content: Vec<InlineNode<'a>>,
+        span: Span,
+        out: &mut Vec<BlockNode<'a>>,
+    ) {
+        let mut current: Vec<InlineNode<'a>> = Vec::new();
+        for node in content {
+            let Some(title) = transcluded_title(&node).map(str::to_string) else {
+                current.push(node);
+                continue;
+            };
+            let Some(blocks) = self.expand(&title, &template_args(&node), node.span) else {
+                current.push(node);
+                continue;
+            };
+            match single_paragraph(blocks) {
+                Ok(content) => current.extend(content),
+                Err(blocks) => {
+                    push_paragraph(out, std::mem::take(&mut current), span);
+                    out.extend(blocks);
+                }
+            }
+        }
+        push_paragraph(out, current, span);
+    }
+
+    /// Expand transclusions of single-paragraph pages inside `content` (headings,
+    /// which can't be split).
+    fn inlines<'a>(&mut self, content: &mut Vec<InlineNode<'a>>) {
+        if !content.iter().any(|n| transcluded_title(n).is_some()) {
+            return;
+        }
+        let mut out = Vec::with_capacity(content.len());
+        for node in content.drain(..) {
+            let Some(title) = transcluded_title(&node).map(str::to_string) else {
+                out.push(node);
+                continue;
+            };
+            let Some(blocks) = self.expand(&title, &template_args(&node), node.span) else {
+                out.push(node);
+                continue;
+            };
+            match single_paragraph(blocks) {
+                Ok(content) => out.extend(content),
+                Err(_) => {
+                    self.warn(
+                        "wiki2md.transclusion.not_inline",
+                        format!(
+                            "'{}' is not a single paragraph; it can't be transcluded here",
+                            title
+                        ),
+                        node.span,
+                    );
+                    out.push(node);
+                }
+            }
+        }
+        *content = out;
+    }
+
+    /// The included blocks of the page, recursively expanded, or `None` (with a
+    /// diagnostic) if it can't be transcluded.
+    fn expand(
+        &mut self,
+        title: &str,
+        args: &TemplateArgs,
+        span: Span,
+    ) -> Option<Vec<BlockNode<'static>>> {
+        let key = page_key(title);
+        if self.stack.contains(&key) {
+            let cycle = self.stack.join(" -> ");
+            self.warn(
+                "wiki2md.transclusion.cycle",
+                format!("'{}' transcludes itself ({} -> {})", title, cycle, key),
+                span,
+            );
+            return None;
+        }
+        if self.stack.len() > self.opts.max_depth {
+            self.warn(
+                "wiki2md.transclusion.depth_exceeded",
+                format!(
+                    "Transclusion of '{}' exceeds max depth {}",
+                    title, self.opts.max_depth
+                ),
+                span,
+            );
+            return None;
+        }
+        let Some(src) = (self.load)(title) else {
+            self.warn(
+                "wiki2md.transclusion.missing",
+                format!("Transcluded page '{}' is not in the wiki cache", title),
+                span,
+            );
+            return None;
+        };
+
+        let mut blocks = included_blocks(parse::parse_wiki(&src).document.into_owned().blocks);
+        substitute_args(&mut blocks, args);
+        self.stack.push(key);
+        self.blocks(&mut blocks);
+        self.stack.pop();
+        Some(blocks)
+    }
+
+    fn warn(&mut self, code: &str, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            phase: Some(DiagnosticPhase::Normalize),
+            code: Some(code.to_string()),
+            message,
+            span: Some(span),
+            notes: vec![],
+            occurrences: 1,
+        });
+    }
+}
+
+/// The content of `blocks` if it is a single paragraph, otherwise `blocks`.
+fn single_paragraph<'a>(
+    mut blocks: Vec<BlockNode<'a>>,
+) -> Result<Vec<InlineNode<'a>>, Vec<BlockNode<'a>>> {
+    if blocks.len() == 1
+        && let BlockKind::Paragraph { content } = &mut blocks[0].kind
+    {
+        return Ok(std::mem::take(content));
+    }
+    Err(blocks)
+}
+
+/// Push the text around a transcluded page as a paragraph, unless it is blank.
+fn push_paragraph<'a>(out: &mut Vec<BlockNode<'a>>, mut content: Vec<InlineNode<'a>>, span: Span) {
+    let is_blank =
+        |n: &InlineNode| matches!(&n.kind, InlineKind::Text { value } if value.trim().is_empty());
+    while content.last().is_some_and(is_blank) {
+        content.pop();
+    }
+    let leading = content.iter().take_while(|n| is_blank(n)).count();
+    content.drain(..leading);
+    if let Some(InlineNode {
+        kind: InlineKind::Text { value },
+        ..
+    }) = content.first_mut()
+    {
+        *value = value.trim_start().to_string().into();
+    }
+    if let Some(InlineNode {
+        kind: InlineKind::Text { value },
+        ..
+    }) = content.last_mut()
+    {
+        *value = value.trim_end().to_string().into();
+    }
+    if content.is_empty() {
+        return;
+    }
+    let span = content
+        .first()
+        .zip(content.last())
+        .map_or(span, |(first, last)| first.span.cover(last.span));
+    out.push(BlockNode {
+        span,
+        kind: BlockKind::Paragraph { content },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::render_doc;
+    use std::collections::HashMap;
+
+    #[test]
+    fn inlines_pages_with_inclusion_markers_and_cycles() {
+        let pages: HashMap<&str, &str> = HashMap::from([
+            (
+                "Engines",
+                "== Engines ==\n* [[Stockfish]]\n{{:Credits}}\n<noinclude>[[Category:Lists]]</noinclude>",
+            ),
+            (
+                "Credits",
+                "Compiled by <includeonly>the [[Editors]]</includeonly>.",
+            ),
+            ("Loop", "Again {{:Loop}}"),
+            (
+                "Part",
+                "Ignored <onlyinclude>only this</onlyinclude> skipped <onlyinclude> and that</onlyinclude>",
+            ),
+        ]);
+        let mut load = |title: &str| pages.get(page_key(title).as_str()).map(|s| s.to_string());
+
+        let src = "Intro.\n{{:engines}}\nSee {{:Part}}. {{:Loop}} {{:Missing}}\n";
+        let mut doc = parse::parse_wiki(src).document;
+        let diagnostics = transclude(
+            &mut doc,
+            "Overview",
+            &mut load,
+            &TranscludeOptions::default(),
+        );
+
+        assert_eq!(
+            render_doc(&doc),
+            "Intro.\n\n### Engines\n\n- [[Stockfish]]\n\nCompiled by the [[Editors]].\n\n\
+             See only this and that. Again {{:Loop}} {{:Missing}}"
+        );
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(
+            codes,
+            ["wiki2md.transclusion.cycle", "wiki2md.transclusion.missing"]
+        );
+        assert!(doc.categories.is_empty());
+    }
+
+    #[test]
+    fn substitutes_placeholders_with_arguments() {
+        let pages: HashMap<&str, &str> = HashMap::from([
+            (
+                "Greeting",
+                "Hello {{{1}}}, see {{{page|[[Perft]]}}} {{{missing}}}.<noinclude> {{{1}}}</noinclude>",
+            ),
+            ("Wrapper", "{{:Greeting|'''{{{who|nobody}}}'''}}"),
+        ]);
+        let mut load = |title: &str| pages.get(page_key(title).as_str()).map(|s| s.to_string());
+
+        let src = "{{:Greeting|world|page=[[Divide]]}}
+
+{{:Wrapper|who=Ken}}
+
+{{:Wrapper}}
+";
+        let mut doc = parse::parse_wiki(src).document;
+        transclude(
+            &mut doc,
+            "Overview",
+            &mut load,
+            &TranscludeOptions::default(),
+        );
+
+        assert_eq!(
+            render_doc(&doc),
+            "Hello world, see [[Divide]] {{{missing}}}.\n\n\
+             Hello **Ken**, see [[Perft]] {{{missing}}}.\n\n\
+             Hello **nobody**, see [[Perft]] {{{missing}}}."
+        );
+    }
+}
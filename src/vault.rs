@@ -0,0 +1,283 @@
+//! Writing into an existing Obsidian vault (`--vault`).
+//!
+//! Generated notes go into a subfolder of the vault ([`DEFAULT_FOLDER`] unless
+//! configured), so they never overwrite the user's own notes. Files hosted by the
+//! wiki (`<base>/images/...`) are downloaded into the attachments folder set in
+//! the vault's `.obsidian/app.json`, and the Markdown links to the local copies.
+//! Attachments that already exist are not downloaded again; ones that fail to
+//! download keep their remote URL.
+
+use crate::error::{BoxError, Error, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The vault subfolder generated notes go into by default.
+pub const DEFAULT_FOLDER: &str = "wiki2md";
+
+/// An Obsidian vault and where in it generated files go.
+#[derive(Debug, Clone)]
+pub struct Vault {
+    root: PathBuf,
+    folder: PathBuf,
+    attachments: Attachments,
+    /// URLs that failed to download, so each is tried (and reported) once. Clones
+    /// of the vault share it.
+    failed: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Obsidian's "Default location for new attachments".
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Attachments {
+    /// A folder relative to the vault root (`/` is the root itself).
+    Vault(PathBuf),
+    /// A folder relative to each note (`./` is the note's own folder).
+    Note(PathBuf),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct AppConfig {
+    attachment_folder_path: Option<String>,
+}
+
+impl Vault {
+    /// Open the vault at `root` (a directory with a `.obsidian` folder), putting
+    /// generated notes under `folder`, a relative path inside the vault.
+    pub fn open(root: impl Into<PathBuf>, folder: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let folder = folder.into();
+        let settings = root.join(".obsidian");
+        if !settings.is_dir() {
+            return Err(Error::Config(format!(
+                "{} is not an Obsidian vault (it has no .obsidian folder)",
+                root.display()
+            )));
+        }
+        if folder.as_os_str().is_empty()
+            || !folder
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::Config(format!(
+                "the vault folder must be a relative path inside the vault: {:?}",
+                folder
+            )));
+        }
+        let app_json = settings.join("app.json");
+        let app: AppConfig = match fs::read_to_string(&app_json) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| Error::Config(format!("{}: {}", app_json.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AppConfig::default(),
+            Err(e) => return Err(Error::io(&app_json)(e)),
+        };
+        Ok(Vault {
+            root,
+            folder,
+            attachments: Attachments::parse(app.attachment_folder_path.as_deref().unwrap_or("/")),
+            failed: Arc::default(),
+        })
+    }
+
+    /// The vault's root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where generated notes go: the configured subfolder of the vault.
+    pub fn notes_dir(&self) -> PathBuf {
+        self.root.join(&self.folder)
+    }
+
+    /// The attachments folder for the note at `note_path`.
+    pub fn attachments_dir(&self, note_path: &Path) -> PathBuf {
+        match &self.attachments {
+            Attachments::Vault(dir) => self.root.join(dir),
+            Attachments::Note(dir) => note_path.parent().unwrap_or(&self.root).join(dir),
+        }
+    }
+
+    /// Download the files `md` links to under `image_base` (the wiki's `/images`,
+    /// see [`RenderOptions::image_base`](crate::render::RenderOptions::image_base))
+    /// into the attachments folder of `note_path` and point the links at them.
+    /// With `dry_run`, the links are rewritten but nothing is downloaded.
+    pub fn localize_files(
+        &self,
+        md: &str,
+        note_path: &Path,
+        image_base: &str,
+        dry_run: bool,
+    ) -> String {
+        let prefix = format!("]({}/", image_base.trim_end_matches('/'));
+        let attachments = self.attachments_dir(note_path);
+        let note_dir = note_path.parent().unwrap_or(Path::new(""));
+        let mut out = String::with_capacity(md.len());
+        let mut rest = md;
+        while let Some(pos) = rest.find(&prefix) {
+            let url_start = pos + 2;
+            let Some(len) = rest[url_start..].find([')', ' ']) else {
+                break;
+            };
+            out.push_str(&rest[..url_start]);
+            let url = &rest[url_start..url_start + len];
+            let name = url.rsplit('/').next().unwrap_or_default();
+            let path = attachments.join(name);
+            if dry_run || self.download(url, &path) {
+                out.push_str(&link_path(&relative_path(note_dir, &path)));
+            } else {
+                out.push_str(url);
+            }
+            rest = &rest[url_start + len..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// The URLs [`Self::localize_files`] could not download, sorted.
+    pub fn failed_downloads(&self) -> Vec<String> {
+        let mut failed: Vec<String> = self.failed.lock().unwrap().iter().cloned().collect();
+        failed.sort();
+        failed
+    }
+
+    /// Download `url` to `path` unless it's already there. Failures are logged
+    /// once per URL and return `false`.
+    fn download(&self, url: &str, path: &Path) -> bool {
+        if path.exists() {
+            return true;
+        }
+        if self.failed.lock().unwrap().contains(url) {
+            return false;
+        }
+        let result = (|| -> std::result::Result<(), BoxError> {
+            let resp = crate::http_cache::get(url)?;
+            if !resp.is_success() {
+                return Err(format!("request failed: {}", resp.status).into());
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // through a temporary file, so an interrupted download never leaves a
+            // truncated attachment that counts as done.
+            crate::driver::write_atomic(path, &resp.body)?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(%url, error = %e, "could not download attachment");
+                self.failed.lock().unwrap().insert(url.to_string());
+                false
+            }
+        }
+    }
+}
+
+impl Attachments {
+    fn parse(setting: &str) -> Self {
+        match setting.strip_prefix("./") {
+            Some(dir) => Attachments::Note(PathBuf::from(dir)),
+            None => Attachments::Vault(PathBuf::from(setting.trim_start_matches('/'))),
+        }
+    }
+}
+
+/// `to` relative to the directory `from`; both are under the vault root.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut rel = PathBuf::new();
+    for _ in common..from.len() {
+        rel.push("..");
+    }
+    rel.extend(&to[common..]);
+    rel
+}
+
+/// A path as a Markdown link target: `/` separators, and the characters that end
+/// or break a link percent-encoded.
+fn link_path(path: &Path) -> String {
+    let mut out = String::new();
+    for (i, part) in path.components().enumerate() {
+        if i > 0 {
+            out.push('/');
+        }
+        for c in part.as_os_str().to_string_lossy().chars() {
+            match c {
+                ' ' => out.push_str("%20"),
+                '(' => out.push_str("%28"),
+                ')' => out.push_str("%29"),
+                '%' => out.push_str("%25"),
+                c => out.push(c),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_point_at_the_attachments_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Vault::open(dir.path(), DEFAULT_FOLDER).is_err());
+
+        fs::create_dir(dir.path().join(".obsidian")).unwrap();
+        fs::write(
+            dir.path().join(".obsidian/app.json"),
+            r#"{"attachmentFolderPath":"Assets/Files"}"#,
+        )
+        .unwrap();
+        assert!(Vault::open(dir.path(), "../notes").is_err());
+        let vault = Vault::open(dir.path(), DEFAULT_FOLDER).unwrap();
+        assert_eq!(vault.notes_dir(), dir.path().join("wiki2md"));
+
+        let note = vault.notes_dir().join("k").join("Ken Thompson.md");
+        let md = "![Ken](https://www.chessprogramming.org/images/thumb/1/1e/Ken_Thompson.jpg/300px-Ken_Thompson.jpg)<br />*Ken* \
+                  [paper](https://example.org/images/x.pdf)";
+        assert_eq!(
+            vault.localize_files(md, &note, "https://www.chessprogramming.org/images/", true),
+            "![Ken](../../Assets/Files/300px-Ken_Thompson.jpg)<br />*Ken* [paper](https://example.org/images/x.pdf)"
+        );
+        assert_eq!(link_path(Path::new("../a b(1).png")), "../a%20b%281%29.png");
+
+        fs::write(
+            dir.path().join(".obsidian/app.json"),
+            r#"{"attachmentFolderPath":"./"}"#,
+        )
+        .unwrap();
+        let vault = Vault::open(dir.path(), "Wiki/CPW").unwrap();
+        let note = vault.notes_dir().join("k").join("Ken Thompson.md");
+        assert_eq!(vault.attachments_dir(&note), dir.path().join("Wiki/CPW/k"));
+    }
+
+    #[test]
+    fn failed_downloads_keep_the_url_and_leave_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".obsidian")).unwrap();
+        let vault = Vault::open(dir.path(), DEFAULT_FOLDER).unwrap();
+        let note = vault.notes_dir().join("Perft.md");
+        // nothing listens on port 1, so the download fails right away.
+        let md = "[results](http://127.0.0.1:1/images/5/5a/Perft_results.pdf)";
+        assert_eq!(
+            vault.localize_files(md, &note, "http://127.0.0.1:1/images", false),
+            md
+        );
+        assert_eq!(
+            vault.clone().failed_downloads(),
+            ["http://127.0.0.1:1/images/5/5a/Perft_results.pdf"]
+        );
+        assert!(!dir.path().join("Perft_results.pdf").exists());
+        assert!(
+            Vault::open(dir.path(), DEFAULT_FOLDER)
+                .unwrap()
+                .failed_downloads()
+                .is_empty()
+        );
+    }
+}
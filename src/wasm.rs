@@ -0,0 +1,70 @@
+//! WebAssembly bindings (the `wasm` feature).
+//!
+//! ```js
+//! import init, { convert } from "./pkg/wiki2md.js";
+//!
+//! await init();
+//! const { markdown, astJson, diagnostics } = convert(wikitext, '{"center_tables": true}');
+//! ```
+//!
+//! Options use the same keys as the `wiki2md.render:` frontmatter overrides (see
+//! [`RenderOverrides`]); an empty string means the defaults.
+
+use crate::ast::Diagnostic;
+use crate::frontmatter::RenderOverrides;
+use crate::parse::ParseOptions;
+use crate::render::RenderOptions;
+use crate::{parse_source, render_body};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// The result of [`convert`].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversion {
+    pub markdown: String,
+    /// The parsed document, as in the `.json` files written with `--json`.
+    pub ast_json: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Convert `wikitext` to Markdown with render options given as JSON.
+pub fn convert_with_json_options(wikitext: &str, options_json: &str) -> Result<Conversion, String> {
+    let overrides =
+        RenderOverrides::from_json(options_json).map_err(|e| format!("invalid options: {}", e))?;
+    let render_opts = overrides.apply(&RenderOptions::default());
+
+    let parsed = parse_source(wikitext, &ParseOptions::default());
+    let markdown = render_body(&parsed.document, &render_opts);
+    let ast_json = serde_json::to_string(&parsed.document).map_err(|e| e.to_string())?;
+    Ok(Conversion {
+        markdown,
+        ast_json,
+        diagnostics: parsed.diagnostics,
+    })
+}
+
+/// `convert(wikitext, optionsJson) -> { markdown, astJson, diagnostics }`
+#[wasm_bindgen]
+pub fn convert(wikitext: &str, options_json: &str) -> Result<JsValue, JsError> {
+    let conversion =
+        convert_with_json_options(wikitext, options_json).map_err(|e| JsError::new(&e))?;
+    serde_wasm_bindgen::to_value(&conversion).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_with_json_options() {
+        let out = convert_with_json_options("'''Bold''' text.\n", "").unwrap();
+        assert_eq!(out.markdown.trim(), "**Bold** text.");
+        assert!(out.ast_json.starts_with('{'));
+
+        let out = convert_with_json_options("Body.\n", r#"{"wrap_width": 40}"#).unwrap();
+        assert_eq!(out.markdown.trim(), "Body.");
+
+        assert!(convert_with_json_options("Body.\n", r#"{"nope": 1}"#).is_err());
+    }
+}
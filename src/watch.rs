@@ -0,0 +1,145 @@
+//! Watch mode (`wiki2md watch`): regenerate each `.wiki` file's `.md` file as soon
+//! as it changes, and print its diagnostics.
+
+use crate::config::Config;
+use crate::driver::regenerate_file;
+use crate::error::{Error, Result};
+use crate::layout;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Editors often save in several steps (truncate, write, rename); events this close
+/// together are handled as one change.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Watch the config's wiki directory until the process is stopped.
+pub fn watch(config: &Config) -> Result<()> {
+    let wiki_root: &Path = &config.wiki_dir;
+    if !wiki_root.exists() {
+        return Err(Error::Io {
+            path: wiki_root.to_path_buf(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "wiki source directory not found",
+            ),
+        });
+    }
+
+    if let Some(template) = &config.write.path_template {
+        layout::validate_path_template(template).map_err(Error::config)?;
+    }
+
+    // events carry absolute paths; map them back under `wiki_root`.
+    let canonical_root = wiki_root.canonicalize().map_err(Error::io(wiki_root))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| watch_error(wiki_root, e))?;
+    watcher
+        .watch(wiki_root, RecursiveMode::Recursive)
+        .map_err(|e| watch_error(wiki_root, e))?;
+    eprintln!(
+        "Watching {} for changes (Ctrl+C to stop).",
+        wiki_root.display()
+    );
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        collect_wiki_paths(event, &mut changed);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect_wiki_paths(event, &mut changed);
+        }
+        for path in &changed {
+            let path = match path.strip_prefix(&canonical_root) {
+                Ok(relative) => wiki_root.join(relative),
+                Err(_) => path.clone(),
+            };
+            if path.is_file() {
+                regenerate_and_report(config, &path);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn regenerate_and_report(config: &Config, path: &Path) {
+    let start = Instant::now();
+    match regenerate_file(config, path) {
+        Ok((md_path, diagnostics)) => {
+            eprintln!(
+                "Regenerated {} ({:.3}s, {} diagnostics)",
+                md_path.display(),
+                start.elapsed().as_secs_f64(),
+                diagnostics.len()
+            );
+            let source = std::fs::read_to_string(path).unwrap_or_default();
+            for d in &diagnostics {
+                match d.span.and_then(|s| line_of(&source, s.start as usize)) {
+                    Some(line) => eprintln!("  {}:{}: {}", path.display(), line, d),
+                    None => eprintln!("  {}: {}", path.display(), d),
+                }
+            }
+        }
+        // keep watching; the next save may fix it.
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+fn collect_wiki_paths(event: notify::Result<notify::Event>, out: &mut BTreeSet<PathBuf>) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::warn!(error = %e, "watch error");
+            return;
+        }
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+    out.extend(
+        event
+            .paths
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|ext| ext == "wiki")),
+    );
+}
+
+/// The 1-based line of byte offset `offset` in `source`.
+fn line_of(source: &str, offset: usize) -> Option<usize> {
+    let before = source.get(..offset)?;
+    Some(before.bytes().filter(|b| *b == b'\n').count() + 1)
+}
+
+fn watch_error(path: &Path, e: notify::Error) -> Error {
+    Error::Io {
+        path: path.to_path_buf(),
+        source: std::io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, Event, RemoveKind};
+
+    #[test]
+    fn collects_created_and_modified_wiki_files() {
+        let mut changed = BTreeSet::new();
+        let create = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(PathBuf::from("docs/wiki/a/Alpha.wiki"))
+            .add_path(PathBuf::from("docs/wiki/a/.Alpha.wiki.swp"));
+        let remove = Event::new(EventKind::Remove(RemoveKind::File))
+            .add_path(PathBuf::from("docs/wiki/b/Beta.wiki"));
+        collect_wiki_paths(Ok(create), &mut changed);
+        collect_wiki_paths(Ok(remove), &mut changed);
+        assert_eq!(
+            changed.into_iter().collect::<Vec<_>>(),
+            [PathBuf::from("docs/wiki/a/Alpha.wiki")]
+        );
+
+        assert_eq!(line_of("a\nb\nc", 4), Some(3));
+        assert_eq!(line_of("a", 9), None);
+    }
+}
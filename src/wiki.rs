@@ -1,23 +1,77 @@
+use crate::error::{BoxError, Error, Result};
+use crate::http_cache;
+use crate::title::Title;
 use reqwest::Url;
 use scraper::{Html, Selector};
-use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::path::Path;
 
-fn build_edit_url(title: &str) -> Result<Url, Box<dyn Error>> {
+/// Where article wikitext comes from when it isn't in the `.wiki` cache.
+/// [`HttpFetcher`] (the default) downloads it from chessprogramming.org; tests and
+/// embedders can supply their own, e.g. a closure over fixture pages:
+///
+/// ```no_run
+/// use wiki2md::{Config, Error};
+///
+/// let config = Config::new().fetcher(|title: &str| match title {
+///     "Perft" => Ok("'''Perft''' counts nodes.\n".to_string()),
+///     _ => Err(Error::Fetch { title: title.to_string(), source: "not a fixture".into() }),
+/// });
+/// wiki2md::run_with_config("Perft", &config)?;
+/// # Ok::<(), wiki2md::Error>(())
+/// ```
+pub trait WikiFetcher: Send + Sync {
+    /// The raw wikitext of the article `title`.
+    fn fetch(&self, title: &str) -> Result<String>;
+
+    /// Fetches `title` and writes it to `path`.
+    fn fetch_and_save(&self, title: &str, path: &Path) -> Result<()> {
+        let wiki = self.fetch(title)?;
+        fs::write(path, wiki).map_err(Error::io(path))
+    }
+}
+
+impl fmt::Debug for dyn WikiFetcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WikiFetcher")
+    }
+}
+
+impl<F> WikiFetcher for F
+where
+    F: Fn(&str) -> Result<String> + Send + Sync,
+{
+    fn fetch(&self, title: &str) -> Result<String> {
+        self(title)
+    }
+}
+
+/// Fetches articles from the wiki's edit pages over HTTP.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpFetcher;
+
+impl WikiFetcher for HttpFetcher {
+    fn fetch(&self, title: &str) -> Result<String> {
+        fetch(title)
+    }
+}
+
+fn build_edit_url(title: &str) -> Result<Url, BoxError> {
     let mut url = Url::parse("https://www.chessprogramming.org/index.php")?;
     url.query_pairs_mut()
-        .append_pair("title", title)
+        .append_pair("title", &Title::parse(title).to_string())
         .append_pair("action", "edit");
     Ok(url)
 }
 
-fn extract_wiki_text_from_edit_html(html_body: &str) -> Result<String, Box<dyn Error>> {
+fn extract_wiki_text_from_edit_html(html_body: &str) -> Result<String, BoxError> {
     let document = Html::parse_document(html_body);
 
     // MediaWiki edit pages typically keep the article content in a textarea with this id.
     // fall back to the first textarea if the structure changes.
-    let selector_primary = Selector::parse("textarea#wpTextbox1")?;
-    let selector_fallback = Selector::parse("textarea")?;
+    let selector_primary = Selector::parse("textarea#wpTextbox1").map_err(|e| e.to_string())?;
+    let selector_fallback = Selector::parse("textarea").map_err(|e| e.to_string())?;
 
     let textarea = document
         .select(&selector_primary)
@@ -31,21 +85,33 @@ fn extract_wiki_text_from_edit_html(html_body: &str) -> Result<String, Box<dyn E
 }
 
 /// Fetches the raw Wiki markup from the Edit page and saves it to a file.
-pub fn fetch_and_save(title: &str, filename: &str) -> Result<(), Box<dyn Error>> {
-    let url = build_edit_url(title)?;
+pub fn fetch_and_save(title: &str, filename: &str) -> Result<()> {
+    HttpFetcher.fetch_and_save(title, Path::new(filename))
+}
 
-    let resp = reqwest::blocking::get(url.clone())?;
+/// Fetches the raw Wiki markup from the Edit page.
+#[tracing::instrument(name = "fetch", level = "info", skip_all, fields(title))]
+pub fn fetch(title: &str) -> Result<String> {
+    fetch_wikitext(title).map_err(|source| Error::Fetch {
+        title: title.to_string(),
+        source,
+    })
+}
 
-    if !resp.status().is_success() {
-        return Err(format!("Request failed: {} (URL: {})", resp.status(), url).into());
-    }
+fn fetch_wikitext(title: &str) -> Result<String, BoxError> {
+    let url = build_edit_url(title)?;
 
-    let html_body = resp.text()?;
-    let decoded_wiki = extract_wiki_text_from_edit_html(&html_body)?;
+    let resp = http_cache::get(url.as_str())?;
 
-    fs::write(filename, decoded_wiki)?;
+    if !resp.is_success() {
+        tracing::warn!(status = resp.status, %url, "fetch failed");
+        return Err(format!("Request failed: {} (URL: {})", resp.status, url).into());
+    }
 
-    Ok(())
+    let html_body = resp.text();
+    let wiki = extract_wiki_text_from_edit_html(&html_body)?;
+    tracing::info!(%url, bytes = wiki.len(), "fetched");
+    Ok(wiki)
 }
 
 #[cfg(test)]
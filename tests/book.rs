@@ -0,0 +1,86 @@
+use assert_cmd::cargo_bin_cmd;
+use std::fs;
+use tempfile::tempdir;
+
+fn write_wiki(root: &std::path::Path, bucket: &str, id: &str, text: &str) {
+    let path = root
+        .join("docs")
+        .join("wiki")
+        .join(bucket)
+        .join(format!("{}.wiki", id));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|w| w == needle.as_bytes())
+}
+
+#[test]
+fn book_subcommand_writes_epub_with_intra_book_links() {
+    let dir = tempdir().unwrap();
+    write_wiki(
+        dir.path(),
+        "p",
+        "Perft",
+        "==Usage==\nSee [[Move Generation#Legality|legal moves]] and [[Search]].&nbsp;Done.\n",
+    );
+    write_wiki(
+        dir.path(),
+        "m",
+        "Move_Generation",
+        "==Legality==\nBack to [[perft]].\n",
+    );
+    fs::write(
+        dir.path().join("index.txt"),
+        "# Basics\nPerft\n\nMove Generation\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "book",
+        "--index",
+        "index.txt",
+        "--output",
+        "out.epub",
+        "--title",
+        "Basics",
+        "--no-images",
+        "--offline",
+    ]);
+    cmd.assert().success();
+
+    let epub = fs::read(dir.path().join("out.epub")).unwrap();
+    // mimetype must be the first, uncompressed entry.
+    assert_eq!(&epub[0..4], b"PK\x03\x04");
+    assert_eq!(&epub[30..38], b"mimetype");
+    assert_eq!(&epub[38..58], b"application/epub+zip");
+
+    assert!(contains(&epub, "OEBPS/ch001.xhtml"));
+    assert!(contains(&epub, "OEBPS/ch002.xhtml"));
+    assert!(contains(&epub, "<dc:title>Basics</dc:title>"));
+    assert!(contains(
+        &epub,
+        "<a href=\"ch002.xhtml#Legality\">legal moves</a>"
+    ));
+    assert!(contains(&epub, "<a href=\"ch001.xhtml\">perft</a>"));
+    // articles outside the book keep linking to the wiki.
+    assert!(contains(
+        &epub,
+        "href=\"https://www.chessprogramming.org/Search\""
+    ));
+    // named HTML entities are not valid XML.
+    assert!(!contains(&epub, "&nbsp;"));
+}
+
+#[test]
+fn book_subcommand_fails_offline_for_missing_article() {
+    let dir = tempdir().unwrap();
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["book", "Missing", "--offline", "--no-images"]);
+    cmd.assert().failure();
+}
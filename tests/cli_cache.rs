@@ -1,6 +1,7 @@
 use assert_cmd::cargo_bin_cmd;
 use predicates::prelude::*;
 use std::fs;
+use std::io::Read;
 use tempfile::tempdir;
 
 #[test]
@@ -119,3 +120,602 @@ fn regenerate_frontmatter_flag_overwrites_existing_frontmatter() {
     assert!(md.starts_with("---\nwiki2md:\n"), "{md}");
     assert!(md.contains("summary: \"keep\""), "{md}");
 }
+
+#[test]
+fn dry_run_prints_diff_against_cached_md() {
+    let dir = tempdir().unwrap();
+
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "New body.\n").unwrap();
+
+    let md_path = dir
+        .path()
+        .join("docs")
+        .join("md")
+        .join("t")
+        .join("Test Page.md");
+    fs::create_dir_all(md_path.parent().unwrap()).unwrap();
+    let old = "---\ncustom: 1\n---\n\n# Test Page\n\nOld body.";
+    fs::write(&md_path, old).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args(["--dry-run", "Test Page"]);
+
+    cmd.assert().success().stdout(
+        predicate::str::contains("--- a/docs/md/t/Test Page.md")
+            .and(predicate::str::contains("+++ b/docs/md/t/Test Page.md"))
+            .and(predicate::str::contains("-Old body."))
+            .and(predicate::str::contains("+New body.")),
+    );
+    assert_eq!(fs::read_to_string(&md_path).unwrap(), old);
+}
+
+#[test]
+fn path_template_controls_md_location() {
+    let dir = tempdir().unwrap();
+
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body.\n[[Category:Engines]]\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "--path-template",
+        "content/{category}/{slug}.{ext}",
+        "Test Page",
+    ]);
+    cmd.assert().success();
+
+    let md = fs::read_to_string(
+        dir.path()
+            .join("content")
+            .join("Engines")
+            .join("test-page.md"),
+    )
+    .unwrap();
+    assert!(md.contains("Body."), "{md}");
+    assert!(!dir.path().join("docs").join("md").exists());
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--path-template", "{oops}.md", "Test Page"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "unknown path template variable {oops}",
+    ));
+}
+
+#[test]
+fn unsafe_titles_get_safe_file_names_and_keep_aliases() {
+    let dir = tempdir().unwrap();
+
+    // `?` is percent-encoded in the cache file name.
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("w")
+        .join("What_is_a_Bitboard%3F.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).arg("What is a Bitboard?");
+    cmd.assert().success();
+    let md_dir = dir.path().join("docs").join("md").join("w");
+    assert!(md_dir.join("What is a Bitboard%3F.md").exists());
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--slugify", "--regenerate-all"]);
+    cmd.assert().success();
+    let md = fs::read_to_string(md_dir.join("what-is-a-bitboard.md")).unwrap();
+    assert!(md.contains("aliases:\n  - \"What is a Bitboard?\""), "{md}");
+}
+
+#[test]
+fn stdin_mode_converts_without_touching_disk() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .arg("--stdin")
+        .write_stdin("=Title=\nSee [[Other Page|link]].\n");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("## Title\n\nSee [[Other Page|link]].\n"));
+    assert!(!dir.path().join("docs").exists());
+}
+
+#[test]
+fn stdout_mode_renders_from_cache_without_writing() {
+    let dir = tempdir().unwrap();
+
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args(["--stdout", "Test Page"]);
+
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("---\nwiki2md:\n")
+            .and(predicate::str::contains("# Test Page\n\nBody.")),
+    );
+    assert!(!dir.path().join("docs").join("md").exists());
+}
+
+#[test]
+fn regenerate_all_verbosity_levels() {
+    let dir = tempdir().unwrap();
+
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .env_remove("RUST_LOG")
+        .args(["--regenerate-all", "--quiet"]);
+    cmd.assert().success().stderr(predicate::eq(""));
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--regenerate-all", "--verbose", "--no-tty"]);
+    cmd.assert().success().stderr(
+        predicate::str::contains("Regenerated: ")
+            .and(predicate::str::contains("(parse "))
+            .and(predicate::str::contains("Done. Regenerated 1 files")),
+    );
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--regenerate-all", "--timings", "--no-tty"]);
+    cmd.assert().success().stderr(
+        predicate::str::contains("Time per phase (1 files):")
+            .and(predicate::str::contains("  frontmatter "))
+            .and(predicate::str::contains("Slowest files:"))
+            .and(predicate::str::contains("Test Page.md (read ")),
+    );
+}
+
+#[test]
+fn log_format_json_emits_one_event_per_line() {
+    let dir = tempdir().unwrap();
+
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).env_remove("RUST_LOG").args([
+        "--regenerate-all",
+        "--quiet",
+        "--log-format",
+        "json",
+    ]);
+    let output = cmd.assert().success().get_output().clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("each stderr line is JSON"))
+        .collect();
+    let messages: Vec<&str> = events
+        .iter()
+        .filter_map(|e| e["fields"]["message"].as_str())
+        .collect();
+    for expected in ["parsed", "rendered", "wrote", "regenerated"] {
+        assert!(
+            messages.contains(&expected),
+            "missing {expected:?} in {messages:?}"
+        );
+    }
+    let wrote = events
+        .iter()
+        .find(|e| e["fields"]["message"] == "wrote")
+        .unwrap();
+    assert_eq!(wrote["span"]["name"], "write");
+    assert!(
+        wrote["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|s| s["name"] == "article" && s["id"] == "Test_Page")
+    );
+}
+
+#[test]
+fn stats_counts_parsed_and_cached_json_documents() {
+    let dir = tempdir().unwrap();
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "=Title=\nSee [[Other Page|link]] {{Cite|x}}.\n").unwrap();
+
+    cargo_bin_cmd!("wiki2md")
+        .current_dir(dir.path())
+        .args(["stats", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"documents\": 1,")
+                .and(predicate::str::contains("\"heading\": 1"))
+                .and(predicate::str::contains("\"Cite\": 1"))
+                .and(predicate::str::contains("\"p50\"")),
+        );
+
+    let json_path = dir
+        .path()
+        .join("docs")
+        .join("json")
+        .join("t")
+        .join("Test_Page.json");
+    fs::create_dir_all(json_path.parent().unwrap()).unwrap();
+    let envelope = wiki2md::parse::parse_wiki_to_envelope(&fs::read_to_string(&wiki_path).unwrap());
+    fs::write(&json_path, serde_json::to_string(&envelope).unwrap()).unwrap();
+    cargo_bin_cmd!("wiki2md")
+        .current_dir(dir.path())
+        .args(["stats", "--from-json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::starts_with("Documents: 1 (")
+                .and(predicate::str::contains("Parse time: n/a"))
+                .and(predicate::str::contains("internal_link")),
+        );
+}
+
+#[test]
+fn section_prints_only_the_named_section() {
+    let dir = tempdir().unwrap();
+    let wiki_path = dir
+        .path()
+        .join("docs")
+        .join("wiki")
+        .join("k")
+        .join("Ken_Thompson.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(
+        &wiki_path,
+        "Intro.\n== Publications ==\n=== 1990s ===\n* Old paper<ref>Journal</ref>\n=== 2000s ===\n* New paper\n== See also ==\n* [[Unix]]\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "Ken Thompson",
+        "--offline",
+        "--section",
+        "publications > 1990s",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("- Old paper[^1]\n"))
+        .stdout(predicate::str::contains("[^1]: Journal"))
+        .stdout(predicate::str::contains("New paper").not());
+    assert!(!dir.path().join("docs").join("md").exists());
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--stdin", "--section", "Missing"])
+        .write_stdin("== Other ==\nText.\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("section not found: Missing"));
+}
+
+#[test]
+fn transclude_inlines_cached_pages() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki");
+    fs::create_dir_all(wiki_dir.join("o")).unwrap();
+    fs::create_dir_all(wiki_dir.join("s")).unwrap();
+    fs::write(
+        wiki_dir.join("o").join("Overview.wiki"),
+        "Intro.\n{{:Shared Part}}\n",
+    )
+    .unwrap();
+    fs::write(
+        wiki_dir.join("s").join("Shared_Part.wiki"),
+        "Shared text.<noinclude>[[Category:Parts]]</noinclude>\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["Overview", "--offline", "--transclude"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("Intro. Shared text.")
+            .and(predicate::str::contains("Parts").not()),
+    );
+}
+
+#[test]
+fn tables_subcommand_writes_one_file_per_table() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki").join("p");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(
+        wiki_dir.join("Perft_Results.wiki"),
+        "{|\n! Depth !! Nodes\n|-\n| 1 || 20\n|}\n\nText.\n\n{|\n| a\tb\n|}\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "tables",
+        "Perft Results",
+        "--format",
+        "tsv",
+        "--out-dir",
+        "out",
+    ]);
+    cmd.assert().success();
+
+    let out = dir.path().join("out");
+    assert_eq!(
+        fs::read_to_string(out.join("Perft_Results-1.tsv")).unwrap(),
+        "Depth\tNodes\n1\t20\n"
+    );
+    assert_eq!(
+        fs::read_to_string(out.join("Perft_Results-2.tsv")).unwrap(),
+        "a b\n"
+    );
+}
+
+#[test]
+fn infobox_fields_move_to_frontmatter() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki").join("s");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(
+        wiki_dir.join("Stockfish.wiki"),
+        "{{Infobox engine|license=GPL|language=[[C++]]}}\n'''Stockfish''' is an engine.\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "Stockfish",
+        "--offline",
+        "--infobox",
+        "--infobox-namespace",
+        "engine",
+    ]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("engine:\n  license: \"GPL\"\n  language: \"C++\"\n")
+            .and(predicate::str::contains("{{").not())
+            .and(predicate::str::contains(
+                "# Stockfish\n\n**Stockfish** is an engine.",
+            )),
+    );
+}
+
+#[test]
+fn from_json_renders_an_edited_ast() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki").join("t");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(
+        wiki_dir.join("Test_Page.wiki"),
+        "Intro.\n\n==Keep==\nKept.\n\n==Drop==\nDropped.\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["Test_Page", "--offline", "--json"]);
+    cmd.assert().success();
+
+    // prune the last section, as a post-processing script would.
+    let json_path = dir
+        .path()
+        .join("docs")
+        .join("json")
+        .join("t")
+        .join("Test_Page.json");
+    let mut ast: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+    let blocks = ast["document"]["blocks"].as_array_mut().unwrap();
+    let len = blocks.len();
+    blocks.truncate(len - 2);
+    let edited = dir.path().join("edited.json");
+    fs::write(&edited, serde_json::to_string(&ast).unwrap()).unwrap();
+    fs::remove_dir_all(dir.path().join("docs").join("wiki")).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--from-json", "edited.json"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("article_id: Test_Page")
+            .and(predicate::str::contains(
+                "# Test Page\n\nIntro.\n\n### Keep\n\nKept.",
+            ))
+            .and(predicate::str::contains("Dropped").not()),
+    );
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--from-json", "edited.json", "--section", "Keep"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("Kept."));
+}
+
+#[test]
+fn json_gzip_writes_a_compact_compressed_ast() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki").join("t");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(
+        wiki_dir.join("Test_Page.wiki"),
+        "Intro.\n\n==Keep==\nKept.\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "Test_Page",
+        "--offline",
+        "--json",
+        "--json-compact",
+        "--json-gzip",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("### Keep\n\nKept."));
+
+    let json_dir = dir.path().join("docs").join("json").join("t");
+    assert!(!json_dir.join("Test_Page.json").exists());
+    let gz = fs::File::open(json_dir.join("Test_Page.json.gz")).unwrap();
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(gz)
+        .read_to_string(&mut json)
+        .unwrap();
+    assert!(!json.contains('\n'));
+    let ast: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(ast["article_id"], "Test_Page");
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--from-json", "docs/json/t/Test_Page.json.gz"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("# Test Page\n\nIntro."));
+}
+
+#[test]
+fn convert_writes_a_single_file_anywhere() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("drafts").join("Ken_Thompson.wiki");
+    fs::create_dir_all(input.parent().unwrap()).unwrap();
+    fs::write(&input, "'''Ken Thompson''' wrote [[Belle]].\n").unwrap();
+    let output = dir.path().join("out").join("ken.md");
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path()).args([
+        "convert",
+        "drafts/Ken_Thompson.wiki",
+        "-o",
+        "out/ken.md",
+        "--json",
+    ]);
+    cmd.assert().success();
+
+    let md = fs::read_to_string(&output).unwrap();
+    assert!(md.contains("article_id: Ken_Thompson"), "{md}");
+    assert!(
+        md.contains("# Ken Thompson\n\n**Ken Thompson** wrote [[Belle]]."),
+        "{md}"
+    );
+    assert!(dir.path().join("out").join("ken.json").exists());
+    assert!(!dir.path().join("docs").exists());
+
+    // without -o, the Markdown goes next to the input.
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["convert", "drafts/Ken_Thompson.wiki"]);
+    cmd.assert().success();
+    assert!(dir.path().join("drafts").join("Ken_Thompson.md").exists());
+}
+
+#[test]
+fn converts_several_titles_and_fails_if_any_fail() {
+    let dir = tempdir().unwrap();
+    let wiki = dir.path().join("docs").join("wiki");
+    fs::create_dir_all(wiki.join("p")).unwrap();
+    fs::create_dir_all(wiki.join("k")).unwrap();
+    fs::write(wiki.join("p").join("Perft.wiki"), "Counts nodes.\n").unwrap();
+    fs::write(wiki.join("k").join("Ken_Thompson.wiki"), "Wrote Belle.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["Perft", "Missing Page", "Ken Thompson", "--offline"]);
+    cmd.assert().failure().code(1).stderr(
+        predicate::str::contains("Error processing 'Missing Page'")
+            .and(predicate::str::contains(
+                "Processed 3 articles: 2 succeeded, 1 failed.",
+            ))
+            .and(predicate::str::contains("Failed: Missing Page")),
+    );
+    assert!(
+        dir.path()
+            .join("docs")
+            .join("md")
+            .join("p")
+            .join("Perft.md")
+            .exists()
+    );
+    assert!(
+        dir.path()
+            .join("docs")
+            .join("md")
+            .join("k")
+            .join("Ken Thompson.md")
+            .exists()
+    );
+}
+
+#[test]
+fn max_warnings_fails_a_run_with_too_many_diagnostics() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("docs").join("wiki").join("t");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(wiki_dir.join("Test_Page.wiki"), "Body.<ref>Unclosed\n").unwrap();
+    fs::write(wiki_dir.join("Tidy_Page.wiki"), "Body.\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--regenerate-all", "--no-tty", "--max-warnings", "1"]);
+    cmd.assert().success();
+
+    let mut cmd = cargo_bin_cmd!("wiki2md");
+    cmd.current_dir(dir.path())
+        .args(["--regenerate-all", "--no-tty", "--max-warnings", "0"]);
+    cmd.assert().failure().stderr(
+        predicate::str::contains("1 warnings (at most 0 allowed) and 0 errors.")
+            .and(predicate::str::contains("Test_Page  1"))
+            .and(predicate::str::contains("wikitext.ref.unclosed  1"))
+            .and(predicate::str::contains("Tidy_Page").not()),
+    );
+    // everything is still written.
+    assert!(
+        dir.path()
+            .join("docs")
+            .join("md")
+            .join("t")
+            .join("Test Page.md")
+            .exists()
+    );
+}
@@ -35,7 +35,16 @@ fn test_wikitable_text_align() {
     assert_eq!(actual_md, want_md);
 }
 
+/// Every case in tests/testdata. Run `wiki2md golden --bless` to update them.
+#[cfg(feature = "native")]
 #[test]
+fn test_golden_corpus() {
+    let report = wiki2md::golden::run(&base_dir(), false).expect("golden cases run");
+    assert!(report.is_success(), "{}", report.to_text());
+}
+
+#[test]
+#[allow(clippy::field_reassign_with_default)]
 fn test_table_centering_option_wraps_caption_and_table() {
     // verify that when `center_tables_and_captions` is enabled, the renderer
     // wraps the caption + table in a centering HTML container.
@@ -56,3 +65,20 @@ fn test_table_centering_option_wraps_caption_and_table() {
     assert!(md.contains("| H1 | H2 |"), "{}", md.to_string());
     assert!(md.ends_with("</div>"), "{}", md.to_string());
 }
+
+#[test]
+fn test_table_caption_style_and_position() {
+    let src = "{| class=\"wikitable\"\n|+ Caption\n|-\n! H1\n! H2\n|-\n| A\n| B\n|}\n";
+
+    let ast = parse::parse_wiki(src);
+
+    let opts = render::RenderOptions {
+        table_caption_style: render::TableCaptionStyle::Italic,
+        table_caption_position: render::TableCaptionPosition::Below,
+        ..Default::default()
+    };
+
+    let md = render::render_doc_with_options(&ast.document, &opts);
+
+    assert_eq!(md, "| H1 | H2 |\n|---|---|\n| A | B |\n\n*Caption*");
+}
@@ -2,9 +2,30 @@ use std::fs;
 
 use tempfile::tempdir;
 
-use wiki2md::frontmatter::{normalize_tag, split_yaml_frontmatter};
+use wiki2md::ast_filter::AstFilter;
+use wiki2md::frontmatter::{
+    Frontmatter, Wiki2mdMeta, display_title, normalize_tag, split_yaml_frontmatter,
+};
+use wiki2md::generation::GenerationInfo;
+use wiki2md::index::IndexPages;
+use wiki2md::manifest::{Manifest, content_hash};
+use wiki2md::parse::{ParseOptions, parse_wiki};
 use wiki2md::render::RenderOptions;
-use wiki2md::{WriteOptions, regenerate_all_in_dirs};
+use wiki2md::vault::Vault;
+use wiki2md::{Config, WriteOptions, regenerate_all_with_config};
+
+fn regenerate(
+    wiki_root: &std::path::Path,
+    md_root: &std::path::Path,
+    write_opts: &WriteOptions,
+) -> wiki2md::Result<()> {
+    regenerate_all_with_config(
+        &Config::new()
+            .wiki_dir(wiki_root)
+            .md_dir(md_root)
+            .write_options(write_opts.clone()),
+    )
+}
 
 fn is_yyyy_mm_dd(s: &str) -> bool {
     let parts: Vec<&str> = s.split('-').collect();
@@ -71,6 +92,129 @@ fn normalize_tag_rules() {
     assert!(!normalize_tag("x/y").unwrap().contains('/'));
 }
 
+#[test]
+fn display_title_from_magic_word_or_bold_lead() {
+    let doc = parse_wiki("{{DISPLAYTITLE:''iCE''}}\n'''ICE''' is an engine.\n").document;
+    assert_eq!(display_title(&doc, "ICE"), "iCE");
+
+    let doc =
+        parse_wiki("'''[[Main Page|Home]] * [[Engines]] * ICE'''\n\n'''iCE''', a chess engine.\n")
+            .document;
+    assert_eq!(display_title(&doc, "ICE"), "iCE");
+
+    // a display title naming another page is ignored, as in MediaWiki.
+    let doc = parse_wiki("{{DISPLAYTITLE:Something else}}\n'''Other''' text.\n").document;
+    assert_eq!(display_title(&doc, "Ken_Thompson"), "Ken Thompson");
+}
+
+#[test]
+fn yaml_round_trips_awkward_values() {
+    let mut extras = serde_yaml::Mapping::new();
+    extras.insert("reviewed".into(), true.into());
+    extras.insert("see".into(), vec!["a: b", "@c"].into());
+    let fm = Frontmatter {
+        wiki2md: Wiki2mdMeta {
+            article_id: "Key:Value".to_string(),
+            source_url: "https://www.chessprogramming.org/Key:Value".to_string(),
+            generated_by: "wiki2md".to_string(),
+            last_fetched_date: "2024-01-02".to_string(),
+            schema_version: 1,
+            render: None,
+        },
+        aliases: vec!["Key: Value".to_string(), "@handle".to_string()],
+        tags: vec!["true".to_string(), "chess".to_string()],
+        related: vec!["Perft Results".to_string()],
+        summary: Some("Line one.\nLine \"two\".".to_string()),
+        infobox: None,
+        extras,
+    };
+    let yaml = fm.to_yaml_string();
+    assert!(
+        yaml.contains("aliases:\n  - \"Key: Value\"\n  - \"@handle\"\n"),
+        "{yaml}"
+    );
+    assert!(yaml.contains("tags:\n  - 'true'\n  - chess\n"), "{yaml}");
+
+    let inner = yaml.trim_start_matches("---\n").trim_end_matches("---\n");
+    let value: serde_yaml::Value = serde_yaml::from_str(inner).unwrap();
+    assert_eq!(value["wiki2md"]["article_id"], "Key:Value");
+    assert_eq!(value["aliases"][1], "@handle");
+    assert_eq!(value["tags"][0], "true");
+    assert_eq!(value["summary"], "Line one.\nLine \"two\".");
+    assert_eq!(value["reviewed"], true);
+    assert_eq!(value["see"][0], "a: b");
+    assert_eq!(value["related"][0], "[[Perft Results]]");
+}
+
+#[test]
+fn display_title_sets_heading_and_alias() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("i")).unwrap();
+    fs::write(
+        wiki_root.join("i").join("ICE.wiki"),
+        "{{DISPLAYTITLE:iCE}}\n'''iCE''' is an engine.\n",
+    )
+    .unwrap();
+    regenerate(&wiki_root, &md_root, &WriteOptions::default()).unwrap();
+
+    let md = fs::read_to_string(md_root.join("i").join("ICE.md")).unwrap();
+    assert!(md.contains("aliases:\n  - \"iCE\"\n  - \"ICE\"\n"), "{md}");
+    assert!(md.ends_with("# iCE\n\n**iCE** is an engine."), "{md}");
+}
+
+#[test]
+fn aliases_include_lead_bold_phrases_and_redirects() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("p")).unwrap();
+    fs::write(
+        wiki_root.join("p").join("Perft_Results.wiki"),
+        "'''Perft Results''', or '''perft numbers''', are node counts. See '''Divide'''.\n",
+    )
+    .unwrap();
+    fs::write(
+        wiki_root.join("p").join("Perft_Table.wiki"),
+        "#REDIRECT [[perft Results]]\n",
+    )
+    .unwrap();
+    fs::write(
+        wiki_root.join("p").join("Perft_Numbers.wiki"),
+        "#redirect [[Perft_Results#Top]]\n",
+    )
+    .unwrap();
+    regenerate(&wiki_root, &md_root, &WriteOptions::default()).unwrap();
+
+    let md = fs::read_to_string(md_root.join("p").join("Perft Results.md")).unwrap();
+    assert!(
+        md.contains("aliases:\n  - \"Perft Results\"\n  - \"perft numbers\"\n  - \"Perft Numbers\"\n  - \"Perft Table\"\n"),
+        "{md}"
+    );
+    assert!(!md.contains("\"Divide\""), "{md}");
+}
+
+#[test]
+fn see_also_links_become_related() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("p")).unwrap();
+    fs::write(
+        wiki_root.join("p").join("Perft.wiki"),
+        "Counts [[Nodes]].\n\n=See also=\n* [[Divide]]\n* [[perft Results#Start|Results]]\n* [[Divide]]\n* [[:Category:Debugging]]\n\n=Forum Posts=\n* [[Search]]\n",
+    )
+    .unwrap();
+    regenerate(&wiki_root, &md_root, &WriteOptions::default()).unwrap();
+
+    let md = fs::read_to_string(md_root.join("p").join("Perft.md")).unwrap();
+    assert!(
+        md.contains("related:\n  - \"[[Divide]]\"\n  - \"[[Perft Results]]\"\n---"),
+        "{md}"
+    );
+}
+
 #[test]
 fn generates_frontmatter_when_missing_and_extracts_tags() {
     let dir = tempdir().unwrap();
@@ -94,13 +238,7 @@ fn generates_frontmatter_when_missing_and_extracts_tags() {
 
     let wiki_root = root.join("docs").join("wiki");
     let md_root = root.join("docs").join("md");
-    regenerate_all_in_dirs(
-        &wiki_root,
-        &md_root,
-        &RenderOptions::default(),
-        &WriteOptions::default(),
-    )
-    .unwrap();
+    regenerate(&wiki_root, &md_root, &WriteOptions::default()).unwrap();
 
     let md_path = md_root.join("b").join("Barend Swets.md");
     let md = fs::read_to_string(&md_path).unwrap();
@@ -160,13 +298,7 @@ fn preserves_existing_frontmatter_verbatim_by_default() {
 
     let wiki_root = root.join("docs").join("wiki");
     let md_root = root.join("docs").join("md");
-    regenerate_all_in_dirs(
-        &wiki_root,
-        &md_root,
-        &RenderOptions::default(),
-        &WriteOptions::default(),
-    )
-    .unwrap();
+    regenerate(&wiki_root, &md_root, &WriteOptions::default()).unwrap();
 
     let md = fs::read_to_string(&md_path).unwrap();
     assert!(md.starts_with(existing_fm), "{md}");
@@ -200,10 +332,11 @@ fn regenerate_frontmatter_flag_regenerates_but_preserves_summary_and_extras() {
 
     let write_opts = WriteOptions {
         regenerate_frontmatter: true,
+        ..Default::default()
     };
     let wiki_root = root.join("docs").join("wiki");
     let md_root = root.join("docs").join("md");
-    regenerate_all_in_dirs(&wiki_root, &md_root, &RenderOptions::default(), &write_opts).unwrap();
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
 
     let md = fs::read_to_string(&md_path).unwrap();
     assert!(md.starts_with("---\nwiki2md:\n"), "{md}");
@@ -214,3 +347,745 @@ fn regenerate_frontmatter_flag_regenerates_but_preserves_summary_and_extras() {
     // numeric tag normalization
     assert!(md.contains("- y1984"), "{md}");
 }
+
+#[test]
+fn render_overrides_in_frontmatter_apply_per_article_and_survive_regeneration() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let table = "{| class=\"wikitable\"\n|+ Caption\n|-\n! H1\n|-\n| A\n|}\n";
+    let wiki_dir = root.join("docs").join("wiki").join("t");
+    fs::create_dir_all(&wiki_dir).unwrap();
+    fs::write(wiki_dir.join("Test_Page.wiki"), table).unwrap();
+    fs::write(wiki_dir.join("Other_Page.wiki"), table).unwrap();
+
+    let md_dir = root.join("docs").join("md").join("t");
+    fs::create_dir_all(&md_dir).unwrap();
+    let md_path = md_dir.join("Test Page.md");
+    fs::write(
+        &md_path,
+        "---\nwiki2md:\n  article_id: Test_Page\n  render:\n    center_tables: true\n---\n\nOLD BODY\n",
+    )
+    .unwrap();
+
+    let wiki_root = root.join("docs").join("wiki");
+    let md_root = root.join("docs").join("md");
+    let write_opts = WriteOptions {
+        regenerate_frontmatter: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    let md = fs::read_to_string(&md_path).unwrap();
+    assert!(md.contains("<div style="), "{md}");
+    assert!(md.contains("  render:\n    center_tables: true\n"), "{md}");
+
+    // other articles keep the global options.
+    let other = fs::read_to_string(md_dir.join("Other Page.md")).unwrap();
+    assert!(!other.contains("<div style="), "{other}");
+}
+
+#[test]
+fn backup_keeps_previous_version_of_changed_files() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let wiki_path = root
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body\n").unwrap();
+
+    let md_path = root.join("docs").join("md").join("t").join("Test Page.md");
+    fs::create_dir_all(md_path.parent().unwrap()).unwrap();
+    let old = "---\ncustom: 123\n---\n\nOLD BODY\n";
+    fs::write(&md_path, old).unwrap();
+
+    let wiki_root = root.join("docs").join("wiki");
+    let md_root = root.join("docs").join("md");
+    let write_opts = WriteOptions {
+        backup: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    let bak = md_root.join("t").join("Test Page.md.bak");
+    assert_eq!(fs::read_to_string(&bak).unwrap(), old);
+    assert!(fs::read_to_string(&md_path).unwrap().contains("Body"));
+    // no temp files are left behind.
+    let names: Vec<String> = fs::read_dir(md_root.join("t"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names.len(), 2, "{names:?}");
+
+    let backup_dir = root.join("backup");
+    let write_opts = WriteOptions {
+        backup_dir: Some(backup_dir.clone()),
+        ..Default::default()
+    };
+    fs::write(&md_path, old).unwrap();
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert_eq!(
+        fs::read_to_string(backup_dir.join("t").join("Test Page.md")).unwrap(),
+        old
+    );
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let wiki_path = root
+        .join("docs")
+        .join("wiki")
+        .join("t")
+        .join("Test_Page.wiki");
+    fs::create_dir_all(wiki_path.parent().unwrap()).unwrap();
+    fs::write(&wiki_path, "Body\n").unwrap();
+    fs::write(wiki_path.with_file_name("New_Page.wiki"), "New\n").unwrap();
+
+    let md_dir = root.join("docs").join("md").join("t");
+    fs::create_dir_all(&md_dir).unwrap();
+    let old = "---\ncustom: 123\n---\n\nOLD BODY\n";
+    fs::write(md_dir.join("Test Page.md"), old).unwrap();
+
+    let wiki_root = root.join("docs").join("wiki");
+    let md_root = root.join("docs").join("md");
+    let write_opts = WriteOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(md_dir.join("Test Page.md")).unwrap(),
+        old
+    );
+    assert!(!md_dir.join("New Page.md").exists());
+}
+
+#[test]
+fn filter_limits_bulk_regeneration() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let wiki_root = root.join("docs").join("wiki");
+    for (bucket, id) in [
+        ("a", "Alpha-Beta"),
+        ("a", "Aegon_Tournament_1990"),
+        ("p", "Perft"),
+    ] {
+        fs::create_dir_all(wiki_root.join(bucket)).unwrap();
+        fs::write(wiki_root.join(bucket).join(format!("{id}.wiki")), "Body\n").unwrap();
+    }
+
+    let md_root = root.join("docs").join("md");
+    let write_opts = WriteOptions {
+        filter: wiki2md::filter::ArticleFilter::new()
+            .include("A*")
+            .unwrap()
+            .exclude("*_Tournament*")
+            .unwrap(),
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    assert!(md_root.join("a").join("Alpha-Beta.md").exists());
+    assert!(!md_root.join("a").join("Aegon Tournament 1990.md").exists());
+    assert!(!md_root.join("p").join("Perft.md").exists());
+}
+
+#[test]
+fn skip_existing_and_resume_from() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let wiki_root = root.join("docs").join("wiki");
+    for (bucket, id) in [("a", "Alpha"), ("b", "Beta"), ("g", "Gamma")] {
+        fs::create_dir_all(wiki_root.join(bucket)).unwrap();
+        fs::write(wiki_root.join(bucket).join(format!("{id}.wiki")), "Body\n").unwrap();
+    }
+    let md_root = root.join("docs").join("md");
+    fs::create_dir_all(md_root.join("g")).unwrap();
+    fs::write(md_root.join("g").join("Gamma.md"), "KEEP").unwrap();
+
+    let write_opts = WriteOptions {
+        resume_from: Some("Beta".to_string()),
+        skip_existing: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    assert!(!md_root.join("a").join("Alpha.md").exists());
+    assert!(md_root.join("b").join("Beta.md").exists());
+    assert_eq!(
+        fs::read_to_string(md_root.join("g").join("Gamma.md")).unwrap(),
+        "KEEP"
+    );
+
+    let write_opts = WriteOptions {
+        resume_from: Some("Nope".to_string()),
+        ..Default::default()
+    };
+    let err = regenerate(&wiki_root, &md_root, &write_opts).unwrap_err();
+    assert!(matches!(err, wiki2md::Error::Config(_)), "{err:?}");
+}
+
+#[test]
+fn errors_carry_the_path_they_are_about() {
+    let dir = tempdir().unwrap();
+    let missing = dir.path().join("no-such-wiki");
+    let err = regenerate(&missing, &dir.path().join("md"), &WriteOptions::default()).unwrap_err();
+    match &err {
+        wiki2md::Error::Io { path, source } => {
+            assert_eq!(path, &missing);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert!(err.to_string().contains("no-such-wiki"));
+}
+
+#[test]
+fn keep_going_records_failures_and_converts_the_rest() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+
+    let wiki_root = root.join("docs").join("wiki");
+    fs::create_dir_all(wiki_root.join("a")).unwrap();
+    fs::create_dir_all(wiki_root.join("b")).unwrap();
+    fs::write(wiki_root.join("a").join("Alpha.wiki"), "Body\n").unwrap();
+    fs::write(wiki_root.join("b").join("Beta.wiki"), "Body\n").unwrap();
+    let md_root = root.join("docs").join("md");
+    // a directory where `Alpha.md` should go makes that file fail to write.
+    fs::create_dir_all(md_root.join("a").join("Alpha.md")).unwrap();
+
+    // without keep-going, the first bad file stops the run.
+    assert!(regenerate(&wiki_root, &md_root, &WriteOptions::default()).is_err());
+    assert!(!md_root.join("b").join("Beta.md").exists());
+
+    let failures_path = root.join("failures.json");
+    let write_opts = WriteOptions {
+        keep_going: true,
+        failures_path: Some(failures_path.clone()),
+        verbosity: wiki2md::Verbosity::Quiet,
+        ..Default::default()
+    };
+    let err = regenerate(&wiki_root, &md_root, &write_opts).unwrap_err();
+    assert!(err.to_string().starts_with("1 of 2 files failed"), "{err}");
+    assert!(matches!(
+        err,
+        wiki2md::Error::Failures {
+            failed: 1,
+            total: 2,
+            ..
+        }
+    ));
+    assert_eq!(err.path(), Some(failures_path.as_path()));
+    assert!(md_root.join("b").join("Beta.md").exists());
+
+    let failures: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&failures_path).unwrap()).unwrap();
+    let failures = failures.as_array().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(
+        failures[0]["file"]
+            .as_str()
+            .unwrap()
+            .ends_with("Alpha.wiki")
+    );
+    assert_eq!(failures[0]["stage"], "write");
+    assert!(!failures[0]["error"].as_str().unwrap().is_empty());
+
+    // a clean run leaves an empty list behind.
+    fs::remove_dir(md_root.join("a").join("Alpha.md")).unwrap();
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert_eq!(fs::read_to_string(&failures_path).unwrap().trim(), "[]");
+}
+
+#[test]
+#[allow(deprecated)]
+fn positional_entry_points_still_work() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("a")).unwrap();
+    fs::write(wiki_root.join("a").join("Alpha.wiki"), "Body\n").unwrap();
+
+    wiki2md::regenerate_all_in_dirs(
+        &wiki_root,
+        &md_root,
+        &RenderOptions::default(),
+        &WriteOptions::default(),
+    )
+    .unwrap();
+    assert!(md_root.join("a").join("Alpha.md").exists());
+}
+
+#[test]
+fn run_with_config_uses_its_directories_and_respects_offline() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("cache");
+    fs::create_dir_all(wiki_dir.join("a")).unwrap();
+    fs::write(wiki_dir.join("a").join("Alpha.wiki"), "Body\n").unwrap();
+    let template = format!("{}/out/{{name}}.{{ext}}", dir.path().display());
+    let config = Config::new()
+        .wiki_dir(&wiki_dir)
+        .path_template(template)
+        .offline(true);
+
+    wiki2md::run_with_config("Alpha", &config).unwrap();
+    let md = fs::read_to_string(dir.path().join("out").join("Alpha.md")).unwrap();
+    assert!(md.contains("# Alpha\n\nBody"));
+
+    let err = wiki2md::run_with_config("Beta", &config).unwrap_err();
+    assert!(
+        matches!(&err, wiki2md::Error::Io { source, .. } if source.kind() == std::io::ErrorKind::NotFound)
+    );
+    assert_eq!(
+        err.path(),
+        Some(wiki_dir.join("b").join("Beta.wiki").as_path())
+    );
+    assert!(!wiki_dir.join("b").exists());
+}
+
+#[test]
+fn config_parse_options_reach_the_parser() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("Lists.wiki");
+    fs::write(&input, "* a\n** b\n*** c\n").unwrap();
+    let output = dir.path().join("Lists.md");
+    let config = Config::new().parse_options(ParseOptions {
+        max_list_depth: 1,
+        ..Default::default()
+    });
+    wiki2md::convert_file(&input, &output, &config).unwrap();
+    let md = fs::read_to_string(&output).unwrap();
+    assert!(md.contains("- a\n- b\n- c"), "{md}");
+    assert!(!md.contains("  - b"), "{md}");
+
+    // with `max_warnings`, diagnostics past the cap still count.
+    let wiki_dir = dir.path().join("wiki");
+    fs::create_dir_all(wiki_dir.join("b")).unwrap();
+    fs::write(
+        wiki_dir.join("b").join("Broken.wiki"),
+        "<ref>one\n\n<blockquote>two\n\n{|\n| x\n",
+    )
+    .unwrap();
+    let capped = Config::new()
+        .wiki_dir(&wiki_dir)
+        .md_dir(dir.path().join("md"))
+        .parse_options(ParseOptions {
+            max_diagnostics: Some(1),
+            ..Default::default()
+        });
+    regenerate_all_with_config(&capped).unwrap();
+    let gated = capped.write_options(WriteOptions {
+        max_warnings: Some(1),
+        ..Default::default()
+    });
+    let err = regenerate_all_with_config(&gated).unwrap_err();
+    assert!(
+        matches!(err, wiki2md::Error::Diagnostics { warnings, .. } if warnings > 1),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn run_with_config_fetches_through_its_fetcher() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("cache");
+    let config = Config::new()
+        .wiki_dir(&wiki_dir)
+        .md_dir(dir.path().join("md"))
+        .fetcher(|title: &str| match title {
+            "Alpha" => Ok("'''Alpha''' body.\n".to_string()),
+            _ => Err(wiki2md::Error::Fetch {
+                title: title.to_string(),
+                source: "no fixture".into(),
+            }),
+        });
+
+    wiki2md::run_with_config("Alpha", &config).unwrap();
+    assert_eq!(
+        fs::read_to_string(wiki_dir.join("a").join("Alpha.wiki")).unwrap(),
+        "'''Alpha''' body.\n"
+    );
+    let md = fs::read_to_string(dir.path().join("md").join("a").join("Alpha.md")).unwrap();
+    assert!(md.contains("**Alpha** body."), "{md}");
+
+    let err = wiki2md::run_with_config("Beta", &config).unwrap_err();
+    assert!(matches!(err, wiki2md::Error::Fetch { title, .. } if title == "Beta"));
+}
+
+#[test]
+fn stdout_rendering_uses_the_config_fetcher_and_wiki_dir() {
+    let dir = tempdir().unwrap();
+    let wiki_dir = dir.path().join("cache");
+    fs::create_dir_all(wiki_dir.join("b")).unwrap();
+    fs::write(
+        wiki_dir.join("b").join("Beta.wiki"),
+        "== Moves ==\n{|\n! Depth\n|-\n| 1\n|}\n",
+    )
+    .unwrap();
+    let config = Config::new()
+        .wiki_dir(&wiki_dir)
+        .fetcher(|title: &str| match title {
+            "Alpha" => Ok("'''Alpha''' body.\n".to_string()),
+            _ => Err(wiki2md::Error::Fetch {
+                title: title.to_string(),
+                source: "no fixture".into(),
+            }),
+        });
+
+    let md = wiki2md::render_article("Alpha", &config).unwrap();
+    assert!(md.contains("**Alpha** body."), "{md}");
+    let section = wiki2md::render_article_section("Beta", &["Moves"], &config).unwrap();
+    assert!(section.contains("| Depth |"), "{section}");
+    let (id, tables) = wiki2md::article_tables("Beta", &config).unwrap();
+    assert_eq!((id.as_str(), tables.len()), ("Beta", 1));
+
+    let err = wiki2md::render_article("Gamma", &config.offline(true)).unwrap_err();
+    assert!(matches!(err, wiki2md::Error::Io { .. }), "{err:?}");
+}
+
+#[test]
+fn search_index_covers_converted_and_skipped_articles() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("a")).unwrap();
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(
+        wiki_root.join("a").join("Alpha-Beta.wiki"),
+        "'''Alpha-beta''' prunes the [[minimax]] tree.\n== History ==\nOld.\n",
+    )
+    .unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote Belle.\n",
+    )
+    .unwrap();
+    fs::create_dir_all(md_root.join("k")).unwrap();
+    fs::write(md_root.join("k").join("Ken Thompson.md"), "KEEP").unwrap();
+
+    let index_path = dir.path().join("site").join("search-index.json");
+    let write_opts = WriteOptions {
+        skip_existing: true,
+        search_index: Some(index_path.clone()),
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&index_path).unwrap()).unwrap();
+    assert_eq!(index["ref"], "id");
+    let docs = index["documents"].as_array().unwrap();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0]["id"], "Alpha-Beta");
+    assert_eq!(docs[0]["path"], "a/Alpha-Beta.md");
+    assert_eq!(docs[0]["headings"][0], "History");
+    assert_eq!(docs[0]["body"], "Alpha-beta prunes the minimax tree.\nOld.");
+    assert_eq!(docs[1]["title"], "Ken Thompson");
+    assert_eq!(docs[1]["path"], "k/Ken Thompson.md");
+}
+
+#[test]
+fn full_runs_write_the_alphabetical_index() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    for (bucket, id) in [("a", "Alpha-Beta"), ("k", "Ken_Thompson"), ("a", "Attack")] {
+        fs::create_dir_all(wiki_root.join(bucket)).unwrap();
+        fs::write(wiki_root.join(bucket).join(format!("{id}.wiki")), "Body\n").unwrap();
+    }
+
+    let write_opts = WriteOptions {
+        index_pages: IndexPages::Single,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert_eq!(
+        fs::read_to_string(md_root.join("Index.md")).unwrap(),
+        "# Index\n\n## A\n\n- [[Alpha-Beta]]\n- [[Attack]]\n\n## K\n\n- [[Ken Thompson]]\n"
+    );
+
+    // a filtered run doesn't replace it with a partial listing.
+    let write_opts = WriteOptions {
+        index_pages: IndexPages::PerLetter,
+        resume_from: Some("Ken_Thompson".to_string()),
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert!(
+        fs::read_to_string(md_root.join("Index.md"))
+            .unwrap()
+            .contains("[[Attack]]")
+    );
+    assert!(!md_root.join("Index").exists());
+}
+
+#[test]
+fn category_pages_list_their_articles() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("a")).unwrap();
+    fs::create_dir_all(wiki_root.join("m")).unwrap();
+    fs::write(
+        wiki_root.join("a").join("Alpha-Beta.wiki"),
+        "Body\n[[Category:Search]]\n",
+    )
+    .unwrap();
+    fs::write(
+        wiki_root.join("m").join("Minimax.wiki"),
+        "Body\n[[Category:Search]]\n",
+    )
+    .unwrap();
+
+    let write_opts = WriteOptions {
+        category_pages: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert_eq!(
+        fs::read_to_string(md_root.join("Categories.md")).unwrap(),
+        "# Categories\n\n- [[Categories/Search|Search]] (2)\n"
+    );
+    assert_eq!(
+        fs::read_to_string(md_root.join("Categories").join("Search.md")).unwrap(),
+        "# Category: Search\n\n- [[Alpha-Beta]]\n- [[Minimax]]\n"
+    );
+    assert!(!md_root.join("Index.md").exists());
+}
+
+#[test]
+fn manifest_lists_sources_outputs_and_hashes() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("a")).unwrap();
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(wiki_root.join("a").join("Alpha-Beta.wiki"), "Body\n").unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote Belle.\n",
+    )
+    .unwrap();
+
+    let manifest_path = dir.path().join("manifest.json");
+    let write_opts = WriteOptions {
+        manifest: Some(manifest_path.clone()),
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+
+    let manifest = Manifest::load(&manifest_path).unwrap().unwrap();
+    let ids: Vec<&str> = manifest
+        .articles
+        .iter()
+        .map(|a| a.article_id.as_str())
+        .collect();
+    assert_eq!(ids, ["Alpha-Beta", "Ken_Thompson"]);
+    let ken = &manifest.articles[1];
+    assert!(
+        ken.source.ends_with("wiki/k/Ken_Thompson.wiki"),
+        "{}",
+        ken.source
+    );
+    assert_eq!(ken.source_hash, content_hash(b"Wrote Belle.\n"));
+    assert!(
+        ken.outputs[0].path.ends_with("md/k/Ken Thompson.md"),
+        "{:?}",
+        ken.outputs
+    );
+    let md = fs::read(md_root.join("k").join("Ken Thompson.md")).unwrap();
+    assert_eq!(ken.outputs[0].hash, content_hash(&md));
+    assert_eq!(ken.render_options, RenderOptions::default().fingerprint());
+
+    // a partial run updates its own entries and keeps the others.
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote Belle and Unix.\n",
+    )
+    .unwrap();
+    let write_opts = WriteOptions {
+        resume_from: Some("Ken_Thompson".to_string()),
+        ..write_opts
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    let updated = Manifest::load(&manifest_path).unwrap().unwrap();
+    assert_eq!(updated.articles.len(), 2);
+    assert_eq!(updated.articles[0], manifest.articles[0]);
+    assert_ne!(updated.articles[1].source_hash, ken.source_hash);
+}
+
+#[test]
+fn bulk_mode_writes_json_next_to_markdown() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    let json_root = dir.path().join("json");
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote [[Belle]].\n",
+    )
+    .unwrap();
+
+    let manifest_path = dir.path().join("manifest.json");
+    let write_opts = WriteOptions {
+        write_json: true,
+        manifest: Some(manifest_path.clone()),
+        ..Default::default()
+    };
+    regenerate_all_with_config(
+        &Config::new()
+            .wiki_dir(&wiki_root)
+            .md_dir(&md_root)
+            .json_dir(&json_root)
+            .write_options(write_opts),
+    )
+    .unwrap();
+
+    let json_path = json_root.join("k").join("Ken_Thompson.json");
+    let json = fs::read_to_string(&json_path).unwrap();
+    let ast: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(ast["article_id"], "Ken_Thompson");
+    assert!(md_root.join("k").join("Ken Thompson.md").exists());
+
+    let manifest = Manifest::load(&manifest_path).unwrap().unwrap();
+    let outputs = &manifest.articles[0].outputs;
+    assert_eq!(outputs.len(), 2);
+    assert!(
+        outputs[1].path.ends_with("json/k/Ken_Thompson.json"),
+        "{:?}",
+        outputs
+    );
+    assert_eq!(outputs[1].hash, content_hash(json.as_bytes()));
+
+    // without the option, only Markdown is written.
+    let other = dir.path().join("json2");
+    regenerate_all_with_config(
+        &Config::new()
+            .wiki_dir(&wiki_root)
+            .md_dir(&md_root)
+            .json_dir(&other),
+    )
+    .unwrap();
+    assert!(!other.exists());
+}
+
+#[test]
+fn generation_metadata_regenerates_stale_files() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote Belle.\n",
+    )
+    .unwrap();
+    let md_path = md_root.join("k").join("Ken Thompson.md");
+
+    let write_opts = WriteOptions {
+        generation_metadata: true,
+        skip_existing: true,
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    let md = fs::read_to_string(&md_path).unwrap();
+    let info = GenerationInfo::from_markdown(&md).unwrap();
+    assert_eq!(info, GenerationInfo::current(&RenderOptions::default()));
+    assert!(
+        md.contains("Wrote Belle.\n\n<!-- wiki2md parser="),
+        "{}",
+        md
+    );
+
+    // up to date: skipped.
+    fs::write(&md_path, md.replace("Wrote Belle.", "Edited.")).unwrap();
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert!(fs::read_to_string(&md_path).unwrap().contains("Edited."));
+
+    // made by an older version: regenerated even though the source didn't change.
+    let old = md.replace(
+        &info.to_comment(),
+        "<!-- wiki2md parser=0.0.1 schema=1 render=x -->",
+    );
+    fs::write(&md_path, old.replace("Wrote Belle.", "Edited.")).unwrap();
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    assert_eq!(fs::read_to_string(&md_path).unwrap(), md);
+}
+
+#[cfg(unix)]
+#[test]
+fn ast_filters_run_between_parse_and_render() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let md_root = dir.path().join("md");
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "Wrote [[Belle]].\n",
+    )
+    .unwrap();
+
+    let write_opts = WriteOptions {
+        ast_filters: vec![
+            AstFilter::parse("sed s/Belle/Unix/").unwrap(),
+            AstFilter::new("sed").arg("s/Wrote /Co-wrote /"),
+        ],
+        ..Default::default()
+    };
+    regenerate(&wiki_root, &md_root, &write_opts).unwrap();
+    let md = fs::read_to_string(md_root.join("k").join("Ken Thompson.md")).unwrap();
+    assert!(md.contains("Co-wrote [[Unix]]."), "{md}");
+
+    let write_opts = WriteOptions {
+        ast_filters: vec![AstFilter::new("false")],
+        ..Default::default()
+    };
+    let err = regenerate(&wiki_root, &md_root, &write_opts).unwrap_err();
+    assert!(matches!(err, wiki2md::Error::Filter { .. }), "{err}");
+    assert_eq!(err.article_id(), Some("Ken_Thompson"));
+}
+
+#[test]
+fn vault_output_stays_in_its_folder_and_links_local_attachments() {
+    let dir = tempdir().unwrap();
+    let wiki_root = dir.path().join("wiki");
+    let vault_root = dir.path().join("vault");
+    fs::create_dir_all(wiki_root.join("k")).unwrap();
+    fs::write(
+        wiki_root.join("k").join("Ken_Thompson.wiki"),
+        "[[File:Ken.jpg|thumb|Ken]]\n\nWrote [[Belle]].\n",
+    )
+    .unwrap();
+    fs::create_dir_all(vault_root.join(".obsidian")).unwrap();
+    fs::write(
+        vault_root.join(".obsidian").join("app.json"),
+        r#"{"attachmentFolderPath":"Files"}"#,
+    )
+    .unwrap();
+    fs::write(vault_root.join("Ken Thompson.md"), "my own note").unwrap();
+    // already downloaded, so nothing is fetched.
+    fs::create_dir_all(vault_root.join("Files")).unwrap();
+    fs::write(vault_root.join("Files").join("300px-Ken.jpg"), "jpg").unwrap();
+
+    let vault = Vault::open(&vault_root, "CPW").unwrap();
+    regenerate_all_with_config(&Config::new().wiki_dir(&wiki_root).vault(vault)).unwrap();
+
+    let md = fs::read_to_string(vault_root.join("CPW").join("k").join("Ken Thompson.md")).unwrap();
+    assert!(md.contains("![Ken](../../Files/300px-Ken.jpg)"), "{md}");
+    assert_eq!(
+        fs::read_to_string(vault_root.join("Ken Thompson.md")).unwrap(),
+        "my own note"
+    );
+    assert!(Vault::open(&wiki_root, "CPW").is_err());
+}
@@ -33,7 +33,7 @@ fn check_inlines(nodes: &[InlineNode], len: usize) {
                     check_inlines(&p.content, len);
                 }
             }
-            InlineKind::LineBreak => {}
+            InlineKind::LineBreak | InlineKind::MagicLink { .. } => {}
             InlineKind::Ref { node } => {
                 for a in &node.attrs {
                     if let Some(s) = &a.span {
@@ -58,6 +58,12 @@ fn check_inlines(nodes: &[InlineNode], len: usize) {
                     check_inlines(&p.value, len);
                 }
             }
+            InlineKind::TemplateArg { default, .. } => {
+                if let Some(d) = default {
+                    check_inlines(d, len);
+                }
+            }
+            InlineKind::Inclusion { content, .. } => check_inlines(content, len),
             InlineKind::Raw { .. } => {}
         }
     }
@@ -109,12 +115,19 @@ fn check_blocks(nodes: &[BlockNode], len: usize) {
                 }
             }
             BlockKind::CodeBlock { .. } => {}
+            BlockKind::Poem { lines } => {
+                for line in lines {
+                    check_inlines(line, len);
+                }
+            }
+            BlockKind::Timeline { .. } => {}
             BlockKind::References { node } => {
                 for a in &node.attrs {
                     if let Some(s) = &a.span {
                         check_span(s, len);
                     }
                 }
+                check_inlines(&node.refs, len);
             }
             BlockKind::HtmlBlock { node } => {
                 for a in &node.attrs {
@@ -126,7 +139,18 @@ fn check_blocks(nodes: &[BlockNode], len: usize) {
             }
             BlockKind::MagicWord { .. } => {}
             BlockKind::HorizontalRule => {}
-            BlockKind::BlockQuote { blocks } => check_blocks(blocks, len),
+            BlockKind::BlockQuote {
+                blocks,
+                attribution,
+                ..
+            } => {
+                if let Some(a) = attribution {
+                    check_span(&a.span, len);
+                    check_inlines(&a.content, len);
+                }
+                check_blocks(blocks, len)
+            }
+            BlockKind::Inclusion { blocks, .. } => check_blocks(blocks, len),
             BlockKind::Raw { .. } => {}
         }
     }
@@ -211,7 +235,7 @@ fn fuzz_parse_codeblock_same_line_with_tail_does_not_hang() {
                 let txt: String = content
                     .iter()
                     .filter_map(|n| match &n.kind {
-                        InlineKind::Text { value } => Some(value.as_str()),
+                        InlineKind::Text { value } => Some(value.as_ref()),
                         _ => None,
                     })
                     .collect();
@@ -0,0 +1,62 @@
+use assert_cmd::cargo_bin_cmd;
+use std::fs;
+use tempfile::tempdir;
+
+fn write_wiki(root: &std::path::Path, bucket: &str, id: &str, text: &str) {
+    let path = root
+        .join("docs")
+        .join("wiki")
+        .join(bucket)
+        .join(format!("{}.wiki", id));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn graph_subcommand_writes_dot_and_json() {
+    let dir = tempdir().unwrap();
+    write_wiki(
+        dir.path(),
+        "a",
+        "Alpha-Beta",
+        "Uses [[Minimax]] and [[Nowhere]].\n",
+    );
+    write_wiki(
+        dir.path(),
+        "m",
+        "Minimax",
+        "See [[Alpha-Beta|alpha-beta]].\n",
+    );
+    write_wiki(dir.path(), "m", "MiniMax", "#REDIRECT [[Minimax]]\n");
+
+    let output = cargo_bin_cmd!("wiki2md")
+        .current_dir(dir.path())
+        .arg("graph")
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let dot = String::from_utf8(output.stdout).unwrap();
+    assert!(dot.starts_with("digraph wiki {\n"));
+    assert!(dot.contains("  \"Alpha-Beta\" -> \"Minimax\";\n"));
+    assert!(dot.contains("  \"MiniMax\" -> \"Minimax\";\n"));
+    assert!(!dot.contains("Nowhere"));
+
+    cargo_bin_cmd!("wiki2md")
+        .current_dir(dir.path())
+        .args([
+            "graph",
+            "--format",
+            "json",
+            "--no-redirects",
+            "-o",
+            "graph.json",
+        ])
+        .assert()
+        .success();
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("graph.json")).unwrap()).unwrap();
+    assert_eq!(json["Alpha-Beta"], serde_json::json!(["Minimax"]));
+    assert_eq!(json["Minimax"], serde_json::json!(["Alpha-Beta"]));
+    assert!(json.get("MiniMax").is_none());
+}
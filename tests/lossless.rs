@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::PathBuf;
+use wiki2md::parse;
+use wiki2md::render::wikitext::serialize_doc;
+
+fn corpus_files() -> Vec<PathBuf> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    for dir in [
+        root.join("fuzz").join("afl").join("in"),
+        root.join("tests").join("testdata"),
+        root.join("tests").join("crashes"),
+    ] {
+        for entry in fs::read_dir(&dir).unwrap_or_else(|e| panic!("read {}: {e}", dir.display())) {
+            let path = entry.unwrap().path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn lossless_parse_round_trips_corpus_byte_for_byte() {
+    let mut failures: Vec<String> = Vec::new();
+    for path in corpus_files() {
+        let bytes = fs::read(&path).unwrap();
+        let src = String::from_utf8_lossy(&bytes).into_owned();
+        let out = parse::parse_wiki_lossless(&src);
+        if serialize_doc(&out.document) != src {
+            failures.push(path.display().to_string());
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "round trip mismatch:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn lossless_trivia_survives_json_round_trip() {
+    let src = "  == Heading ==  \n\n\n* item\n\n[[Category:Thing]]\n";
+    let out = parse::parse_wiki_lossless(src);
+    let json = serde_json::to_string(&out.document).unwrap();
+    let back: wiki2md::ast::Document = serde_json::from_str(&json).unwrap();
+    assert_eq!(serialize_doc(&back), src);
+}
+
+#[test]
+fn lossless_parse_round_trips_random_inputs() {
+    // xorshift over a wikitext-heavy alphabet; see `tests/fuzz_parse.rs`.
+    const DICT: &[u8] = b"abcXYZ019 \n\r\t[]{}|!<>='\"/:#*;-_";
+    let mut state: u64 = 0x5EED;
+    for _case in 0..1_000 {
+        let mut next = || {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            state.wrapping_mul(0x2545F4914F6CDD1D) as usize
+        };
+        let len = next() % 600;
+        let src: String = (0..len)
+            .map(|_| DICT[next() % DICT.len()] as char)
+            .collect();
+        let out = parse::parse_wiki_lossless(&src);
+        assert_eq!(serialize_doc(&out.document), src, "input: {src:?}");
+    }
+}
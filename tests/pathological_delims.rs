@@ -36,3 +36,46 @@ fn pathological_open_delimiter_runs_are_treated_as_text() {
         }
     }
 }
+
+#[test]
+fn parse_options_tighten_nesting_limits() {
+    let opts = parse::ParseOptions {
+        max_list_depth: 2,
+        max_template_depth: 1,
+        max_inline_depth: 2,
+        max_diagnostics: Some(2),
+        ..Default::default()
+    };
+    let src = "* a\n** b\n*** c\n\n{{a|{{b|x}}}}\n\n''x [[A|y]]''\n";
+    let out = parse::parse_wiki_with_options(src, &opts);
+
+    let codes: Vec<_> = out
+        .diagnostics
+        .iter()
+        .filter_map(|d| d.code.as_deref())
+        .collect();
+    assert_eq!(
+        codes,
+        [
+            "wikitext.list.depth_clamped",
+            "wikitext.template.depth_exceeded",
+            "wiki2md.diagnostics.truncated"
+        ]
+    );
+
+    let out = parse::parse_wiki_with_options(
+        src,
+        &parse::ParseOptions {
+            max_diagnostics: None,
+            ..opts
+        },
+    );
+    assert!(
+        out.diagnostics
+            .iter()
+            .any(|d| d.code.as_deref() == Some("wikitext.inline.depth_exceeded")),
+        "{:?}",
+        out.diagnostics
+    );
+    assert!(parse::parse_wiki(src).diagnostics.is_empty());
+}